@@ -21,6 +21,12 @@ pub struct RefCharacterData {
     pub berserk_speed: u32,               // column 48
     pub base_range: u16,                  // column 50
     pub pickup_range: Option<NonZeroU16>, // column 61
+    pub phys_defense: f32,                // column 72
+    pub mag_defense: f32,                 // column 73
+    pub parry_rate: f32,                  // column 74
+    pub block_ratio: f32,                 // column 76
+    pub hit_rate: f32,                    // column 77
+    pub critical_rate: f32,               // column 78
     pub aggressive: bool,                 // column 93
     pub skills: Vec<u32>,                 // column 83-92
 }
@@ -62,6 +68,12 @@ impl FromStr for RefCharacterData {
             berserk_speed: elements.get(48).ok_or(ParseError::MissingColumn(48))?.parse()?,
             base_range: elements.get(50).ok_or(ParseError::MissingColumn(50))?.parse()?,
             pickup_range: NonZeroU16::new(pickup_range),
+            phys_defense: elements.get(72).ok_or(ParseError::MissingColumn(72))?.parse()?,
+            mag_defense: elements.get(73).ok_or(ParseError::MissingColumn(73))?.parse()?,
+            parry_rate: elements.get(74).ok_or(ParseError::MissingColumn(74))?.parse()?,
+            block_ratio: elements.get(76).ok_or(ParseError::MissingColumn(76))?.parse()?,
+            hit_rate: elements.get(77).ok_or(ParseError::MissingColumn(77))?.parse()?,
+            critical_rate: elements.get(78).ok_or(ParseError::MissingColumn(78))?.parse()?,
             aggressive: aggressive == 1,
             skills,
         })
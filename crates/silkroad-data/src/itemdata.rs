@@ -40,14 +40,76 @@ impl FromStr for RefBiologicalType {
     }
 }
 
+/// A combat value of an item. The actual value lies between the lower and upper bound, depending
+/// on the variance of the item, and grows with each upgrade level.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct RefItemStat {
+    pub lower: f32,
+    pub upper: f32,
+    pub per_upgrade: f32,
+}
+
+impl RefItemStat {
+    fn from_columns(elements: &[&str], lower: u8, upper: u8, per_upgrade: Option<u8>) -> Result<Self, ParseError> {
+        let column = |column: u8| {
+            elements
+                .get(usize::from(column))
+                .ok_or(ParseError::MissingColumn(column))?
+                .parse::<f32>()
+                .map_err(ParseError::from)
+        };
+        Ok(Self {
+            lower: column(lower)?,
+            upper: column(upper)?,
+            per_upgrade: per_upgrade.map(column).transpose()?.unwrap_or_default(),
+        })
+    }
+}
+
+/// The combat values of an item.
+#[derive(Copy, Clone, Default)]
+pub struct RefItemStats {
+    pub phys_defense: RefItemStat,    // column 65-67
+    pub parry_rate: RefItemStat,      // column 71-73
+    pub block_ratio: RefItemStat,     // column 74-75
+    pub mag_defense: RefItemStat,     // column 76-78
+    pub phys_attack_min: RefItemStat, // column 95, 96, 99
+    pub phys_attack_max: RefItemStat, // column 97-99
+    pub mag_attack_min: RefItemStat,  // column 100, 101, 104
+    pub mag_attack_max: RefItemStat,  // column 102-104
+    pub hit_rate: RefItemStat,        // column 113-115
+    pub critical_rate: RefItemStat,   // column 116-117
+}
+
+impl RefItemStats {
+    fn from_columns(elements: &[&str]) -> Result<Self, ParseError> {
+        Ok(Self {
+            phys_defense: RefItemStat::from_columns(elements, 65, 66, Some(67))?,
+            parry_rate: RefItemStat::from_columns(elements, 71, 72, Some(73))?,
+            block_ratio: RefItemStat::from_columns(elements, 74, 75, None)?,
+            mag_defense: RefItemStat::from_columns(elements, 76, 77, Some(78))?,
+            phys_attack_min: RefItemStat::from_columns(elements, 95, 96, Some(99))?,
+            phys_attack_max: RefItemStat::from_columns(elements, 97, 98, Some(99))?,
+            mag_attack_min: RefItemStat::from_columns(elements, 100, 101, Some(104))?,
+            mag_attack_max: RefItemStat::from_columns(elements, 102, 103, Some(104))?,
+            hit_rate: RefItemStat::from_columns(elements, 113, 114, Some(115))?,
+            critical_rate: RefItemStat::from_columns(elements, 116, 117, None)?,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct RefItemData {
     pub common: RefCommon,
     pub price: u64,
+    pub sell_price: u64,
+    /// The fee for putting a single item into the storage.
+    pub keeping_fee: u64,
     pub max_stack_size: u16,
     pub range: Option<NonZeroU16>,
     pub required_level: Option<NonZeroU8>,
     pub biological_type: RefBiologicalType,
+    pub stats: RefItemStats,
     pub params: [isize; 4],
 }
 
@@ -78,6 +140,8 @@ impl FromStr for RefItemData {
         Ok(Self {
             common,
             price: elements.get(26).ok_or(ParseError::MissingColumn(26))?.parse()?,
+            keeping_fee: elements.get(30).ok_or(ParseError::MissingColumn(30))?.parse()?,
+            sell_price: elements.get(31).ok_or(ParseError::MissingColumn(31))?.parse()?,
             params: [
                 elements.get(118).ok_or(ParseError::MissingColumn(118))?.parse()?,
                 elements.get(120).ok_or(ParseError::MissingColumn(120))?.parse()?,
@@ -88,6 +152,7 @@ impl FromStr for RefItemData {
             required_level: NonZeroU8::new(required_level),
             biological_type: elements.get(58).ok_or(ParseError::MissingColumn(58))?.parse()?,
             max_stack_size: elements.get(57).ok_or(ParseError::MissingColumn(57))?.parse()?,
+            stats: RefItemStats::from_columns(&elements)?,
         })
     }
 }
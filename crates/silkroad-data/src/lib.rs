@@ -10,6 +10,7 @@ pub mod itemdata;
 pub mod level;
 pub mod masterydata;
 pub mod npc_pos;
+pub mod shop;
 pub mod skilldata;
 pub mod teleport;
 
@@ -0,0 +1,299 @@
+use crate::{parse_file, FileError, ParseError};
+use pk2::Pk2;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Loads all shops and links them to the NPCs that sell them.
+///
+/// The shop data is spread over multiple files: an NPC belongs to a shop group, which maps to a
+/// shop. The shop in turn maps to a group of tabs, which contain the goods. Each good references
+/// a package, which finally contains the actual item.
+pub fn load_shop_map(pk2: &Pk2) -> Result<ShopMap, FileError> {
+    let groups: Vec<RefShopGroup> = load_file(pk2, "refshopgroup.txt")?;
+    let group_mappings: Vec<RefShopGroupMapping> = load_file(pk2, "refmappingshopgroup.txt")?;
+    let shops: Vec<RefShop> = load_file(pk2, "refshop.txt")?;
+    let tab_mappings: Vec<RefShopTabMapping> = load_file(pk2, "refmappingshopwithtab.txt")?;
+    let tabs: Vec<RefShopTab> = load_file(pk2, "refshoptab.txt")?;
+    let goods: Vec<RefShopGoods> = load_file(pk2, "refshopgoods.txt")?;
+    let packages: Vec<RefPackageItem> = load_file(pk2, "refpackageitem.txt")?;
+    let package_contents: Vec<RefScrapOfPackageItem> = load_file(pk2, "refscrapofpackageitem.txt")?;
+
+    let packages: HashMap<&str, &RefScrapOfPackageItem> = packages
+        .iter()
+        .filter(|package| package.active)
+        .filter_map(|package| {
+            package_contents
+                .iter()
+                .find(|content| content.active && content.package == package.code)
+                .map(|content| (package.code.as_str(), content))
+        })
+        .collect();
+
+    let mut map = HashMap::new();
+    for group in groups.iter().filter(|group| group.active) {
+        let Some(shop) = group_mappings
+            .iter()
+            .filter(|mapping| mapping.active && mapping.group == group.code)
+            .find_map(|mapping| shops.iter().find(|shop| shop.active && shop.code == mapping.shop))
+        else {
+            continue;
+        };
+
+        let shop_tabs = tab_mappings
+            .iter()
+            .filter(|mapping| mapping.active && mapping.shop == shop.code)
+            .flat_map(|mapping| {
+                tabs.iter()
+                    .filter(|tab| tab.active && tab.tab_group == mapping.tab_group)
+            })
+            .map(|tab| {
+                let mut tab_goods: Vec<ShopGood> = goods
+                    .iter()
+                    .filter(|good| good.active && good.tab == tab.code)
+                    .filter_map(|good| {
+                        packages.get(good.package.as_str()).map(|content| ShopGood {
+                            slot: good.slot,
+                            package: good.package.clone(),
+                            item: content.item.clone(),
+                            upgrade_level: content.upgrade_level,
+                            variance: content.variance,
+                            data: content.data,
+                        })
+                    })
+                    .collect();
+                tab_goods.sort_by_key(|good| good.slot);
+                ShopTab {
+                    code: tab.code.clone(),
+                    goods: tab_goods,
+                }
+            })
+            .collect();
+
+        map.insert(
+            group.npc.clone(),
+            Shop {
+                code: shop.code.clone(),
+                tabs: shop_tabs,
+            },
+        );
+    }
+
+    Ok(ShopMap(map))
+}
+
+fn load_file<T: FromStr<Err = ParseError>>(pk2: &Pk2, name: &str) -> Result<Vec<T>, FileError> {
+    let mut file = pk2.open_file(&format!("/server_dep/silkroad/textdata/{}", name))?;
+    parse_file(&mut file)
+}
+
+/// All shops, keyed by the code of the NPC selling them.
+pub struct ShopMap(HashMap<String, Shop>);
+
+impl Deref for ShopMap {
+    type Target = HashMap<String, Shop>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Shop {
+    pub code: String,
+    pub tabs: Vec<ShopTab>,
+}
+
+impl Shop {
+    pub fn find_good(&self, tab: u8, slot: u8) -> Option<&ShopGood> {
+        self.tabs
+            .get(tab as usize)
+            .and_then(|tab| tab.goods.iter().find(|good| good.slot == slot))
+    }
+}
+
+pub struct ShopTab {
+    pub code: String,
+    pub goods: Vec<ShopGood>,
+}
+
+pub struct ShopGood {
+    pub slot: u8,
+    pub package: String,
+    pub item: String,
+    pub upgrade_level: u8,
+    pub variance: u64,
+    /// Durability for equipment and the stack size for everything else.
+    pub data: u32,
+}
+
+pub struct RefShopGroup {
+    pub active: bool,
+    pub ref_id: u32,
+    pub code: String,
+    pub npc: String,
+}
+
+impl FromStr for RefShopGroup {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            ref_id: elements.get(2).ok_or(ParseError::MissingColumn(2))?.parse()?,
+            code: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+            npc: elements.get(4).ok_or(ParseError::MissingColumn(4))?.to_string(),
+        })
+    }
+}
+
+pub struct RefShopGroupMapping {
+    pub active: bool,
+    pub group: String,
+    pub shop: String,
+}
+
+impl FromStr for RefShopGroupMapping {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            group: elements.get(2).ok_or(ParseError::MissingColumn(2))?.to_string(),
+            shop: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+        })
+    }
+}
+
+pub struct RefShop {
+    pub active: bool,
+    pub ref_id: u32,
+    pub code: String,
+}
+
+impl FromStr for RefShop {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            ref_id: elements.get(2).ok_or(ParseError::MissingColumn(2))?.parse()?,
+            code: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+        })
+    }
+}
+
+pub struct RefShopTabMapping {
+    pub active: bool,
+    pub shop: String,
+    pub tab_group: String,
+}
+
+impl FromStr for RefShopTabMapping {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            shop: elements.get(2).ok_or(ParseError::MissingColumn(2))?.to_string(),
+            tab_group: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+        })
+    }
+}
+
+pub struct RefShopTab {
+    pub active: bool,
+    pub ref_id: u32,
+    pub code: String,
+    pub tab_group: String,
+}
+
+impl FromStr for RefShopTab {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            ref_id: elements.get(2).ok_or(ParseError::MissingColumn(2))?.parse()?,
+            code: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+            tab_group: elements.get(4).ok_or(ParseError::MissingColumn(4))?.to_string(),
+        })
+    }
+}
+
+pub struct RefShopGoods {
+    pub active: bool,
+    pub tab: String,
+    pub package: String,
+    pub slot: u8,
+}
+
+impl FromStr for RefShopGoods {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            tab: elements.get(2).ok_or(ParseError::MissingColumn(2))?.to_string(),
+            package: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+            slot: elements.get(4).ok_or(ParseError::MissingColumn(4))?.parse()?,
+        })
+    }
+}
+
+pub struct RefPackageItem {
+    pub active: bool,
+    pub ref_id: u32,
+    pub code: String,
+}
+
+impl FromStr for RefPackageItem {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            ref_id: elements.get(2).ok_or(ParseError::MissingColumn(2))?.parse()?,
+            code: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+        })
+    }
+}
+
+pub struct RefScrapOfPackageItem {
+    pub active: bool,
+    pub package: String,
+    pub item: String,
+    pub upgrade_level: u8,
+    pub variance: u64,
+    pub data: u32,
+}
+
+impl FromStr for RefScrapOfPackageItem {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let elements = s.split('\t').collect::<Vec<&str>>();
+        let active: u8 = elements.get(0).ok_or(ParseError::MissingColumn(0))?.parse()?;
+        Ok(Self {
+            active: active == 1,
+            package: elements.get(2).ok_or(ParseError::MissingColumn(2))?.to_string(),
+            item: elements.get(3).ok_or(ParseError::MissingColumn(3))?.to_string(),
+            upgrade_level: elements.get(4).ok_or(ParseError::MissingColumn(4))?.parse()?,
+            variance: elements.get(5).ok_or(ParseError::MissingColumn(5))?.parse()?,
+            data: elements.get(6).ok_or(ParseError::MissingColumn(6))?.parse()?,
+        })
+    }
+}
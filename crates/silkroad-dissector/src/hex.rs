@@ -0,0 +1,95 @@
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use std::fmt::Write;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// Parses a hex dump into its bytes. Whitespace is ignored and everything after a `#` until
+/// the end of the line is treated as a comment, which is the same format the protocol fixtures
+/// use.
+pub(crate) fn parse_hex(input: &str) -> Result<Vec<u8>> {
+    let digits = input
+        .lines()
+        .map(|line| line.split_once('#').map_or(line, |(data, _)| data))
+        .flat_map(|line| line.split_whitespace())
+        .map(|part| part.strip_prefix("0x").unwrap_or(part))
+        .collect::<String>();
+
+    if !digits.is_ascii() {
+        return Err(eyre!("The hex dump contains characters that are not hex digits."));
+    }
+
+    if digits.len() % 2 != 0 {
+        return Err(eyre!("The hex dump contains an odd number of digits."));
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|index| {
+            let byte = &digits[index..index + 2];
+            u8::from_str_radix(byte, 16).map_err(|_| eyre!("Invalid hex byte '{byte}'."))
+        })
+        .collect()
+}
+
+/// Parses an opcode, which is always interpreted as hex, with or without a `0x` prefix.
+pub(crate) fn parse_opcode(input: &str) -> Result<u16, String> {
+    let digits = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .unwrap_or(input);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("'{input}' is not a valid opcode"))
+}
+
+/// Formats the bytes as a classic hex dump, where each line starts with the offset of its
+/// first byte. `start` is the offset of the first byte inside the whole payload.
+pub(crate) fn hex_dump(data: &[u8], start: usize) -> String {
+    let mut output = String::new();
+    for (line, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() { byte as char } else { '.' })
+            .collect::<String>();
+        let _ = writeln!(
+            output,
+            "{:08x}  {hex:<width$}  |{ascii}|",
+            start + line * BYTES_PER_LINE,
+            width = BYTES_PER_LINE * 3 - 1
+        );
+    }
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        let bytes = parse_hex("01 06 # pickup\nfe e803 0x00 00\n").unwrap();
+        assert_eq!(bytes, vec![0x01, 0x06, 0xFE, 0xE8, 0x03, 0x00, 0x00]);
+        assert!(parse_hex("01 0").is_err());
+        assert!(parse_hex("zz").is_err());
+
+        assert_eq!(parse_opcode("0x3013"), Ok(0x3013));
+        assert_eq!(parse_opcode("B034"), Ok(0xB034));
+        assert!(parse_opcode("0x13013").is_err());
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let data = (0u8..20).map(|i| i + 0x40).collect::<Vec<_>>();
+        let dump = hex_dump(&data, 0x10);
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("00000010  40 41 42"));
+        assert!(lines[0].ends_with("|@ABCDEFGHIJKLMNO|"));
+        assert!(lines[1].starts_with("00000020  50 51 52 53  "));
+        assert!(lines[1].ends_with("|PQRS|"));
+    }
+}
@@ -0,0 +1,153 @@
+mod hex;
+mod registry;
+
+use crate::hex::{hex_dump, parse_hex, parse_opcode};
+use crate::registry::{lookup, registry, PacketType, Protocol};
+use clap::{Parser, Subcommand};
+use color_eyre::Result;
+use skrillax_server::{Direction, RecordingReader};
+use std::path::PathBuf;
+
+/// Decodes the payload of a packet into the types of the protocol crates and prints them.
+///
+/// Every type that is known for the opcode is tried. If a type could not be read completely,
+/// or if bytes were left after reading it, the offset where reading stopped is shown together
+/// with the remaining bytes.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Only considers the types of the given protocol.
+    #[arg(long, value_enum, global = true)]
+    protocol: Option<Protocol>,
+    #[command(subcommand)]
+    input: Input,
+}
+
+#[derive(Subcommand, Debug)]
+enum Input {
+    /// Decodes a payload given as hex, e.g. `b034 01 06 fe e8 03 00 00`.
+    Hex {
+        /// The opcode of the packet, in hex.
+        #[arg(value_parser = parse_opcode)]
+        opcode: u16,
+        /// The bytes of the payload. Whitespace between bytes is ignored.
+        hex: Vec<String>,
+    },
+    /// Decodes the payload contained in a file. The file is read as a hex dump, in which
+    /// everything following a `#` is ignored, unless `--raw` is given.
+    File {
+        /// The opcode of the packet, in hex.
+        #[arg(value_parser = parse_opcode)]
+        opcode: u16,
+        file: PathBuf,
+        /// Reads the file as plain bytes instead.
+        #[arg(long)]
+        raw: bool,
+    },
+    /// Decodes the packets contained in a session recording.
+    Recording {
+        recording: PathBuf,
+        /// Only decodes packets with the given opcode.
+        #[arg(long, value_parser = parse_opcode)]
+        opcode: Option<u16>,
+        /// Only decodes packets of the given connection.
+        #[arg(long)]
+        connection: Option<u64>,
+    },
+    /// Lists all known types and their opcodes.
+    List,
+}
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let args = Args::parse();
+    let registry = registry();
+
+    match args.input {
+        Input::Hex { opcode, hex } => {
+            let data = parse_hex(&hex.join(" "))?;
+            dissect(&registry, opcode, &data, args.protocol);
+        },
+        Input::File { opcode, file, raw } => {
+            let data = if raw {
+                std::fs::read(file)?
+            } else {
+                parse_hex(&std::fs::read_to_string(file)?)?
+            };
+            dissect(&registry, opcode, &data, args.protocol);
+        },
+        Input::Recording {
+            recording,
+            opcode,
+            connection,
+        } => {
+            for packet in RecordingReader::open(recording)? {
+                let packet = packet?;
+                if opcode.is_some_and(|opcode| opcode != packet.opcode)
+                    || connection.is_some_and(|connection| connection != packet.connection)
+                {
+                    continue;
+                }
+
+                let direction = match packet.direction {
+                    Direction::Inbound => "client -> server",
+                    Direction::Outbound => "server -> client",
+                };
+                println!(
+                    "=== {:.3}s, connection {}, {direction}",
+                    packet.time.as_secs_f64(),
+                    packet.connection
+                );
+                dissect(&registry, packet.opcode, &packet.data, args.protocol);
+                println!();
+            }
+        },
+        Input::List => {
+            let mut packets = registry.iter().collect::<Vec<_>>();
+            packets.sort_by_key(|packet| packet.opcode);
+            for packet in packets {
+                println!("{:#06x} {:<8} {}", packet.opcode, packet.protocol, packet.name);
+            }
+        },
+    }
+
+    Ok(())
+}
+
+fn dissect(registry: &[PacketType], opcode: u16, data: &[u8], protocol: Option<Protocol>) {
+    let candidates = lookup(registry, opcode, protocol);
+    if candidates.is_empty() {
+        println!("{opcode:#06x}: no known type, {} bytes", data.len());
+        print!("{}", hex_dump(data, 0));
+        return;
+    }
+
+    for candidate in candidates {
+        let header = format!("{opcode:#06x} {} ({})", candidate.name, candidate.protocol);
+        let Some(dissection) = candidate.dissect(data) else {
+            println!("{header}: only supports serialization and cannot be decoded");
+            continue;
+        };
+
+        match dissection.result {
+            Ok(value) => {
+                println!("{header}: read {} of {} bytes", dissection.consumed, data.len());
+                println!("{value}");
+                if dissection.consumed < data.len() {
+                    println!("{} leftover bytes:", data.len() - dissection.consumed);
+                    print!("{}", hex_dump(&data[dissection.consumed..], dissection.consumed));
+                }
+            },
+            Err(e) => {
+                println!(
+                    "{header}: failed at offset {:#x} after reading {} of {} bytes: {e}",
+                    dissection.consumed,
+                    dissection.consumed,
+                    data.len()
+                );
+                print!("{}", hex_dump(&data[..dissection.consumed], 0));
+                println!("--- unread:");
+                print!("{}", hex_dump(&data[dissection.consumed..], dissection.consumed));
+            },
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use clap::ValueEnum;
+use skrillax_packet::Packet;
+use skrillax_serde::{Deserialize, SerializationError};
+use std::fmt::{Debug, Display, Formatter};
+use std::io::Cursor;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub(crate) enum Protocol {
+    Agent,
+    Gateway,
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::Agent => write!(f, "agent"),
+            Protocol::Gateway => write!(f, "gateway"),
+        }
+    }
+}
+
+/// The outcome of reading a payload as a specific type.
+pub(crate) struct Dissection {
+    /// The amount of bytes read, either until the value was complete or until reading failed.
+    pub(crate) consumed: usize,
+    /// The decoded value, formatted using its `Debug` implementation.
+    pub(crate) result: Result<String, SerializationError>,
+}
+
+/// A type that may be contained in a packet with the given opcode.
+pub(crate) struct PacketType {
+    pub(crate) opcode: u16,
+    pub(crate) name: &'static str,
+    pub(crate) protocol: Protocol,
+    decoder: Option<fn(&[u8]) -> Dissection>,
+}
+
+impl PacketType {
+    fn decodable<P: Packet + Deserialize + Debug>(protocol: Protocol) -> Self {
+        PacketType {
+            opcode: P::ID,
+            name: type_name::<P>(),
+            protocol,
+            decoder: Some(decode::<P>),
+        }
+    }
+
+    /// A packet we know of, but which only implements serialization, because its content cannot
+    /// be decoded without additional context.
+    fn known<P: Packet>(protocol: Protocol) -> Self {
+        PacketType {
+            opcode: P::ID,
+            name: type_name::<P>(),
+            protocol,
+            decoder: None,
+        }
+    }
+
+    /// A type which is not (yet) a packet itself, but describes a possible layout of the
+    /// packet with the given opcode.
+    fn layout<T: Deserialize + Debug>(opcode: u16, protocol: Protocol) -> Self {
+        PacketType {
+            opcode,
+            name: type_name::<T>(),
+            protocol,
+            decoder: Some(decode::<T>),
+        }
+    }
+
+    /// Reads the payload as this type, or returns `None` if this type cannot be decoded.
+    pub(crate) fn dissect(&self, data: &[u8]) -> Option<Dissection> {
+        self.decoder.map(|decoder| decoder(data))
+    }
+}
+
+fn type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+fn decode<T: Deserialize + Debug>(data: &[u8]) -> Dissection {
+    let mut cursor = Cursor::new(data);
+    let result = T::read_from(&mut cursor).map(|value| format!("{value:#?}"));
+    Dissection {
+        consumed: cursor.position() as usize,
+        result,
+    }
+}
+
+/// Returns all types that may be contained in a packet with the given opcode.
+pub(crate) fn lookup(registry: &[PacketType], opcode: u16, protocol: Option<Protocol>) -> Vec<&PacketType> {
+    registry
+        .iter()
+        .filter(|packet| packet.opcode == opcode)
+        .filter(|packet| protocol.is_none_or(|protocol| packet.protocol == protocol))
+        .collect()
+}
+
+pub(crate) fn registry() -> Vec<PacketType> {
+    let mut packets = agent_packets();
+    packets.extend(gateway_packets());
+    packets
+}
+
+fn agent_packets() -> Vec<PacketType> {
+    use silkroad_protocol::auth::*;
+    use silkroad_protocol::character::*;
+    use silkroad_protocol::chat::*;
+    use silkroad_protocol::combat::*;
+    use silkroad_protocol::community::*;
+    use silkroad_protocol::consignment::*;
+    use silkroad_protocol::exchange::*;
+    use silkroad_protocol::general::*;
+    use silkroad_protocol::gm::*;
+    use silkroad_protocol::guild::*;
+    use silkroad_protocol::inventory::*;
+    use silkroad_protocol::movement::*;
+    use silkroad_protocol::party::*;
+    use silkroad_protocol::skill::*;
+    use silkroad_protocol::spawn::*;
+    use silkroad_protocol::stall::*;
+    use silkroad_protocol::storage::*;
+    use silkroad_protocol::world::*;
+
+    const AGENT: Protocol = Protocol::Agent;
+
+    vec![
+        PacketType::decodable::<IdentityInformation>(AGENT),
+        PacketType::decodable::<KeepAlive>(AGENT),
+        PacketType::decodable::<SecuritySetup>(AGENT),
+        PacketType::decodable::<HandshakeChallenge>(AGENT),
+        PacketType::decodable::<HandshakeAccepted>(AGENT),
+        PacketType::decodable::<AuthRequest>(AGENT),
+        PacketType::decodable::<AuthResponse>(AGENT),
+        PacketType::decodable::<LogoutRequest>(AGENT),
+        PacketType::decodable::<LogoutResponse>(AGENT),
+        PacketType::decodable::<LogoutFinished>(AGENT),
+        PacketType::decodable::<Disconnect>(AGENT),
+        PacketType::decodable::<UnknownLargePacket>(AGENT),
+        PacketType::layout::<silkroad_gateway_protocol::UnknownLargePacketD>(0x3612, AGENT),
+        PacketType::decodable::<CharacterListRequest>(AGENT),
+        PacketType::decodable::<CharacterListResponse>(AGENT),
+        PacketType::decodable::<CharacterJoinRequest>(AGENT),
+        PacketType::decodable::<CharacterJoinResponse>(AGENT),
+        PacketType::decodable::<CharacterStatsMessage>(AGENT),
+        PacketType::decodable::<UnknownPacket>(AGENT),
+        PacketType::decodable::<UnknownPacket2>(AGENT),
+        PacketType::decodable::<MacroStatus>(AGENT),
+        PacketType::decodable::<FinishLoading>(AGENT),
+        PacketType::decodable::<TextCharacterInitialization>(AGENT),
+        PacketType::decodable::<ChatUpdate>(AGENT),
+        PacketType::decodable::<ChatMessage>(AGENT),
+        PacketType::decodable::<ChatMessageResponse>(AGENT),
+        PacketType::decodable::<PerformAction>(AGENT),
+        PacketType::decodable::<PerformActionResponse>(AGENT),
+        PacketType::decodable::<PerformActionUpdate>(AGENT),
+        PacketType::decodable::<BuffBegin>(AGENT),
+        PacketType::decodable::<BuffEnd>(AGENT),
+        PacketType::known::<ReceiveExperience>(AGENT),
+        PacketType::decodable::<FriendListInfo>(AGENT),
+        PacketType::decodable::<AddFriend>(AGENT),
+        PacketType::decodable::<CreateFriendGroup>(AGENT),
+        PacketType::decodable::<DeleteFriend>(AGENT),
+        PacketType::decodable::<AddFriendResponse>(AGENT),
+        PacketType::decodable::<FriendRequest>(AGENT),
+        PacketType::decodable::<FriendRequestAnswer>(AGENT),
+        PacketType::decodable::<DeleteFriendResponse>(AGENT),
+        PacketType::decodable::<FriendListUpdate>(AGENT),
+        PacketType::decodable::<CreateFriendGroupResponse>(AGENT),
+        PacketType::decodable::<DeleteFriendGroup>(AGENT),
+        PacketType::decodable::<DeleteFriendGroupResponse>(AGENT),
+        PacketType::decodable::<MoveFriendToGroup>(AGENT),
+        PacketType::decodable::<MoveFriendToGroupResponse>(AGENT),
+        PacketType::decodable::<GmCommand>(AGENT),
+        PacketType::decodable::<GmResponse>(AGENT),
+        PacketType::decodable::<InventoryOperation>(AGENT),
+        PacketType::known::<InventoryOperationResult>(AGENT),
+        PacketType::decodable::<ConsignmentRegister>(AGENT),
+        PacketType::decodable::<ConsignmentRegisterResponse>(AGENT),
+        PacketType::decodable::<ConsignmentCancel>(AGENT),
+        PacketType::decodable::<ConsignmentCancelResponse>(AGENT),
+        PacketType::decodable::<ConsignmentSettle>(AGENT),
+        PacketType::decodable::<ConsignmentSettleResponse>(AGENT),
+        PacketType::decodable::<ConsignmentSearch>(AGENT),
+        PacketType::known::<ConsignmentSearchResponse>(AGENT),
+        PacketType::decodable::<ConsignmentBuy>(AGENT),
+        PacketType::decodable::<ConsignmentBuyResponse>(AGENT),
+        PacketType::decodable::<ConsignmentList>(AGENT),
+        PacketType::decodable::<ConsignmentResponse>(AGENT),
+        PacketType::decodable::<OpenItemMall>(AGENT),
+        PacketType::decodable::<OpenItemMallResponse>(AGENT),
+        PacketType::decodable::<PlayerMovementRequest>(AGENT),
+        PacketType::decodable::<PlayerMovementResponse>(AGENT),
+        PacketType::decodable::<EntityMovementInterrupt>(AGENT),
+        PacketType::decodable::<Rotation>(AGENT),
+        PacketType::decodable::<ChangeSpeed>(AGENT),
+        PacketType::decodable::<LevelUpMastery>(AGENT),
+        PacketType::decodable::<LevelUpMasteryResponse>(AGENT),
+        PacketType::decodable::<LearnSkill>(AGENT),
+        PacketType::decodable::<LearnSkillResponse>(AGENT),
+        PacketType::decodable::<HotbarUpdate>(AGENT),
+        PacketType::decodable::<PartyCreateRequest>(AGENT),
+        PacketType::decodable::<PartyCreateResponse>(AGENT),
+        PacketType::decodable::<PartyLeaveRequest>(AGENT),
+        PacketType::decodable::<PartyLeaveResponse>(AGENT),
+        PacketType::decodable::<PartyInviteRequest>(AGENT),
+        PacketType::decodable::<PartyInviteResponse>(AGENT),
+        PacketType::decodable::<PartyKickRequest>(AGENT),
+        PacketType::decodable::<PartyKickResponse>(AGENT),
+        PacketType::decodable::<PartyLeaderTransferRequest>(AGENT),
+        PacketType::decodable::<PartyLeaderTransferResponse>(AGENT),
+        PacketType::decodable::<InvitationRequest>(AGENT),
+        PacketType::decodable::<InvitationResponse>(AGENT),
+        PacketType::decodable::<PartyInfo>(AGENT),
+        PacketType::decodable::<PartyUpdate>(AGENT),
+        PacketType::decodable::<GuildCreateRequest>(AGENT),
+        PacketType::decodable::<GuildCreateResponse>(AGENT),
+        PacketType::decodable::<GuildDisbandRequest>(AGENT),
+        PacketType::decodable::<GuildDisbandResponse>(AGENT),
+        PacketType::decodable::<GuildLeaveRequest>(AGENT),
+        PacketType::decodable::<GuildLeaveResponse>(AGENT),
+        PacketType::decodable::<GuildInviteRequest>(AGENT),
+        PacketType::decodable::<GuildInviteResponse>(AGENT),
+        PacketType::decodable::<GuildKickRequest>(AGENT),
+        PacketType::decodable::<GuildKickResponse>(AGENT),
+        PacketType::decodable::<GuildNoticeRequest>(AGENT),
+        PacketType::decodable::<GuildNoticeResponse>(AGENT),
+        PacketType::decodable::<GuildAuthorityRequest>(AGENT),
+        PacketType::decodable::<GuildAuthorityResponse>(AGENT),
+        PacketType::decodable::<GuildInfo>(AGENT),
+        PacketType::decodable::<GuildUpdate>(AGENT),
+        PacketType::decodable::<EntityGuildUpdate>(AGENT),
+        PacketType::decodable::<ExchangeStartRequest>(AGENT),
+        PacketType::decodable::<ExchangeStartResponse>(AGENT),
+        PacketType::decodable::<ExchangeStarted>(AGENT),
+        PacketType::decodable::<ExchangeConfirmRequest>(AGENT),
+        PacketType::decodable::<ExchangeConfirmResponse>(AGENT),
+        PacketType::decodable::<ExchangePartnerConfirmed>(AGENT),
+        PacketType::decodable::<ExchangeApproveRequest>(AGENT),
+        PacketType::decodable::<ExchangeApproveResponse>(AGENT),
+        PacketType::decodable::<ExchangeCompleted>(AGENT),
+        PacketType::decodable::<ExchangeCancelRequest>(AGENT),
+        PacketType::decodable::<ExchangeCancelResponse>(AGENT),
+        PacketType::decodable::<ExchangeCancelled>(AGENT),
+        PacketType::known::<ExchangeItemsUpdate>(AGENT),
+        PacketType::decodable::<ExchangeGoldUpdate>(AGENT),
+        PacketType::decodable::<StorageOpenRequest>(AGENT),
+        PacketType::decodable::<StorageOpenResponse>(AGENT),
+        PacketType::decodable::<StorageGold>(AGENT),
+        PacketType::decodable::<StorageItemsEnd>(AGENT),
+        PacketType::known::<StorageItems>(AGENT),
+        PacketType::decodable::<StallCreateRequest>(AGENT),
+        PacketType::decodable::<StallCreateResponse>(AGENT),
+        PacketType::decodable::<StallCloseRequest>(AGENT),
+        PacketType::decodable::<StallCloseResponse>(AGENT),
+        PacketType::decodable::<StallEnterRequest>(AGENT),
+        PacketType::known::<StallEnterResponse>(AGENT),
+        PacketType::decodable::<StallBuyRequest>(AGENT),
+        PacketType::decodable::<StallBuyResponse>(AGENT),
+        PacketType::decodable::<StallLeaveRequest>(AGENT),
+        PacketType::decodable::<StallLeaveResponse>(AGENT),
+        PacketType::decodable::<StallUpdateRequest>(AGENT),
+        PacketType::decodable::<StallUpdateResponse>(AGENT),
+        PacketType::known::<StallAction>(AGENT),
+        PacketType::decodable::<StallEntityOpened>(AGENT),
+        PacketType::decodable::<StallEntityClosed>(AGENT),
+        PacketType::decodable::<StallEntityTitle>(AGENT),
+        PacketType::decodable::<CharacterSpawnStart>(AGENT),
+        PacketType::known::<CharacterSpawn>(AGENT),
+        PacketType::decodable::<CharacterSpawnEnd>(AGENT),
+        PacketType::decodable::<EntityDespawn>(AGENT),
+        PacketType::known::<EntitySpawn>(AGENT),
+        PacketType::decodable::<GroupEntitySpawnStart>(AGENT),
+        PacketType::known::<GroupEntitySpawnData>(AGENT),
+        PacketType::decodable::<GroupEntitySpawnEnd>(AGENT),
+        PacketType::decodable::<CelestialUpdate>(AGENT),
+        PacketType::decodable::<LunarEventInfo>(AGENT),
+        PacketType::decodable::<CharacterFinished>(AGENT),
+        PacketType::decodable::<WeatherUpdate>(AGENT),
+        PacketType::decodable::<GameNotification>(AGENT),
+        PacketType::decodable::<EntityUpdateState>(AGENT),
+        PacketType::decodable::<TargetEntity>(AGENT),
+        PacketType::known::<TargetEntityResponse>(AGENT),
+        PacketType::decodable::<UnTargetEntity>(AGENT),
+        PacketType::decodable::<UnTargetEntityResponse>(AGENT),
+        PacketType::decodable::<EntityBarsUpdate>(AGENT),
+        PacketType::decodable::<CharacterPointsUpdate>(AGENT),
+        PacketType::decodable::<PlayerPickupAnimation>(AGENT),
+        PacketType::decodable::<LevelUpEffect>(AGENT),
+        PacketType::decodable::<UpdateGameGuide>(AGENT),
+        PacketType::decodable::<GameGuideResponse>(AGENT),
+        PacketType::decodable::<IncreaseStr>(AGENT),
+        PacketType::decodable::<IncreaseStrResponse>(AGENT),
+        PacketType::decodable::<IncreaseInt>(AGENT),
+        PacketType::decodable::<IncreaseIntResponse>(AGENT),
+    ]
+}
+
+fn gateway_packets() -> Vec<PacketType> {
+    use silkroad_gateway_protocol::*;
+
+    const GATEWAY: Protocol = Protocol::Gateway;
+
+    vec![
+        PacketType::decodable::<IdentityInformation>(GATEWAY),
+        PacketType::decodable::<KeepAlive>(GATEWAY),
+        PacketType::decodable::<SecuritySetup>(GATEWAY),
+        PacketType::decodable::<HandshakeChallenge>(GATEWAY),
+        PacketType::decodable::<HandshakeAccepted>(GATEWAY),
+        PacketType::decodable::<PatchRequest>(GATEWAY),
+        PacketType::decodable::<PatchResponse>(GATEWAY),
+        PacketType::decodable::<LoginRequest>(GATEWAY),
+        PacketType::decodable::<LoginResponse>(GATEWAY),
+        PacketType::decodable::<SecurityCodeInput>(GATEWAY),
+        PacketType::decodable::<SecurityCodeResponse>(GATEWAY),
+        PacketType::decodable::<PasscodeResponse>(GATEWAY),
+        PacketType::decodable::<PasscodeRequiredResponse>(GATEWAY),
+        PacketType::decodable::<GatewayNoticeRequest>(GATEWAY),
+        PacketType::decodable::<GatewayNoticeResponse>(GATEWAY),
+        PacketType::decodable::<PingServerRequest>(GATEWAY),
+        PacketType::decodable::<PingServerResponse>(GATEWAY),
+        PacketType::decodable::<ShardListRequest>(GATEWAY),
+        PacketType::decodable::<ShardListResponse>(GATEWAY),
+        PacketType::known::<QueueUpdate>(GATEWAY),
+        PacketType::decodable::<TempCharacterData>(GATEWAY),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use silkroad_protocol::auth::UnknownLargePacket;
+    use skrillax_serde::Serialize;
+
+    #[test]
+    fn test_unknown_large_packet_layout() {
+        let registry = registry();
+        let data = UnknownLargePacket::new().to_bytes();
+        let candidates = lookup(&registry, 0x3612, Some(Protocol::Agent));
+        assert_eq!(candidates.len(), 2);
+        for candidate in candidates {
+            let dissection = candidate.dissect(&data).unwrap();
+            assert!(dissection.result.is_ok(), "{} could not be decoded", candidate.name);
+            assert_eq!(dissection.consumed, data.len(), "{} left bytes unread", candidate.name);
+        }
+    }
+}
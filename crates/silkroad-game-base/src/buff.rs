@@ -0,0 +1,356 @@
+use crate::{AttackPower, AttackStats, DefenseStats};
+use silkroad_data::skilldata::{RefSkillData, SkillParam, TargetOption};
+use std::iter::Sum;
+use std::ops::Add;
+use std::time::Duration;
+
+/// The changes a buff makes to the stats of an entity while it is active.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct BuffModifiers {
+    pub phys_defense: f32,
+    pub mag_defense: f32,
+    pub parry_rate: f32,
+    /// The added chance to block an attack, in percent.
+    pub block_ratio: f32,
+    pub hit_rate: f32,
+    /// The added chance to land a critical hit, in percent.
+    pub critical_rate: f32,
+    /// The increase of physical damage, in percent.
+    pub phys_damage: f32,
+    /// The increase of magical damage, in percent.
+    pub mag_damage: f32,
+    pub max_hp: u32,
+    pub max_hp_percent: u32,
+    pub max_mp: u32,
+    pub max_mp_percent: u32,
+}
+
+impl BuffModifiers {
+    pub fn from_skill(skill: &RefSkillData) -> Self {
+        let mut modifiers = BuffModifiers::default();
+        for param in skill.params.iter() {
+            match param {
+                SkillParam::IncreaseDefense { phys, mag, .. } => {
+                    modifiers.phys_defense += *phys as f32;
+                    modifiers.mag_defense += *mag as f32;
+                },
+                SkillParam::BlockRatio { percent, .. } => modifiers.block_ratio += f32::from(*percent),
+                SkillParam::IncreaseHP { absolute, percent } => {
+                    modifiers.max_hp += *absolute;
+                    modifiers.max_hp_percent += u32::from(*percent);
+                },
+                SkillParam::IncreaseMP { absolute, percent } => {
+                    modifiers.max_mp += *absolute;
+                    modifiers.max_mp_percent += u32::from(*percent);
+                },
+                SkillParam::IncreaseCrit { amount, .. } => modifiers.critical_rate += f32::from(*amount),
+                SkillParam::IncreaseHitRate { hit_rate, .. } => modifiers.hit_rate += f32::from(*hit_rate),
+                SkillParam::IncreaseEvasion { parry, .. } => modifiers.parry_rate += f32::from(*parry),
+                SkillParam::IncreaseDamage { phys, mag } => {
+                    modifiers.phys_damage += f32::from(*phys);
+                    modifiers.mag_damage += f32::from(*mag);
+                },
+                _ => {},
+            }
+        }
+        modifiers
+    }
+
+    pub fn apply_to_attack(&self, attack: &mut AttackStats) {
+        scale(&mut attack.physical, self.phys_damage);
+        scale(&mut attack.magical, self.mag_damage);
+        attack.hit_rate += self.hit_rate;
+        attack.critical_rate += self.critical_rate;
+    }
+
+    pub fn apply_to_defense(&self, defense: &mut DefenseStats) {
+        defense.physical += self.phys_defense;
+        defense.magical += self.mag_defense;
+        defense.parry_rate += self.parry_rate;
+        defense.block_ratio += self.block_ratio;
+    }
+
+    /// Calculates the maximum health given the maximum health without any buffs. Percentage
+    /// increases only apply to the base value, not to the absolute increases.
+    pub fn max_health(&self, base: u32) -> u32 {
+        base + base * self.max_hp_percent / 100 + self.max_hp
+    }
+
+    /// Calculates the maximum mana given the maximum mana without any buffs, like [Self::max_health].
+    pub fn max_mana(&self, base: u32) -> u32 {
+        base + base * self.max_mp_percent / 100 + self.max_mp
+    }
+}
+
+fn scale(power: &mut AttackPower, percent: f32) {
+    let factor = 1.0 + percent / 100.0;
+    power.min *= factor;
+    power.max *= factor;
+}
+
+impl Add for BuffModifiers {
+    type Output = BuffModifiers;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        BuffModifiers {
+            phys_defense: self.phys_defense + rhs.phys_defense,
+            mag_defense: self.mag_defense + rhs.mag_defense,
+            parry_rate: self.parry_rate + rhs.parry_rate,
+            block_ratio: self.block_ratio + rhs.block_ratio,
+            hit_rate: self.hit_rate + rhs.hit_rate,
+            critical_rate: self.critical_rate + rhs.critical_rate,
+            phys_damage: self.phys_damage + rhs.phys_damage,
+            mag_damage: self.mag_damage + rhs.mag_damage,
+            max_hp: self.max_hp + rhs.max_hp,
+            max_hp_percent: self.max_hp_percent + rhs.max_hp_percent,
+            max_mp: self.max_mp + rhs.max_mp,
+            max_mp_percent: self.max_mp_percent + rhs.max_mp_percent,
+        }
+    }
+}
+
+impl Sum for BuffModifiers {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(BuffModifiers::default(), Add::add)
+    }
+}
+
+/// The duration of the buff a skill creates, as defined by [SkillParam::Duration]. Skills
+/// without a duration don't create a buff.
+pub fn buff_duration(skill: &RefSkillData) -> Option<Duration> {
+    skill.params.iter().find_map(|param| match param {
+        SkillParam::Duration(duration) if *duration > 0 => Some(Duration::from_millis(u64::from(*duration))),
+        _ => None,
+    })
+}
+
+/// How the target of a skill relates to the caster of the skill.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TargetRelation {
+    Caster,
+    Ally,
+    EnemyMonster,
+    EnemyPlayer,
+}
+
+impl TargetRelation {
+    pub fn between(is_caster: bool, caster_is_monster: bool, target_is_monster: bool) -> Self {
+        match (is_caster, caster_is_monster, target_is_monster) {
+            (true, _, _) => TargetRelation::Caster,
+            (false, caster, target) if caster == target => TargetRelation::Ally,
+            (false, _, true) => TargetRelation::EnemyMonster,
+            (false, _, false) => TargetRelation::EnemyPlayer,
+        }
+    }
+}
+
+/// Checks whether a skill with the given target options may be used on a target. Buffs target
+/// the caster or allies, while debuffs target enemies. Skills without any target options can
+/// only be used on the caster.
+pub fn can_target(options: &TargetOption, relation: TargetRelation) -> bool {
+    if options.contains(TargetOption::ANY) {
+        return true;
+    }
+
+    match relation {
+        TargetRelation::Caster => options.is_empty() || options.contains(TargetOption::SELF),
+        TargetRelation::Ally => options.intersects(TargetOption::ALLY | TargetOption::PARTY),
+        TargetRelation::EnemyMonster => options.contains(TargetOption::ENEMY_MONSTER),
+        TargetRelation::EnemyPlayer => options.contains(TargetOption::ENEMY_PLAYER),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Buff {
+    pub skill: u32,
+    pub group: u32,
+    /// The bitflags of other buffs this buff cannot coexist with, as defined by
+    /// [RefSkillData::buff_interference].
+    pub interference: u32,
+    /// The instance of this buff, which is how the client refers to it.
+    pub token: u32,
+    pub remaining: Duration,
+    pub modifiers: BuffModifiers,
+}
+
+impl Buff {
+    pub fn new(skill: &RefSkillData, token: u32, duration: Duration) -> Self {
+        Buff {
+            skill: skill.ref_id,
+            group: skill.group,
+            interference: skill.buff_interference,
+            token,
+            remaining: duration,
+            modifiers: BuffModifiers::from_skill(skill),
+        }
+    }
+
+    /// Checks if this buff replaces the given buff when applied, which is the case if they
+    /// belong to the same skill group or interfere with each other.
+    fn overrides(&self, other: &Buff) -> bool {
+        (self.group != 0 && self.group == other.group) || (self.interference & other.interference) != 0
+    }
+}
+
+/// The buffs which are currently active on an entity. Buffs of different skill groups stack,
+/// while a buff replaces any active buff of the same group.
+#[derive(Clone, Default, Debug)]
+pub struct BuffList {
+    buffs: Vec<Buff>,
+}
+
+impl BuffList {
+    /// Adds the buff, returning all buffs it has replaced.
+    pub fn add(&mut self, buff: Buff) -> Vec<Buff> {
+        let (replaced, remaining): (Vec<Buff>, Vec<Buff>) =
+            self.buffs.drain(..).partition(|active| buff.overrides(active));
+        self.buffs = remaining;
+        self.buffs.push(buff);
+        replaced
+    }
+
+    pub fn remove(&mut self, token: u32) -> Option<Buff> {
+        let index = self.buffs.iter().position(|buff| buff.token == token)?;
+        Some(self.buffs.remove(index))
+    }
+
+    pub fn remove_skill(&mut self, skill: u32) -> Option<Buff> {
+        let index = self.buffs.iter().position(|buff| buff.skill == skill)?;
+        Some(self.buffs.remove(index))
+    }
+
+    /// Advances the remaining time of all buffs, returning those which have expired.
+    pub fn tick(&mut self, delta: Duration) -> Vec<Buff> {
+        let (expired, active): (Vec<Buff>, Vec<Buff>) = self
+            .buffs
+            .drain(..)
+            .map(|buff| Buff {
+                remaining: buff.remaining.saturating_sub(delta),
+                ..buff
+            })
+            .partition(|buff| buff.remaining.is_zero());
+        self.buffs = active;
+        expired
+    }
+
+    pub fn clear(&mut self) -> Vec<Buff> {
+        self.buffs.drain(..).collect()
+    }
+
+    pub fn modifiers(&self) -> BuffModifiers {
+        self.buffs.iter().map(|buff| buff.modifiers).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Buff> {
+        self.buffs.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn buff(skill: u32, group: u32, interference: u32, token: u32) -> Buff {
+        Buff {
+            skill,
+            group,
+            interference,
+            token,
+            remaining: Duration::from_secs(10),
+            modifiers: BuffModifiers {
+                phys_defense: 10.0,
+                max_hp_percent: 10,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_stacking() {
+        let mut buffs = BuffList::default();
+        assert!(buffs.add(buff(1, 1, 0, 1)).is_empty());
+        assert!(buffs.add(buff(2, 2, 0, 2)).is_empty());
+        assert_eq!(20.0, buffs.modifiers().phys_defense);
+
+        let replaced = buffs.add(buff(3, 1, 0, 3));
+        assert_eq!(1, replaced.len());
+        assert_eq!(1, replaced[0].token);
+        assert_eq!(2, buffs.iter().count());
+
+        let replaced = buffs.add(buff(4, 4, 0b10, 4));
+        assert!(replaced.is_empty());
+        let replaced = buffs.add(buff(5, 5, 0b11, 5));
+        assert_eq!(4, replaced[0].token);
+        assert_eq!(3, buffs.iter().count());
+    }
+
+    #[test]
+    fn test_expiry_and_removal() {
+        let mut buffs = BuffList::default();
+        buffs.add(buff(1, 1, 0, 1));
+        buffs.add(Buff {
+            remaining: Duration::from_secs(5),
+            ..buff(2, 2, 0, 2)
+        });
+
+        assert!(buffs.tick(Duration::from_secs(4)).is_empty());
+        let expired = buffs.tick(Duration::from_secs(1));
+        assert_eq!(1, expired.len());
+        assert_eq!(2, expired[0].token);
+
+        assert_eq!(None, buffs.remove_skill(2));
+        assert_eq!(Some(1), buffs.remove_skill(1).map(|buff| buff.token));
+        assert!(buffs.is_empty());
+    }
+
+    #[test]
+    fn test_target_options() {
+        assert_eq!(TargetRelation::Caster, TargetRelation::between(true, false, false));
+        assert_eq!(TargetRelation::Ally, TargetRelation::between(false, true, true));
+        assert_eq!(
+            TargetRelation::EnemyMonster,
+            TargetRelation::between(false, false, true)
+        );
+        assert_eq!(TargetRelation::EnemyPlayer, TargetRelation::between(false, true, false));
+
+        assert!(can_target(&TargetOption::NONE, TargetRelation::Caster));
+        assert!(!can_target(&TargetOption::NONE, TargetRelation::Ally));
+
+        let buff = TargetOption::SELF | TargetOption::ALLY;
+        assert!(can_target(&buff, TargetRelation::Ally));
+        assert!(!can_target(&buff, TargetRelation::EnemyMonster));
+
+        let debuff = TargetOption::ENEMY_MONSTER | TargetOption::ENEMY_PLAYER;
+        assert!(can_target(&debuff, TargetRelation::EnemyMonster));
+        assert!(can_target(&debuff, TargetRelation::EnemyPlayer));
+        assert!(!can_target(&debuff, TargetRelation::Caster));
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let modifiers = BuffModifiers {
+            max_hp: 50,
+            max_hp_percent: 10,
+            phys_damage: 50.0,
+            block_ratio: 10.0,
+            ..Default::default()
+        };
+        assert_eq!(150, modifiers.max_health(100));
+        assert_eq!(100, modifiers.max_mana(100));
+
+        let mut attack = AttackStats {
+            physical: AttackPower::new(100.0, 200.0),
+            ..Default::default()
+        };
+        modifiers.apply_to_attack(&mut attack);
+        assert_eq!(AttackPower::new(150.0, 300.0), attack.physical);
+
+        let mut defense = DefenseStats::default();
+        modifiers.apply_to_defense(&mut defense);
+        assert_eq!(10.0, defense.block_ratio);
+    }
+}
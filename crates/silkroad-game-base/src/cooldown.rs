@@ -0,0 +1,136 @@
+use silkroad_data::skilldata::RefSkillData;
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SkillCooldown {
+    pub skill: u32,
+    /// The skill group the cooldown applies to. All levels of a skill share the same group and
+    /// therefore also the same cooldown.
+    pub group: u32,
+    pub remaining: Duration,
+}
+
+/// Keeps track of the skills an entity cannot use right now. Apart from the cooldown of each
+/// skill group, using any skill prevents using another one until the global cooldown of that
+/// skill has passed.
+#[derive(Clone, Default, Debug)]
+pub struct SkillCooldowns {
+    cooldowns: Vec<SkillCooldown>,
+    global: Duration,
+}
+
+impl SkillCooldowns {
+    pub fn is_ready(&self, skill: u32, group: u32) -> bool {
+        self.global.is_zero()
+            && !self
+                .cooldowns
+                .iter()
+                .any(|cooldown| cooldown.skill == skill || (group != 0 && cooldown.group == group))
+    }
+
+    pub fn is_ready_for(&self, skill: &RefSkillData) -> bool {
+        self.is_ready(skill.ref_id, skill.group)
+    }
+
+    /// Starts the cooldown of the given skill as well as the global cooldown. A new cooldown
+    /// replaces any existing cooldown of the same skill group.
+    pub fn start(&mut self, skill: u32, group: u32, cooldown: Duration, global: Duration) {
+        self.global = self.global.max(global);
+        if cooldown.is_zero() {
+            return;
+        }
+
+        self.cooldowns
+            .retain(|existing| existing.skill != skill && (group == 0 || existing.group != group));
+        self.cooldowns.push(SkillCooldown {
+            skill,
+            group,
+            remaining: cooldown,
+        });
+    }
+
+    pub fn start_for(&mut self, skill: &RefSkillData) {
+        self.start(
+            skill.ref_id,
+            skill.group,
+            Duration::from_millis(u64::from(skill.timings.cooldown)),
+            Duration::from_millis(u64::from(skill.timings.next_delay)),
+        );
+    }
+
+    pub fn tick(&mut self, delta: Duration) {
+        self.global = self.global.saturating_sub(delta);
+        for cooldown in self.cooldowns.iter_mut() {
+            cooldown.remaining = cooldown.remaining.saturating_sub(delta);
+        }
+        self.cooldowns.retain(|cooldown| !cooldown.remaining.is_zero());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SkillCooldown> {
+        self.cooldowns.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.global.is_zero() && self.cooldowns.is_empty()
+    }
+
+    /// The time until all cooldowns, including the global cooldown, have passed.
+    pub fn longest_remaining(&self) -> Duration {
+        self.cooldowns
+            .iter()
+            .map(|cooldown| cooldown.remaining)
+            .fold(self.global, Duration::max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_group_cooldown() {
+        let mut cooldowns = SkillCooldowns::default();
+        cooldowns.start(1, 10, Duration::from_secs(5), Duration::ZERO);
+        assert!(!cooldowns.is_ready(1, 10));
+        assert!(!cooldowns.is_ready(2, 10));
+        assert!(cooldowns.is_ready(3, 20));
+
+        cooldowns.tick(Duration::from_secs(4));
+        assert!(!cooldowns.is_ready(2, 10));
+        cooldowns.tick(Duration::from_secs(1));
+        assert!(cooldowns.is_ready(2, 10));
+        assert!(cooldowns.is_empty());
+    }
+
+    #[test]
+    fn test_global_cooldown() {
+        let mut cooldowns = SkillCooldowns::default();
+        cooldowns.start(1, 10, Duration::ZERO, Duration::from_millis(500));
+        assert!(!cooldowns.is_ready(3, 20));
+        assert_eq!(0, cooldowns.iter().count());
+
+        cooldowns.tick(Duration::from_millis(500));
+        assert!(cooldowns.is_ready(1, 10));
+    }
+
+    #[test]
+    fn test_restart_replaces_group() {
+        let mut cooldowns = SkillCooldowns::default();
+        cooldowns.start(1, 10, Duration::from_secs(5), Duration::ZERO);
+        cooldowns.start(2, 10, Duration::from_secs(2), Duration::ZERO);
+        assert_eq!(1, cooldowns.iter().count());
+
+        cooldowns.tick(Duration::from_secs(2));
+        assert!(cooldowns.is_ready(1, 10));
+    }
+
+    #[test]
+    fn test_longest_remaining() {
+        let mut cooldowns = SkillCooldowns::default();
+        assert_eq!(Duration::ZERO, cooldowns.longest_remaining());
+
+        cooldowns.start(1, 10, Duration::from_secs(2), Duration::from_secs(1));
+        cooldowns.start(2, 20, Duration::from_secs(5), Duration::ZERO);
+        assert_eq!(Duration::from_secs(5), cooldowns.longest_remaining());
+    }
+}
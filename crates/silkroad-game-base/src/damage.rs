@@ -0,0 +1,588 @@
+use crate::{Item, Stats};
+use rand::Rng;
+use silkroad_data::characterdata::RefCharacterData;
+use silkroad_data::itemdata::RefItemStat;
+use silkroad_data::skilldata::{RefSkillData, SkillParam};
+use silkroad_definitions::type_id::{ObjectEquippable, ObjectItem, ObjectType};
+
+/// Attack power gained per point of strength or intelligence respectively.
+const ATTACK_PER_STAT: f32 = 0.5;
+/// Defense gained per point of strength or intelligence respectively.
+const DEFENSE_PER_STAT: f32 = 0.3;
+/// Hit and parry rate every character has, on top of its level.
+const BASE_RATE: f32 = 10.0;
+/// The chance to parry an attack if the parry rate of the defender completely outweighs the
+/// hit rate of the attacker.
+const MAX_PARRY_CHANCE: f32 = 0.3;
+const CRITICAL_MULTIPLIER: f32 = 2.0;
+/// Damage lost for each level the attacker is below the defender.
+const LOWER_LEVEL_PENALTY: f32 = 0.05;
+/// Damage gained for each level the attacker is above the defender.
+const HIGHER_LEVEL_BONUS: f32 = 0.01;
+const MIN_LEVEL_FACTOR: f32 = 0.1;
+const MAX_LEVEL_FACTOR: f32 = 1.2;
+/// The variance of an item holds a roll for each of its values, using this many bits each.
+const VARIANCE_BITS: u32 = 5;
+const MAX_VARIANCE: u64 = (1 << VARIANCE_BITS) - 1;
+
+/// The values of an item which may be affected by its variance.
+#[derive(Copy, Clone)]
+enum ItemValue {
+    PhysicalDefense,
+    MagicalDefense,
+    ParryRate,
+    BlockRatio,
+    PhysicalAttack,
+    MagicalAttack,
+    HitRate,
+    CriticalRate,
+}
+
+/// The position of the roll for the value inside the variance of the item. The first values are
+/// durability and the reinforcements, followed by the values depending on the kind of item.
+fn variance_position(item: &Item, value: ItemValue) -> Option<u32> {
+    let ObjectType::Item(ObjectItem::Equippable(equippable)) =
+        ObjectType::from_type_id(&item.reference.common.type_id)?
+    else {
+        return None;
+    };
+    match (equippable, value) {
+        (ObjectEquippable::Weapon(_), ItemValue::HitRate) => Some(3),
+        (ObjectEquippable::Weapon(_), ItemValue::PhysicalAttack) => Some(4),
+        (ObjectEquippable::Weapon(_), ItemValue::MagicalAttack) => Some(5),
+        (ObjectEquippable::Weapon(_), ItemValue::CriticalRate) => Some(6),
+        (ObjectEquippable::Clothing(..), ItemValue::PhysicalDefense) => Some(3),
+        (ObjectEquippable::Clothing(..), ItemValue::MagicalDefense) => Some(4),
+        (ObjectEquippable::Clothing(..), ItemValue::ParryRate) => Some(5),
+        (ObjectEquippable::Shield(_), ItemValue::BlockRatio) => Some(3),
+        (ObjectEquippable::Shield(_), ItemValue::PhysicalDefense) => Some(4),
+        (ObjectEquippable::Shield(_), ItemValue::MagicalDefense) => Some(5),
+        _ => None,
+    }
+}
+
+/// The actual value of the item, based on where its variance puts it between the bounds and its
+/// upgrade level. Items without a variance have the lowest possible value.
+fn item_value(item: &Item, stat: RefItemStat, value: ItemValue) -> f32 {
+    let roll = match (item.variance, variance_position(item, value)) {
+        (Some(variance), Some(position)) => {
+            ((variance >> (position * VARIANCE_BITS)) & MAX_VARIANCE) as f32 / MAX_VARIANCE as f32
+        },
+        _ => 0.0,
+    };
+    stat.lower + (stat.upper - stat.lower) * roll + stat.per_upgrade * f32::from(item.upgrade_level())
+}
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct AttackPower {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl AttackPower {
+    pub fn new(min: f32, max: f32) -> Self {
+        AttackPower { min, max }
+    }
+
+    fn at(&self, roll: f32) -> f32 {
+        self.min + (self.max - self.min) * roll
+    }
+
+    fn add(&mut self, amount: f32) {
+        self.min += amount;
+        self.max += amount;
+    }
+}
+
+/// The values of the attacking entity relevant for calculating the damage of an attack.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct AttackStats {
+    pub level: u8,
+    pub physical: AttackPower,
+    pub magical: AttackPower,
+    pub hit_rate: f32,
+    /// The chance to land a critical hit, in percent.
+    pub critical_rate: f32,
+}
+
+impl AttackStats {
+    pub fn for_character(level: u8, stats: Stats, weapon: Option<&Item>) -> Self {
+        let mut attack = AttackStats {
+            level,
+            physical: AttackPower::default(),
+            magical: AttackPower::default(),
+            hit_rate: BASE_RATE + f32::from(level),
+            critical_rate: 0.0,
+        };
+        if let Some(weapon) = weapon {
+            let stats = weapon.reference.stats;
+            attack.physical = AttackPower::new(
+                item_value(weapon, stats.phys_attack_min, ItemValue::PhysicalAttack),
+                item_value(weapon, stats.phys_attack_max, ItemValue::PhysicalAttack),
+            );
+            attack.magical = AttackPower::new(
+                item_value(weapon, stats.mag_attack_min, ItemValue::MagicalAttack),
+                item_value(weapon, stats.mag_attack_max, ItemValue::MagicalAttack),
+            );
+            attack.hit_rate += item_value(weapon, stats.hit_rate, ItemValue::HitRate);
+            attack.critical_rate = item_value(weapon, stats.critical_rate, ItemValue::CriticalRate);
+        }
+        attack.physical.add(f32::from(stats.strength()) * ATTACK_PER_STAT);
+        attack.magical.add(f32::from(stats.intelligence()) * ATTACK_PER_STAT);
+        attack
+    }
+
+    /// Monsters don't have any attack power on their own, their damage is defined by their skills.
+    pub fn for_monster(character: &RefCharacterData) -> Self {
+        AttackStats {
+            level: character.level,
+            physical: AttackPower::default(),
+            magical: AttackPower::default(),
+            hit_rate: character.hit_rate,
+            critical_rate: character.critical_rate,
+        }
+    }
+}
+
+/// The values of the attacked entity relevant for calculating the damage of an attack.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DefenseStats {
+    pub level: u8,
+    pub physical: f32,
+    pub magical: f32,
+    pub parry_rate: f32,
+    /// The chance to block an attack, in percent.
+    pub block_ratio: f32,
+    pub knocked_down: bool,
+}
+
+impl DefenseStats {
+    pub fn for_character<'a>(level: u8, stats: Stats, equipment: impl IntoIterator<Item = &'a Item>) -> Self {
+        let mut defense = DefenseStats {
+            level,
+            physical: f32::from(stats.strength()) * DEFENSE_PER_STAT,
+            magical: f32::from(stats.intelligence()) * DEFENSE_PER_STAT,
+            parry_rate: BASE_RATE + f32::from(level),
+            block_ratio: 0.0,
+            knocked_down: false,
+        };
+        for item in equipment {
+            let stats = item.reference.stats;
+            defense.physical += item_value(item, stats.phys_defense, ItemValue::PhysicalDefense);
+            defense.magical += item_value(item, stats.mag_defense, ItemValue::MagicalDefense);
+            defense.parry_rate += item_value(item, stats.parry_rate, ItemValue::ParryRate);
+            defense.block_ratio += item_value(item, stats.block_ratio, ItemValue::BlockRatio);
+        }
+        defense
+    }
+
+    pub fn for_monster(character: &RefCharacterData) -> Self {
+        DefenseStats {
+            level: character.level,
+            physical: character.phys_defense,
+            magical: character.mag_defense,
+            parry_rate: character.parry_rate,
+            block_ratio: character.block_ratio,
+            knocked_down: false,
+        }
+    }
+}
+
+/// The attack part of a skill, as defined by [SkillParam::Attack].
+#[derive(Copy, Clone, Debug)]
+pub struct SkillAttack {
+    /// How much of the physical attack power is applied, in percent.
+    pub physical_percent: u32,
+    /// How much of the magical attack power is applied, in percent.
+    pub magical_percent: u32,
+    /// The attack power the skill adds on its own.
+    pub power: AttackPower,
+    /// The additional damage, in percent, dealt to targets which have been knocked down, as
+    /// defined by [SkillParam::DownAttack].
+    pub down_attack: Option<u16>,
+}
+
+impl SkillAttack {
+    pub fn from_skill(skill: &RefSkillData) -> Option<Self> {
+        let down_attack = skill.params.iter().find_map(|param| match param {
+            SkillParam::DownAttack(bonus) => Some(*bonus),
+            _ => None,
+        });
+        skill.params.iter().find_map(|param| match param {
+            SkillParam::Attack {
+                phys, min, max, mag, ..
+            } => Some(SkillAttack {
+                physical_percent: *phys,
+                magical_percent: *mag,
+                power: AttackPower::new(*min as f32, *max as f32),
+                down_attack,
+            }),
+            _ => None,
+        })
+    }
+
+    fn is_magical(&self) -> bool {
+        self.magical_percent > 0 && self.physical_percent == 0
+    }
+
+    /// Splits the power of the skill between the physical and the magical part, according to
+    /// how much each of them contributes. This keeps skills doing both kinds of damage from
+    /// adding their power twice.
+    fn split_power(&self, power: f32) -> (f32, f32) {
+        let total = self.physical_percent + self.magical_percent;
+        if total == 0 {
+            return (0.0, 0.0);
+        }
+        let physical = power * self.physical_percent as f32 / total as f32;
+        (physical, power - physical)
+    }
+}
+
+/// The random values that go into a single damage calculation, each between `0.0` and `1.0`.
+/// These are kept separate so the calculation itself stays deterministic.
+#[derive(Copy, Clone, Debug)]
+pub struct DamageRoll {
+    pub power: f32,
+    pub parry: f32,
+    pub block: f32,
+    pub critical: f32,
+}
+
+impl DamageRoll {
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        DamageRoll {
+            power: rng.random(),
+            parry: rng.random(),
+            block: rng.random(),
+            critical: rng.random(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DamageOutcome {
+    Parried,
+    Blocked,
+    Hit { amount: u32, critical: bool },
+}
+
+impl DamageOutcome {
+    pub fn amount(&self) -> u32 {
+        match self {
+            DamageOutcome::Hit { amount, .. } => *amount,
+            _ => 0,
+        }
+    }
+}
+
+/// Calculates the damage of a skill. Each of the physical and magical part of the skill gets
+/// reduced by the respective defense of the target, after which the level difference between
+/// attacker and defender, a potential critical hit and the down attack bonus is applied.
+/// Physical attacks may be parried, based on the ratio of hit and parry rate, while any attack
+/// may be blocked.
+pub fn calculate_damage(
+    skill: &SkillAttack,
+    attacker: &AttackStats,
+    defender: &DefenseStats,
+    roll: DamageRoll,
+) -> DamageOutcome {
+    if !skill.is_magical() && roll.parry < parry_chance(attacker.hit_rate, defender.parry_rate) {
+        return DamageOutcome::Parried;
+    }
+
+    if roll.block < defender.block_ratio / 100.0 {
+        return DamageOutcome::Blocked;
+    }
+
+    let (physical_power, magical_power) = skill.split_power(skill.power.at(roll.power));
+    let physical = damage_part(
+        skill.physical_percent,
+        attacker.physical.at(roll.power) + physical_power,
+        defender.physical,
+    );
+    let magical = damage_part(
+        skill.magical_percent,
+        attacker.magical.at(roll.power) + magical_power,
+        defender.magical,
+    );
+
+    let critical = !skill.is_magical() && roll.critical < attacker.critical_rate / 100.0;
+    let mut damage = (physical + magical) * level_factor(attacker.level, defender.level);
+    if critical {
+        damage *= CRITICAL_MULTIPLIER;
+    }
+    if let Some(bonus) = skill.down_attack.filter(|_| defender.knocked_down) {
+        damage *= 1.0 + f32::from(bonus) / 100.0;
+    }
+
+    DamageOutcome::Hit {
+        amount: damage.max(1.0).round() as u32,
+        critical,
+    }
+}
+
+fn damage_part(percent: u32, power: f32, defense: f32) -> f32 {
+    if percent == 0 {
+        return 0.0;
+    }
+    (power * percent as f32 / 100.0 - defense).max(0.0)
+}
+
+fn parry_chance(hit_rate: f32, parry_rate: f32) -> f32 {
+    let total = hit_rate + parry_rate;
+    if total <= 0.0 {
+        return 0.0;
+    }
+    parry_rate / total * MAX_PARRY_CHANCE
+}
+
+fn level_factor(attacker: u8, defender: u8) -> f32 {
+    let difference = i16::from(attacker) - i16::from(defender);
+    let factor = if difference < 0 {
+        1.0 + f32::from(difference) * LOWER_LEVEL_PENALTY
+    } else {
+        1.0 + f32::from(difference) * HIGHER_LEVEL_BONUS
+    };
+    factor.clamp(MIN_LEVEL_FACTOR, MAX_LEVEL_FACTOR)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ItemTypeData;
+    use once_cell::sync::Lazy;
+    use silkroad_data::common::{RefCommon, RefOrigin};
+    use silkroad_data::itemdata::{RefBiologicalType, RefItemData, RefItemStats};
+    use silkroad_definitions::type_id::ObjectWeaponType;
+
+    static BLADE: Lazy<RefItemData> = Lazy::new(|| RefItemData {
+        common: RefCommon {
+            ref_id: 1,
+            id: "TestBlade".to_string(),
+            type_id: ObjectType::Item(ObjectItem::Equippable(ObjectEquippable::Weapon(
+                ObjectWeaponType::Blade,
+            )))
+            .type_id(),
+            country: RefOrigin::Chinese,
+            despawn_time: Default::default(),
+        },
+        price: 100,
+        sell_price: 50,
+        keeping_fee: 10,
+        max_stack_size: 1,
+        range: None,
+        required_level: None,
+        biological_type: RefBiologicalType::Both,
+        stats: RefItemStats {
+            phys_attack_min: RefItemStat {
+                lower: 10.0,
+                upper: 20.0,
+                per_upgrade: 2.0,
+            },
+            phys_attack_max: RefItemStat {
+                lower: 20.0,
+                upper: 40.0,
+                per_upgrade: 2.0,
+            },
+            ..Default::default()
+        },
+        params: [0, 0, 0, 0],
+    });
+
+    const PHYSICAL_SKILL: SkillAttack = SkillAttack {
+        physical_percent: 100,
+        magical_percent: 0,
+        power: AttackPower { min: 10.0, max: 20.0 },
+        down_attack: None,
+    };
+
+    const NO_ROLL: DamageRoll = DamageRoll {
+        power: 0.0,
+        parry: 1.0,
+        block: 1.0,
+        critical: 1.0,
+    };
+
+    fn attacker(level: u8) -> AttackStats {
+        AttackStats {
+            level,
+            physical: AttackPower::new(100.0, 200.0),
+            magical: AttackPower::new(50.0, 60.0),
+            hit_rate: 50.0,
+            critical_rate: 10.0,
+        }
+    }
+
+    fn defender(level: u8) -> DefenseStats {
+        DefenseStats {
+            level,
+            physical: 30.0,
+            magical: 10.0,
+            parry_rate: 50.0,
+            block_ratio: 20.0,
+            knocked_down: false,
+        }
+    }
+
+    #[test]
+    fn test_physical_damage() {
+        let outcome = calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(10), NO_ROLL);
+        assert_eq!(
+            DamageOutcome::Hit {
+                amount: 80,
+                critical: false
+            },
+            outcome
+        );
+
+        let roll = DamageRoll { power: 1.0, ..NO_ROLL };
+        let outcome = calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(10), roll);
+        assert_eq!(190, outcome.amount());
+    }
+
+    #[test]
+    fn test_magical_damage() {
+        let skill = SkillAttack {
+            physical_percent: 0,
+            magical_percent: 200,
+            power: AttackPower::new(10.0, 10.0),
+            down_attack: None,
+        };
+        // Magical attacks can neither be parried nor be critical.
+        let roll = DamageRoll {
+            parry: 0.0,
+            critical: 0.0,
+            ..NO_ROLL
+        };
+        let outcome = calculate_damage(&skill, &attacker(10), &defender(10), roll);
+        assert_eq!(
+            DamageOutcome::Hit {
+                amount: 110,
+                critical: false
+            },
+            outcome
+        );
+    }
+
+    #[test]
+    fn test_mixed_damage() {
+        let skill = SkillAttack {
+            physical_percent: 100,
+            magical_percent: 100,
+            power: AttackPower::new(20.0, 20.0),
+            down_attack: None,
+        };
+        // Each part only gets half of the skill power: (100 + 10 - 30) + (50 + 10 - 10).
+        let outcome = calculate_damage(&skill, &attacker(10), &defender(10), NO_ROLL);
+        assert_eq!(130, outcome.amount());
+    }
+
+    #[test]
+    fn test_parry_and_block() {
+        let parried = DamageRoll { parry: 0.1, ..NO_ROLL };
+        assert_eq!(
+            DamageOutcome::Parried,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(10), parried)
+        );
+        let not_parried = DamageRoll { parry: 0.2, ..NO_ROLL };
+        assert_ne!(
+            DamageOutcome::Parried,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(10), not_parried)
+        );
+
+        let blocked = DamageRoll { block: 0.1, ..NO_ROLL };
+        assert_eq!(
+            DamageOutcome::Blocked,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(10), blocked)
+        );
+    }
+
+    #[test]
+    fn test_critical() {
+        let roll = DamageRoll {
+            critical: 0.05,
+            ..NO_ROLL
+        };
+        assert_eq!(
+            DamageOutcome::Hit {
+                amount: 160,
+                critical: true
+            },
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(10), roll)
+        );
+    }
+
+    #[test]
+    fn test_level_difference() {
+        assert_eq!(
+            40,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &defender(20), NO_ROLL).amount()
+        );
+        assert_eq!(
+            88,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(20), &defender(10), NO_ROLL).amount()
+        );
+        assert_eq!(
+            8,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(1), &defender(100), NO_ROLL).amount()
+        );
+    }
+
+    #[test]
+    fn test_minimum_damage() {
+        let strong = DefenseStats {
+            physical: 1000.0,
+            ..defender(10)
+        };
+        assert_eq!(
+            1,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &strong, NO_ROLL).amount()
+        );
+    }
+
+    #[test]
+    fn test_down_attack() {
+        let skill = SkillAttack {
+            down_attack: Some(50),
+            ..PHYSICAL_SKILL
+        };
+        let knocked_down = DefenseStats {
+            knocked_down: true,
+            ..defender(10)
+        };
+        assert_eq!(
+            80,
+            calculate_damage(&skill, &attacker(10), &defender(10), NO_ROLL).amount()
+        );
+        assert_eq!(
+            120,
+            calculate_damage(&skill, &attacker(10), &knocked_down, NO_ROLL).amount()
+        );
+        assert_eq!(
+            80,
+            calculate_damage(&PHYSICAL_SKILL, &attacker(10), &knocked_down, NO_ROLL).amount()
+        );
+    }
+
+    #[test]
+    fn test_weapon_values() {
+        let mut weapon = Item {
+            reference: &BLADE,
+            variance: None,
+            type_data: ItemTypeData::Equipment { upgrade_level: 0 },
+        };
+        let stats = Stats::new(0, 0);
+        assert_eq!(
+            AttackPower::new(10.0, 20.0),
+            AttackStats::for_character(1, stats, Some(&weapon)).physical
+        );
+
+        // The physical attack is the fifth value of the variance of a weapon.
+        weapon.variance = Some(MAX_VARIANCE << (4 * VARIANCE_BITS));
+        weapon.type_data = ItemTypeData::Equipment { upgrade_level: 3 };
+        assert_eq!(
+            AttackPower::new(26.0, 46.0),
+            AttackStats::for_character(1, stats, Some(&weapon)).physical
+        );
+    }
+}
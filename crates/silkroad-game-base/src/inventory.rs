@@ -57,6 +57,28 @@ impl Item {
         };
         Ok(())
     }
+
+    /// Splits the given amount of this item into stacks which each don't exceed the maximum
+    /// stack size. Items that cannot be stacked always result in a single item.
+    pub fn into_stacks(self, amount: u16) -> Vec<Item> {
+        match self.type_data {
+            ItemTypeData::Consumable { .. } => {
+                let max_stack_size = self.reference.max_stack_size.max(1);
+                let mut remaining = amount;
+                let mut stacks = Vec::new();
+                while remaining > 0 {
+                    let stack_size = remaining.min(max_stack_size);
+                    stacks.push(Item {
+                        type_data: ItemTypeData::Consumable { amount: stack_size },
+                        ..self
+                    });
+                    remaining -= stack_size;
+                }
+                stacks
+            },
+            _ => vec![self],
+        }
+    }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -293,6 +315,7 @@ impl Change for InventoryChange {
 
 pub struct Inventory {
     size: usize,
+    has_equipment: bool,
     // TODO: wouldn't this make more sense as an array of N size?
     items: HashMap<u8, Item>,
     changes: Vec<InventoryChange>,
@@ -303,6 +326,17 @@ impl Inventory {
         assert!(size > 0xC, "Minimum Inventory size is 12");
         Inventory {
             size,
+            has_equipment: true,
+            items: HashMap::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Creates an inventory where every slot can hold any item, such as the storage.
+    pub fn without_equipment(size: usize) -> Self {
+        Inventory {
+            size,
+            has_equipment: false,
             items: HashMap::new(),
             changes: Vec::new(),
         }
@@ -329,13 +363,20 @@ impl Inventory {
     }
 
     fn non_equipment_slots(&self) -> impl Iterator<Item = u8> {
-        (0u8..(self.size as u8)).filter(|index| !Self::is_equipment_slot(*index))
+        (0u8..(self.size as u8)).filter(|index| !self.has_equipment || !Self::is_equipment_slot(*index))
     }
 
     fn empty_slot(&self) -> Option<u8> {
         self.non_equipment_slots().find(|slot| !self.items.contains_key(slot))
     }
 
+    /// Counts the slots outside the equipment which are not occupied by an item.
+    pub fn free_slots(&self) -> usize {
+        self.non_equipment_slots()
+            .filter(|slot| !self.items.contains_key(slot))
+            .count()
+    }
+
     pub fn move_item(&mut self, source: u8, target: u8, amount: u16) -> Result<u16, MoveError> {
         if let Some(mut source_item) = self.items.remove(&source) {
             if let Some(mut target_item) = self.items.remove(&target) {
@@ -426,6 +467,7 @@ impl Inventory {
 
     pub fn add_item(&mut self, mut item: Item) -> Option<u8> {
         if item.reference.max_stack_size > 1 {
+            let free_slot = self.empty_slot();
             for i in self.find_slots_matching(item).collect::<Vec<_>>() {
                 let existing = self.items.get_mut(&i).expect("The matching slot should have an item");
                 if !existing.is_max_stacked() && existing.reference.ref_id() == item.reference.ref_id() {
@@ -448,6 +490,8 @@ impl Inventory {
                                 });
                                 Some(i)
                             } else {
+                                // The remainder needs a slot of its own, otherwise we leave the inventory untouched.
+                                let free_slot = free_slot?;
                                 let old_data = existing.type_data;
                                 let new_data = ItemTypeData::Consumable {
                                     amount: item.reference.max_stack_size,
@@ -459,15 +503,10 @@ impl Inventory {
                                     new_item: new_data,
                                 });
                                 let remaining = sum_amount - item.reference.max_stack_size;
-                                if let Some(free_slot) = self.empty_slot() {
-                                    item.type_data = ItemTypeData::Consumable { amount: remaining };
-                                    self.set_item(free_slot, item);
-                                    self.changes.push(InventoryChange::AddItem { slot: free_slot, item });
-                                    Some(free_slot)
-                                } else {
-                                    // We should possibly undo the previous change to update the size.
-                                    None
-                                }
+                                item.type_data = ItemTypeData::Consumable { amount: remaining };
+                                self.set_item(free_slot, item);
+                                self.changes.push(InventoryChange::AddItem { slot: free_slot, item });
+                                Some(free_slot)
                             }
                         },
                         _ => {
@@ -487,6 +526,48 @@ impl Inventory {
         None
     }
 
+    /// Puts the item into the given slot, which needs to be empty and inside the inventory.
+    pub fn insert_item_at(&mut self, slot: u8, item: Item) -> Result<(), MoveError> {
+        if usize::from(slot) >= self.size || self.items.contains_key(&slot) {
+            return Err(MoveError::Impossible);
+        }
+        self.items.insert(slot, item);
+        self.changes.push(InventoryChange::AddItem { slot, item });
+        Ok(())
+    }
+
+    /// Removes the whole item at the given slot, if there is any.
+    pub fn take_item_at(&mut self, slot: u8) -> Option<Item> {
+        let item = self.items.remove(&slot)?;
+        self.changes.push(InventoryChange::RemoveItem { slot });
+        Some(item)
+    }
+
+    /// Takes the given amount off the stack at the given slot. If the whole stack is taken, the
+    /// item is removed from the slot.
+    pub fn split_item_at(&mut self, slot: u8, amount: u16) -> Result<Item, MoveError> {
+        let existing = self.items.get_mut(&slot).ok_or(MoveError::ItemDoesNotExist)?;
+        if amount == 0 || amount > existing.stack_size() {
+            return Err(MoveError::Impossible);
+        }
+
+        if amount == existing.stack_size() {
+            return self.take_item_at(slot).ok_or(MoveError::ItemDoesNotExist);
+        }
+
+        let old_data = existing.type_data;
+        existing.change_stack_size(-(amount as i16))?;
+        let new_data = existing.type_data;
+        let mut split = *existing;
+        split.type_data = ItemTypeData::Consumable { amount };
+        self.changes.push(InventoryChange::ChangeTypeData {
+            slot,
+            old_item: old_data,
+            new_item: new_data,
+        });
+        Ok(split)
+    }
+
     pub fn remove_item(&mut self, item: Item) -> Result<u16, MoveError> {
         let mut to_remove = item.stack_size();
         let mut removed = 0;
@@ -562,10 +643,13 @@ mod test {
             despawn_time: Default::default(),
         },
         price: 100,
+        sell_price: 50,
+        keeping_fee: 10,
         max_stack_size: 50,
         range: None,
         required_level: None,
         biological_type: RefBiologicalType::Both,
+        stats: Default::default(),
         params: [0, 0, 0, 0],
     });
 
@@ -581,10 +665,13 @@ mod test {
             despawn_time: Default::default(),
         },
         price: 100,
+        sell_price: 50,
+        keeping_fee: 10,
         max_stack_size: 50,
         range: None,
         required_level: None,
         biological_type: RefBiologicalType::Both,
+        stats: Default::default(),
         params: [0, 0, 0, 0],
     });
 
@@ -667,4 +754,124 @@ mod test {
         assert_eq!(1, changes.len());
         assert!(matches!(changes.pop().unwrap(), InventoryChange::RemoveItem { slot }));
     }
+
+    #[test]
+    pub fn test_take_item() {
+        let mut inv = Inventory::default();
+        let free = inv.free_slots();
+        assert_eq!(45 - 13, free);
+
+        let slot = inv
+            .add_item(Item {
+                variance: None,
+                reference: FIRST_ITEM_DATA.deref(),
+                type_data: ItemTypeData::Consumable { amount: 5 },
+            })
+            .unwrap();
+        assert_eq!(free - 1, inv.free_slots());
+        let _ = inv.changes();
+
+        let taken = inv.take_item_at(slot).unwrap();
+        assert_eq!(5, taken.stack_size());
+        assert!(inv.take_item_at(slot).is_none());
+        assert_eq!(free, inv.free_slots());
+        let mut changes = inv.changes();
+        assert_eq!(1, changes.len());
+        assert!(matches!(changes.pop().unwrap(), InventoryChange::RemoveItem { .. }));
+    }
+
+    #[test]
+    pub fn test_into_stacks() {
+        let item = Item {
+            variance: None,
+            reference: FIRST_ITEM_DATA.deref(),
+            type_data: ItemTypeData::Consumable { amount: 1 },
+        };
+        let stacks = item.into_stacks(120);
+        assert_eq!(3, stacks.len());
+        assert_eq!(50, stacks[0].stack_size());
+        assert_eq!(50, stacks[1].stack_size());
+        assert_eq!(20, stacks[2].stack_size());
+        assert!(item.into_stacks(0).is_empty());
+    }
+
+    #[test]
+    pub fn test_split_item() {
+        let mut inv = Inventory::default();
+        let slot = inv
+            .add_item(Item {
+                variance: None,
+                reference: FIRST_ITEM_DATA.deref(),
+                type_data: ItemTypeData::Consumable { amount: 20 },
+            })
+            .unwrap();
+        let _ = inv.changes();
+
+        assert!(inv.split_item_at(slot, 21).is_err());
+        let split = inv.split_item_at(slot, 5).unwrap();
+        assert_eq!(5, split.stack_size());
+        assert_eq!(15, inv.get_item_at(slot).unwrap().stack_size());
+        let mut changes = inv.changes();
+        assert_eq!(1, changes.len());
+        assert!(matches!(changes.pop().unwrap(), InventoryChange::ChangeTypeData { .. }));
+
+        let rest = inv.split_item_at(slot, 15).unwrap();
+        assert_eq!(15, rest.stack_size());
+        assert!(inv.get_item_at(slot).is_none());
+    }
+
+    #[test]
+    pub fn test_without_equipment() {
+        let mut inv = Inventory::without_equipment(5);
+        assert_eq!(5, inv.free_slots());
+        let slot = inv
+            .add_item(Item {
+                variance: None,
+                reference: FIRST_ITEM_DATA.deref(),
+                type_data: ItemTypeData::Consumable { amount: 5 },
+            })
+            .unwrap();
+        assert_eq!(0, slot);
+        assert_eq!(4, inv.free_slots());
+    }
+
+    #[test]
+    pub fn test_insert_item() {
+        let mut inv = Inventory::without_equipment(5);
+        let item = Item {
+            variance: None,
+            reference: FIRST_ITEM_DATA.deref(),
+            type_data: ItemTypeData::Consumable { amount: 5 },
+        };
+        assert!(inv.insert_item_at(3, item).is_ok());
+        assert!(inv.insert_item_at(3, item).is_err());
+        assert!(inv.insert_item_at(5, item).is_err());
+        assert_eq!(5, inv.get_item_at(3).unwrap().stack_size());
+        let mut changes = inv.changes();
+        assert_eq!(1, changes.len());
+        assert!(matches!(
+            changes.pop().unwrap(),
+            InventoryChange::AddItem { slot: 3, .. }
+        ));
+    }
+
+    #[test]
+    pub fn test_add_item_overflow_without_space() {
+        let mut inv = Inventory::without_equipment(1);
+        let item = Item {
+            variance: None,
+            reference: FIRST_ITEM_DATA.deref(),
+            type_data: ItemTypeData::Consumable { amount: 40 },
+        };
+        assert_eq!(Some(0), inv.add_item(item));
+        assert_eq!(None, inv.add_item(item));
+        assert_eq!(40, inv.get_item_at(0).unwrap().stack_size());
+
+        let small = Item {
+            type_data: ItemTypeData::Consumable { amount: 10 },
+            ..item
+        };
+        assert_eq!(Some(0), inv.add_item(small));
+        assert_eq!(50, inv.get_item_at(0).unwrap().stack_size());
+    }
 }
@@ -1,19 +1,27 @@
+mod buff;
 mod changes;
 mod character;
+mod cooldown;
+mod damage;
 mod inventory;
 mod movement;
 mod pos;
 mod skill;
 mod stats;
+mod status;
 mod vec;
 
+pub use buff::*;
 pub use changes::*;
 pub use character::*;
+pub use cooldown::*;
+pub use damage::*;
 pub use inventory::*;
 pub use movement::*;
 pub use pos::*;
 pub use skill::*;
 pub use stats::*;
+pub use status::*;
 pub use vec::*;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -1,6 +1,7 @@
-use crate::Item;
+use crate::{GlobalLocation, Item};
+use cgmath::{Deg, InnerSpace, MetricSpace};
 use silkroad_data::itemdata::RefItemData;
-use silkroad_data::skilldata::RefSkillData;
+use silkroad_data::skilldata::{RefSkillData, SkillParam};
 use silkroad_data::DataMap;
 use silkroad_definitions::type_id::{ObjectEquippable, ObjectItem, ObjectType, ObjectWeaponType};
 use thiserror::Error;
@@ -65,3 +66,173 @@ pub enum AttackSkillError {
     #[error("The type of weapon was not known")]
     UnknownWeapon,
 }
+
+/// The distance a chained attack may jump from one target to the next.
+pub const CHAIN_RANGE: f32 = 50.0;
+/// The angle of a cone shaped area, in degrees.
+const CONE_ANGLE: f32 = 90.0;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AreaOrigin {
+    Caster,
+    Target,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AreaShape {
+    Circle {
+        radius: f32,
+    },
+    /// A cone starting at the caster, pointing towards the target.
+    Cone {
+        radius: f32,
+    },
+}
+
+/// The area affected by a skill, as defined by [SkillParam::AOE]. We assume an `origin` of `1`
+/// to center the area around the caster and an `area_type` of `2` to describe a cone, while
+/// everything else is a circle around the target.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkillArea {
+    pub origin: AreaOrigin,
+    pub shape: AreaShape,
+}
+
+impl SkillArea {
+    pub fn from_skill(skill: &RefSkillData) -> Option<Self> {
+        skill.params.iter().find_map(|param| match param {
+            SkillParam::AOE {
+                origin,
+                area_type,
+                area_size,
+                ..
+            } => {
+                let radius = f32::from(*area_size);
+                let shape = match area_type {
+                    2 => AreaShape::Cone { radius },
+                    _ => AreaShape::Circle { radius },
+                };
+                let origin = match (*origin, shape) {
+                    (_, AreaShape::Cone { .. }) | (1, _) => AreaOrigin::Caster,
+                    _ => AreaOrigin::Target,
+                };
+                Some(SkillArea { origin, shape })
+            },
+            _ => None,
+        })
+    }
+
+    /// Checks if the candidate is affected by the skill, which was cast at the given target.
+    pub fn contains(&self, caster: GlobalLocation, target: GlobalLocation, candidate: GlobalLocation) -> bool {
+        let center = match self.origin {
+            AreaOrigin::Caster => caster,
+            AreaOrigin::Target => target,
+        };
+        match self.shape {
+            AreaShape::Circle { radius } => center.0.distance2(candidate.0) <= radius * radius,
+            AreaShape::Cone { radius } => {
+                if center.0.distance2(candidate.0) > radius * radius {
+                    return false;
+                }
+                let direction = target.0 - caster.0;
+                let to_candidate = candidate.0 - caster.0;
+                if direction.magnitude2() == 0.0 || to_candidate.magnitude2() == 0.0 {
+                    return true;
+                }
+                let angle: Deg<f32> = direction.angle(to_candidate).into();
+                angle.0.abs() <= CONE_ANGLE / 2.0
+            },
+        }
+    }
+}
+
+/// The number of entities a skill jumps to, as defined by [SkillParam::Chain], including the
+/// initial target.
+pub fn chain_count(skill: &RefSkillData) -> Option<usize> {
+    skill.params.iter().find_map(|param| match param {
+        SkillParam::Chain { count, .. } => Some(*count as usize),
+        _ => None,
+    })
+}
+
+/// Resolves the targets of a chained attack. Starting with the initial target, the attack jumps
+/// to the closest candidate within [CHAIN_RANGE] of the last target that hasn't been hit yet,
+/// until `count` targets have been hit or there are no more candidates in range.
+pub fn chain_targets<T: Copy + PartialEq>(
+    first: (T, GlobalLocation),
+    candidates: &[(T, GlobalLocation)],
+    count: usize,
+) -> Vec<T> {
+    let mut targets = vec![first.0];
+    let mut last = first.1;
+    while targets.len() < count {
+        let next = candidates
+            .iter()
+            .filter(|(candidate, _)| !targets.contains(candidate))
+            .map(|(candidate, location)| (*candidate, *location, last.0.distance2(location.0)))
+            .filter(|(_, _, distance)| *distance <= CHAIN_RANGE * CHAIN_RANGE)
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+        let Some((candidate, location, _)) = next else {
+            break;
+        };
+        targets.push(candidate);
+        last = location;
+    }
+    targets
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::Vector2;
+
+    fn location(x: f32, y: f32) -> GlobalLocation {
+        GlobalLocation(Vector2::new(x, y))
+    }
+
+    #[test]
+    fn test_circle_area() {
+        let area = SkillArea {
+            origin: AreaOrigin::Target,
+            shape: AreaShape::Circle { radius: 10.0 },
+        };
+        let caster = location(0.0, 0.0);
+        let target = location(100.0, 0.0);
+        assert!(area.contains(caster, target, location(105.0, 5.0)));
+        assert!(!area.contains(caster, target, location(115.0, 0.0)));
+        assert!(!area.contains(caster, target, location(5.0, 0.0)));
+
+        let around_caster = SkillArea {
+            origin: AreaOrigin::Caster,
+            ..area
+        };
+        assert!(around_caster.contains(caster, target, location(5.0, 0.0)));
+    }
+
+    #[test]
+    fn test_cone_area() {
+        let area = SkillArea {
+            origin: AreaOrigin::Caster,
+            shape: AreaShape::Cone { radius: 50.0 },
+        };
+        let caster = location(0.0, 0.0);
+        let target = location(10.0, 0.0);
+        assert!(area.contains(caster, target, location(30.0, 20.0)));
+        assert!(!area.contains(caster, target, location(10.0, 30.0)));
+        assert!(!area.contains(caster, target, location(-10.0, 0.0)));
+        assert!(!area.contains(caster, target, location(60.0, 0.0)));
+    }
+
+    #[test]
+    fn test_chain_targets() {
+        let candidates = [
+            (2, location(40.0, 0.0)),
+            (3, location(80.0, 0.0)),
+            (4, location(20.0, 0.0)),
+            (5, location(500.0, 0.0)),
+        ];
+        assert_eq!(vec![1, 4, 2, 3], chain_targets((1, location(0.0, 0.0)), &candidates, 5));
+        assert_eq!(vec![1, 4], chain_targets((1, location(0.0, 0.0)), &candidates, 2));
+        assert_eq!(vec![1], chain_targets((1, location(0.0, 0.0)), &candidates, 0));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::GlobalLocation;
+use cgmath::InnerSpace;
+use silkroad_data::skilldata::{RefSkillData, SkillParam};
+use std::time::Duration;
+
+/// How long an entity stays on the ground after being knocked down.
+pub const KNOCKDOWN_DURATION: Duration = Duration::from_secs(3);
+/// How long an entity is unable to act after being knocked back.
+pub const KNOCKBACK_DURATION: Duration = Duration::from_millis(1500);
+/// The distance in which we check whether a knockback may continue along its path.
+const KNOCKBACK_STEP: f32 = 1.0;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum StatusEffectKind {
+    Stun,
+    Knockdown,
+    Knockback,
+}
+
+/// A status effect that has been inflicted on an entity, preventing it from acting for the
+/// given duration.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    pub duration: Duration,
+}
+
+/// The status effects a skill may inflict, as defined by [SkillParam::Stun],
+/// [SkillParam::Knockdown] and [SkillParam::Knockback].
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SkillStatusEffects {
+    /// The duration of the stun and the chance, in percent, to inflict it.
+    pub stun: Option<(Duration, u8)>,
+    /// The chance, in percent, to knock the target down.
+    pub knockdown: Option<u8>,
+    /// The chance, in percent, to knock the target back and the distance it is knocked back.
+    pub knockback: Option<(u8, f32)>,
+}
+
+impl SkillStatusEffects {
+    pub fn from_skill(skill: &RefSkillData) -> Self {
+        let mut effects = SkillStatusEffects::default();
+        for param in skill.params.iter() {
+            match param {
+                SkillParam::Stun { duration, chance, .. } => {
+                    effects.stun = Some((Duration::from_millis(u64::from(*duration)), *chance))
+                },
+                SkillParam::Knockdown { chance, .. } => effects.knockdown = Some(*chance),
+                SkillParam::Knockback { chance, distance } => effects.knockback = Some((*chance, f32::from(*distance))),
+                _ => {},
+            }
+        }
+        effects
+    }
+
+    /// Determines the status effect inflicted by a hit, given a random value between `0.0` and
+    /// `1.0`. A knockdown takes precedence over a knockback, which takes precedence over a stun.
+    /// Targets which are already knocked down cannot be knocked down or back again.
+    pub fn roll(&self, roll: f32, knocked_down: bool) -> Option<StatusEffect> {
+        let succeeds = |chance: u8| roll < f32::from(chance) / 100.0;
+        if !knocked_down {
+            if self.knockdown.is_some_and(succeeds) {
+                return Some(StatusEffect {
+                    kind: StatusEffectKind::Knockdown,
+                    duration: KNOCKDOWN_DURATION,
+                });
+            }
+
+            if self.knockback.is_some_and(|(chance, _)| succeeds(chance)) {
+                return Some(StatusEffect {
+                    kind: StatusEffectKind::Knockback,
+                    duration: KNOCKBACK_DURATION,
+                });
+            }
+        }
+
+        self.stun
+            .filter(|(_, chance)| succeeds(*chance))
+            .map(|(duration, _)| StatusEffect {
+                kind: StatusEffectKind::Stun,
+                duration,
+            })
+    }
+
+    pub fn knockback_distance(&self) -> f32 {
+        self.knockback.map(|(_, distance)| distance).unwrap_or(0.0)
+    }
+}
+
+/// Finds the location a target gets knocked back to, pushing it away from the attacker. The
+/// target moves along the path as long as the given check considers the next step walkable.
+pub fn knockback_destination(
+    attacker: GlobalLocation,
+    target: GlobalLocation,
+    distance: f32,
+    is_walkable: impl Fn(GlobalLocation) -> bool,
+) -> GlobalLocation {
+    let direction = target.0 - attacker.0;
+    if direction.magnitude2() == 0.0 {
+        return target;
+    }
+
+    let step = direction.normalize() * KNOCKBACK_STEP;
+    let mut current = target;
+    let mut travelled = 0.0;
+    while travelled + KNOCKBACK_STEP <= distance {
+        let next = current + step;
+        if !is_walkable(next) {
+            break;
+        }
+        current = next;
+        travelled += KNOCKBACK_STEP;
+    }
+    current
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cgmath::Vector2;
+
+    const EFFECTS: SkillStatusEffects = SkillStatusEffects {
+        stun: Some((Duration::from_secs(2), 50)),
+        knockdown: Some(10),
+        knockback: Some((20, 30.0)),
+    };
+
+    #[test]
+    fn test_roll_effects() {
+        assert_eq!(
+            Some(StatusEffectKind::Knockdown),
+            EFFECTS.roll(0.05, false).map(|effect| effect.kind)
+        );
+        assert_eq!(
+            Some(StatusEffectKind::Knockback),
+            EFFECTS.roll(0.15, false).map(|effect| effect.kind)
+        );
+        assert_eq!(
+            Some(StatusEffect {
+                kind: StatusEffectKind::Stun,
+                duration: Duration::from_secs(2),
+            }),
+            EFFECTS.roll(0.3, false)
+        );
+        assert_eq!(None, EFFECTS.roll(0.6, false));
+        assert_eq!(
+            Some(StatusEffectKind::Stun),
+            EFFECTS.roll(0.05, true).map(|effect| effect.kind)
+        );
+        assert_eq!(None, SkillStatusEffects::default().roll(0.0, false));
+    }
+
+    #[test]
+    fn test_knockback_destination() {
+        let attacker = GlobalLocation(Vector2::new(0.0, 0.0));
+        let target = GlobalLocation(Vector2::new(10.0, 0.0));
+        let destination = knockback_destination(attacker, target, 5.0, |_| true);
+        assert_eq!(15.0, destination.0.x);
+        assert_eq!(0.0, destination.0.y);
+
+        let blocked = knockback_destination(attacker, target, 5.0, |location| location.0.x <= 12.0);
+        assert_eq!(12.0, blocked.0.x);
+
+        assert!(knockback_destination(target, target, 5.0, |_| true) == target);
+    }
+}
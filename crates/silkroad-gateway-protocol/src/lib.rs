@@ -612,17 +612,21 @@ impl TempCharacterData {
     }
 }
 
+/// The presumed layout of the unknown `0x3612` packet sent by the agent server.
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub struct UnknownLargePacketD {
     pub unknown_1: u8, // 0
     pub inner: Vec<UnknownLargePacketDInner>,
 }
 
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub struct UnknownLargePacketDInner {
     pub index: u32,
     pub unknown_1: u8, // 0x01
     pub inner: Vec<UnknownLargePacketDInnerInner>,
 }
 
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub struct UnknownLargePacketDInnerInner {
     pub index: u64,
     pub data: u32,
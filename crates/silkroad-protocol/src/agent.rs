@@ -0,0 +1,66 @@
+use crate::auth::AuthProtocol;
+use crate::character::{CharselectClientProtocol, CharselectServerProtocol};
+use crate::chat::{ChatClientProtocol, ChatServerProtocol};
+use crate::combat::{CombatClientProtocol, CombatServerProtocol};
+use crate::community::{
+    FriendListClientProtocol, FriendListServerProtocol, InvitationClientProtocol, InvitationServerProtocol,
+};
+use crate::consignment::{ConsignmentClientProtocol, ConsignmentServerProtocol};
+use crate::exchange::{ExchangeClientProtocol, ExchangeServerProtocol};
+use crate::general::BaseProtocol;
+use crate::gm::{GmClientProtocol, GmServerProtocol};
+use crate::guild::{GuildClientProtocol, GuildServerProtocol};
+use crate::inventory::{InventoryClientProtocol, InventoryServerProtocol};
+use crate::movement::{MovementClientProtocol, MovementServerProtocol};
+use crate::party::{PartyClientProtocol, PartyServerProtocol};
+use crate::skill::{SkillClientProtocol, SkillServerProtocol};
+use crate::stall::{StallClientProtocol, StallServerProtocol};
+use crate::storage::{StorageClientProtocol, StorageServerProtocol};
+use crate::world::{StatClientProtocol, StatServerProtocol, WorldClientProtocol, WorldServerProtocol};
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+
+define_inbound_protocol! { AgentClientProtocol =>
+    +
+    BaseProtocol,
+    AuthProtocol,
+    MovementClientProtocol,
+    SkillClientProtocol,
+    ChatClientProtocol,
+    FriendListClientProtocol,
+    CharselectClientProtocol,
+    StatClientProtocol,
+    CombatClientProtocol,
+    WorldClientProtocol,
+    InventoryClientProtocol,
+    GmClientProtocol,
+    PartyClientProtocol,
+    GuildClientProtocol,
+    InvitationClientProtocol,
+    ExchangeClientProtocol,
+    StorageClientProtocol,
+    StallClientProtocol,
+    ConsignmentClientProtocol
+}
+
+define_outbound_protocol! { AgentServerProtocol =>
+    +
+    BaseProtocol,
+    AuthProtocol,
+    ChatServerProtocol,
+    MovementServerProtocol,
+    FriendListServerProtocol,
+    CharselectServerProtocol,
+    SkillServerProtocol,
+    StatServerProtocol,
+    CombatServerProtocol,
+    WorldServerProtocol,
+    InventoryServerProtocol,
+    GmServerProtocol,
+    PartyServerProtocol,
+    GuildServerProtocol,
+    InvitationServerProtocol,
+    ExchangeServerProtocol,
+    StorageServerProtocol,
+    StallServerProtocol,
+    ConsignmentServerProtocol
+}
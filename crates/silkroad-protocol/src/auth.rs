@@ -177,3 +177,30 @@ define_protocol! { AuthProtocol =>
     AuthResponse,
     Disconnect
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&AuthRequest {
+            token: 0x1234,
+            username: "test".to_string(),
+            password: "password".to_string(),
+            unknown: 0x16,
+            mac_bytes: [0x00, 0x11, 0x22, 0x33, 0x44, 0x55],
+        });
+        assert_roundtrip(&AuthResponse::new(AuthResult::success()));
+        assert_roundtrip(&AuthResponse::new(AuthResult::error(AuthResultError::ServerFull)));
+        assert_roundtrip(&LogoutRequest {
+            mode: LogoutMode::Restart,
+        });
+        assert_roundtrip(&LogoutResponse::new(LogoutResult::success(5, LogoutMode::Logout)));
+        assert_roundtrip(&LogoutResponse::new(LogoutResult::wait_30_seconds()));
+        assert_roundtrip(&LogoutFinished);
+        assert_roundtrip(&Disconnect::new());
+        assert_roundtrip(&UnknownLargePacket::new());
+    }
+}
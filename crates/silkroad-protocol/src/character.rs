@@ -1,7 +1,9 @@
+use byteorder::ReadBytesExt;
 use skrillax_packet::Packet;
 use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
 use skrillax_serde::*;
 use std::fmt::{Debug, Formatter};
+use std::io::Read;
 
 #[derive(Clone, Eq, PartialEq, PartialOrd, Copy, Serialize, Deserialize, ByteSize, Debug)]
 pub enum CharacterListAction {
@@ -21,7 +23,7 @@ pub enum CharacterListAction {
     AssignJob,
 }
 
-#[derive(Clone, Eq, PartialEq, PartialOrd, Copy, Serialize, ByteSize, Debug)]
+#[derive(Clone, Eq, PartialEq, PartialOrd, Copy, Serialize, Deserialize, ByteSize, Debug)]
 #[silkroad(size = 2)]
 pub enum CharacterListError {
     #[silkroad(value = 0x403)]
@@ -92,7 +94,7 @@ impl CharacterListResult {
     }
 }
 
-#[derive(Clone, Deserialize, ByteSize, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub enum CharacterListRequestAction {
     #[silkroad(value = 1)]
     Create {
@@ -156,7 +158,7 @@ impl CharacterListRequestAction {
     }
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, ByteSize, Debug)]
 pub enum CharacterJoinResult {
     #[silkroad(value = 1)]
     Success,
@@ -170,7 +172,7 @@ impl CharacterJoinResult {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize)]
+#[derive(Clone, Serialize, Deserialize, ByteSize)]
 pub enum TimeInformation {
     #[silkroad(value = 1)]
     Deleting {
@@ -213,7 +215,7 @@ impl TimeInformation {
     }
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, ByteSize, Debug)]
 pub struct CharacterListEquippedItem {
     pub id: u32,
     pub upgrade_level: u8,
@@ -225,7 +227,7 @@ impl CharacterListEquippedItem {
     }
 }
 
-#[derive(Clone, Debug, Serialize, ByteSize)]
+#[derive(Clone, Debug, Serialize, Deserialize, ByteSize)]
 pub struct CharacterListAvatarItem {
     pub id: u32,
 }
@@ -236,7 +238,7 @@ impl CharacterListAvatarItem {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub struct CharacterListEntry {
     pub ref_id: u32,
     pub name: String,
@@ -317,19 +319,60 @@ impl CharacterListResponse {
     }
 }
 
-#[derive(Clone, Deserialize, ByteSize, Packet, Debug)]
+// The content of a successful result does not carry its own type, it depends on the action
+// the response is for, so this cannot be derived.
+impl Deserialize for CharacterListResponse {
+    fn read_from<T: Read + ReadBytesExt>(reader: &mut T) -> Result<Self, SerializationError>
+    where
+        Self: Sized,
+    {
+        let action = CharacterListAction::read_from(reader)?;
+        let result = match reader.read_u8()? {
+            1 => {
+                let content = match action {
+                    CharacterListAction::List => {
+                        let amount = reader.read_u8()?;
+                        let characters = (0..amount)
+                            .map(|_| CharacterListEntry::read_from(reader))
+                            .collect::<Result<Vec<_>, _>>()?;
+                        let job = reader.read_u8()?;
+                        CharacterListContent::Characters { characters, job }
+                    },
+                    CharacterListAction::ShowJobSpread => CharacterListContent::JobSpread {
+                        hunters: reader.read_u8()?,
+                        thieves: reader.read_u8()?,
+                    },
+                    _ => CharacterListContent::Empty,
+                };
+                CharacterListResult::Ok { content }
+            },
+            2 => CharacterListResult::Error {
+                error: CharacterListError::read_from(reader)?,
+            },
+            other => {
+                return Err(SerializationError::UnknownVariation(
+                    other as usize,
+                    "CharacterListResult",
+                ))
+            },
+        };
+        Ok(CharacterListResponse { action, result })
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x7007)]
 pub struct CharacterListRequest {
     pub action: CharacterListRequestAction,
 }
 
-#[derive(Clone, Deserialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x7001)]
 pub struct CharacterJoinRequest {
     pub character_name: String,
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0xB001)]
 pub struct CharacterJoinResponse {
     pub result: CharacterJoinResult,
@@ -349,7 +392,7 @@ impl CharacterJoinResponse {
     }
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x303D)]
 pub struct CharacterStatsMessage {
     pub phys_attack_min: u32,
@@ -398,7 +441,7 @@ impl CharacterStatsMessage {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x3601)]
 pub struct UnknownPacket {
     pub unknown_1: u8,
@@ -406,7 +449,7 @@ pub struct UnknownPacket {
     pub unknown_2: Vec<UnknownPacketInner>,
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, ByteSize, Debug)]
 pub struct UnknownPacketInner {
     unknown: u32,
     unknown_2: Option<u32>,
@@ -421,7 +464,7 @@ impl UnknownPacket {
     }
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Copy, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0xB602)]
 pub struct UnknownPacket2 {
     pub unknown_1: u8,
@@ -443,7 +486,7 @@ pub const MACRO_POTION: u8 = 1;
 pub const MACRO_SKILL: u8 = 2;
 pub const MACRO_HUNT: u8 = 4;
 
-#[derive(Serialize, ByteSize, Clone, Packet)]
+#[derive(Serialize, Deserialize, ByteSize, Clone, Packet, Debug)]
 #[packet(opcode = 0x3555)]
 pub enum MacroStatus {
     #[silkroad(value = 0)]
@@ -468,3 +511,85 @@ define_outbound_protocol! { CharselectServerProtocol =>
     UnknownPacket,
     UnknownPacket2
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    fn character() -> CharacterListEntry {
+        CharacterListEntry::new(
+            1907,
+            "Tester".to_string(),
+            34,
+            12,
+            1500,
+            30,
+            25,
+            3,
+            100,
+            420,
+            310,
+            24744,
+            TimeInformation::playable(SilkroadTime::default()),
+            0,
+            None,
+            0,
+            vec![
+                CharacterListEquippedItem::new(3643, 0),
+                CharacterListEquippedItem::new(3632, 2),
+            ],
+            vec![CharacterListAvatarItem::new(23370)],
+        )
+    }
+
+    #[test]
+    fn test_character_list_roundtrip() {
+        assert_roundtrip(&CharacterListRequest {
+            action: CharacterListRequestAction::List,
+        });
+        assert_roundtrip(&CharacterListRequest {
+            action: CharacterListRequestAction::create("Tester".to_string(), 1907, 34, 3643, 3644, 3645, 3632),
+        });
+        assert_roundtrip(&CharacterListRequest {
+            action: CharacterListRequestAction::checkname("Tester".to_string()),
+        });
+        assert_roundtrip(&CharacterListResponse::new(
+            CharacterListAction::List,
+            CharacterListResult::ok(CharacterListContent::characters(vec![character()], 0)),
+        ));
+        assert_roundtrip(&CharacterListResponse::new(
+            CharacterListAction::ShowJobSpread,
+            CharacterListResult::ok(CharacterListContent::jobspread(40, 60)),
+        ));
+        assert_roundtrip(&CharacterListResponse::new(
+            CharacterListAction::Create,
+            CharacterListResult::ok(CharacterListContent::Empty),
+        ));
+        assert_roundtrip(&CharacterListResponse::new(
+            CharacterListAction::CheckName,
+            CharacterListResult::error(CharacterListError::NameAlreadyUsed),
+        ));
+    }
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&CharacterJoinRequest {
+            character_name: "Tester".to_string(),
+        });
+        assert_roundtrip(&CharacterJoinResponse::success());
+        assert_roundtrip(&CharacterJoinResponse::error(CharacterListError::ReachedCapacity));
+        assert_roundtrip(&CharacterStatsMessage::new(
+            10, 15, 8, 12, 20, 18, 11, 13, 420, 310, 30, 25,
+        ));
+        assert_roundtrip(&UnknownPacket::new());
+        assert_roundtrip(&UnknownPacket2::new(0x1234));
+        assert_roundtrip(&MacroStatus::Possible(MACRO_POTION | MACRO_SKILL, 0));
+        assert_roundtrip(&MacroStatus::Disabled(
+            "macro".to_string(),
+            "disabled".to_string(),
+            MACRO_HUNT,
+        ));
+        assert_roundtrip(&FinishLoading);
+    }
+}
@@ -190,3 +190,43 @@ define_outbound_protocol! { ChatServerProtocol =>
     ChatUpdate,
     TextCharacterInitialization
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&TextCharacterInitialization::new(vec![0x1, 0xFFFF_FFFF_FFFF]));
+        assert_roundtrip(&ChatUpdate::new(ChatSource::all(0x1234), "Hello".to_string()));
+        assert_roundtrip(&ChatUpdate::new(ChatSource::system(), "Server restart".to_string()));
+        assert_roundtrip(&ChatUpdate::new(ChatSource::Notice, "Notice".to_string()));
+        assert_roundtrip(&ChatMessage {
+            target: ChatTarget::All,
+            index: 1,
+            contains_link: false,
+            unknown: 0,
+            recipient: None,
+            message: "Hello".to_string(),
+        });
+        assert_roundtrip(&ChatMessage {
+            target: ChatTarget::PrivateMessage,
+            index: 2,
+            contains_link: false,
+            unknown: 0,
+            recipient: Some("Tester".to_string()),
+            message: "Hi".to_string(),
+        });
+        assert_roundtrip(&ChatMessageResponse::new(
+            ChatMessageResult::Success,
+            ChatTarget::All,
+            1,
+        ));
+        assert_roundtrip(&ChatMessageResponse::new(
+            ChatMessageResult::error(ChatErrorCode::WhisperMuted),
+            ChatTarget::PrivateMessage,
+            2,
+        ));
+    }
+}
@@ -117,6 +117,9 @@ pub enum SkillPartDamage {
     Default(DamageValue),
     #[silkroad(value = 0x80)]
     KillingBlow(DamageValue),
+    /// The attack was blocked or parried, so no damage follows.
+    #[silkroad(value = 0x02)]
+    Blocked,
     #[silkroad(value = 0x08)]
     Abort,
 }
@@ -210,6 +213,35 @@ pub struct ReceiveExperience {
     pub new_level: Option<u16>,
 }
 
+/// A buff has been applied to the given entity. The token identifies this instance of the buff
+/// and is used to refer to it once it ends.
+#[derive(Serialize, ByteSize, Deserialize, Copy, Clone, Packet, Debug)]
+#[packet(opcode = 0xB0BD)]
+pub struct BuffBegin {
+    pub target: u32,
+    pub skill: u32,
+    pub token: u32,
+}
+
+impl BuffBegin {
+    pub fn new(target: u32, skill: u32, token: u32) -> Self {
+        BuffBegin { target, skill, token }
+    }
+}
+
+#[derive(Serialize, ByteSize, Deserialize, Copy, Clone, Packet, Debug)]
+#[packet(opcode = 0xB072)]
+pub struct BuffEnd {
+    pub unknown: u8, // always 1?
+    pub token: u32,
+}
+
+impl BuffEnd {
+    pub fn new(token: u32) -> Self {
+        BuffEnd { unknown: 1, token }
+    }
+}
+
 define_inbound_protocol! { CombatClientProtocol =>
     PerformAction
 }
@@ -217,5 +249,79 @@ define_inbound_protocol! { CombatClientProtocol =>
 define_outbound_protocol! { CombatServerProtocol =>
     PerformActionResponse,
     PerformActionUpdate,
-    ReceiveExperience
+    ReceiveExperience,
+    BuffBegin,
+    BuffEnd
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{assert_roundtrip, assert_size};
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&PerformAction::Do(DoActionType::Attack {
+            target: ActionTarget::Entity(0x1234),
+        }));
+        assert_roundtrip(&PerformAction::Do(DoActionType::UseSkill {
+            ref_id: 42,
+            target: ActionTarget::Area(Location {
+                region: 24744,
+                pos_x: 960.0,
+                pos_y: 0.0,
+                pos_z: 1280.0,
+            }),
+        }));
+        assert_roundtrip(&PerformAction::Stop);
+        assert_roundtrip(&PerformActionResponse::Do(DoActionResponseCode::Success));
+        assert_roundtrip(&PerformActionResponse::Stop(PerformActionError::Cooldown));
+        assert_roundtrip(&PerformActionUpdate::success(1, 0x10, 0x20, 0x30, ActionType::Teleport));
+        assert_roundtrip(&PerformActionUpdate::success(
+            1,
+            0x10,
+            0x20,
+            0x30,
+            ActionType::Attack { damage: None },
+        ));
+        assert_roundtrip(&PerformActionUpdate::Failure(PerformActionError::InvalidTarget));
+        assert_roundtrip(&BuffBegin::new(0x1234, 42, 0x30));
+        assert_roundtrip(&BuffEnd::new(0x30));
+    }
+
+    #[test]
+    fn test_packets_size() {
+        // The damage of an entity is not delimited, so it cannot be read back reliably.
+        let damage = DamageContent {
+            damage_instances: 1,
+            entities: vec![PerEntityDamage {
+                target: 0x20,
+                damage: vec![SkillPartDamage::KillingBlow(DamageValue::new(
+                    DamageKind::Critical,
+                    250,
+                ))],
+            }],
+        };
+        assert_size(&PerformActionUpdate::success(
+            1,
+            0x10,
+            0x20,
+            0x30,
+            ActionType::Attack { damage: Some(damage) },
+        ));
+        assert_size(&ReceiveExperience {
+            exp_origin: 0x20,
+            experience: 1000,
+            sp: 50,
+            unknown: 0,
+            new_level: None,
+        });
+        assert_size(&ReceiveExperience {
+            exp_origin: 0x20,
+            experience: 1000,
+            sp: 50,
+            unknown: 0,
+            new_level: Some(2),
+        });
+    }
 }
@@ -1,8 +1,9 @@
+use crate::party::PartySettings;
 use skrillax_packet::Packet;
 use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
 use skrillax_serde::*;
 
-#[derive(Clone, Serialize, ByteSize)]
+#[derive(Clone, Default, Serialize, Deserialize, ByteSize, Debug)]
 pub struct GuildInformation {
     pub name: String,
     pub id: u32,
@@ -37,7 +38,7 @@ impl GuildInformation {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub struct FriendListGroup {
     pub id: u16,
     pub name: String,
@@ -53,7 +54,7 @@ impl FriendListGroup {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Debug)]
 pub struct FriendListEntry {
     pub char_id: u32,
     pub name: String,
@@ -74,7 +75,7 @@ impl FriendListEntry {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x3305)]
 pub struct FriendListInfo {
     pub groups: Vec<FriendListGroup>,
@@ -87,30 +88,266 @@ impl FriendListInfo {
     }
 }
 
-#[derive(Clone, Deserialize, ByteSize, Packet, Debug)]
+// TODO: these codes have not been verified against the client yet
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+#[silkroad(size = 2)]
+pub enum FriendError {
+    #[silkroad(value = 0x6401)]
+    InvalidTarget,
+    #[silkroad(value = 0x6402)]
+    AlreadyFriends,
+    #[silkroad(value = 0x6403)]
+    ListFull,
+    #[silkroad(value = 0x6404)]
+    TargetListFull,
+    #[silkroad(value = 0x6405)]
+    NotFriends,
+    #[silkroad(value = 0x6406)]
+    TargetBusy,
+    #[silkroad(value = 0x6407)]
+    Declined,
+    #[silkroad(value = 0x6410)]
+    InvalidGroupName,
+    #[silkroad(value = 0x6411)]
+    GroupExists,
+    #[silkroad(value = 0x6412)]
+    GroupNotFound,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x7302)]
 pub struct AddFriend {
     pub name: String,
 }
 
-#[derive(Clone, Deserialize, ByteSize, Packet, Debug)]
-#[packet(opcode = 0x7310)]
-pub struct CreateFriendGroup {
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB302)]
+pub enum AddFriendResponse {
+    #[silkroad(value = 1)]
+    Success(FriendListEntry),
+    #[silkroad(value = 2)]
+    Failure { code: FriendError },
+}
+
+/// Asks the player whether they want to become friends with the player of the given unique id.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7302)]
+pub struct FriendRequest {
+    pub inviter: u32,
     pub name: String,
 }
 
-#[derive(Clone, Deserialize, ByteSize, Packet, Debug)]
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3303)]
+pub struct FriendRequestAnswer {
+    pub inviter: u32,
+    pub accepted: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x7304)]
 pub struct DeleteFriend {
     pub friend_character_id: u32,
 }
 
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB304)]
+pub enum DeleteFriendResponse {
+    #[silkroad(value = 1)]
+    Success { friend_character_id: u32 },
+    #[silkroad(value = 2)]
+    Failure { code: FriendError },
+}
+
+/// Changes to the friend list which were not caused by the player themselves.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3306)]
+pub enum FriendListUpdate {
+    #[silkroad(value = 2)]
+    Added(FriendListEntry),
+    #[silkroad(value = 3)]
+    Removed { friend_character_id: u32 },
+    #[silkroad(value = 9)]
+    StatusChanged { friend_character_id: u32, offline: bool },
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7310)]
+pub struct CreateFriendGroup {
+    pub name: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB310)]
+pub enum CreateFriendGroupResponse {
+    #[silkroad(value = 1)]
+    Success(FriendListGroup),
+    #[silkroad(value = 2)]
+    Failure { code: FriendError },
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7311)]
+pub struct DeleteFriendGroup {
+    pub group_id: u16,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB311)]
+pub enum DeleteFriendGroupResponse {
+    #[silkroad(value = 1)]
+    Success { group_id: u16 },
+    #[silkroad(value = 2)]
+    Failure { code: FriendError },
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7312)]
+pub struct MoveFriendToGroup {
+    pub friend_character_id: u32,
+    pub group_id: u16,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB312)]
+pub enum MoveFriendToGroupResponse {
+    #[silkroad(value = 1)]
+    Success { friend_character_id: u32, group_id: u16 },
+    #[silkroad(value = 2)]
+    Failure { code: FriendError },
+}
+
+/// Asks the player whether they want to accept the invitation of another player.
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3080)]
+pub enum InvitationRequest {
+    #[silkroad(value = 1)]
+    Exchange { inviter: u32 },
+    #[silkroad(value = 2)]
+    PartyCreation { inviter: u32, settings: PartySettings },
+    #[silkroad(value = 3)]
+    PartyInvitation { inviter: u32, settings: PartySettings },
+    #[silkroad(value = 5)]
+    GuildInvitation { inviter: u32 },
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3080)]
+pub enum InvitationResponse {
+    #[silkroad(value = 1)]
+    Answer { accepted: bool },
+    #[silkroad(value = 2)]
+    Failure { code: u16 },
+}
+
+impl InvitationResponse {
+    pub fn accepted(&self) -> bool {
+        matches!(self, InvitationResponse::Answer { accepted: true })
+    }
+}
+
 define_inbound_protocol! { FriendListClientProtocol =>
     AddFriend,
+    FriendRequestAnswer,
+    DeleteFriend,
     CreateFriendGroup,
-    DeleteFriend
+    DeleteFriendGroup,
+    MoveFriendToGroup
+}
+
+define_inbound_protocol! { InvitationClientProtocol =>
+    InvitationResponse
+}
+
+define_outbound_protocol! { InvitationServerProtocol =>
+    InvitationRequest
 }
 
 define_outbound_protocol! { FriendListServerProtocol =>
-    FriendListInfo
+    FriendListInfo,
+    AddFriendResponse,
+    FriendRequest,
+    DeleteFriendResponse,
+    FriendListUpdate,
+    CreateFriendGroupResponse,
+    DeleteFriendGroupResponse,
+    MoveFriendToGroupResponse
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&InvitationRequest::PartyCreation {
+            inviter: 0x10,
+            settings: PartySettings::new(false, false, false),
+        });
+        assert_roundtrip(&InvitationRequest::GuildInvitation { inviter: 0x10 });
+        assert_roundtrip(&InvitationRequest::Exchange { inviter: 0x10 });
+        assert_roundtrip(&InvitationResponse::Answer { accepted: true });
+        assert_roundtrip(&InvitationResponse::Failure { code: 0x2C0C });
+        assert_roundtrip(&FriendListInfo::new(
+            vec![
+                FriendListGroup::not_assigned(),
+                FriendListGroup::new(1, "Guild".to_string()),
+            ],
+            vec![FriendListEntry::new(0x10, "Tester".to_string(), 1907, 1, false)],
+        ));
+        assert_roundtrip(&AddFriend {
+            name: "Tester".to_string(),
+        });
+        assert_roundtrip(&CreateFriendGroup {
+            name: "Guild".to_string(),
+        });
+        assert_roundtrip(&DeleteFriend {
+            friend_character_id: 0x10,
+        });
+        assert_roundtrip(&AddFriendResponse::Success(FriendListEntry::new(
+            0x10,
+            "Tester".to_string(),
+            1907,
+            0,
+            false,
+        )));
+        assert_roundtrip(&AddFriendResponse::Failure {
+            code: FriendError::Declined,
+        });
+        assert_roundtrip(&FriendRequest {
+            inviter: 0x20,
+            name: "Tester".to_string(),
+        });
+        assert_roundtrip(&FriendRequestAnswer {
+            inviter: 0x20,
+            accepted: true,
+        });
+        assert_roundtrip(&DeleteFriendResponse::Success {
+            friend_character_id: 0x10,
+        });
+        assert_roundtrip(&FriendListUpdate::StatusChanged {
+            friend_character_id: 0x10,
+            offline: true,
+        });
+        assert_roundtrip(&FriendListUpdate::Removed {
+            friend_character_id: 0x10,
+        });
+        assert_roundtrip(&CreateFriendGroupResponse::Success(FriendListGroup::new(
+            1,
+            "Guild".to_string(),
+        )));
+        assert_roundtrip(&DeleteFriendGroup { group_id: 1 });
+        assert_roundtrip(&DeleteFriendGroupResponse::Failure {
+            code: FriendError::GroupNotFound,
+        });
+        assert_roundtrip(&MoveFriendToGroup {
+            friend_character_id: 0x10,
+            group_id: 1,
+        });
+        assert_roundtrip(&MoveFriendToGroupResponse::Success {
+            friend_character_id: 0x10,
+            group_id: 1,
+        });
+    }
 }
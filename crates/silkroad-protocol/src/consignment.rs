@@ -0,0 +1,332 @@
+use crate::inventory::InventoryItemContentData;
+use skrillax_packet::Packet;
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+use skrillax_serde::*;
+
+// TODO: apart from `NotEnoughGold`, these codes have not been verified against the client yet
+#[derive(Clone, Eq, PartialEq, Copy, Serialize, ByteSize, Deserialize, Debug)]
+#[silkroad(size = 2)]
+pub enum ConsignmentErrorCode {
+    #[silkroad(value = 0x7001)]
+    InvalidItem,
+    #[silkroad(value = 0x7002)]
+    InvalidPrice,
+    #[silkroad(value = 0x7003)]
+    InvalidDuration,
+    #[silkroad(value = 0x7004)]
+    TooManyItems,
+    #[silkroad(value = 0x7005)]
+    NotAvailable,
+    #[silkroad(value = 0x7006)]
+    OwnItem,
+    #[silkroad(value = 0x7007)]
+    InventoryFull,
+    #[silkroad(value = 0x700D)]
+    NotEnoughGold,
+}
+
+#[derive(Clone, Serialize, ByteSize, Deserialize, Debug)]
+pub enum ConsignmentResult {
+    #[silkroad(value = 1)]
+    Success { items: Vec<ConsignmentItem> },
+    #[silkroad(value = 2)]
+    Failure { code: ConsignmentErrorCode },
+}
+
+impl ConsignmentResult {
+    pub fn success(items: Vec<ConsignmentItem>) -> Self {
+        ConsignmentResult::Success { items }
+    }
+
+    pub fn error(code: ConsignmentErrorCode) -> Self {
+        ConsignmentResult::Failure { code }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum ConsignmentOperationResult {
+    #[silkroad(value = 1)]
+    Success,
+    #[silkroad(value = 2)]
+    Failure { code: ConsignmentErrorCode },
+}
+
+impl ConsignmentOperationResult {
+    pub fn error(code: ConsignmentErrorCode) -> Self {
+        ConsignmentOperationResult::Failure { code }
+    }
+}
+
+/// An item the player has registered for consignment. The `status` is `0` while the item is
+/// still listed, `1` once it has been sold and `2` once it has expired without being sold.
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub struct ConsignmentItem {
+    pub personal_id: u32,
+    pub status: u8,
+    pub ref_item_id: u32,
+    pub sell_count: u32,
+    pub price: u64,
+    pub deposit: u64,
+    pub fee: u64,
+    pub end_date: u32,
+}
+
+impl ConsignmentItem {
+    pub fn new(
+        personal_id: u32,
+        status: u8,
+        ref_item_id: u32,
+        sell_count: u32,
+        price: u64,
+        deposit: u64,
+        fee: u64,
+        end_date: u32,
+    ) -> Self {
+        ConsignmentItem {
+            personal_id,
+            status,
+            ref_item_id,
+            sell_count,
+            price,
+            deposit,
+            fee,
+            end_date,
+        }
+    }
+}
+
+/// Limits a search to items of the given type. A value of `0` matches any type on that level.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+pub struct ConsignmentCategory {
+    pub type_id2: u8,
+    pub type_id3: u8,
+    pub type_id4: u8,
+}
+
+/// An item offered by another player, as it shows up in the search.
+#[derive(Clone, Serialize, ByteSize, Debug)]
+pub struct ConsignmentSearchEntry {
+    pub id: u32,
+    pub seller: String,
+    pub ref_item_id: u32,
+    pub content_data: InventoryItemContentData,
+    pub price: u64,
+    pub end_date: u32,
+}
+
+#[derive(Clone, Serialize, ByteSize, Debug)]
+pub enum ConsignmentSearchResult {
+    #[silkroad(value = 1)]
+    Success {
+        page: u8,
+        pages: u8,
+        entries: Vec<ConsignmentSearchEntry>,
+    },
+    #[silkroad(value = 2)]
+    Failure { code: ConsignmentErrorCode },
+}
+
+#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
+#[packet(opcode = 0x7508)]
+pub struct ConsignmentRegister {
+    pub slot: u8,
+    pub amount: u16,
+    pub price: u64,
+    /// The number of days the item stays listed.
+    pub duration: u8,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Packet, Debug)]
+#[packet(opcode = 0xB508)]
+pub struct ConsignmentRegisterResponse {
+    pub result: ConsignmentOperationResult,
+}
+
+impl ConsignmentRegisterResponse {
+    pub fn new(result: ConsignmentOperationResult) -> Self {
+        ConsignmentRegisterResponse { result }
+    }
+}
+
+/// Takes back an item which has either not been sold yet or which has expired.
+#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
+#[packet(opcode = 0x7509)]
+pub struct ConsignmentCancel {
+    pub id: u32,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Packet, Debug)]
+#[packet(opcode = 0xB509)]
+pub struct ConsignmentCancelResponse {
+    pub result: ConsignmentOperationResult,
+}
+
+impl ConsignmentCancelResponse {
+    pub fn new(result: ConsignmentOperationResult) -> Self {
+        ConsignmentCancelResponse { result }
+    }
+}
+
+/// Collects the gold of all sold items as well as all expired items.
+#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
+#[packet(opcode = 0x750A)]
+pub struct ConsignmentSettle;
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Packet, Debug)]
+#[packet(opcode = 0xB50A)]
+pub struct ConsignmentSettleResponse {
+    pub result: ConsignmentOperationResult,
+}
+
+impl ConsignmentSettleResponse {
+    pub fn new(result: ConsignmentOperationResult) -> Self {
+        ConsignmentSettleResponse { result }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
+#[packet(opcode = 0x750C)]
+pub struct ConsignmentSearch {
+    pub category: ConsignmentCategory,
+    pub page: u8,
+}
+
+#[derive(Clone, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB50C)]
+pub struct ConsignmentSearchResponse {
+    pub result: ConsignmentSearchResult,
+}
+
+impl ConsignmentSearchResponse {
+    pub fn new(result: ConsignmentSearchResult) -> Self {
+        ConsignmentSearchResponse { result }
+    }
+}
+
+#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
+#[packet(opcode = 0x750D)]
+pub struct ConsignmentBuy {
+    pub id: u32,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Packet, Debug)]
+#[packet(opcode = 0xB50D)]
+pub struct ConsignmentBuyResponse {
+    pub result: ConsignmentOperationResult,
+}
+
+impl ConsignmentBuyResponse {
+    pub fn new(result: ConsignmentOperationResult) -> Self {
+        ConsignmentBuyResponse { result }
+    }
+}
+
+/// Lists the items the player has registered.
+#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
+#[packet(opcode = 0x750E)]
+pub struct ConsignmentList;
+
+#[derive(Clone, Serialize, ByteSize, Deserialize, Packet, Debug)]
+#[packet(opcode = 0xB50E)]
+pub struct ConsignmentResponse {
+    pub result: ConsignmentResult,
+}
+
+impl ConsignmentResponse {
+    pub fn new(result: ConsignmentResult) -> Self {
+        ConsignmentResponse { result }
+    }
+
+    pub fn success_empty() -> Self {
+        ConsignmentResponse {
+            result: ConsignmentResult::Success { items: vec![] },
+        }
+    }
+}
+
+define_inbound_protocol! { ConsignmentClientProtocol =>
+    ConsignmentRegister,
+    ConsignmentCancel,
+    ConsignmentSettle,
+    ConsignmentSearch,
+    ConsignmentBuy,
+    ConsignmentList
+}
+
+define_outbound_protocol! { ConsignmentServerProtocol =>
+    ConsignmentRegisterResponse,
+    ConsignmentCancelResponse,
+    ConsignmentSettleResponse,
+    ConsignmentSearchResponse,
+    ConsignmentBuyResponse,
+    ConsignmentResponse
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{assert_golden, assert_roundtrip, assert_size};
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&ConsignmentList);
+        assert_roundtrip(&ConsignmentResponse::success_empty());
+        assert_roundtrip(&ConsignmentResponse::new(ConsignmentResult::success(vec![
+            ConsignmentItem::new(1, 0, 8, 5, 1000, 100, 10, 0x12345678),
+        ])));
+        assert_roundtrip(&ConsignmentResponse::new(ConsignmentResult::error(
+            ConsignmentErrorCode::NotEnoughGold,
+        )));
+        assert_roundtrip(&ConsignmentRegister {
+            slot: 13,
+            amount: 5,
+            price: 1000,
+            duration: 3,
+        });
+        assert_roundtrip(&ConsignmentRegisterResponse::new(ConsignmentOperationResult::Success));
+        assert_roundtrip(&ConsignmentCancel { id: 1 });
+        assert_roundtrip(&ConsignmentCancelResponse::new(ConsignmentOperationResult::error(
+            ConsignmentErrorCode::NotAvailable,
+        )));
+        assert_roundtrip(&ConsignmentSettle);
+        assert_roundtrip(&ConsignmentSettleResponse::new(ConsignmentOperationResult::error(
+            ConsignmentErrorCode::InventoryFull,
+        )));
+        assert_roundtrip(&ConsignmentSearch {
+            category: ConsignmentCategory {
+                type_id2: 1,
+                type_id3: 6,
+                type_id4: 0,
+            },
+            page: 0,
+        });
+        assert_roundtrip(&ConsignmentBuy { id: 1 });
+        assert_roundtrip(&ConsignmentBuyResponse::new(ConsignmentOperationResult::error(
+            ConsignmentErrorCode::NotAvailable,
+        )));
+    }
+
+    #[test]
+    fn test_packets_size() {
+        assert_size(&ConsignmentSearchResponse::new(ConsignmentSearchResult::Success {
+            page: 0,
+            pages: 1,
+            entries: vec![ConsignmentSearchEntry {
+                id: 1,
+                seller: "Seller".to_string(),
+                ref_item_id: 8,
+                content_data: InventoryItemContentData::expendable(5),
+                price: 1000,
+                end_date: 0x12345678,
+            }],
+        }));
+    }
+
+    #[test]
+    fn test_packets_golden() {
+        assert_golden(
+            &ConsignmentCancelResponse::new(ConsignmentOperationResult::error(ConsignmentErrorCode::NotAvailable)),
+            include_str!("../fixtures/consignment_cancel_unavailable.hex"),
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use crate::inventory::InventoryItemData;
+use skrillax_packet::Packet;
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+use skrillax_serde::*;
+
+// TODO: these codes have not been verified against the client yet
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+#[silkroad(size = 2)]
+pub enum ExchangeError {
+    #[silkroad(value = 0x3C01)]
+    InvalidTarget,
+    #[silkroad(value = 0x3C02)]
+    TooFarAway,
+    #[silkroad(value = 0x3C03)]
+    AlreadyExchanging,
+    #[silkroad(value = 0x3C04)]
+    TargetBusy,
+    #[silkroad(value = 0x3C05)]
+    Declined,
+    #[silkroad(value = 0x3C06)]
+    NotExchanging,
+    #[silkroad(value = 0x3C07)]
+    NotConfirmed,
+    #[silkroad(value = 0x3C08)]
+    InventoryFull,
+    #[silkroad(value = 0x3C09)]
+    TargetInventoryFull,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum ExchangeResult {
+    #[silkroad(value = 1)]
+    Success,
+    #[silkroad(value = 2)]
+    Failure { code: ExchangeError },
+}
+
+impl ExchangeResult {
+    pub fn error(code: ExchangeError) -> Self {
+        ExchangeResult::Failure { code }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7081)]
+pub struct ExchangeStartRequest {
+    pub target: u32,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB081)]
+pub enum ExchangeStartResponse {
+    #[silkroad(value = 1)]
+    Success { partner: u32 },
+    #[silkroad(value = 2)]
+    Failure { code: ExchangeError },
+}
+
+/// Opens the exchange window for the player who accepted the invitation.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3085)]
+pub struct ExchangeStarted {
+    pub partner: u32,
+}
+
+/// Locks the offer of the player, such that it can no longer be changed.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7082)]
+pub struct ExchangeConfirmRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB082)]
+pub struct ExchangeConfirmResponse {
+    pub result: ExchangeResult,
+}
+
+impl ExchangeConfirmResponse {
+    pub fn new(result: ExchangeResult) -> Self {
+        ExchangeConfirmResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3086)]
+pub struct ExchangePartnerConfirmed;
+
+/// Agrees to the offers of both players. Once both players approved, the exchange is done.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7083)]
+pub struct ExchangeApproveRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB083)]
+pub struct ExchangeApproveResponse {
+    pub result: ExchangeResult,
+}
+
+impl ExchangeApproveResponse {
+    pub fn new(result: ExchangeResult) -> Self {
+        ExchangeApproveResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3087)]
+pub struct ExchangeCompleted;
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7084)]
+pub struct ExchangeCancelRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB084)]
+pub struct ExchangeCancelResponse {
+    pub result: ExchangeResult,
+}
+
+impl ExchangeCancelResponse {
+    pub fn new(result: ExchangeResult) -> Self {
+        ExchangeCancelResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3088)]
+pub struct ExchangeCancelled;
+
+/// The items the given player currently offers, where the slot of each item is its position
+/// inside the exchange window.
+#[derive(Clone, Serialize, ByteSize, Packet)]
+#[packet(opcode = 0x3089)]
+pub struct ExchangeItemsUpdate {
+    pub owner: u32,
+    pub items: Vec<InventoryItemData>,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x308C)]
+pub struct ExchangeGoldUpdate {
+    pub owner: u32,
+    pub amount: u64,
+}
+
+define_inbound_protocol! { ExchangeClientProtocol =>
+    ExchangeStartRequest,
+    ExchangeConfirmRequest,
+    ExchangeApproveRequest,
+    ExchangeCancelRequest
+}
+
+define_outbound_protocol! { ExchangeServerProtocol =>
+    ExchangeStartResponse,
+    ExchangeStarted,
+    ExchangeConfirmResponse,
+    ExchangePartnerConfirmed,
+    ExchangeApproveResponse,
+    ExchangeCompleted,
+    ExchangeCancelResponse,
+    ExchangeCancelled,
+    ExchangeItemsUpdate,
+    ExchangeGoldUpdate
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inventory::{InventoryItemContentData, RentInfo};
+    use crate::testing::{assert_roundtrip, assert_size};
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&ExchangeStartRequest { target: 0x20 });
+        assert_roundtrip(&ExchangeStartResponse::Success { partner: 0x20 });
+        assert_roundtrip(&ExchangeStartResponse::Failure {
+            code: ExchangeError::TooFarAway,
+        });
+        assert_roundtrip(&ExchangeStarted { partner: 0x10 });
+        assert_roundtrip(&ExchangeConfirmRequest);
+        assert_roundtrip(&ExchangeConfirmResponse::new(ExchangeResult::Success));
+        assert_roundtrip(&ExchangePartnerConfirmed);
+        assert_roundtrip(&ExchangeApproveRequest);
+        assert_roundtrip(&ExchangeApproveResponse::new(ExchangeResult::error(
+            ExchangeError::TargetInventoryFull,
+        )));
+        assert_roundtrip(&ExchangeCompleted);
+        assert_roundtrip(&ExchangeCancelRequest);
+        assert_roundtrip(&ExchangeCancelResponse::new(ExchangeResult::Success));
+        assert_roundtrip(&ExchangeCancelled);
+        assert_roundtrip(&ExchangeGoldUpdate {
+            owner: 0x10,
+            amount: 10_000,
+        });
+    }
+
+    #[test]
+    fn test_items_update_size() {
+        assert_size(&ExchangeItemsUpdate {
+            owner: 0x10,
+            items: vec![InventoryItemData::new(
+                0,
+                RentInfo::Empty,
+                1907,
+                InventoryItemContentData::expendable(5),
+            )],
+        });
+    }
+}
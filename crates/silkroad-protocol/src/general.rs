@@ -94,3 +94,27 @@ define_protocol! { HandshakeProtocol =>
     HandshakeChallenge,
     HandshakeAccepted
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&IdentityInformation::new("AgentServer".to_string(), 0));
+        assert_roundtrip(&KeepAlive);
+        assert_roundtrip(&SecuritySetup::new(HandshakeStage::initialize(
+            0x0123456789ABCDEF,
+            0x21,
+            0x42,
+            0xFEDCBA9876543210,
+            1,
+            2,
+            3,
+        )));
+        assert_roundtrip(&SecuritySetup::new(HandshakeStage::finalize(0x1122334455667788)));
+        assert_roundtrip(&HandshakeChallenge { b: 0x42, key: 0x1234 });
+        assert_roundtrip(&HandshakeAccepted);
+    }
+}
@@ -83,3 +83,26 @@ define_inbound_protocol! { GmClientProtocol =>
 define_outbound_protocol! { GmServerProtocol =>
     GmResponse
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+    use silkroad_definitions::rarity::EntityRarityType;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&GmCommand::SpawnMonster {
+            ref_id: 1954,
+            amount: 2,
+            rarity: EntityRarityType::Champion.into(),
+        });
+        assert_roundtrip(&GmCommand::MakeItem { ref_id: 8, upgrade: 0 });
+        assert_roundtrip(&GmCommand::Invincible);
+        assert_roundtrip(&GmResponse::success_message("Done".to_string()));
+        assert_roundtrip(&GmResponse::print_entity_ids(1, 2, 3));
+        assert_roundtrip(&GmResponse {
+            result: GmResponseResult::Failure,
+        });
+    }
+}
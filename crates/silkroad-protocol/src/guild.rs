@@ -0,0 +1,415 @@
+use crate::community::GuildInformation;
+use skrillax_packet::Packet;
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+use skrillax_serde::*;
+
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+pub enum GuildRank {
+    #[silkroad(value = 0)]
+    Master,
+    #[silkroad(value = 1)]
+    Deputy,
+    #[silkroad(value = 2)]
+    Member,
+}
+
+/// The actions a member is allowed to perform inside their guild, packed into a single byte.
+/// The master of the guild is always allowed to do everything, regardless of these flags.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+pub struct GuildPermissions(pub u8);
+
+impl GuildPermissions {
+    const INVITE: u8 = 0b001;
+    const KICK: u8 = 0b010;
+    const NOTICE: u8 = 0b100;
+
+    pub fn new(invite: bool, kick: bool, notice: bool) -> Self {
+        let mut flags = 0;
+        if invite {
+            flags |= Self::INVITE;
+        }
+        if kick {
+            flags |= Self::KICK;
+        }
+        if notice {
+            flags |= Self::NOTICE;
+        }
+        GuildPermissions(flags)
+    }
+
+    pub fn all() -> Self {
+        GuildPermissions(Self::INVITE | Self::KICK | Self::NOTICE)
+    }
+
+    pub fn none() -> Self {
+        GuildPermissions(0)
+    }
+
+    pub fn can_invite(&self) -> bool {
+        self.0 & Self::INVITE != 0
+    }
+
+    pub fn can_kick(&self) -> bool {
+        self.0 & Self::KICK != 0
+    }
+
+    pub fn can_edit_notice(&self) -> bool {
+        self.0 & Self::NOTICE != 0
+    }
+}
+
+// TODO: these codes have not been verified against the client yet
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+#[silkroad(size = 2)]
+pub enum GuildError {
+    #[silkroad(value = 0x4C01)]
+    InvalidTarget,
+    #[silkroad(value = 0x4C02)]
+    InvalidName,
+    #[silkroad(value = 0x4C03)]
+    NameTaken,
+    #[silkroad(value = 0x4C04)]
+    NotEnoughGold,
+    #[silkroad(value = 0x4C05)]
+    LevelTooLow,
+    #[silkroad(value = 0x4C06)]
+    TooFarAway,
+    #[silkroad(value = 0x4C07)]
+    AlreadyInGuild,
+    #[silkroad(value = 0x4C08)]
+    TargetAlreadyInGuild,
+    #[silkroad(value = 0x4C09)]
+    NotInGuild,
+    #[silkroad(value = 0x4C0A)]
+    NoPermission,
+    #[silkroad(value = 0x4C0B)]
+    GuildFull,
+    #[silkroad(value = 0x4C0C)]
+    TargetBusy,
+    #[silkroad(value = 0x4C0D)]
+    Declined,
+    #[silkroad(value = 0x4C0E)]
+    MasterCannotLeave,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum GuildResult {
+    #[silkroad(value = 1)]
+    Success,
+    #[silkroad(value = 2)]
+    Failure { code: GuildError },
+}
+
+impl GuildResult {
+    pub fn error(code: GuildError) -> Self {
+        GuildResult::Failure { code }
+    }
+}
+
+#[derive(Clone, Serialize, ByteSize, Deserialize, Debug)]
+pub struct GuildMemberInfo {
+    pub character_id: u32,
+    pub name: String,
+    pub rank: GuildRank,
+    pub permissions: GuildPermissions,
+    pub level: u8,
+    pub ref_id: u32,
+    pub online: bool,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum GuildLeaveReason {
+    #[silkroad(value = 1)]
+    Left,
+    #[silkroad(value = 2)]
+    Kicked,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70F0)]
+pub struct GuildCreateRequest {
+    pub npc: u32,
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0F0)]
+pub struct GuildCreateResponse {
+    pub result: GuildResult,
+}
+
+impl GuildCreateResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildCreateResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70F1)]
+pub struct GuildDisbandRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0F1)]
+pub struct GuildDisbandResponse {
+    pub result: GuildResult,
+}
+
+impl GuildDisbandResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildDisbandResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70F2)]
+pub struct GuildLeaveRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0F2)]
+pub struct GuildLeaveResponse {
+    pub result: GuildResult,
+}
+
+impl GuildLeaveResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildLeaveResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70F3)]
+pub struct GuildInviteRequest {
+    pub target: u32,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0F3)]
+pub struct GuildInviteResponse {
+    pub result: GuildResult,
+}
+
+impl GuildInviteResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildInviteResponse { result }
+    }
+}
+
+/// Kicks a member by name, as they don't have to be online to be kicked.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70F4)]
+pub struct GuildKickRequest {
+    pub name: String,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0F4)]
+pub struct GuildKickResponse {
+    pub result: GuildResult,
+}
+
+impl GuildKickResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildKickResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70F9)]
+pub struct GuildNoticeRequest {
+    pub title: String,
+    pub content: String,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0F9)]
+pub struct GuildNoticeResponse {
+    pub result: GuildResult,
+}
+
+impl GuildNoticeResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildNoticeResponse { result }
+    }
+}
+
+/// Changes the rank and permissions of a member. Promoting a member to [GuildRank::Master]
+/// hands over the guild, turning the current master into a deputy.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7104)]
+pub struct GuildAuthorityRequest {
+    pub name: String,
+    pub rank: GuildRank,
+    pub permissions: GuildPermissions,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB104)]
+pub struct GuildAuthorityResponse {
+    pub result: GuildResult,
+}
+
+impl GuildAuthorityResponse {
+    pub fn new(result: GuildResult) -> Self {
+        GuildAuthorityResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3101)]
+pub struct GuildInfo {
+    pub id: u32,
+    pub name: String,
+    pub level: u8,
+    pub notice_title: String,
+    pub notice: String,
+    pub members: Vec<GuildMemberInfo>,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x38F5)]
+pub enum GuildUpdate {
+    #[silkroad(value = 1)]
+    Disbanded,
+    #[silkroad(value = 2)]
+    MemberJoined(GuildMemberInfo),
+    #[silkroad(value = 3)]
+    MemberLeft {
+        character_id: u32,
+        reason: GuildLeaveReason,
+    },
+    #[silkroad(value = 6)]
+    MemberAuthority {
+        character_id: u32,
+        rank: GuildRank,
+        permissions: GuildPermissions,
+    },
+    #[silkroad(value = 7)]
+    MemberStatus { character_id: u32, online: bool },
+    #[silkroad(value = 8)]
+    MemberLevel { character_id: u32, level: u8 },
+    #[silkroad(value = 9)]
+    Notice { title: String, content: String },
+}
+
+/// Updates the guild shown above the head of the given player for everyone who can see them.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x30FF)]
+pub struct EntityGuildUpdate {
+    pub unique_id: u32,
+    pub guild: GuildInformation,
+}
+
+define_inbound_protocol! { GuildClientProtocol =>
+    GuildCreateRequest,
+    GuildDisbandRequest,
+    GuildLeaveRequest,
+    GuildInviteRequest,
+    GuildKickRequest,
+    GuildNoticeRequest,
+    GuildAuthorityRequest
+}
+
+define_outbound_protocol! { GuildServerProtocol =>
+    GuildCreateResponse,
+    GuildDisbandResponse,
+    GuildLeaveResponse,
+    GuildInviteResponse,
+    GuildKickResponse,
+    GuildNoticeResponse,
+    GuildAuthorityResponse,
+    GuildInfo,
+    GuildUpdate,
+    EntityGuildUpdate
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    fn member() -> GuildMemberInfo {
+        GuildMemberInfo {
+            character_id: 0x10,
+            name: "Tester".to_string(),
+            rank: GuildRank::Deputy,
+            permissions: GuildPermissions::new(true, false, true),
+            level: 20,
+            ref_id: 1907,
+            online: true,
+        }
+    }
+
+    #[test]
+    fn test_permissions() {
+        let permissions = GuildPermissions::new(true, false, true);
+        assert_eq!(permissions, GuildPermissions(0b101));
+        assert!(permissions.can_invite());
+        assert!(!permissions.can_kick());
+        assert!(permissions.can_edit_notice());
+        assert!(GuildPermissions::all().can_kick());
+        assert!(!GuildPermissions::none().can_invite());
+    }
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&GuildCreateRequest {
+            npc: 0x20,
+            name: "Guild".to_string(),
+        });
+        assert_roundtrip(&GuildCreateResponse::new(GuildResult::error(GuildError::NameTaken)));
+        assert_roundtrip(&GuildDisbandRequest);
+        assert_roundtrip(&GuildDisbandResponse::new(GuildResult::Success));
+        assert_roundtrip(&GuildLeaveRequest);
+        assert_roundtrip(&GuildLeaveResponse::new(GuildResult::error(
+            GuildError::MasterCannotLeave,
+        )));
+        assert_roundtrip(&GuildInviteRequest { target: 0x20 });
+        assert_roundtrip(&GuildInviteResponse::new(GuildResult::Success));
+        assert_roundtrip(&GuildKickRequest {
+            name: "Tester".to_string(),
+        });
+        assert_roundtrip(&GuildKickResponse::new(GuildResult::error(GuildError::NoPermission)));
+        assert_roundtrip(&GuildNoticeRequest {
+            title: "Welcome".to_string(),
+            content: "Be nice.".to_string(),
+        });
+        assert_roundtrip(&GuildNoticeResponse::new(GuildResult::Success));
+        assert_roundtrip(&GuildAuthorityRequest {
+            name: "Tester".to_string(),
+            rank: GuildRank::Member,
+            permissions: GuildPermissions::none(),
+        });
+        assert_roundtrip(&GuildAuthorityResponse::new(GuildResult::Success));
+        assert_roundtrip(&GuildInfo {
+            id: 1,
+            name: "Guild".to_string(),
+            level: 1,
+            notice_title: String::new(),
+            notice: String::new(),
+            members: vec![member()],
+        });
+        assert_roundtrip(&GuildUpdate::Disbanded);
+        assert_roundtrip(&GuildUpdate::MemberJoined(member()));
+        assert_roundtrip(&GuildUpdate::MemberLeft {
+            character_id: 0x10,
+            reason: GuildLeaveReason::Kicked,
+        });
+        assert_roundtrip(&GuildUpdate::MemberAuthority {
+            character_id: 0x10,
+            rank: GuildRank::Master,
+            permissions: GuildPermissions::all(),
+        });
+        assert_roundtrip(&GuildUpdate::MemberStatus {
+            character_id: 0x10,
+            online: false,
+        });
+        assert_roundtrip(&GuildUpdate::Notice {
+            title: "Welcome".to_string(),
+            content: "Be nice.".to_string(),
+        });
+        assert_roundtrip(&EntityGuildUpdate {
+            unique_id: 0x10,
+            guild: GuildInformation::new("Guild".to_string(), 1, "Tester".to_string(), 0, 0, 0, 1),
+        });
+    }
+}
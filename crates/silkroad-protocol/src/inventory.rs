@@ -12,6 +12,35 @@ pub enum InventoryOperationRequest {
     PickupItem { unique_id: u32 },
     #[silkroad(value = 0x07)]
     DropItem { slot: u8 },
+    #[silkroad(value = 0x04)]
+    InventoryToExchange { slot: u8 },
+    #[silkroad(value = 0x05)]
+    ExchangeToInventory { exchange_slot: u8 },
+    #[silkroad(value = 0x0D)]
+    ExchangeGold { amount: u64 },
+    #[silkroad(value = 0x08)]
+    BuyItem { tab: u8, slot: u8, amount: u16, npc: u32 },
+    #[silkroad(value = 0x09)]
+    SellItem { slot: u8, amount: u16, npc: u32 },
+    // TODO: the layout of the buy back request has not been verified yet
+    #[silkroad(value = 0x22)]
+    BuyBackItem { npc: u32, buyback_slot: u8, amount: u16 },
+    // TODO: the layout of the storage requests has not been verified yet
+    #[silkroad(value = 0x01)]
+    StorageMove {
+        source: u8,
+        target: u8,
+        amount: u16,
+        npc: u32,
+    },
+    #[silkroad(value = 0x02)]
+    InventoryToStorage { slot: u8, storage_slot: u8, npc: u32 },
+    #[silkroad(value = 0x03)]
+    StorageToInventory { storage_slot: u8, slot: u8, npc: u32 },
+    #[silkroad(value = 0x0C)]
+    StorageDepositGold { amount: u64, npc: u32 },
+    #[silkroad(value = 0x0B)]
+    StorageWithdrawGold { amount: u64, npc: u32 },
 }
 
 impl InventoryOperationRequest {
@@ -104,6 +133,43 @@ pub enum InventoryOperationResponseData {
     DropGold { amount: u64 },
     #[silkroad(value = 0x06)]
     PickupItem { slot: u8, item: ItemPickupData },
+    #[silkroad(value = 0x04)]
+    InventoryToExchange { slot: u8 },
+    #[silkroad(value = 0x05)]
+    ExchangeToInventory { exchange_slot: u8 },
+    #[silkroad(value = 0x0D)]
+    ExchangeGold { amount: u64 },
+    #[silkroad(value = 0x08)]
+    BuyItem {
+        tab: u8,
+        slot: u8,
+        #[silkroad(list_type = "length")]
+        target_slots: Vec<u8>,
+        amount: u16,
+    },
+    #[silkroad(value = 0x09)]
+    SellItem {
+        slot: u8,
+        amount: u16,
+        npc: u32,
+        buyback_slot: u8,
+    },
+    #[silkroad(value = 0x22)]
+    BuyBackItem {
+        target_slot: u8,
+        buyback_slot: u8,
+        amount: u16,
+    },
+    #[silkroad(value = 0x01)]
+    StorageMove { source: u8, target: u8, amount: u16 },
+    #[silkroad(value = 0x02)]
+    InventoryToStorage { slot: u8, storage_slot: u8 },
+    #[silkroad(value = 0x03)]
+    StorageToInventory { storage_slot: u8, slot: u8 },
+    #[silkroad(value = 0x0C)]
+    StorageDepositGold { amount: u64 },
+    #[silkroad(value = 0x0B)]
+    StorageWithdrawGold { amount: u64 },
     #[silkroad(value = 0x0e)]
     AddedByServer {
         slot: u8,
@@ -131,31 +197,6 @@ impl InventoryOperationResponseData {
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Copy, Serialize, ByteSize, Deserialize, Debug)]
-#[silkroad(size = 2)]
-pub enum ConsignmentErrorCode {
-    #[silkroad(value = 0x700D)]
-    NotEnoughGold,
-}
-
-#[derive(Clone, Serialize, ByteSize, Deserialize, Debug)]
-pub enum ConsignmentResult {
-    #[silkroad(value = 1)]
-    Success { items: Vec<ConsignmentItem> },
-    #[silkroad(value = 2)]
-    Failure { code: ConsignmentErrorCode },
-}
-
-impl ConsignmentResult {
-    pub fn success(items: Vec<ConsignmentItem>) -> Self {
-        ConsignmentResult::Success { items }
-    }
-
-    pub fn error(code: ConsignmentErrorCode) -> Self {
-        ConsignmentResult::Failure { code }
-    }
-}
-
 #[derive(Clone, Serialize, ByteSize, Debug)]
 #[silkroad(size = 0)]
 pub enum InventoryItemContentData {
@@ -353,64 +394,6 @@ impl CharacterSpawnItemData {
     }
 }
 
-#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
-pub struct ConsignmentItem {
-    pub personal_id: u32,
-    pub status: u8,
-    pub ref_item_id: u32,
-    pub sell_count: u32,
-    pub price: u64,
-    pub deposit: u64,
-    pub fee: u64,
-    pub end_date: u32,
-}
-
-impl ConsignmentItem {
-    pub fn new(
-        personal_id: u32,
-        status: u8,
-        ref_item_id: u32,
-        sell_count: u32,
-        price: u64,
-        deposit: u64,
-        fee: u64,
-        end_date: u32,
-    ) -> Self {
-        ConsignmentItem {
-            personal_id,
-            status,
-            ref_item_id,
-            sell_count,
-            price,
-            deposit,
-            fee,
-            end_date,
-        }
-    }
-}
-
-#[derive(Clone, Copy, Deserialize, ByteSize, Serialize, Packet, Debug)]
-#[packet(opcode = 0x750E)]
-pub struct ConsignmentList;
-
-#[derive(Clone, Serialize, ByteSize, Deserialize, Packet, Debug)]
-#[packet(opcode = 0xB50E)]
-pub struct ConsignmentResponse {
-    pub result: ConsignmentResult,
-}
-
-impl ConsignmentResponse {
-    pub fn new(result: ConsignmentResult) -> Self {
-        ConsignmentResponse { result }
-    }
-
-    pub fn success_empty() -> Self {
-        ConsignmentResponse {
-            result: ConsignmentResult::Success { items: vec![] },
-        }
-    }
-}
-
 #[derive(Clone, Debug, Deserialize, ByteSize, Serialize, Packet)]
 #[packet(opcode = 0x7034)]
 pub struct InventoryOperation {
@@ -435,12 +418,152 @@ pub enum OpenItemMallResult {
 
 define_inbound_protocol! { InventoryClientProtocol =>
     OpenItemMall,
-    InventoryOperation,
-    ConsignmentList
+    InventoryOperation
 }
 
 define_outbound_protocol! { InventoryServerProtocol =>
     OpenItemMallResponse,
-    ConsignmentResponse,
     InventoryOperationResult
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{assert_golden, assert_roundtrip, assert_size};
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::Move {
+                source: 13,
+                target: 14,
+                amount: 2,
+            },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::dropgold(1000),
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::PickupItem { unique_id: 0x1234 },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::InventoryToExchange { slot: 13 },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::ExchangeGold { amount: 1000 },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::BuyItem {
+                tab: 1,
+                slot: 4,
+                amount: 120,
+                npc: 0x20,
+            },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::SellItem {
+                slot: 13,
+                amount: 5,
+                npc: 0x20,
+            },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::BuyBackItem {
+                npc: 0x20,
+                buyback_slot: 0,
+                amount: 5,
+            },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::InventoryToStorage {
+                slot: 13,
+                storage_slot: 0,
+                npc: 0x20,
+            },
+        });
+        assert_roundtrip(&InventoryOperation {
+            data: InventoryOperationRequest::StorageWithdrawGold {
+                amount: 1000,
+                npc: 0x20,
+            },
+        });
+        assert_roundtrip(&OpenItemMall);
+        assert_roundtrip(&OpenItemMallResponse(OpenItemMallResult::Success {
+            jid: 1,
+            token: "token".to_string(),
+        }));
+        assert_roundtrip(&OpenItemMallResponse(OpenItemMallResult::Failure));
+    }
+
+    #[test]
+    fn test_packets_size() {
+        let equipment = InventoryItemContentData::equipment(
+            3,
+            0x1234,
+            50,
+            vec![],
+            InventoryItemBindingData::new(1, 0),
+            InventoryItemBindingData::new(2, 0),
+            InventoryItemBindingData::new(3, 0),
+            InventoryItemBindingData::new(4, 0),
+        );
+        assert_size(&InventoryOperationResult::success_gain_item(13, 3643, equipment));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::move_item(13, 14, 1),
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::dropgold(1000),
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::ExchangeToInventory { exchange_slot: 2 },
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::BuyItem {
+                tab: 1,
+                slot: 4,
+                target_slots: vec![13, 14, 15],
+                amount: 120,
+            },
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::SellItem {
+                slot: 13,
+                amount: 5,
+                npc: 0x20,
+                buyback_slot: 0,
+            },
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::StorageToInventory {
+                storage_slot: 0,
+                slot: 13,
+            },
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::StorageDepositGold { amount: 1000 },
+        ));
+        assert_size(&InventoryOperationResult::Success(
+            InventoryOperationResponseData::AddedByServer {
+                slot: 13,
+                unknown: 0,
+                data: ItemPickupData::item(RentInfo::first(0, 1, 2), 8, InventoryItemContentData::expendable(1)),
+            },
+        ));
+    }
+
+    #[test]
+    fn test_operation_result_golden() {
+        assert_golden(
+            &InventoryOperationResult::success_gain_gold(1000),
+            include_str!("../fixtures/inventory_gain_gold.hex"),
+        );
+        assert_golden(
+            &InventoryOperationResult::success_gain_item(13, 8, InventoryItemContentData::expendable(5)),
+            include_str!("../fixtures/inventory_gain_item.hex"),
+        );
+        assert_golden(
+            &InventoryOperationResult::Failure(InventoryOperationError::InventoryFull),
+            include_str!("../fixtures/inventory_full.hex"),
+        );
+    }
+}
@@ -1,14 +1,23 @@
+pub mod agent;
 pub mod auth;
 pub mod character;
 pub mod chat;
 pub mod combat;
 pub mod community;
+pub mod consignment;
+pub mod exchange;
 pub mod general;
 pub mod gm;
+pub mod guild;
 pub mod inventory;
 pub mod movement;
+pub mod party;
 pub mod skill;
 pub mod spawn;
+pub mod stall;
+pub mod storage;
+#[cfg(test)]
+mod testing;
 pub mod world;
 
 pub use skrillax_serde::SilkroadTime;
@@ -189,3 +189,39 @@ define_outbound_protocol! { MovementServerProtocol =>
     EntityMovementInterrupt,
     ChangeSpeed
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&PlayerMovementRequest {
+            kind: MovementTarget::targetlocation(24744, 960, 0, 1280),
+        });
+        assert_roundtrip(&PlayerMovementRequest {
+            kind: MovementTarget::direction(1, 0x4000),
+        });
+        assert_roundtrip(&PlayerMovementResponse::new(
+            0x1234,
+            MovementDestination::location(24744, 960, 0, 1280),
+            Some(MovementSource::new(24744, 9600, 0.0, 12800)),
+        ));
+        assert_roundtrip(&PlayerMovementResponse::new(
+            0x1234,
+            MovementDestination::direction(true, 0x4000),
+            None,
+        ));
+        assert_roundtrip(&EntityMovementInterrupt {
+            entity_id: 0x1234,
+            position: Position::new(24744, 960.0, 0.0, 1280.0, 0x4000),
+        });
+        assert_roundtrip(&Rotation { heading: 0x4000 });
+        assert_roundtrip(&ChangeSpeed {
+            entity: 0x1234,
+            walk_speed: 16.0,
+            running_speed: 50.0,
+        });
+    }
+}
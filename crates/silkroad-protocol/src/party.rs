@@ -0,0 +1,396 @@
+use skrillax_packet::Packet;
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+use skrillax_serde::*;
+
+/// The settings of a party, which the client packs into a single byte. The lowest bit marks
+/// experience being shared, the second bit marks items being shared and the third bit allows
+/// any member to invite new members, instead of just the leader.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+pub struct PartySettings(pub u8);
+
+impl PartySettings {
+    const EXPERIENCE_SHARED: u8 = 0b001;
+    const ITEMS_SHARED: u8 = 0b010;
+    const ANYONE_CAN_INVITE: u8 = 0b100;
+
+    pub fn new(experience_shared: bool, items_shared: bool, anyone_can_invite: bool) -> Self {
+        let mut flags = 0;
+        if experience_shared {
+            flags |= Self::EXPERIENCE_SHARED;
+        }
+        if items_shared {
+            flags |= Self::ITEMS_SHARED;
+        }
+        if anyone_can_invite {
+            flags |= Self::ANYONE_CAN_INVITE;
+        }
+        PartySettings(flags)
+    }
+
+    pub fn experience_shared(&self) -> bool {
+        self.0 & Self::EXPERIENCE_SHARED != 0
+    }
+
+    pub fn items_shared(&self) -> bool {
+        self.0 & Self::ITEMS_SHARED != 0
+    }
+
+    pub fn anyone_can_invite(&self) -> bool {
+        self.0 & Self::ANYONE_CAN_INVITE != 0
+    }
+}
+
+// TODO: these codes have not been verified against the client yet
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+#[silkroad(size = 2)]
+pub enum PartyError {
+    #[silkroad(value = 0x2C01)]
+    InvalidTarget,
+    #[silkroad(value = 0x2C02)]
+    AlreadyInParty,
+    #[silkroad(value = 0x2C03)]
+    TargetAlreadyInParty,
+    #[silkroad(value = 0x2C04)]
+    NotInParty,
+    #[silkroad(value = 0x2C05)]
+    NotLeader,
+    #[silkroad(value = 0x2C06)]
+    PartyFull,
+    #[silkroad(value = 0x2C07)]
+    TargetBusy,
+    #[silkroad(value = 0x2C0C)]
+    Declined,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum PartyResult {
+    #[silkroad(value = 1)]
+    Success,
+    #[silkroad(value = 2)]
+    Failure { code: PartyError },
+}
+
+impl PartyResult {
+    pub fn error(code: PartyError) -> Self {
+        PartyResult::Failure { code }
+    }
+}
+
+/// The health and mana of a party member, each as tenths of their maximum. The health is
+/// stored in the lower four bits and the mana in the upper four bits.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+pub struct PartyMemberBars(pub u8);
+
+impl PartyMemberBars {
+    pub fn new(health: u32, max_health: u32, mana: u32, max_mana: u32) -> Self {
+        let health = Self::tenths(health, max_health);
+        let mana = Self::tenths(mana, max_mana);
+        PartyMemberBars(health | (mana << 4))
+    }
+
+    fn tenths(current: u32, max: u32) -> u8 {
+        if max == 0 {
+            return 0;
+        }
+        ((current.min(max) as u64 * 10) / max as u64) as u8
+    }
+
+    pub fn health(&self) -> u8 {
+        self.0 & 0x0F
+    }
+
+    pub fn mana(&self) -> u8 {
+        self.0 >> 4
+    }
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub struct PartyMemberPosition {
+    pub region: u16,
+    pub x: u16,
+    pub y: u16,
+    pub z: u16,
+}
+
+impl PartyMemberPosition {
+    pub fn new(region: u16, x: u16, y: u16, z: u16) -> Self {
+        PartyMemberPosition { region, x, y, z }
+    }
+}
+
+#[derive(Clone, Serialize, ByteSize, Deserialize, Debug)]
+pub struct PartyMemberInfo {
+    pub unknown: u8,
+    pub unique_id: u32,
+    pub name: String,
+    pub ref_id: u32,
+    pub level: u8,
+    pub bars: PartyMemberBars,
+    pub position: PartyMemberPosition,
+    pub guild: String,
+    pub unknown2: u8,
+    pub masteries: [u32; 2],
+}
+
+impl PartyMemberInfo {
+    pub fn new(
+        unique_id: u32,
+        name: String,
+        ref_id: u32,
+        level: u8,
+        bars: PartyMemberBars,
+        position: PartyMemberPosition,
+        masteries: [u32; 2],
+    ) -> Self {
+        PartyMemberInfo {
+            unknown: 0xFF,
+            unique_id,
+            name,
+            ref_id,
+            level,
+            bars,
+            position,
+            guild: String::new(),
+            unknown2: 0,
+            masteries,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum PartyLeaveReason {
+    #[silkroad(value = 1)]
+    Disconnected,
+    #[silkroad(value = 2)]
+    Left,
+    #[silkroad(value = 4)]
+    Kicked,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum PartyMemberUpdate {
+    #[silkroad(value = 2)]
+    Level(u8),
+    #[silkroad(value = 4)]
+    Bars(PartyMemberBars),
+    #[silkroad(value = 0x20)]
+    Position(PartyMemberPosition),
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7060)]
+pub struct PartyCreateRequest {
+    pub target: u32,
+    pub settings: PartySettings,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB060)]
+pub struct PartyCreateResponse {
+    pub result: PartyResult,
+}
+
+impl PartyCreateResponse {
+    pub fn new(result: PartyResult) -> Self {
+        PartyCreateResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7061)]
+pub struct PartyLeaveRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB061)]
+pub struct PartyLeaveResponse {
+    pub result: PartyResult,
+}
+
+impl PartyLeaveResponse {
+    pub fn new(result: PartyResult) -> Self {
+        PartyLeaveResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7062)]
+pub struct PartyInviteRequest {
+    pub target: u32,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB062)]
+pub struct PartyInviteResponse {
+    pub result: PartyResult,
+}
+
+impl PartyInviteResponse {
+    pub fn new(result: PartyResult) -> Self {
+        PartyInviteResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7063)]
+pub struct PartyKickRequest {
+    pub member: u32,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB063)]
+pub struct PartyKickResponse {
+    pub result: PartyResult,
+}
+
+impl PartyKickResponse {
+    pub fn new(result: PartyResult) -> Self {
+        PartyKickResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x7064)]
+pub struct PartyLeaderTransferRequest {
+    pub member: u32,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB064)]
+pub struct PartyLeaderTransferResponse {
+    pub result: PartyResult,
+}
+
+impl PartyLeaderTransferResponse {
+    pub fn new(result: PartyResult) -> Self {
+        PartyLeaderTransferResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3065)]
+pub struct PartyInfo {
+    pub unknown: u8,
+    pub party_id: u32,
+    pub leader: u32,
+    pub settings: PartySettings,
+    pub members: Vec<PartyMemberInfo>,
+}
+
+impl PartyInfo {
+    pub fn new(party_id: u32, leader: u32, settings: PartySettings, members: Vec<PartyMemberInfo>) -> Self {
+        PartyInfo {
+            unknown: 0xFF,
+            party_id,
+            leader,
+            settings,
+            members,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3864)]
+pub enum PartyUpdate {
+    #[silkroad(value = 1)]
+    Dismissed,
+    #[silkroad(value = 2)]
+    MemberJoined(PartyMemberInfo),
+    #[silkroad(value = 3)]
+    MemberLeft { member: u32, reason: PartyLeaveReason },
+    #[silkroad(value = 6)]
+    MemberUpdate { member: u32, update: PartyMemberUpdate },
+    #[silkroad(value = 9)]
+    LeaderChanged { leader: u32 },
+}
+
+define_inbound_protocol! { PartyClientProtocol =>
+    PartyCreateRequest,
+    PartyLeaveRequest,
+    PartyInviteRequest,
+    PartyKickRequest,
+    PartyLeaderTransferRequest
+}
+
+define_outbound_protocol! { PartyServerProtocol =>
+    PartyCreateResponse,
+    PartyLeaveResponse,
+    PartyInviteResponse,
+    PartyKickResponse,
+    PartyLeaderTransferResponse,
+    PartyInfo,
+    PartyUpdate
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    fn member() -> PartyMemberInfo {
+        PartyMemberInfo::new(
+            0x10,
+            "Tester".to_string(),
+            1907,
+            12,
+            PartyMemberBars::new(100, 200, 50, 50),
+            PartyMemberPosition::new(25000, 100, 20, 300),
+            [257, 0],
+        )
+    }
+
+    #[test]
+    fn test_settings() {
+        let settings = PartySettings::new(true, false, true);
+        assert_eq!(settings, PartySettings(0b101));
+        assert!(settings.experience_shared());
+        assert!(!settings.items_shared());
+        assert!(settings.anyone_can_invite());
+    }
+
+    #[test]
+    fn test_member_bars() {
+        let bars = PartyMemberBars::new(100, 200, 50, 50);
+        assert_eq!(bars.health(), 5);
+        assert_eq!(bars.mana(), 10);
+        assert_eq!(PartyMemberBars::new(0, 0, 0, 0), PartyMemberBars(0));
+    }
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&PartyCreateRequest {
+            target: 0x20,
+            settings: PartySettings::new(true, true, false),
+        });
+        assert_roundtrip(&PartyCreateResponse::new(PartyResult::Success));
+        assert_roundtrip(&PartyCreateResponse::new(PartyResult::error(PartyError::Declined)));
+        assert_roundtrip(&PartyLeaveRequest);
+        assert_roundtrip(&PartyLeaveResponse::new(PartyResult::Success));
+        assert_roundtrip(&PartyInviteRequest { target: 0x20 });
+        assert_roundtrip(&PartyInviteResponse::new(PartyResult::error(PartyError::PartyFull)));
+        assert_roundtrip(&PartyKickRequest { member: 0x20 });
+        assert_roundtrip(&PartyKickResponse::new(PartyResult::error(PartyError::NotLeader)));
+        assert_roundtrip(&PartyLeaderTransferRequest { member: 0x20 });
+        assert_roundtrip(&PartyLeaderTransferResponse::new(PartyResult::Success));
+        assert_roundtrip(&PartyInfo::new(
+            1,
+            0x10,
+            PartySettings::new(true, false, false),
+            vec![member()],
+        ));
+        assert_roundtrip(&PartyUpdate::Dismissed);
+        assert_roundtrip(&PartyUpdate::MemberJoined(member()));
+        assert_roundtrip(&PartyUpdate::MemberLeft {
+            member: 0x10,
+            reason: PartyLeaveReason::Kicked,
+        });
+        assert_roundtrip(&PartyUpdate::MemberUpdate {
+            member: 0x10,
+            update: PartyMemberUpdate::Bars(PartyMemberBars(0xA5)),
+        });
+        assert_roundtrip(&PartyUpdate::MemberUpdate {
+            member: 0x10,
+            update: PartyMemberUpdate::Position(PartyMemberPosition::new(25000, 1, 2, 3)),
+        });
+        assert_roundtrip(&PartyUpdate::LeaderChanged { leader: 0x20 });
+    }
+}
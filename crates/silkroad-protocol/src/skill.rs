@@ -91,3 +91,40 @@ define_outbound_protocol! { SkillServerProtocol =>
     LearnSkillResponse,
     LevelUpMasteryResponse
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::assert_roundtrip;
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&LevelUpMastery {
+            mastery: 257,
+            amount: 1,
+        });
+        assert_roundtrip(&LevelUpMasteryResponse::Success {
+            mastery: 257,
+            new_level: 2,
+        });
+        assert_roundtrip(&LevelUpMasteryResponse::Failure(LevelUpMasteryError::InsufficientSP));
+        assert_roundtrip(&LearnSkill(3));
+        assert_roundtrip(&LearnSkillResponse::Success(3));
+        assert_roundtrip(&LearnSkillResponse::Failure(LevelUpMasteryError::ReachedTotalLimit));
+        assert_roundtrip(&HotbarUpdate {
+            size: 2,
+            content: vec![
+                HotbarItem {
+                    slot: 0,
+                    action_flag: 0x46,
+                    action_data: 3,
+                },
+                HotbarItem {
+                    slot: 1,
+                    action_flag: 0x70,
+                    action_data: 13,
+                },
+            ],
+        });
+    }
+}
@@ -2,13 +2,13 @@ use crate::community::GuildInformation;
 use crate::inventory::{BagContent, CharacterSpawnItemData};
 use crate::movement::{EntityMovementState, Position};
 use crate::skill::{HotbarItem, MasteryData, SkillData};
-use crate::world::{ActiveScroll, EntityState, InteractOptions, JobType, PlayerKillState, PvpCape};
+use crate::world::{ActiveScroll, EntityState, InteractOptions, InteractionMode, JobType, PlayerKillState, PvpCape};
 use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
 use silkroad_definitions::rarity::EntityRarity;
 use skrillax_packet::Packet;
 use skrillax_serde::*;
 
-#[derive(Clone, Eq, PartialEq, Copy, Serialize, ByteSize)]
+#[derive(Clone, Eq, PartialEq, Copy, Serialize, ByteSize, Deserialize, Debug)]
 pub enum GroupSpawnType {
     #[silkroad(value = 1)]
     Spawn,
@@ -55,7 +55,7 @@ pub enum ItemSpawnData {
     },
 }
 
-#[derive(Clone, Serialize, ByteSize, Packet)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x34A5)]
 pub struct CharacterSpawnStart;
 
@@ -302,11 +302,11 @@ impl CharacterSpawn {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Packet)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x34A6)]
 pub struct CharacterSpawnEnd;
 
-#[derive(Clone, Serialize, ByteSize, Packet)]
+#[derive(Clone, Serialize, Deserialize, ByteSize, Packet, Debug)]
 #[packet(opcode = 0x3016)]
 pub struct EntityDespawn {
     pub entity_id: u32,
@@ -331,7 +331,7 @@ impl EntitySpawn {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Packet)]
+#[derive(Clone, Serialize, ByteSize, Deserialize, Packet, Debug)]
 #[packet(opcode = 0x3017)]
 pub struct GroupEntitySpawnStart {
     pub kind: GroupSpawnType,
@@ -364,7 +364,7 @@ impl GroupEntitySpawnData {
     }
 }
 
-#[derive(Clone, Serialize, ByteSize, Packet)]
+#[derive(Clone, Serialize, ByteSize, Deserialize, Packet, Debug)]
 #[packet(opcode = 0x3018)]
 pub struct GroupEntitySpawnEnd;
 
@@ -431,6 +431,13 @@ impl ActiveQuestObjectData {
     }
 }
 
+/// The stall a player has currently opened.
+#[derive(Clone, Serialize, ByteSize)]
+pub struct StallSpawnData {
+    pub title: String,
+    pub decoration: u32,
+}
+
 #[derive(Clone, Serialize, ByteSize)]
 #[silkroad(size = 0)]
 pub enum EntityTypeSpawnData {
@@ -455,9 +462,11 @@ pub enum EntityTypeSpawnData {
         mounted: bool,
         in_combat: bool,
         active_scroll: ActiveScroll,
-        unknown2: u8,
+        interaction_mode: InteractionMode,
         guild: GuildInformation,
         unknown3: [u8; 11],
+        #[silkroad(when = "matches!(interaction_mode, InteractionMode::Stall)")]
+        stall: Option<StallSpawnData>,
         equipment_cooldown: bool,
         pk_state: PlayerKillState,
         unknown4: u8,
@@ -537,9 +546,10 @@ impl EntityTypeSpawnData {
             mounted,
             in_combat,
             active_scroll,
-            unknown2: 0,
+            interaction_mode: InteractionMode::None,
             guild,
             unknown3,
+            stall: None,
             equipment_cooldown,
             pk_state,
             unknown4: 0xFF,
@@ -567,3 +577,213 @@ impl EntityTypeSpawnData {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inventory::{InventoryItemContentData, InventoryItemData, RentInfo};
+    use crate::movement::MovementType;
+    use crate::testing::{assert_golden, assert_roundtrip, assert_size, parse_fixture};
+    use crate::world::{ActionState, AliveState, BodyState};
+    use silkroad_definitions::rarity::EntityRarityType;
+
+    fn position() -> Position {
+        Position::new(24744, 960.0, 0.0, 1280.0, 0x4000)
+    }
+
+    fn entity_state() -> EntityState {
+        EntityState::new(
+            AliveState::Alive,
+            ActionState::None,
+            BodyState::None,
+            16.0,
+            50.0,
+            100.0,
+            vec![],
+        )
+    }
+
+    fn character_spawn(active_quests: Vec<ActiveQuestData>) -> CharacterSpawn {
+        let inventory = BagContent::new(
+            45,
+            vec![InventoryItemData::new(
+                13,
+                RentInfo::Empty,
+                8,
+                InventoryItemContentData::expendable(5),
+            )],
+        );
+        CharacterSpawn::new(
+            SilkroadTime::default(),
+            1907,
+            34,
+            12,
+            12,
+            1500,
+            20,
+            1000,
+            100,
+            3,
+            0,
+            420,
+            310,
+            true,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0x4,
+            Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap(),
+            0,
+            100,
+            inventory,
+            BagContent::new(5, Vec::new()),
+            vec![MasteryData::new(257, 1)],
+            vec![SkillData::new(3, true)],
+            vec![1, 2],
+            active_quests,
+            0x1234,
+            position(),
+            EntityMovementState::standing(MovementType::Running, 1, 0x4000),
+            entity_state(),
+            "Tester".to_string(),
+            JobInformation::empty(),
+            0,
+            0,
+            0,
+            false,
+            0,
+            0,
+            1,
+            false,
+            vec![HotbarItem {
+                slot: 0,
+                action_flag: 0x46,
+                action_data: 3,
+            }],
+            0,
+            0,
+            0,
+            0,
+            vec!["Blocked".to_string()],
+        )
+    }
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&CharacterSpawnStart);
+        assert_roundtrip(&CharacterSpawnEnd);
+        assert_roundtrip(&EntityDespawn::new(0x1234));
+        assert_roundtrip(&GroupEntitySpawnStart::new(GroupSpawnType::Spawn, 2));
+        assert_roundtrip(&GroupEntitySpawnStart::new(GroupSpawnType::Despawn, 1));
+        assert_roundtrip(&GroupEntitySpawnEnd);
+    }
+
+    #[test]
+    fn test_packets_size() {
+        assert_size(&character_spawn(vec![ActiveQuestData::new(
+            3,
+            0,
+            8,
+            1,
+            vec![ActiveQuestObjectData::new(
+                0,
+                true,
+                "Kill".to_string(),
+                vec![1],
+                vec![2],
+            )],
+        )]));
+
+        let mut character = EntityTypeSpawnData::character(
+            34,
+            0,
+            PvpCape::None,
+            true,
+            0,
+            45,
+            vec![CharacterSpawnItemData::new(3643, 0)],
+            5,
+            vec![],
+            None,
+            0x1234,
+            position(),
+            EntityMovementState::moving(MovementType::Running, 24744, 960, 0, 1280),
+            entity_state(),
+            "Tester".to_string(),
+            JobType::None,
+            false,
+            false,
+            ActiveScroll::None,
+            GuildInformation::new("Guild".to_string(), 1, "Tester".to_string(), 0, 0, 0, 1),
+            [0; 11],
+            false,
+            PlayerKillState::None,
+        );
+        assert_size(&EntitySpawn::new(1907, character.clone()));
+        if let EntityTypeSpawnData::Character {
+            ref mut interaction_mode,
+            ref mut stall,
+            ..
+        } = character
+        {
+            *interaction_mode = InteractionMode::Stall;
+            *stall = Some(StallSpawnData {
+                title: "Potions".to_string(),
+                decoration: 0,
+            });
+        }
+        assert_size(&EntitySpawn::new(1907, character));
+        assert_size(&EntitySpawn::new(
+            1,
+            EntityTypeSpawnData::gold(250, 0x1234, position(), None, 0),
+        ));
+    }
+
+    #[test]
+    fn test_group_spawn_golden() {
+        assert_golden(
+            &GroupEntitySpawnData::new(vec![
+                GroupSpawnDataContent::despawn(0x1234),
+                GroupSpawnDataContent::despawn(0xABCDEF),
+            ]),
+            include_str!("../fixtures/group_spawn_despawn.hex"),
+        );
+        assert_golden(
+            &GroupEntitySpawnData::new(vec![GroupSpawnDataContent::spawn(
+                1,
+                EntityTypeSpawnData::gold(250, 0x2A, position(), Some(0x10), 0),
+            )]),
+            include_str!("../fixtures/group_spawn_gold.hex"),
+        );
+        assert_golden(
+            &GroupEntitySpawnData::new(vec![GroupSpawnDataContent::spawn(
+                1990,
+                EntityTypeSpawnData::monster(
+                    0x2B,
+                    position(),
+                    EntityMovementState::standing(MovementType::Walking, 1, 0x4000),
+                    entity_state(),
+                    InteractOptions::None,
+                    EntityRarityType::Champion.into(),
+                    0,
+                ),
+            )]),
+            include_str!("../fixtures/group_spawn_monster.hex"),
+        );
+    }
+
+    #[test]
+    fn test_character_spawn_golden() {
+        // How the time and the size of the collection book are encoded is up to skrillax-serde, so
+        // the fixtures only cover what comes before and after them.
+        let spawn = character_spawn(Vec::new());
+        let bytes = assert_size(&spawn);
+        let character = parse_fixture(include_str!("../fixtures/character_spawn_character.hex"));
+        let entity = parse_fixture(include_str!("../fixtures/character_spawn_entity.hex"));
+        let start = spawn.time.byte_size();
+        assert_eq!(bytes[start..start + character.len()], character[..]);
+        assert!(bytes.ends_with(&entity), "CharacterSpawn does not end with the fixture");
+    }
+}
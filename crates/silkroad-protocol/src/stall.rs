@@ -0,0 +1,349 @@
+use crate::inventory::{InventoryItemContentData, RentInfo};
+use skrillax_packet::Packet;
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+use skrillax_serde::*;
+
+// TODO: these codes have not been verified against the client yet
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+#[silkroad(size = 2)]
+pub enum StallError {
+    #[silkroad(value = 0x3C14)]
+    InvalidTarget,
+    #[silkroad(value = 0x3C15)]
+    TooFarAway,
+    #[silkroad(value = 0x3C16)]
+    Busy,
+    #[silkroad(value = 0x3C17)]
+    NotInStall,
+    #[silkroad(value = 0x3C18)]
+    StallClosed,
+    #[silkroad(value = 0x3C19)]
+    StallOpened,
+    #[silkroad(value = 0x3C1A)]
+    StallFull,
+    #[silkroad(value = 0x3C1B)]
+    InvalidItem,
+    #[silkroad(value = 0x3C1C)]
+    ItemNotAvailable,
+    #[silkroad(value = 0x3C1D)]
+    NotEnoughGold,
+    #[silkroad(value = 0x3C1E)]
+    InventoryFull,
+}
+
+#[derive(Clone, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum StallResult {
+    #[silkroad(value = 1)]
+    Success,
+    #[silkroad(value = 2)]
+    Failure { code: StallError },
+}
+
+impl StallResult {
+    pub fn error(code: StallError) -> Self {
+        StallResult::Failure { code }
+    }
+}
+
+/// An item offered inside a stall, including the price for the whole offered amount.
+#[derive(Clone, Serialize, ByteSize, Debug)]
+pub struct StallItemData {
+    pub stall_slot: u8,
+    pub rent_data: RentInfo,
+    pub item_id: u32,
+    pub content_data: InventoryItemContentData,
+    pub inventory_slot: u8,
+    pub amount: u16,
+    pub price: u64,
+}
+
+/// Changes the owner makes to their stall. Items can only be changed while the stall is not open.
+#[derive(Clone, Serialize, ByteSize, Deserialize, Debug)]
+pub enum StallUpdate {
+    #[silkroad(value = 1)]
+    UpdateItem { stall_slot: u8, amount: u16, price: u64 },
+    #[silkroad(value = 2)]
+    AddItem {
+        stall_slot: u8,
+        inventory_slot: u8,
+        amount: u16,
+        price: u64,
+    },
+    #[silkroad(value = 3)]
+    RemoveItem { stall_slot: u8 },
+    #[silkroad(value = 5)]
+    State { open: bool },
+    #[silkroad(value = 6)]
+    Greeting { message: String },
+    #[silkroad(value = 7)]
+    Title { title: String },
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70B1)]
+pub struct StallCreateRequest {
+    pub title: String,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0B1)]
+pub struct StallCreateResponse {
+    pub result: StallResult,
+}
+
+impl StallCreateResponse {
+    pub fn new(result: StallResult) -> Self {
+        StallCreateResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70B2)]
+pub struct StallCloseRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0B2)]
+pub struct StallCloseResponse {
+    pub result: StallResult,
+}
+
+impl StallCloseResponse {
+    pub fn new(result: StallResult) -> Self {
+        StallCloseResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70B3)]
+pub struct StallEnterRequest {
+    pub owner: u32,
+}
+
+#[derive(Clone, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0B3)]
+pub enum StallEnterResponse {
+    #[silkroad(value = 1)]
+    Success {
+        owner: u32,
+        greeting: String,
+        open: bool,
+        items: Vec<StallItemData>,
+    },
+    #[silkroad(value = 2)]
+    Failure { code: StallError },
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70B4)]
+pub struct StallBuyRequest {
+    pub stall_slot: u8,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0B4)]
+pub struct StallBuyResponse {
+    pub result: StallResult,
+}
+
+impl StallBuyResponse {
+    pub fn new(result: StallResult) -> Self {
+        StallBuyResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70B5)]
+pub struct StallLeaveRequest;
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0B5)]
+pub struct StallLeaveResponse {
+    pub result: StallResult,
+}
+
+impl StallLeaveResponse {
+    pub fn new(result: StallResult) -> Self {
+        StallLeaveResponse { result }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x70BA)]
+pub struct StallUpdateRequest {
+    pub update: StallUpdate,
+}
+
+#[derive(Clone, Serialize, ByteSize, Deserialize, Debug)]
+pub enum StallUpdateResult {
+    #[silkroad(value = 1)]
+    Success(StallUpdate),
+    #[silkroad(value = 2)]
+    Failure { code: StallError },
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB0BA)]
+pub struct StallUpdateResponse {
+    pub result: StallUpdateResult,
+}
+
+impl StallUpdateResponse {
+    pub fn new(result: StallUpdateResult) -> Self {
+        StallUpdateResponse { result }
+    }
+}
+
+/// Informs the owner and the visitors of a stall about what is happening inside the stall.
+#[derive(Clone, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x30B7)]
+pub enum StallAction {
+    #[silkroad(value = 1)]
+    Left { unique_id: u32 },
+    #[silkroad(value = 2)]
+    Entered { unique_id: u32, name: String },
+    #[silkroad(value = 3)]
+    ItemSold { stall_slot: u8, buyer: String },
+    #[silkroad(value = 4)]
+    ItemsUpdated { open: bool, items: Vec<StallItemData> },
+    #[silkroad(value = 5)]
+    GreetingUpdated { message: String },
+    #[silkroad(value = 6)]
+    Closed,
+}
+
+/// Shows the stall above the owner for players nearby.
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x30B8)]
+pub struct StallEntityOpened {
+    pub unique_id: u32,
+    pub title: String,
+    pub decoration: u32,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x30B9)]
+pub struct StallEntityClosed {
+    pub unique_id: u32,
+}
+
+#[derive(Clone, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x30BB)]
+pub struct StallEntityTitle {
+    pub unique_id: u32,
+    pub title: String,
+}
+
+define_inbound_protocol! { StallClientProtocol =>
+    StallCreateRequest,
+    StallCloseRequest,
+    StallEnterRequest,
+    StallBuyRequest,
+    StallLeaveRequest,
+    StallUpdateRequest
+}
+
+define_outbound_protocol! { StallServerProtocol =>
+    StallCreateResponse,
+    StallCloseResponse,
+    StallEnterResponse,
+    StallBuyResponse,
+    StallLeaveResponse,
+    StallUpdateResponse,
+    StallAction,
+    StallEntityOpened,
+    StallEntityClosed,
+    StallEntityTitle
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{assert_golden, assert_roundtrip, assert_size};
+
+    fn item() -> StallItemData {
+        StallItemData {
+            stall_slot: 0,
+            rent_data: RentInfo::Empty,
+            item_id: 1907,
+            content_data: InventoryItemContentData::expendable(5),
+            inventory_slot: 13,
+            amount: 5,
+            price: 1000,
+        }
+    }
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&StallCreateRequest {
+            title: "Potions".to_string(),
+        });
+        assert_roundtrip(&StallCreateResponse::new(StallResult::Success));
+        assert_roundtrip(&StallCloseRequest);
+        assert_roundtrip(&StallCloseResponse::new(StallResult::Success));
+        assert_roundtrip(&StallEnterRequest { owner: 0x20 });
+        assert_roundtrip(&StallBuyRequest { stall_slot: 2 });
+        assert_roundtrip(&StallBuyResponse::new(StallResult::error(StallError::NotEnoughGold)));
+        assert_roundtrip(&StallLeaveRequest);
+        assert_roundtrip(&StallLeaveResponse::new(StallResult::error(StallError::NotInStall)));
+        assert_roundtrip(&StallUpdateRequest {
+            update: StallUpdate::AddItem {
+                stall_slot: 0,
+                inventory_slot: 13,
+                amount: 5,
+                price: 1000,
+            },
+        });
+        assert_roundtrip(&StallUpdateResponse::new(StallUpdateResult::Success(
+            StallUpdate::Greeting {
+                message: "Welcome".to_string(),
+            },
+        )));
+        assert_roundtrip(&StallEntityOpened {
+            unique_id: 0x20,
+            title: "Potions".to_string(),
+            decoration: 0,
+        });
+        assert_roundtrip(&StallEntityClosed { unique_id: 0x20 });
+        assert_roundtrip(&StallEntityTitle {
+            unique_id: 0x20,
+            title: "Potions".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_packets_size() {
+        assert_size(&StallEnterResponse::Success {
+            owner: 0x20,
+            greeting: "Welcome".to_string(),
+            open: true,
+            items: vec![item()],
+        });
+        assert_size(&StallAction::ItemsUpdated {
+            open: true,
+            items: vec![item()],
+        });
+        assert_size(&StallAction::ItemSold {
+            stall_slot: 0,
+            buyer: "Buyer".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_packets_golden() {
+        assert_golden(
+            &StallCloseResponse::new(StallResult::Success),
+            include_str!("../fixtures/stall_close_success.hex"),
+        );
+        assert_golden(
+            &StallLeaveResponse::new(StallResult::error(StallError::NotInStall)),
+            include_str!("../fixtures/stall_leave_not_in_stall.hex"),
+        );
+        assert_golden(
+            &StallEntityTitle {
+                unique_id: 0x20,
+                title: "Potions".to_string(),
+            },
+            include_str!("../fixtures/stall_entity_title.hex"),
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::inventory::{InventoryItemData, InventoryOperationError};
+use skrillax_packet::Packet;
+use skrillax_protocol::{define_inbound_protocol, define_outbound_protocol};
+use skrillax_serde::*;
+
+// TODO: the layout of the storage packets has not been verified against the client yet
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x703C)]
+pub struct StorageOpenRequest {
+    pub npc: u32,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0xB03C)]
+pub enum StorageOpenResponse {
+    #[silkroad(value = 1)]
+    Success,
+    #[silkroad(value = 2)]
+    Failure(InventoryOperationError),
+}
+
+/// The gold inside the storage, which starts the transfer of the storage content.
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3047)]
+pub struct StorageGold {
+    pub gold: u64,
+}
+
+#[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
+#[packet(opcode = 0x3048)]
+pub struct StorageItemsEnd;
+
+#[derive(Clone, Serialize, ByteSize, Packet)]
+#[packet(opcode = 0x3049)]
+pub struct StorageItems {
+    pub capacity: u8,
+    pub items: Vec<InventoryItemData>,
+}
+
+define_inbound_protocol! { StorageClientProtocol =>
+    StorageOpenRequest
+}
+
+define_outbound_protocol! { StorageServerProtocol =>
+    StorageOpenResponse,
+    StorageGold,
+    StorageItemsEnd,
+    StorageItems
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::inventory::{InventoryItemContentData, RentInfo};
+    use crate::testing::{assert_roundtrip, assert_size};
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&StorageOpenRequest { npc: 0x20 });
+        assert_roundtrip(&StorageOpenResponse::Success);
+        assert_roundtrip(&StorageOpenResponse::Failure(InventoryOperationError::InvalidTarget));
+        assert_roundtrip(&StorageGold { gold: 10_000 });
+        assert_roundtrip(&StorageItemsEnd);
+    }
+
+    #[test]
+    fn test_items_size() {
+        assert_size(&StorageItems {
+            capacity: 150,
+            items: vec![InventoryItemData::new(
+                0,
+                RentInfo::Empty,
+                1907,
+                InventoryItemContentData::expendable(5),
+            )],
+        });
+    }
+}
@@ -0,0 +1,90 @@
+use bytes::BytesMut;
+use skrillax_serde::{ByteSize, Deserialize, Serialize};
+use std::fmt::Debug;
+use std::io::Cursor;
+
+/// Parses the hex dump of a fixture into its bytes.
+///
+/// Fixtures contain the payload of a packet, without the packet header. Whitespace is ignored
+/// and everything after a `#` until the end of the line is treated as a comment, such that
+/// captures can be annotated field by field.
+pub(crate) fn parse_fixture(fixture: &str) -> Vec<u8> {
+    let digits = fixture
+        .lines()
+        .map(|line| line.split_once('#').map_or(line, |(data, _)| data))
+        .flat_map(|line| line.chars())
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    assert_eq!(digits.len() % 2, 0, "Fixture contains an odd number of hex digits");
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte = pair.iter().collect::<String>();
+            u8::from_str_radix(&byte, 16).unwrap_or_else(|_| panic!("Invalid hex byte '{byte}' in fixture"))
+        })
+        .collect()
+}
+
+/// Serializes the value and checks that the amount of bytes written matches its
+/// [ByteSize::byte_size].
+pub(crate) fn assert_size<T: Serialize + ByteSize>(value: &T) -> Vec<u8> {
+    let mut buffer = BytesMut::new();
+    value.write_to(&mut buffer);
+    assert_eq!(
+        buffer.len(),
+        value.byte_size(),
+        "Written bytes do not match the byte size of {}",
+        std::any::type_name::<T>()
+    );
+    buffer.to_vec()
+}
+
+/// Deserializes the value from the given bytes, making sure all bytes have been consumed.
+pub(crate) fn decode<T: Deserialize>(bytes: &[u8]) -> T {
+    let mut cursor = Cursor::new(bytes);
+    let value = T::read_from(&mut cursor)
+        .unwrap_or_else(|e| panic!("Could not deserialize {}: {e:?}", std::any::type_name::<T>()));
+    assert_eq!(
+        cursor.position() as usize,
+        bytes.len(),
+        "Deserializing {} left bytes unread",
+        std::any::type_name::<T>()
+    );
+    value
+}
+
+/// Checks that the value has the correct size and that it ends up with the same bytes after
+/// a round-trip through deserialization.
+pub(crate) fn assert_roundtrip<T: Serialize + Deserialize + ByteSize + Debug>(value: &T) {
+    let bytes = assert_size(value);
+    let decoded: T = decode(&bytes);
+    assert_eq!(
+        assert_size(&decoded),
+        bytes,
+        "Round-trip changed the bytes of {:?}",
+        value
+    );
+}
+
+/// Checks that the value serializes to exactly the bytes of the given fixture. See the
+/// `fixtures` directory for where the fixtures come from.
+pub(crate) fn assert_golden<T: Serialize + ByteSize>(value: &T, fixture: &str) {
+    let expected = parse_fixture(fixture);
+    let bytes = assert_size(value);
+    assert_eq!(
+        bytes,
+        expected,
+        "{} does not match the fixture",
+        std::any::type_name::<T>()
+    );
+}
+
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_fixture() {
+        let fixture = "01 # result\n  06fe # kind, slot\n\ne8 03 00 00\n";
+        assert_eq!(parse_fixture(fixture), vec![0x01, 0x06, 0xFE, 0xE8, 0x03, 0x00, 0x00]);
+    }
+}
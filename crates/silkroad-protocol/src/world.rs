@@ -69,6 +69,17 @@ pub enum InteractOptions {
     Talk(u64),
 }
 
+/// What the player is currently busy with, as it is shown to other players.
+#[derive(Clone, Copy, Eq, PartialEq, Serialize, ByteSize, Deserialize, Debug)]
+pub enum InteractionMode {
+    #[silkroad(value = 0)]
+    None,
+    #[silkroad(value = 2)]
+    Exchange,
+    #[silkroad(value = 4)]
+    Stall,
+}
+
 impl InteractOptions {
     pub fn talk(options: u64) -> Self {
         InteractOptions::Talk(options)
@@ -95,6 +106,20 @@ pub enum BodyState {
     Invisible,
 }
 
+/// A condition which prevents an entity from acting, like being stunned.
+// TODO: these values have not been verified against the client yet
+#[derive(Clone, Eq, PartialEq, PartialOrd, Copy, Serialize, ByteSize, Deserialize, Debug)]
+pub enum StatusState {
+    #[silkroad(value = 0)]
+    None,
+    #[silkroad(value = 1)]
+    Stunned,
+    #[silkroad(value = 2)]
+    KnockedDown,
+    #[silkroad(value = 3)]
+    KnockedBack,
+}
+
 #[derive(Clone, Eq, PartialEq, PartialOrd, Copy, Serialize, ByteSize, Deserialize, Debug)]
 pub enum WeatherType {
     #[silkroad(value = 1)]
@@ -316,6 +341,9 @@ pub enum UpdatedState {
     Life(AliveState),
     #[silkroad(value = 1)]
     Movement(MovementType),
+    // TODO: this has not been verified against the client yet
+    #[silkroad(value = 2)]
+    Status(StatusState),
     #[silkroad(value = 4)]
     Body(BodyState),
     #[silkroad(value = 7)]
@@ -354,6 +382,13 @@ impl EntityUpdateState {
             update: UpdatedState::Body(new),
         }
     }
+
+    pub fn status(unique_id: u32, new: StatusState) -> Self {
+        EntityUpdateState {
+            unique_id,
+            update: UpdatedState::Status(new),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Deserialize, Serialize, ByteSize, Packet, Debug)]
@@ -485,7 +520,7 @@ pub struct LevelUpEffect {
 #[packet(opcode = 0x70EA)]
 pub struct UpdateGameGuide(pub u64);
 
-#[derive(Serialize, ByteSize, Copy, Clone, Packet, Debug)]
+#[derive(Serialize, ByteSize, Deserialize, Copy, Clone, Packet, Debug)]
 #[packet(opcode = 0xB0EA)]
 pub enum GameGuideResponse {
     #[silkroad(value = 1)]
@@ -542,3 +577,78 @@ define_outbound_protocol! { WorldServerProtocol =>
     PlayerPickupAnimation,
     GameGuideResponse
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::testing::{assert_roundtrip, assert_size};
+
+    #[test]
+    fn test_packets_roundtrip() {
+        assert_roundtrip(&CelestialUpdate::new(0x1234, 0x100, 12, 30));
+        assert_roundtrip(&LunarEventInfo::new(3, 10));
+        assert_roundtrip(&CharacterFinished::default());
+        assert_roundtrip(&CharacterFinished {
+            item_cooldowns: vec![CooldownInfo {
+                ref_id: 8,
+                cooldown: 1000,
+            }],
+            skill_cooldowns: vec![CooldownInfo {
+                ref_id: 3,
+                cooldown: 2500,
+            }],
+        });
+        assert_roundtrip(&WeatherUpdate::new(WeatherType::Rain, 75));
+        assert_roundtrip(&GameNotification::uniquespawned(1954));
+        assert_roundtrip(&GameNotification::uniquekilled(1954, "Tester".to_string()));
+        assert_roundtrip(&EntityUpdateState::life(0x1234, AliveState::Dead));
+        assert_roundtrip(&EntityUpdateState::movement(0x1234, MovementType::Running));
+        assert_roundtrip(&EntityUpdateState::status(0x1234, StatusState::KnockedDown));
+        assert_roundtrip(&TargetEntity { unique_id: 0x1234 });
+        assert_roundtrip(&UnTargetEntity { unique_id: 0x1234 });
+        assert_roundtrip(&UnTargetEntityResponse::new(true));
+        assert_roundtrip(&EntityBarsUpdate::hp(0x1234, EntityBarUpdateSource::Damage, 420));
+        assert_roundtrip(&EntityBarsUpdate::mp(0x1234, EntityBarUpdateSource::Regen, 310));
+        assert_roundtrip(&CharacterPointsUpdate::Gold {
+            amount: 1000,
+            display: true,
+        });
+        assert_roundtrip(&CharacterPointsUpdate::sp(50));
+        assert_roundtrip(&CharacterPointsUpdate::StatPoints(3));
+        assert_roundtrip(&CharacterPointsUpdate::Berserk {
+            amount: 1,
+            source: 0x1234,
+        });
+        assert_roundtrip(&PlayerPickupAnimation {
+            entity: 0x1234,
+            rotation: 0,
+        });
+        assert_roundtrip(&LevelUpEffect { entity: 0x1234 });
+        assert_roundtrip(&UpdateGameGuide(0x10));
+        assert_roundtrip(&GameGuideResponse::Success(0x10));
+        assert_roundtrip(&IncreaseStr);
+        assert_roundtrip(&IncreaseStrResponse::Success);
+        assert_roundtrip(&IncreaseInt);
+        assert_roundtrip(&IncreaseIntResponse::Failure(0x1234));
+    }
+
+    #[test]
+    fn test_packets_size() {
+        assert_size(&TargetEntityResponse::new(TargetEntityResult::success_monster(
+            0x1234, 420,
+        )));
+        assert_size(&TargetEntityResponse::new(TargetEntityResult::success_npc(0x1234)));
+        assert_size(&TargetEntityResponse::new(TargetEntityResult::failure(
+            TargetEntityError::InvalidTarget,
+        )));
+        // The levels of the status effects are not delimited, so they cannot be read back reliably.
+        assert_size(&EntityBarsUpdate {
+            unique_id: 0x1234,
+            source: EntityBarUpdateSource::Damage,
+            updates: EntityBarUpdates::Status {
+                effects: 0x3,
+                levels: vec![1, 2],
+            },
+        });
+    }
+}
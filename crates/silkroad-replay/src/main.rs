@@ -0,0 +1,159 @@
+use bytes::Bytes;
+use clap::Parser;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use silkroad_protocol::agent::AgentClientProtocol;
+use silkroad_protocol::general::BaseProtocol;
+use skrillax_server::{Direction, Framing, RecordedPacket, RecordingReader};
+use skrillax_stream::handshake::PassiveSecuritySetup;
+use skrillax_stream::packet::OutgoingPacket;
+use skrillax_stream::stream::{InStreamError, SilkroadTcpExt};
+use skrillax_stream::InputProtocol;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::net::TcpStream;
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, info, warn};
+
+/// Replays the packets a client sent during a recorded session against a running agent
+/// server.
+///
+/// Keep in mind that the agent server only accepts a login token once, so the login of a
+/// recorded session can generally not be replayed without reserving a new spot for the user
+/// first.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The recording to replay.
+    recording: PathBuf,
+    /// The address of the agent server to replay against.
+    #[arg(long, default_value = "127.0.0.1:15780")]
+    target: String,
+    /// The connection to replay. Can be omitted if the recording only contains one connection.
+    #[arg(long)]
+    connection: Option<u64>,
+    /// Speeds up or slows down the replay. A speed of 0 sends all packets without delay.
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+    /// Only lists the connections contained in the recording.
+    #[arg(long)]
+    list: bool,
+    /// Opcodes, in hex, the client sends encrypted. The recording cannot tell how packets of
+    /// the client were framed, so all other packets are sent unencrypted.
+    #[arg(long, value_delimiter = ',', value_parser = parse_opcode)]
+    encrypted: Vec<u16>,
+}
+
+fn parse_opcode(value: &str) -> Result<u16, String> {
+    let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("'{value}' is not an opcode in hex"))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+    color_eyre::install()?;
+    let args = Args::parse();
+
+    let packets = RecordingReader::open(&args.recording)?.collect::<Result<Vec<_>, _>>()?;
+    let mut connections = BTreeMap::<u64, usize>::new();
+    for packet in packets.iter().filter(|packet| packet.direction == Direction::Inbound) {
+        *connections.entry(packet.connection).or_default() += 1;
+    }
+
+    if args.list {
+        for (connection, count) in connections.iter() {
+            println!("Connection {connection}: {count} packets from the client");
+        }
+        return Ok(());
+    }
+
+    let connection = match args.connection {
+        Some(connection) => connection,
+        None if connections.len() == 1 => *connections.keys().next().unwrap(),
+        None => {
+            return Err(eyre!(
+                "The recording contains multiple connections, please specify one to replay."
+            ))
+        },
+    };
+
+    let packets = packets
+        .into_iter()
+        .filter(|packet| packet.connection == connection && packet.direction == Direction::Inbound)
+        .filter(is_decodable)
+        .collect::<Vec<_>>();
+
+    info!(connection, packets = packets.len(), "Replaying connection");
+    replay(&args.target, packets, &args.encrypted, args.speed).await
+}
+
+fn is_decodable(packet: &RecordedPacket) -> bool {
+    if AgentClientProtocol::create_from(packet.opcode, &packet.data).is_ok() {
+        return true;
+    }
+
+    warn!(
+        opcode = format!("{:#06x}", packet.opcode),
+        "Packet cannot be decoded by the agent protocol, skipping."
+    );
+    false
+}
+
+/// Recreates the packet as it was sent originally, as far as the recording knows it.
+fn outgoing(packet: RecordedPacket, encrypted: &[u16]) -> OutgoingPacket {
+    let opcode = packet.opcode;
+    let data = Bytes::from(packet.data);
+    match packet.framing {
+        Framing::Simple => OutgoingPacket::Simple { opcode, data },
+        Framing::Encrypted => OutgoingPacket::Encrypted { opcode, data },
+        Framing::Massive => OutgoingPacket::Massive {
+            opcode,
+            packets: vec![data],
+        },
+        Framing::Unknown if encrypted.contains(&opcode) => OutgoingPacket::Encrypted { opcode, data },
+        Framing::Unknown => OutgoingPacket::Simple { opcode, data },
+    }
+}
+
+async fn replay(target: &str, packets: Vec<RecordedPacket>, encrypted: &[u16], speed: f64) -> Result<()> {
+    let socket = TcpStream::connect(target).await?;
+    let (mut reader, mut writer) = socket.into_silkroad_stream();
+    PassiveSecuritySetup::handle(&mut reader, &mut writer).await?;
+    debug!("Finished handshake");
+
+    // We don't care about the responses, but still need to read them such that the server
+    // doesn't consider us a slow client.
+    let receiver = tokio::spawn(async move {
+        loop {
+            match reader.next_packet::<BaseProtocol>().await {
+                Ok(_) => {},
+                Err(InStreamError::UnmatchedOpcode(opcode)) => {
+                    debug!(opcode = format!("{opcode:#06x}"), "Received packet");
+                },
+                Err(InStreamError::EndOfStream) => {
+                    info!("Server closed the connection.");
+                    return;
+                },
+                Err(e) => {
+                    warn!(%e, "Could not read from server.");
+                    return;
+                },
+            }
+        }
+    });
+
+    let start = Instant::now();
+    let first = packets.first().map(|packet| packet.time).unwrap_or_default();
+    for packet in packets {
+        if speed > 0.0 {
+            sleep_until(start + (packet.time - first).div_f64(speed)).await;
+        }
+
+        debug!(opcode = format!("{:#06x}", packet.opcode), "Sending packet");
+        writer.write(outgoing(packet, encrypted)).await?;
+    }
+
+    info!("Replayed all packets, waiting for the server to close the connection.");
+    receiver.await?;
+    Ok(())
+}
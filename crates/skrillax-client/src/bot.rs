@@ -0,0 +1,522 @@
+use crate::passcode::PasscodeEncoder;
+use crate::protocol::{AgentServerProtocol, GatewayServerProtocol};
+use crate::script::Step;
+use crate::session::Session;
+use crate::{BotError, Script};
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use silkroad_gateway_protocol::{
+    IdentityInformation as GatewayIdentityInformation, LoginRequest, LoginResult, PasscodeRequiredCode, PatchRequest,
+    PatchResult, SecurityCodeAction, SecurityCodeInput, ShardListRequest,
+};
+use silkroad_protocol::auth::{AuthRequest, AuthResult, LogoutMode, LogoutRequest};
+use silkroad_protocol::character::{
+    CharacterJoinRequest, CharacterJoinResult, CharacterListAction, CharacterListContent, CharacterListError,
+    CharacterListRequest, CharacterListRequestAction, CharacterListResult, FinishLoading,
+};
+use silkroad_protocol::chat::{ChatMessage, ChatSource, ChatTarget};
+use silkroad_protocol::combat::{ActionTarget, DoActionType, PerformAction, PerformActionUpdate};
+use silkroad_protocol::general::{IdentityInformation, KeepAlive};
+use silkroad_protocol::movement::{MovementTarget, PlayerMovementRequest};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::{interval, sleep_until, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, instrument, warn};
+
+const CLIENT_MODULE: &str = "SR_Client";
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Steps other than waiting are sent right away, but we still want a short pause between
+/// them, such that a script without any waits doesn't flood the server.
+const MIN_STEP_DELAY: Duration = Duration::from_millis(100);
+/// The amount of entities we remember as possible targets for attacks.
+const MAX_KNOWN_ENTITIES: usize = 64;
+
+/// The appearance and starting equipment of characters created by bots.
+#[derive(Copy, Clone, Debug)]
+pub struct CharacterTemplate {
+    pub ref_id: u32,
+    pub scale: u8,
+    pub chest: u32,
+    pub pants: u32,
+    pub boots: u32,
+    pub weapon: u32,
+}
+
+impl Default for CharacterTemplate {
+    /// A Chinese male character with the default starting equipment.
+    fn default() -> Self {
+        CharacterTemplate {
+            ref_id: 1907,
+            scale: 34,
+            chest: 3643,
+            pants: 3644,
+            boots: 3645,
+            weapon: 3632,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BotOptions {
+    /// Address of the gateway server, e.g. `127.0.0.1:15779`.
+    pub gateway: String,
+    pub username: String,
+    pub password: String,
+    /// Passcode to enter if the account requires one.
+    pub passcode: Option<String>,
+    /// The shard to log in to. If not set, the first shard that is online is used.
+    pub shard: Option<u16>,
+    /// The name of the character to play. It will be created if the account doesn't have a
+    /// character with that name yet.
+    pub character: String,
+    pub template: CharacterTemplate,
+    pub script: Script,
+    pub locale: u8,
+    pub client_version: u32,
+}
+
+/// Counts how far the bots of a process got. This is shared between all bots, to get an
+/// overview of the whole load test.
+#[derive(Default, Debug)]
+pub struct BotStatistics {
+    pub logged_in: AtomicUsize,
+    pub in_game: AtomicUsize,
+    pub failed: AtomicUsize,
+}
+
+impl Display for BotStatistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} logged in, {} in game, {} failed",
+            self.logged_in.load(Ordering::Relaxed),
+            self.in_game.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed)
+        )
+    }
+}
+
+struct AgentLogin {
+    token: u32,
+    host: String,
+    port: u16,
+}
+
+/// What a bot knows about the world around it, which is just enough to find something to
+/// attack.
+#[derive(Default)]
+struct WorldView {
+    own_id: Option<u32>,
+    entities: Vec<u32>,
+}
+
+impl WorldView {
+    fn saw(&mut self, entity: u32) {
+        if Some(entity) == self.own_id || self.entities.contains(&entity) {
+            return;
+        }
+
+        if self.entities.len() >= MAX_KNOWN_ENTITIES {
+            self.entities.remove(0);
+        }
+        self.entities.push(entity);
+    }
+
+    fn despawned(&mut self, entity: u32) {
+        self.entities.retain(|known| *known != entity);
+    }
+
+    fn set_own_id(&mut self, id: u32) {
+        self.own_id = Some(id);
+        self.despawned(id);
+    }
+}
+
+/// A single headless client. A bot first logs in through the gateway server, then joins the
+/// game with its character on the agent server and finally runs its script over and over
+/// until it is cancelled.
+pub struct Bot {
+    options: BotOptions,
+}
+
+impl Bot {
+    pub fn new(options: BotOptions) -> Self {
+        Bot { options }
+    }
+
+    /// Runs the bot until it gets cancelled, after which it logs out again, or until it runs
+    /// into an error.
+    #[instrument(skip_all, fields(user = %self.options.username))]
+    pub async fn run(self, cancel: CancellationToken, statistics: &BotStatistics) -> Result<(), BotError> {
+        let result = self.play(cancel, statistics).await;
+        if let Err(e) = &result {
+            warn!(%e, "Bot stopped");
+            statistics.failed.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    async fn play(&self, cancel: CancellationToken, statistics: &BotStatistics) -> Result<(), BotError> {
+        let login = self.login().await?;
+        statistics.logged_in.fetch_add(1, Ordering::Relaxed);
+
+        let mut agent = Session::<AgentServerProtocol>::connect((login.host.as_str(), login.port)).await?;
+        self.authenticate(&mut agent, login.token).await?;
+        self.join(&mut agent).await?;
+        info!(character = self.options.character, "Joined the game");
+
+        statistics.in_game.fetch_add(1, Ordering::Relaxed);
+        let result = self.run_script(&mut agent, cancel).await;
+        statistics.in_game.fetch_sub(1, Ordering::Relaxed);
+        result?;
+
+        self.logout(&mut agent).await
+    }
+
+    async fn login(&self) -> Result<AgentLogin, BotError> {
+        let mut gateway = Session::<GatewayServerProtocol>::connect(self.options.gateway.as_str()).await?;
+        gateway
+            .send(GatewayIdentityInformation::new(CLIENT_MODULE.to_string(), 0))
+            .await?;
+        gateway
+            .send(PatchRequest {
+                content: self.options.locale,
+                module: CLIENT_MODULE.to_string(),
+                version: self.options.client_version,
+            })
+            .await?;
+
+        loop {
+            if let GatewayServerProtocol::PatchResponse(response) = *gateway.receive().await? {
+                match response.result {
+                    PatchResult::UpToDate { .. } => break,
+                    PatchResult::Problem { error } => return Err(BotError::Patch(format!("{error:?}"))),
+                }
+            }
+        }
+
+        gateway.send(ShardListRequest).await?;
+        let shard = loop {
+            if let GatewayServerProtocol::ShardListResponse(response) = *gateway.receive().await? {
+                let shard = response
+                    .shards
+                    .iter()
+                    .filter(|shard| shard.is_online)
+                    .find(|shard| self.options.shard.map(|id| id == shard.id).unwrap_or(true));
+                break shard.map(|shard| shard.id).ok_or(BotError::NoShard)?;
+            }
+        };
+
+        debug!(shard, "Logging in");
+        gateway
+            .send(LoginRequest {
+                unknown_1: self.options.locale,
+                username: self.options.username.clone(),
+                password: self.options.password.clone(),
+                shard_id: shard,
+                unknown_2: 0,
+            })
+            .await?;
+
+        loop {
+            match *gateway.receive().await? {
+                GatewayServerProtocol::LoginResponse(response) => {
+                    return match response.result {
+                        LoginResult::Success {
+                            session_id,
+                            agent_ip,
+                            agent_port,
+                            ..
+                        } => Ok(AgentLogin {
+                            token: session_id,
+                            host: agent_ip,
+                            port: agent_port,
+                        }),
+                        LoginResult::LoginError { error } => Err(BotError::Login(format!("{error:?}"))),
+                        LoginResult::Unknown => Err(BotError::Login("Unknown error".to_string())),
+                    };
+                },
+                GatewayServerProtocol::PasscodeRequiredResponse(response) => match response.result {
+                    PasscodeRequiredCode::PasscodeRequired => self.enter_passcode(&mut gateway).await?,
+                    PasscodeRequiredCode::DefinePasscode => {
+                        return Err(BotError::Login(
+                            "The account needs to define a passcode first".to_string(),
+                        ))
+                    },
+                    PasscodeRequiredCode::PasscodeInvalid => {
+                        return Err(BotError::Login("The passcode was not accepted".to_string()))
+                    },
+                    PasscodeRequiredCode::PasscodeBlocked => {
+                        return Err(BotError::Login("The passcode has been blocked".to_string()))
+                    },
+                },
+                _ => {},
+            }
+        }
+    }
+
+    async fn enter_passcode(&self, gateway: &mut Session<GatewayServerProtocol>) -> Result<(), BotError> {
+        let Some(passcode) = self.options.passcode.as_ref() else {
+            return Err(BotError::Login(
+                "A passcode is required, but none was configured".to_string(),
+            ));
+        };
+
+        let data = PasscodeEncoder::default()
+            .encode_passcode(passcode)
+            .ok_or_else(|| BotError::Login("The passcode is too long".to_string()))?;
+        gateway
+            .send(SecurityCodeInput {
+                action: SecurityCodeAction::Enter,
+                inner_size: passcode.len() as u16,
+                data,
+            })
+            .await
+    }
+
+    async fn authenticate(&self, agent: &mut Session<AgentServerProtocol>, token: u32) -> Result<(), BotError> {
+        agent
+            .send(IdentityInformation::new(CLIENT_MODULE.to_string(), 0))
+            .await?;
+        agent
+            .send(AuthRequest {
+                token,
+                username: self.options.username.clone(),
+                password: self.options.password.clone(),
+                unknown: self.options.locale,
+                mac_bytes: [0; 6],
+            })
+            .await?;
+
+        loop {
+            if let AgentServerProtocol::AuthResponse(response) = *agent.receive().await? {
+                return match response.result {
+                    AuthResult::Success { .. } => Ok(()),
+                    AuthResult::Failure { code } => Err(BotError::Auth(format!("{code:?}"))),
+                };
+            }
+        }
+    }
+
+    async fn join(&self, agent: &mut Session<AgentServerProtocol>) -> Result<(), BotError> {
+        let name = &self.options.character;
+        if !self.has_character(agent).await? {
+            debug!(character = name, "Creating character");
+            let template = self.options.template;
+            Self::character_request(agent, CharacterListRequestAction::checkname(name.clone())).await?;
+            Self::character_request(
+                agent,
+                CharacterListRequestAction::create(
+                    name.clone(),
+                    template.ref_id,
+                    template.scale,
+                    template.chest,
+                    template.pants,
+                    template.boots,
+                    template.weapon,
+                ),
+            )
+            .await?;
+
+            // The agent server only allows joining characters it has listed before.
+            if !self.has_character(agent).await? {
+                return Err(BotError::Join(CharacterListError::CouldntCreateCharacter));
+            }
+        }
+
+        agent
+            .send(CharacterJoinRequest {
+                character_name: name.clone(),
+            })
+            .await?;
+        loop {
+            if let AgentServerProtocol::CharacterJoinResponse(response) = *agent.receive().await? {
+                match response.result {
+                    CharacterJoinResult::Success => break,
+                    CharacterJoinResult::Error { error } => return Err(BotError::Join(error)),
+                }
+            }
+        }
+
+        loop {
+            if let AgentServerProtocol::CharacterSpawnEnd(_) = *agent.receive().await? {
+                break;
+            }
+        }
+        agent.send(FinishLoading).await
+    }
+
+    async fn has_character(&self, agent: &mut Session<AgentServerProtocol>) -> Result<bool, BotError> {
+        let content = Self::character_request(agent, CharacterListRequestAction::List).await?;
+        let CharacterListContent::Characters { characters, .. } = content else {
+            return Ok(false);
+        };
+
+        Ok(characters
+            .iter()
+            .any(|character| character.name == self.options.character))
+    }
+
+    async fn character_request(
+        agent: &mut Session<AgentServerProtocol>,
+        action: CharacterListRequestAction,
+    ) -> Result<CharacterListContent, BotError> {
+        let expected = match &action {
+            CharacterListRequestAction::Create { .. } => CharacterListAction::Create,
+            CharacterListRequestAction::List => CharacterListAction::List,
+            CharacterListRequestAction::Delete { .. } => CharacterListAction::Delete,
+            CharacterListRequestAction::CheckName { .. } => CharacterListAction::CheckName,
+            CharacterListRequestAction::Restore { .. } => CharacterListAction::Restore,
+            CharacterListRequestAction::ShowJobSpread => CharacterListAction::ShowJobSpread,
+            CharacterListRequestAction::AssignJob { .. } => CharacterListAction::AssignJob,
+        };
+
+        agent.send(CharacterListRequest { action }).await?;
+        loop {
+            if let AgentServerProtocol::CharacterListResponse(response) = *agent.receive().await? {
+                if response.action != expected {
+                    continue;
+                }
+
+                return match response.result {
+                    CharacterListResult::Ok { content } => Ok(content),
+                    CharacterListResult::Error { error } => Err(BotError::CharacterList {
+                        action: expected,
+                        error,
+                    }),
+                };
+            }
+        }
+    }
+
+    async fn run_script(
+        &self,
+        agent: &mut Session<AgentServerProtocol>,
+        cancel: CancellationToken,
+    ) -> Result<(), BotError> {
+        let mut world = WorldView::default();
+        let mut keep_alive = interval(KEEP_ALIVE_INTERVAL);
+        let mut steps = self.options.script.steps.iter().cycle();
+        let has_steps = !self.options.script.steps.is_empty();
+        let mut next_step = Instant::now();
+        let mut chat_index = 0u8;
+
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    return Ok(());
+                }
+                packet = agent.next() => {
+                    Self::observe(&mut world, *packet?)?;
+                }
+                _ = keep_alive.tick() => {
+                    agent.send(KeepAlive).await?;
+                }
+                _ = sleep_until(next_step), if has_steps => {
+                    let step = steps.next().expect("Script should not be empty");
+                    let delay = self.perform(agent, &world, step, &mut chat_index).await?;
+                    next_step = Instant::now() + delay.max(MIN_STEP_DELAY);
+                }
+            }
+        }
+    }
+
+    fn observe(world: &mut WorldView, packet: AgentServerProtocol) -> Result<(), BotError> {
+        match packet {
+            AgentServerProtocol::UnknownPacket2(spawned) => world.set_own_id(spawned.id),
+            AgentServerProtocol::PlayerMovementResponse(movement) => world.saw(movement.player_id),
+            AgentServerProtocol::EntityMovementInterrupt(interrupt) => world.saw(interrupt.entity_id),
+            AgentServerProtocol::PerformActionUpdate(PerformActionUpdate::Success { source, target, .. }) => {
+                world.saw(source);
+                world.saw(target);
+            },
+            AgentServerProtocol::ChatUpdate(chat) => {
+                if let ChatSource::All { sender } = chat.source {
+                    world.saw(sender);
+                }
+            },
+            AgentServerProtocol::EntityDespawn(despawn) => world.despawned(despawn.entity_id),
+            AgentServerProtocol::Disconnect(_) | AgentServerProtocol::LogoutFinished(_) => {
+                return Err(BotError::Disconnected)
+            },
+            _ => {},
+        }
+        Ok(())
+    }
+
+    async fn perform(
+        &self,
+        agent: &mut Session<AgentServerProtocol>,
+        world: &WorldView,
+        step: &Step,
+        chat_index: &mut u8,
+    ) -> Result<Duration, BotError> {
+        debug!(%step, "Performing step");
+        match step {
+            Step::Move(degrees) => Self::move_towards(agent, *degrees).await?,
+            Step::MoveRandom => {
+                let degrees = rand::rng().random_range(0..360);
+                Self::move_towards(agent, degrees).await?
+            },
+            Step::Chat(message) => {
+                *chat_index = chat_index.wrapping_add(1);
+                agent
+                    .send(ChatMessage {
+                        target: ChatTarget::All,
+                        index: *chat_index,
+                        contains_link: false,
+                        unknown: 0,
+                        recipient: None,
+                        message: message.replace("{name}", &self.options.character),
+                    })
+                    .await?
+            },
+            Step::Attack => {
+                let target = world.entities.choose(&mut rand::rng()).copied();
+                match target {
+                    Some(target) => {
+                        agent
+                            .send(PerformAction::Do(DoActionType::Attack {
+                                target: ActionTarget::Entity(target),
+                            }))
+                            .await?
+                    },
+                    None => debug!("No entity to attack"),
+                }
+            },
+            Step::Wait(duration) => return Ok(*duration),
+        }
+        Ok(Duration::ZERO)
+    }
+
+    async fn move_towards(agent: &mut Session<AgentServerProtocol>, degrees: u16) -> Result<(), BotError> {
+        let angle = (u32::from(degrees % 360) * 0x10000 / 360) as u16;
+        agent
+            .send(PlayerMovementRequest {
+                kind: MovementTarget::direction(1, angle),
+            })
+            .await
+    }
+
+    async fn logout(&self, agent: &mut Session<AgentServerProtocol>) -> Result<(), BotError> {
+        agent
+            .send(LogoutRequest {
+                mode: LogoutMode::Logout,
+            })
+            .await?;
+
+        loop {
+            match agent.receive().await {
+                Ok(packet) => {
+                    if let AgentServerProtocol::LogoutFinished(_) = *packet {
+                        return Ok(());
+                    }
+                },
+                Err(BotError::Disconnected) => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
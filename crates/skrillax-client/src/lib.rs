@@ -0,0 +1,43 @@
+mod bot;
+pub mod passcode;
+mod protocol;
+mod script;
+mod session;
+
+pub use bot::{Bot, BotOptions, BotStatistics, CharacterTemplate};
+pub use script::{InvalidStep, Script, Step};
+use silkroad_protocol::character::{CharacterListAction, CharacterListError};
+use skrillax_stream::stream::{InStreamError, OutStreamError};
+use std::io;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum BotError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Security handshake failed: {0}")]
+    Handshake(String),
+    #[error("Could not read from the server: {0}")]
+    Read(#[from] InStreamError),
+    #[error("Could not send to the server: {0}")]
+    Write(#[from] OutStreamError),
+    #[error("The server closed the connection")]
+    Disconnected,
+    #[error("The server did not respond in time")]
+    Timeout,
+    #[error("The client version was not accepted: {0}")]
+    Patch(String),
+    #[error("There is no shard available to log in to")]
+    NoShard,
+    #[error("Login at the gateway server failed: {0}")]
+    Login(String),
+    #[error("Authentication at the agent server failed: {0}")]
+    Auth(String),
+    #[error("Character {action:?} request failed: {error:?}")]
+    CharacterList {
+        action: CharacterListAction,
+        error: CharacterListError,
+    },
+    #[error("Could not join the game: {0:?}")]
+    Join(CharacterListError),
+}
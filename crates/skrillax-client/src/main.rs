@@ -0,0 +1,128 @@
+use clap::Parser;
+use color_eyre::Result;
+use skrillax_client::{Bot, BotOptions, BotStatistics, CharacterTemplate, Script};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::{interval, sleep};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Runs a number of headless bots against a local server. Each bot logs in with its own
+/// account, joins the game with its own character (creating it if necessary) and then
+/// repeats its script until it is stopped with Ctrl+C.
+///
+/// The accounts need to exist already. In the account and character patterns, `{}` is
+/// replaced with the number of the bot, starting at `--first`.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The address of the gateway server.
+    #[arg(long, default_value = "127.0.0.1:15779")]
+    gateway: String,
+    /// The number of bots to run.
+    #[arg(long, short, default_value_t = 1)]
+    count: usize,
+    /// The number of the first bot.
+    #[arg(long, default_value_t = 0)]
+    first: usize,
+    #[arg(long, default_value = "bot{}")]
+    username: String,
+    #[arg(long, default_value = "bot")]
+    password: String,
+    #[arg(long)]
+    passcode: Option<String>,
+    #[arg(long, default_value = "Bot{}")]
+    character: String,
+    /// The shard to join, otherwise the first available shard is used.
+    #[arg(long)]
+    shard: Option<u16>,
+    /// The steps of the script, separated by `;`. Available steps are `move`, `move <degrees>`,
+    /// `chat <message>`, `attack` and `wait <seconds>`.
+    #[arg(long)]
+    script: Option<Script>,
+    /// Time between starting two bots, in milliseconds.
+    #[arg(long, default_value_t = 100)]
+    ramp_up: u64,
+    /// Stops all bots after the given amount of seconds.
+    #[arg(long)]
+    duration: Option<u64>,
+    #[arg(long, default_value_t = 22)]
+    locale: u8,
+    #[arg(long, default_value_t = 188)]
+    client_version: u32,
+}
+
+impl Args {
+    fn bot_options(&self, number: usize) -> BotOptions {
+        BotOptions {
+            gateway: self.gateway.clone(),
+            username: self.username.replace("{}", &number.to_string()),
+            password: self.password.clone(),
+            passcode: self.passcode.clone(),
+            shard: self.shard,
+            character: self.character.replace("{}", &number.to_string()),
+            template: CharacterTemplate::default(),
+            script: self.script.clone().unwrap_or_default(),
+            locale: self.locale,
+            client_version: self.client_version,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+    color_eyre::install()?;
+    let args = Args::parse();
+
+    let cancel = CancellationToken::new();
+    let statistics = Arc::new(BotStatistics::default());
+
+    let stop = cancel.clone();
+    let duration = args.duration;
+    tokio::spawn(async move {
+        match duration {
+            Some(seconds) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = sleep(Duration::from_secs(seconds)) => {},
+                }
+            },
+            None => {
+                let _ = tokio::signal::ctrl_c().await;
+            },
+        }
+        info!("Stopping all bots");
+        stop.cancel();
+    });
+
+    let mut bots = JoinSet::new();
+    for number in args.first..args.first + args.count {
+        if cancel.is_cancelled() {
+            break;
+        }
+
+        let bot = Bot::new(args.bot_options(number));
+        let cancel = cancel.clone();
+        let statistics = statistics.clone();
+        bots.spawn(async move { bot.run(cancel, &statistics).await });
+        sleep(Duration::from_millis(args.ramp_up)).await;
+    }
+
+    let mut report = interval(Duration::from_secs(10));
+    loop {
+        tokio::select! {
+            finished = bots.join_next() => {
+                if finished.is_none() {
+                    break;
+                }
+            }
+            _ = report.tick() => {
+                info!("{}", statistics);
+            }
+        }
+    }
+
+    info!("All bots stopped: {}", statistics);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use blowfish::cipher::{Block, BlockDecrypt, BlockEncrypt, KeyInit};
+use blowfish::BlowfishLE;
+
+const PASSCODE_KEY: [u8; 8] = [0x0f, 0x07, 0x3d, 0x20, 0x56, 0x62, 0xc9, 0xeb];
+
+type BlockLE = Block<BlowfishLE>;
+
+/// Encrypts passcodes the same way the client does before sending them to the gateway
+/// server. This is the counterpart to the decoder of the gateway server.
+pub struct PasscodeEncoder {
+    blowfish: BlowfishLE,
+}
+
+impl PasscodeEncoder {
+    /// Encrypts the given passcode into a single block. Passcodes can be at most 8 characters
+    /// long; anything beyond that would not fit into the block and is thus rejected.
+    pub fn encode_passcode(&self, passcode: &str) -> Option<[u8; 8]> {
+        if passcode.len() > 8 {
+            return None;
+        }
+
+        let mut data = [0u8; 8];
+        data[..passcode.len()].copy_from_slice(passcode.as_bytes());
+        self.blowfish.encrypt_block(BlockLE::from_mut_slice(&mut data));
+        Some(data)
+    }
+
+    #[cfg(test)]
+    fn decode_passcode(&self, length: usize, encrypted: [u8; 8]) -> String {
+        let mut data = encrypted;
+        self.blowfish.decrypt_block(BlockLE::from_mut_slice(&mut data));
+        String::from_utf8_lossy(&data[..length]).to_string()
+    }
+}
+
+impl Default for PasscodeEncoder {
+    fn default() -> Self {
+        let blowfish = BlowfishLE::new_from_slice(&PASSCODE_KEY).expect("Could not create blowfish key");
+        PasscodeEncoder { blowfish }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let encoder = PasscodeEncoder::default();
+        // As sent by the official client.
+        assert_eq!(encoder.decode_passcode(4, [113, 42, 1, 64, 127, 104, 60, 94]), "1234");
+
+        let encoded = encoder.encode_passcode("1234").unwrap();
+        assert_eq!(encoder.decode_passcode(4, encoded), "1234");
+        assert!(encoder.encode_passcode("123456789").is_none());
+    }
+}
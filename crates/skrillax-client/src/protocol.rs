@@ -0,0 +1,47 @@
+use silkroad_gateway_protocol::{
+    GatewayNoticeResponse, IdentityInformation as GatewayIdentityInformation, LoginResponse, PasscodeRequiredResponse,
+    PatchResponse, PingServerResponse, SecurityCodeResponse, ShardListResponse,
+};
+use silkroad_protocol::auth::{AuthResponse, Disconnect, LogoutFinished, LogoutResponse};
+use silkroad_protocol::character::{CharacterJoinResponse, CharacterListResponse, UnknownPacket2};
+use silkroad_protocol::chat::{ChatMessageResponse, ChatUpdate};
+use silkroad_protocol::combat::{PerformActionResponse, PerformActionUpdate};
+use silkroad_protocol::general::{IdentityInformation, KeepAlive};
+use silkroad_protocol::movement::{EntityMovementInterrupt, PlayerMovementResponse};
+use silkroad_protocol::spawn::{CharacterSpawnEnd, CharacterSpawnStart, EntityDespawn};
+use skrillax_protocol::define_inbound_protocol;
+
+// The servers send a lot more than what is listed here, but a bot only needs to understand
+// what it reacts to. Everything else is skipped when reading.
+
+define_inbound_protocol! { GatewayServerProtocol =>
+    GatewayIdentityInformation,
+    PatchResponse,
+    GatewayNoticeResponse,
+    PasscodeRequiredResponse,
+    SecurityCodeResponse,
+    LoginResponse,
+    ShardListResponse,
+    PingServerResponse
+}
+
+define_inbound_protocol! { AgentServerProtocol =>
+    IdentityInformation,
+    KeepAlive,
+    AuthResponse,
+    LogoutResponse,
+    LogoutFinished,
+    Disconnect,
+    CharacterListResponse,
+    CharacterJoinResponse,
+    CharacterSpawnStart,
+    CharacterSpawnEnd,
+    UnknownPacket2,
+    EntityDespawn,
+    PlayerMovementResponse,
+    EntityMovementInterrupt,
+    ChatUpdate,
+    ChatMessageResponse,
+    PerformActionResponse,
+    PerformActionUpdate
+}
@@ -0,0 +1,124 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+
+/// A single thing a bot does once it is in the game.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Step {
+    /// Starts walking into the given direction, in degrees.
+    Move(u16),
+    /// Starts walking into a random direction.
+    MoveRandom,
+    /// Sends the message to everyone around. `{name}` is replaced by the name of the character.
+    Chat(String),
+    /// Attacks a random entity the bot has seen recently, if there is any.
+    Attack,
+    /// Does nothing for the given time.
+    Wait(Duration),
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid script step '{0}'")]
+pub struct InvalidStep(String);
+
+impl FromStr for Step {
+    type Err = InvalidStep;
+
+    /// Parses a step from its textual form, which is one of `move`, `move <degrees>`,
+    /// `chat <message>`, `attack` or `wait <seconds>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, argument) = match s.split_once(' ') {
+            Some((command, argument)) => (command, Some(argument.trim())),
+            None => (s, None),
+        };
+
+        let step = match (command, argument) {
+            ("move", None) => Step::MoveRandom,
+            ("move", Some(angle)) => Step::Move(angle.parse().map_err(|_| InvalidStep(s.to_string()))?),
+            ("chat", Some(message)) => Step::Chat(message.to_string()),
+            ("attack", None) => Step::Attack,
+            ("wait", Some(seconds)) => {
+                let seconds = seconds.parse::<f32>().map_err(|_| InvalidStep(s.to_string()))?;
+                Step::Wait(Duration::try_from_secs_f32(seconds).map_err(|_| InvalidStep(s.to_string()))?)
+            },
+            _ => return Err(InvalidStep(s.to_string())),
+        };
+        Ok(step)
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Move(angle) => write!(f, "move {angle}"),
+            Step::MoveRandom => write!(f, "move"),
+            Step::Chat(message) => write!(f, "chat {message}"),
+            Step::Attack => write!(f, "attack"),
+            Step::Wait(duration) => write!(f, "wait {}", duration.as_secs_f32()),
+        }
+    }
+}
+
+/// The steps a bot repeats for as long as it is running.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Script {
+    pub steps: Vec<Step>,
+}
+
+impl FromStr for Script {
+    type Err = InvalidStep;
+
+    /// Parses a script made up of steps separated by `;`, e.g. `move; wait 2; chat hi`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split(';')
+            .filter(|step| !step.trim().is_empty())
+            .map(Step::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Script { steps })
+    }
+}
+
+impl Default for Script {
+    fn default() -> Self {
+        Script {
+            steps: vec![
+                Step::MoveRandom,
+                Step::Wait(Duration::from_secs(3)),
+                Step::Chat("Hello, this is {name}.".to_string()),
+                Step::Wait(Duration::from_secs(2)),
+                Step::Attack,
+                Step::Wait(Duration::from_secs(3)),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script = Script::from_str("move; move 90;wait 1.5; chat hello there ;attack;").unwrap();
+        assert_eq!(
+            script.steps,
+            vec![
+                Step::MoveRandom,
+                Step::Move(90),
+                Step::Wait(Duration::from_millis(1500)),
+                Step::Chat("hello there".to_string()),
+                Step::Attack,
+            ]
+        );
+
+        assert!(Script::from_str("dance").is_err());
+        assert!(Script::from_str("wait").is_err());
+        assert!(Script::from_str("wait -1").is_err());
+        assert!(Script::from_str("wait NaN").is_err());
+        assert!(Script::from_str("wait inf").is_err());
+        assert!(Script::from_str("move north").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::BotError;
+use skrillax_stream::handshake::PassiveSecuritySetup;
+use skrillax_stream::packet::AsPacket;
+use skrillax_stream::stream::{InStreamError, SilkroadStreamWrite, SilkroadTcpExt};
+use skrillax_stream::InputProtocol;
+use std::time::Duration;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::trace;
+
+/// How long we wait for the server to answer a request before giving up.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A connection to either the gateway or the agent server, from the client side.
+///
+/// Packets are read on a separate task, such that waiting for a packet can be freely
+/// combined with other futures without losing partially read data.
+pub(crate) struct Session<I: InputProtocol> {
+    writer: SilkroadStreamWrite<OwnedWriteHalf>,
+    packets: mpsc::Receiver<Result<I::Proto, InStreamError>>,
+    reader: JoinHandle<()>,
+}
+
+impl<I: InputProtocol + 'static> Session<I>
+where
+    I::Proto: Send,
+{
+    pub(crate) async fn connect<A: ToSocketAddrs>(address: A) -> Result<Self, BotError> {
+        let socket = TcpStream::connect(address).await?;
+        socket.set_nodelay(true)?;
+        let (mut reader, mut writer) = socket.into_silkroad_stream();
+        PassiveSecuritySetup::handle(&mut reader, &mut writer)
+            .await
+            .map_err(|e| BotError::Handshake(e.to_string()))?;
+
+        let (sender, packets) = mpsc::channel(64);
+        let reader = tokio::spawn(async move {
+            loop {
+                match reader.next_packet::<I>().await {
+                    Ok(packet) => {
+                        if sender.send(Ok(packet)).await.is_err() {
+                            return;
+                        }
+                    },
+                    Err(InStreamError::UnmatchedOpcode(opcode)) => {
+                        trace!(opcode = format!("{opcode:#06x}"), "Skipping packet");
+                    },
+                    Err(InStreamError::EndOfStream) => return,
+                    Err(e) => {
+                        let _ = sender.send(Err(e)).await;
+                        return;
+                    },
+                }
+            }
+        });
+
+        Ok(Session {
+            writer,
+            packets,
+            reader,
+        })
+    }
+
+    pub(crate) async fn send<P: AsPacket>(&mut self, packet: P) -> Result<(), BotError> {
+        self.writer.write(packet.as_packet()).await?;
+        Ok(())
+    }
+
+    /// Waits for the next packet the server sends, which may take at most
+    /// [RESPONSE_TIMEOUT].
+    pub(crate) async fn receive(&mut self) -> Result<I::Proto, BotError> {
+        match timeout(RESPONSE_TIMEOUT, self.next()).await {
+            Ok(result) => result,
+            Err(_) => Err(BotError::Timeout),
+        }
+    }
+
+    /// Waits for the next packet the server sends, without any timeout. This is safe to use
+    /// inside `select!`.
+    pub(crate) async fn next(&mut self) -> Result<I::Proto, BotError> {
+        match self.packets.recv().await {
+            Some(Ok(packet)) => Ok(packet),
+            Some(Err(e)) => Err(e.into()),
+            None => Err(BotError::Disconnected),
+        }
+    }
+}
+
+impl<I: InputProtocol> Drop for Session<I> {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
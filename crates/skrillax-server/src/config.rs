@@ -0,0 +1,153 @@
+use crate::{
+    ConnectionLimits, IpNetwork, OverflowStrategy, ProxyProtocolOptions, QueueLimits, RecordingFilter,
+    RecordingOptions, ServerOptions,
+};
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// The network settings of a server, as they appear in the configuration files. Settings that
+/// are missing fall back to the defaults of [ServerOptions].
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct NetworkConfig {
+    pub inbound_queue_size: usize,
+    pub outbound_queue_size: usize,
+    pub queue_overflow: OverflowStrategy,
+    pub max_connections_per_ip: Option<usize>,
+    pub max_connections_per_second: Option<u32>,
+    /// The handshake timeout in seconds.
+    pub handshake_timeout: Option<u64>,
+    pub allowlist: Vec<IpNetwork>,
+    pub denylist: Vec<IpNetwork>,
+    /// The interval, in seconds, in which the traffic should be reported.
+    pub traffic_report_interval: Option<u64>,
+    pub record_to: Option<String>,
+    pub record_filter: Vec<IpNetwork>,
+    pub proxy_protocol: bool,
+    pub trusted_proxies: Vec<IpNetwork>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        let queue = QueueLimits::default();
+        let limits = ConnectionLimits::default();
+        NetworkConfig {
+            inbound_queue_size: queue.inbound,
+            outbound_queue_size: queue.outbound,
+            queue_overflow: queue.overflow,
+            max_connections_per_ip: limits.max_connections_per_ip,
+            max_connections_per_second: limits.max_connection_rate_per_ip,
+            handshake_timeout: limits.handshake_timeout.map(|timeout| timeout.as_secs()),
+            allowlist: limits.allowlist,
+            denylist: limits.denylist,
+            traffic_report_interval: None,
+            record_to: None,
+            record_filter: Vec::new(),
+            proxy_protocol: false,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl NetworkConfig {
+    pub fn server_options(&self) -> ServerOptions {
+        ServerOptions {
+            queue: QueueLimits {
+                inbound: self.inbound_queue_size,
+                outbound: self.outbound_queue_size,
+                overflow: self.queue_overflow,
+            },
+            limits: ConnectionLimits {
+                max_connections_per_ip: self.max_connections_per_ip,
+                max_connection_rate_per_ip: self.max_connections_per_second,
+                handshake_timeout: self.handshake_timeout.map(Duration::from_secs),
+                allowlist: self.allowlist.clone(),
+                denylist: self.denylist.clone(),
+            },
+            recording: self.record_to.as_ref().map(|path| RecordingOptions {
+                path: path.into(),
+                filter: if self.record_filter.is_empty() {
+                    RecordingFilter::All
+                } else {
+                    RecordingFilter::Addresses(self.record_filter.clone())
+                },
+            }),
+            proxy_protocol: self.proxy_protocol.then(|| ProxyProtocolOptions {
+                trusted_proxies: self.trusted_proxies.clone(),
+            }),
+        }
+    }
+
+    pub fn traffic_report_interval(&self) -> Option<Duration> {
+        self.traffic_report_interval.map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug)]
+pub struct InvalidListenAddress(String);
+
+impl Display for InvalidListenAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not a valid ip address, optionally including a port", self.0)
+    }
+}
+
+impl Error for InvalidListenAddress {}
+
+/// One or more addresses to listen on. Each address may either be a plain ip address, in which
+/// case the listen port is used, or an address including a port.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum ListenAddresses {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl ListenAddresses {
+    pub fn socket_addrs(&self, default_port: u16) -> Result<Vec<SocketAddr>, InvalidListenAddress> {
+        let addresses = match self {
+            ListenAddresses::Single(address) => std::slice::from_ref(address),
+            ListenAddresses::Multiple(addresses) => addresses.as_slice(),
+        };
+
+        addresses
+            .iter()
+            .map(|address| {
+                address
+                    .parse::<SocketAddr>()
+                    .or_else(|_| address.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, default_port)))
+                    .map_err(|_| InvalidListenAddress(address.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_listen_addresses() {
+        let single = ListenAddresses::Single("0.0.0.0".to_string());
+        assert_eq!(
+            vec![SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 15779)],
+            single.socket_addrs(15779).unwrap()
+        );
+
+        let multiple = ListenAddresses::Multiple(vec!["127.0.0.1:1234".to_string(), "::".to_string()]);
+        assert_eq!(
+            vec![
+                SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 1234),
+                SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 15779)
+            ],
+            multiple.socket_addrs(15779).unwrap()
+        );
+
+        let invalid = ListenAddresses::Multiple(vec!["::".to_string(), "localhost".to_string()]);
+        assert!(invalid.socket_addrs(15779).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+
+/// The reason why a [crate::Connection] was closed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DisconnectReason {
+    /// The client closed the connection.
+    PeerClosed,
+    /// Some I/O error occurred on the socket, other than the client closing it.
+    IoError(ErrorKind),
+    /// The client sent data that could not be framed or decoded into a packet.
+    FramingError,
+    /// The client did not complete the security handshake.
+    HandshakeFailed,
+    /// The client did not keep up with the packets we were sending and its outbound
+    /// queue overflowed.
+    SlowConsumer,
+    /// The client did not send anything for too long.
+    Timeout,
+    /// The client was removed by the server, e.g. by a GM.
+    Kicked,
+    /// The session ended regularly, e.g. because the client logged out.
+    Finished,
+}
+
+impl DisconnectReason {
+    pub(crate) fn from_io_error(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted => {
+                DisconnectReason::PeerClosed
+            },
+            kind => DisconnectReason::IoError(kind),
+        }
+    }
+}
+
+impl Display for DisconnectReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisconnectReason::PeerClosed => write!(f, "connection closed by peer"),
+            DisconnectReason::IoError(kind) => write!(f, "I/O error: {kind}"),
+            DisconnectReason::FramingError => write!(f, "received malformed data"),
+            DisconnectReason::HandshakeFailed => write!(f, "security handshake failed"),
+            DisconnectReason::SlowConsumer => write!(f, "client did not keep up with outgoing packets"),
+            DisconnectReason::Timeout => write!(f, "client timed out"),
+            DisconnectReason::Kicked => write!(f, "kicked by the server"),
+            DisconnectReason::Finished => write!(f, "session finished"),
+        }
+    }
+}
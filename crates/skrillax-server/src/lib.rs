@@ -1,25 +1,70 @@
-use kanal::{unbounded, AsyncReceiver, AsyncSender, ReceiveError, Receiver, SendError, Sender};
+#[cfg(feature = "serde")]
+mod config;
+mod disconnect;
+mod limits;
+mod loopback;
+mod proxy;
+mod queue;
+mod record;
+mod stats;
+
+use crate::limits::{ConnectionSlot, ConnectionTracker};
+use crate::proxy::read_proxy_header;
+use crate::queue::{OutboundQueue, PushError, QueuedPacket};
+use crate::record::{ConnectionRecorder, SessionRecorder};
+use crate::stats::{outgoing_payload, outgoing_size, Measured, TrafficStats};
+#[cfg(feature = "serde")]
+pub use config::{InvalidListenAddress, ListenAddresses, NetworkConfig};
+pub use disconnect::*;
+use kanal::{bounded, unbounded, AsyncReceiver, AsyncSender, ReceiveError, Receiver, SendError, Sender};
+pub use limits::{ConnectionLimits, InvalidIpNetwork, IpNetwork, RejectedConnections};
+pub use loopback::{CapturedPacket, EncryptedLoopbackHandle, LoopbackHandle, LoopbackOptions};
+pub use proxy::ProxyProtocolOptions;
+pub use queue::{OverflowStrategy, QueueLimits};
+pub use record::{Direction, Framing, RecordedPacket, RecordingFilter, RecordingOptions, RecordingReader};
 use skrillax_stream::handshake::ActiveSecuritySetup;
 use skrillax_stream::packet::AsPacket;
 use skrillax_stream::stream::{InStreamError, OutStreamError, SilkroadStreamRead, SilkroadStreamWrite, SilkroadTcpExt};
 use skrillax_stream::InputProtocol;
-use std::io::{self, ErrorKind};
+use socket2::{Domain, Protocol, Socket, Type};
+pub use stats::{OpcodeStats, TrafficSnapshot};
+use std::io;
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
-use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, instrument, trace, warn};
+use tracing::{debug, info, instrument, trace, warn};
 
 static STREAM_IDENTIFIER: AtomicU64 = AtomicU64::new(1);
 
+struct ConnectionState {
+    cancel: CancellationToken,
+    reason: OnceLock<DisconnectReason>,
+    // Frees up the place of this connection in the per-address limits once dropped.
+    _slot: ConnectionSlot,
+    recorder: Option<ConnectionRecorder>,
+}
+
+impl ConnectionState {
+    /// Records the reason (unless there already is one) and stops the connection immediately.
+    fn close(&self, reason: DisconnectReason) {
+        let _ = self.reason.set(reason);
+        self.cancel.cancel();
+    }
+}
+
 #[derive(Clone)]
 pub struct Connection<I: InputProtocol> {
     remote_addr: SocketAddr,
     identifier: u64,
     inbound: Receiver<I::Proto>,
-    outbound: Sender<Box<dyn AsPacket + Send>>,
+    outbound: OutboundQueue,
+    state: Arc<ConnectionState>,
+    stats: Arc<TrafficStats>,
 }
 
 impl<I: InputProtocol + Send + 'static> Connection<I> {
@@ -31,8 +76,88 @@ impl<I: InputProtocol + Send + 'static> Connection<I> {
         self.inbound.as_async().recv().await
     }
 
+    /// Queues the packet to be sent to the client. The packet will never be dropped, even if
+    /// the client cannot keep up. If the queue limit is reached, the configured
+    /// [OverflowStrategy] decides what happens instead.
     pub fn send<S: AsPacket + Send + 'static>(&self, packet: S) -> Result<(), SendError> {
-        self.outbound.send(Box::new(packet))
+        self.enqueue(QueuedPacket::new(Box::new(packet), false))
+    }
+
+    /// Queues the packet to be sent to the client, but allows it to be dropped in favor of
+    /// newer packets, if the client cannot keep up. This should only be used for packets
+    /// that will be superseded by later packets anyway, e.g. updates of other entities.
+    pub fn send_droppable<S: AsPacket + Send + 'static>(&self, packet: S) -> Result<(), SendError> {
+        self.enqueue(QueuedPacket::new(Box::new(packet), true))
+    }
+
+    /// Queues the packet to be sent to the client like [Connection::send], but waits for room
+    /// in the queue if the configured [OverflowStrategy] is [OverflowStrategy::Block]. This is
+    /// meant for senders that run asynchronously and can afford to wait for the client.
+    pub async fn send_async<S: AsPacket + Send + 'static>(&self, packet: S) -> Result<(), SendError> {
+        if !self.is_connected() {
+            return Err(SendError::Closed);
+        }
+
+        let result = self
+            .outbound
+            .push_async(QueuedPacket::new(Box::new(packet), false))
+            .await;
+        self.handle_push_result(result)
+    }
+
+    fn enqueue(&self, packet: QueuedPacket) -> Result<(), SendError> {
+        if !self.is_connected() {
+            return Err(SendError::Closed);
+        }
+
+        self.handle_push_result(self.outbound.push(packet))
+    }
+
+    fn handle_push_result(&self, result: Result<(), PushError>) -> Result<(), SendError> {
+        match result {
+            Ok(_) => Ok(()),
+            Err(PushError::Closed) => Err(SendError::Closed),
+            Err(PushError::Overflow) => {
+                debug!(identifier = self.identifier, "Outbound queue overflowed.");
+                self.close(DisconnectReason::SlowConsumer);
+                Err(SendError::Closed)
+            },
+        }
+    }
+
+    fn close(&self, reason: DisconnectReason) {
+        self.state.close(reason);
+        self.outbound.close();
+        let _ = self.inbound.close();
+    }
+
+    /// Disconnects the client with the given reason.
+    ///
+    /// Packets that have already been queued will still be sent before the connection gets
+    /// closed, unless the queue is full, in which case the connection is closed right away.
+    /// Further packets can no longer be sent and [Connection::next] will report the
+    /// connection as closed. Calling this on a connection that is already disconnecting
+    /// has no effect, the first reason will be kept.
+    pub fn disconnect(&self, reason: DisconnectReason) {
+        if self.state.reason.set(reason).is_err() {
+            return;
+        }
+
+        let _ = self.inbound.close();
+        if !self.outbound.push_close() {
+            self.close(reason);
+        }
+    }
+
+    /// Checks if the connection is still alive, i.e. it has neither been closed by the
+    /// client, nor been disconnected by us.
+    pub fn is_connected(&self) -> bool {
+        self.state.reason.get().is_none() && !self.state.cancel.is_cancelled()
+    }
+
+    /// Provides the reason why this connection was closed, if it was closed already.
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.state.reason.get().copied()
     }
 
     pub fn id(&self) -> u64 {
@@ -43,37 +168,68 @@ impl<I: InputProtocol + Send + 'static> Connection<I> {
         self.remote_addr
     }
 
-    #[instrument(skip(socket, inbound, outbound, cancel))]
+    /// Provides the traffic of this connection so far.
+    pub fn traffic(&self) -> TrafficSnapshot {
+        self.stats.snapshot()
+    }
+
+    #[instrument(skip(socket, inbound, outbound, state, stats))]
     async fn handle(
         socket: TcpStream,
         identifier: u64,
-        cancel: CancellationToken,
+        state: Arc<ConnectionState>,
+        stats: Arc<TrafficStats>,
+        handshake_timeout: Option<Duration>,
         inbound: Sender<I::Proto>,
-        outbound: Receiver<Box<dyn AsPacket + Send>>,
+        outbound: Receiver<QueuedPacket>,
     ) {
         let (mut reader, mut writer) = socket.into_silkroad_stream();
-        if let Err(err) = ActiveSecuritySetup::handle(&mut reader, &mut writer).await {
+        let handshake = ActiveSecuritySetup::handle(&mut reader, &mut writer);
+        let handshake_result = match handshake_timeout {
+            Some(duration) => match tokio::time::timeout(duration, handshake).await {
+                Ok(result) => result,
+                Err(_) => {
+                    debug!("Client did not finish the handshake in time.");
+                    state.close(DisconnectReason::Timeout);
+                    return;
+                },
+            },
+            None => handshake.await,
+        };
+
+        if let Err(err) = handshake_result {
             warn!(%err, "Failed to finish handshake.");
+            state.close(DisconnectReason::HandshakeFailed);
             return;
         }
 
         let outbound = outbound.to_async();
         let inbound = inbound.to_async();
-        let send_cancel = cancel.clone();
-        tokio::spawn(Self::handle_send(writer, outbound, identifier, send_cancel));
-        tokio::spawn(Self::handle_receive(reader, inbound, identifier, cancel));
+        tokio::spawn(Self::handle_send(
+            writer,
+            outbound,
+            identifier,
+            state.clone(),
+            stats.clone(),
+        ));
+        if state.recorder.is_some() {
+            tokio::spawn(Self::handle_receive::<true>(reader, inbound, identifier, state, stats));
+        } else {
+            tokio::spawn(Self::handle_receive::<false>(reader, inbound, identifier, state, stats));
+        }
     }
 
-    #[instrument(skip(writer, oubound_receiver, cancel))]
+    #[instrument(skip(writer, oubound_receiver, state, stats))]
     async fn handle_send(
         mut writer: SilkroadStreamWrite<OwnedWriteHalf>,
-        oubound_receiver: AsyncReceiver<Box<dyn AsPacket + Send>>,
+        oubound_receiver: AsyncReceiver<QueuedPacket>,
         identifier: u64,
-        cancel: CancellationToken,
+        state: Arc<ConnectionState>,
+        stats: Arc<TrafficStats>,
     ) {
         loop {
             tokio::select! {
-                _ = cancel.cancelled() => {
+                _ = state.cancel.cancelled() => {
                     return;
                 }
                 recv = oubound_receiver.recv() => {
@@ -81,13 +237,25 @@ impl<I: InputProtocol + Send + 'static> Connection<I> {
                         return;
                     };
 
-                    let p = packet.as_packet();
-                    drop(packet);
+                    let p = match packet {
+                        QueuedPacket::Packet { packet, .. } => packet.as_packet(),
+                        QueuedPacket::Close => {
+                            trace!(identifier, "Closing connection after sending all remaining packets.");
+                            state.cancel.cancel();
+                            return;
+                        }
+                    };
+                    let (opcode, size) = outgoing_size(&p);
+                    if let Some(recorder) = &state.recorder {
+                        let (opcode, payload) = outgoing_payload(&p);
+                        recorder.record(Direction::Outbound, Framing::of(&p), opcode, payload.concat());
+                    }
                     match writer.write(p).await {
-                        Ok(_) => {},
+                        Ok(_) => stats.record_sent(opcode, size),
                         Err(OutStreamError::IoError(io_error)) => {
-                            cancel.cancel();
-                            if matches!(io_error.kind(), ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset) {
+                            let reason = DisconnectReason::from_io_error(io_error.kind());
+                            state.close(reason);
+                            if reason == DisconnectReason::PeerClosed {
                                 trace!(identifier, "Connection was closed by the peer.");
                             } else {
                                 warn!(identifier, %io_error, "Encountered some I/O error in connection.");
@@ -95,6 +263,7 @@ impl<I: InputProtocol + Send + 'static> Connection<I> {
                             return;
                         }
                         Err(OutStreamError::Framing(_)) => {
+                            stats.record_encryption_error();
                             warn!(identifier, "Tried to send an encrypted packet, but encryption was not set up.");
                         }
                     }
@@ -103,37 +272,55 @@ impl<I: InputProtocol + Send + 'static> Connection<I> {
         }
     }
 
-    #[instrument(skip(reader, inbound_sender, cancel))]
-    async fn handle_receive(
+    /// Receives packets from the client. `RECORD` should be set if the connection is being
+    /// recorded, as we otherwise don't keep the payload of received packets around.
+    #[instrument(skip(reader, inbound_sender, state, stats))]
+    async fn handle_receive<const RECORD: bool>(
         mut reader: SilkroadStreamRead<OwnedReadHalf>,
         inbound_sender: AsyncSender<I::Proto>,
         identifier: u64,
-        cancel: CancellationToken,
+        state: Arc<ConnectionState>,
+        stats: Arc<TrafficStats>,
     ) {
         loop {
             tokio::select! {
-                _ = cancel.cancelled() => {
+                _ = state.cancel.cancelled() => {
                     return;
                 }
-                recv = reader.next_packet::<I>() => {
+                recv = reader.next_packet::<Measured<I, RECORD>>() => {
                     match recv {
-                        Ok(packet) => {
-                            let to_send = inbound_sender.send(packet);
+                        Ok(measured) => {
+                            stats.record_received(measured.opcode, measured.size);
+                            if let (Some(recorder), Some(data)) = (&state.recorder, measured.data) {
+                                recorder.record(Direction::Inbound, Framing::Unknown, measured.opcode, data);
+                            }
+                            let to_send = inbound_sender.send(measured.packet);
                             if to_send.await.is_err() {
                                 return;
                             }
                         },
                         Err(InStreamError::EndOfStream) => {
                             debug!("Disconnected.");
+                            state.close(DisconnectReason::PeerClosed);
                             return;
                         },
                         Err(InStreamError::UnmatchedOpcode(opcode)) => {
+                            stats.record_unknown_opcode(opcode);
                             warn!(opcode, "Encountered unknown opcode.");
                             continue;
                         },
+                        Err(InStreamError::IoError(io_error)) => {
+                            let reason = DisconnectReason::from_io_error(io_error.kind());
+                            if reason != DisconnectReason::PeerClosed {
+                                warn!(%io_error, "Encountered some I/O error in connection.");
+                            }
+                            state.close(reason);
+                            return;
+                        },
                         Err(other) => {
+                            stats.record_framing_error();
                             warn!(error = %other, "Unexpected error occurred.");
-                            cancel.cancel();
+                            state.close(DisconnectReason::FramingError);
                             return;
                         }
                     }
@@ -143,40 +330,132 @@ impl<I: InputProtocol + Send + 'static> Connection<I> {
     }
 }
 
+/// Options to configure the behavior of a [Server] and its connections.
+#[derive(Clone, Debug, Default)]
+pub struct ServerOptions {
+    pub queue: QueueLimits,
+    pub limits: ConnectionLimits,
+    /// Records the packets of the connections into a file, if set.
+    pub recording: Option<RecordingOptions>,
+    /// Expects connections from trusted proxies to use the PROXY protocol, if set.
+    pub proxy_protocol: Option<ProxyProtocolOptions>,
+}
+
 struct AsyncServerRunner<I: InputProtocol + Send + 'static> {
     token: CancellationToken,
     stream_receiver: Receiver<Connection<I>>,
-    handle: JoinHandle<()>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+/// Everything the listeners of a server share to set up new connections.
+struct ConnectionFactory<I: InputProtocol> {
+    queue_limits: QueueLimits,
+    tracker: Arc<ConnectionTracker>,
+    traffic: Arc<TrafficStats>,
+    recorder: Option<Arc<SessionRecorder>>,
+    proxy_protocol: Option<ProxyProtocolOptions>,
+    cancel_token: CancellationToken,
+    connection_sender: Sender<Connection<I>>,
+}
+
+impl<I: InputProtocol + Send + 'static> ConnectionFactory<I> {
+    /// Handles a newly accepted socket. Sockets from trusted proxies first need to provide
+    /// the address of the actual client, which happens on a separate task as to not block
+    /// accepting other clients.
+    fn accept(self: &Arc<Self>, socket: TcpStream, addr: SocketAddr) {
+        let is_proxied = self
+            .proxy_protocol
+            .as_ref()
+            .is_some_and(|proxy| proxy.is_trusted(addr.ip()));
+        if !is_proxied {
+            self.admit(socket, addr);
+            return;
+        }
+
+        let factory = self.clone();
+        tokio::spawn(async move {
+            let mut socket = socket;
+            let header = read_proxy_header(&mut socket);
+            let header = match factory.tracker.handshake_timeout() {
+                Some(duration) => tokio::time::timeout(duration, header)
+                    .await
+                    .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+                None => header.await,
+            };
+
+            match header {
+                Ok(client) => {
+                    let client = client.map(|client| SocketAddr::new(client.ip().to_canonical(), client.port()));
+                    factory.admit(socket, client.unwrap_or(addr));
+                },
+                Err(e) => debug!(%addr, %e, "Could not read PROXY header."),
+            }
+        });
+    }
+
+    fn admit(&self, socket: TcpStream, addr: SocketAddr) {
+        let slot = match self.tracker.try_admit(addr.ip()) {
+            Ok(slot) => slot,
+            Err(rejection) => {
+                debug!(%addr, %rejection, "Rejected client.");
+                return;
+            },
+        };
+
+        let identifier = STREAM_IDENTIFIER.fetch_add(1, Ordering::SeqCst);
+        let (inbound_sender, inbound_receiver) = bounded(self.queue_limits.inbound.max(1));
+        let (outbound, outbound_receiver) = OutboundQueue::new(self.queue_limits.outbound, self.queue_limits.overflow);
+        let state = Arc::new(ConnectionState {
+            cancel: self.cancel_token.child_token(),
+            reason: OnceLock::new(),
+            _slot: slot,
+            recorder: self
+                .recorder
+                .as_ref()
+                .and_then(|recorder| recorder.for_connection(identifier, addr)),
+        });
+        let stats = TrafficStats::child_of(&self.traffic);
+        let connection = Connection {
+            remote_addr: addr,
+            identifier,
+            inbound: inbound_receiver,
+            outbound,
+            state: state.clone(),
+            stats: stats.clone(),
+        };
+
+        let handshake_timeout = self.tracker.handshake_timeout();
+        tokio::spawn(async move {
+            Connection::<I>::handle(
+                socket,
+                identifier,
+                state,
+                stats,
+                handshake_timeout,
+                inbound_sender,
+                outbound_receiver,
+            )
+            .await;
+        });
+
+        if let Err(e) = self.connection_sender.send(connection) {
+            warn!(%e, "Could not send client over.");
+        }
+    }
 }
 
 impl<I: InputProtocol + Send> AsyncServerRunner<I> {
-    async fn run(listener: TcpListener, cancel_token: CancellationToken, connection_sender: Sender<Connection<I>>) {
+    async fn run(listener: TcpListener, factory: Arc<ConnectionFactory<I>>) {
         loop {
             tokio::select! {
-                _ = cancel_token.cancelled() => break,
+                _ = factory.cancel_token.cancelled() => break,
                 accepted = listener.accept() => {
                     match accepted {
                         Ok((socket, addr)) => {
-                            let identifier = STREAM_IDENTIFIER.fetch_add(1, Ordering::SeqCst);
-                            let (inbound_sender, inbound_receiver) = unbounded();
-                            let (outbound_sender, outbound_receiver) = unbounded();
-                            let connection = Connection {
-                                remote_addr: addr,
-                                identifier,
-                                inbound: inbound_receiver,
-                                outbound: outbound_sender
-                            };
-
-                            let child = cancel_token.child_token();
-
-                            tokio::spawn(async move {
-                                Connection::<I>::handle(socket, identifier, child, inbound_sender, outbound_receiver).await;
-                            });
-
-                            if let Err(e) = connection_sender.send(connection) {
-                                warn!(%e, "Could not send client over.");
-                            }
-                            continue;
+                            // Clients connecting through a dual-stack socket show up with their
+                            // IPv4 address mapped into IPv6, which we want to treat as IPv4.
+                            let addr = SocketAddr::new(addr.ip().to_canonical(), addr.port());
+                            factory.accept(socket, addr);
                         },
                         Err(e) => {
                             warn!(%e, "Could not accept client.")
@@ -188,39 +467,140 @@ impl<I: InputProtocol + Send> AsyncServerRunner<I> {
     }
 }
 
+/// Checks if a listener for the given address should only accept IPv6 clients, which is the
+/// case if another listener on the same port already accepts IPv4 clients.
+fn is_only_v6(addr: SocketAddr, addrs: &[SocketAddr]) -> bool {
+    addr.is_ipv6() && addrs.iter().any(|other| other.is_ipv4() && other.port() == addr.port())
+}
+
+/// Creates a listening socket for the given address. IPv6 sockets are created as dual-stack
+/// sockets, accepting IPv4 clients as well, unless `only_v6` is set.
+fn bind_listener(addr: SocketAddr, only_v6: bool) -> Result<TcpListener, io::Error> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(only_v6)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
 pub struct Server<I: InputProtocol + Send + 'static> {
-    listen_addr: SocketAddr,
+    listen_addrs: Vec<SocketAddr>,
     async_connector: AsyncServerRunner<I>,
+    tracker: Arc<ConnectionTracker>,
+    traffic: Arc<TrafficStats>,
+    recorder: Option<Arc<SessionRecorder>>,
 }
 
 impl<I: InputProtocol + Send + 'static> Server<I> {
-    pub fn new(addr: SocketAddr) -> Result<Self, io::Error> {
+    /// Creates a new server listening on all the given addresses, accepting clients on each
+    /// of them.
+    ///
+    /// IPv6 addresses will also accept IPv4 clients (i.e. are dual-stack), unless there is
+    /// also an IPv4 address with the same port in the list. This makes it possible to listen
+    /// on `[::]` alone, as well as on `0.0.0.0` and `[::]` together.
+    pub fn new(addrs: &[SocketAddr], options: ServerOptions) -> Result<Self, io::Error> {
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "At least one address to listen on is required.",
+            ));
+        }
+
         let (sender, receiver) = unbounded();
         let cancel = CancellationToken::new();
+        let tracker = ConnectionTracker::new(options.limits);
+        let traffic = TrafficStats::new();
+        let recorder = options.recording.as_ref().map(SessionRecorder::create).transpose()?;
+
+        let mut listeners = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            listeners.push(bind_listener(*addr, is_only_v6(*addr, addrs))?);
+        }
 
-        let inner_cancel = cancel.clone();
-        let socket = TcpSocket::new_v4()?;
-        socket.bind(addr)?;
-        let listener = socket.listen(1024)?;
-        let join_handle =
-            tokio::spawn(async move { AsyncServerRunner::<I>::run(listener, inner_cancel, sender).await });
+        let listen_addrs = listeners
+            .iter()
+            .map(|listener| listener.local_addr())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let factory = Arc::new(ConnectionFactory {
+            queue_limits: options.queue,
+            tracker: tracker.clone(),
+            traffic: traffic.clone(),
+            recorder: recorder.clone(),
+            proxy_protocol: options.proxy_protocol,
+            cancel_token: cancel.clone(),
+            connection_sender: sender,
+        });
+
+        let handles = listeners
+            .into_iter()
+            .map(|listener| tokio::spawn(AsyncServerRunner::<I>::run(listener, factory.clone())))
+            .collect();
 
         Ok(Self {
-            listen_addr: addr,
+            listen_addrs,
             async_connector: AsyncServerRunner {
                 token: cancel,
-                handle: join_handle,
+                handles,
                 stream_receiver: receiver,
             },
+            tracker,
+            traffic,
+            recorder,
         })
     }
 
+    /// Provides the traffic of all connections of this server so far, including connections
+    /// that have already been closed.
+    pub fn traffic(&self) -> TrafficSnapshot {
+        self.traffic.snapshot()
+    }
+
+    /// Provides the amount of connections that have been rejected so far, because they
+    /// exceeded the configured [ConnectionLimits].
+    pub fn rejected_connections(&self) -> RejectedConnections {
+        self.tracker.rejected()
+    }
+
+    /// Provides the amount of packets that were left out of the recording, because the disk
+    /// could not keep up. Without a recording, this is always 0.
+    pub fn dropped_recorded_packets(&self) -> u64 {
+        self.recorder.as_ref().map_or(0, |recorder| recorder.dropped())
+    }
+
+    /// Logs the traffic and rejected connections of this server so far. The opcodes with the
+    /// most traffic are additionally logged at the debug level.
+    pub fn report_traffic(&self) {
+        let traffic = self.traffic();
+        let rejected = self.rejected_connections();
+        info!(%traffic, %rejected, "Traffic report");
+        let dropped = self.dropped_recorded_packets();
+        if dropped > 0 {
+            warn!(dropped, "Packets were dropped from the session recording.");
+        }
+        for (opcode, stats) in traffic.top_sent(5) {
+            debug!(
+                opcode = format!("{opcode:#06x}"),
+                stats.packets, stats.bytes, "Most sent opcode"
+            );
+        }
+        for (opcode, stats) in traffic.top_received(5) {
+            debug!(
+                opcode = format!("{opcode:#06x}"),
+                stats.packets, stats.bytes, "Most received opcode"
+            );
+        }
+    }
+
     pub fn is_running(&self) -> bool {
-        !self.async_connector.handle.is_finished()
+        self.async_connector.handles.iter().any(|handle| !handle.is_finished())
     }
 
-    pub fn local_addr(&self) -> SocketAddr {
-        self.listen_addr
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.listen_addrs
     }
 
     pub fn stop(&self) {
@@ -249,3 +629,50 @@ impl<I: InputProtocol> Iterator for AcceptedClients<'_, I> {
         self.inner.try_recv().unwrap_or_else(|_| None)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_only_v6_with_ipv4_on_same_port() {
+        let v4 = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 15779);
+        let v6 = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 15779);
+        let other_port = SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 15780);
+        assert!(is_only_v6(v6, &[v4, v6]));
+        assert!(!is_only_v6(v6, &[v6]));
+        assert!(!is_only_v6(other_port, &[v4, other_port]));
+        assert!(!is_only_v6(v4, &[v4, v6]));
+    }
+
+    #[tokio::test]
+    async fn test_bind_ipv4() {
+        let listener = bind_listener(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), false).unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.is_ipv4());
+        assert!(TcpStream::connect(addr).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_bind_dual_stack() {
+        // Not every environment we run the tests in has IPv6 available.
+        let Ok(listener) = bind_listener(SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0), false) else {
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+        let client = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await.unwrap();
+        let (_, addr) = listener.accept().await.unwrap();
+        assert_eq!(addr.ip().to_canonical(), client.local_addr().unwrap().ip());
+    }
+
+    #[tokio::test]
+    async fn test_bind_only_v6() {
+        let Ok(listener) = bind_listener(SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0), true) else {
+            return;
+        };
+        let port = listener.local_addr().unwrap().port();
+        assert!(TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await.is_err());
+        assert!(TcpStream::connect((Ipv6Addr::LOCALHOST, port)).await.is_ok());
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// A range of ip addresses, given by a base address and the amount of leading bits that need
+/// to match, e.g. `10.0.0.0/8`. A single address is equivalent to a network where all bits
+/// need to match.
+///
+/// IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`) are treated the same as their IPv4 counterpart.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(try_from = "String"))]
+pub struct IpNetwork {
+    address: IpAddr,
+    prefix: u8,
+}
+
+#[derive(Debug)]
+pub struct InvalidIpNetwork(String);
+
+impl Display for InvalidIpNetwork {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not a valid ip address or network", self.0)
+    }
+}
+
+impl Error for InvalidIpNetwork {}
+
+impl IpNetwork {
+    pub fn new(address: IpAddr, prefix: u8) -> Option<IpNetwork> {
+        let address = address.to_canonical();
+        let max_prefix = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix > max_prefix {
+            return None;
+        }
+
+        Some(IpNetwork { address, prefix })
+    }
+
+    pub fn contains(&self, address: IpAddr) -> bool {
+        let (network, address) = match (self.address, address.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                ((u32::from(network) as u128) << 96, (u32::from(address) as u128) << 96)
+            },
+            (IpAddr::V6(network), IpAddr::V6(address)) => (u128::from(network), u128::from(address)),
+            _ => return false,
+        };
+
+        let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+        network & mask == address & mask
+    }
+}
+
+impl From<IpAddr> for IpNetwork {
+    fn from(address: IpAddr) -> Self {
+        let address = address.to_canonical();
+        let prefix = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        IpNetwork { address, prefix }
+    }
+}
+
+impl TryFrom<String> for IpNetwork {
+    type Error = InvalidIpNetwork;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = InvalidIpNetwork;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidIpNetwork(s.to_string());
+        match s.split_once('/') {
+            Some((address, prefix)) => {
+                let address = address.parse().map_err(|_| invalid())?;
+                let prefix = prefix.parse().map_err(|_| invalid())?;
+                IpNetwork::new(address, prefix).ok_or_else(invalid)
+            },
+            None => s.parse::<IpAddr>().map(IpNetwork::from).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl Display for IpNetwork {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+/// Limits which clients may connect and how many connections they may open.
+#[derive(Clone, Debug)]
+pub struct ConnectionLimits {
+    /// The maximum amount of connections a single ip address may have open at the same time.
+    pub max_connections_per_ip: Option<usize>,
+    /// The maximum amount of new connections a single ip address may open per second.
+    pub max_connection_rate_per_ip: Option<u32>,
+    /// The time a client has to complete the security handshake before being disconnected.
+    pub handshake_timeout: Option<Duration>,
+    /// Addresses that are never rejected, regardless of the limits or the denylist.
+    pub allowlist: Vec<IpNetwork>,
+    /// Addresses that are always rejected.
+    pub denylist: Vec<IpNetwork>,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        ConnectionLimits {
+            max_connections_per_ip: None,
+            max_connection_rate_per_ip: None,
+            handshake_timeout: Some(Duration::from_secs(10)),
+            allowlist: Vec::new(),
+            denylist: Vec::new(),
+        }
+    }
+}
+
+/// The amount of connections that have been rejected, by the reason of the rejection.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct RejectedConnections {
+    pub denied: u64,
+    pub too_many_connections: u64,
+    pub rate_limited: u64,
+}
+
+impl RejectedConnections {
+    pub fn total(&self) -> u64 {
+        self.denied + self.too_many_connections + self.rate_limited
+    }
+}
+
+impl Display for RejectedConnections {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rejected connections ({} denied, {} too many connections, {} rate limited)",
+            self.total(),
+            self.denied,
+            self.too_many_connections,
+            self.rate_limited
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Rejection {
+    Denied,
+    TooManyConnections,
+    RateLimited,
+}
+
+impl Display for Rejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::Denied => write!(f, "address is denied"),
+            Rejection::TooManyConnections => write!(f, "too many open connections"),
+            Rejection::RateLimited => write!(f, "too many new connections"),
+        }
+    }
+}
+
+struct AddressEntry {
+    active: usize,
+    window_start: Instant,
+    accepted_in_window: u32,
+}
+
+struct TrackedAddresses {
+    entries: HashMap<IpAddr, AddressEntry>,
+    last_cleanup: Instant,
+}
+
+/// Keeps track of the open connections per ip address and decides if a new connection
+/// should be admitted.
+pub(crate) struct ConnectionTracker {
+    limits: ConnectionLimits,
+    addresses: Mutex<TrackedAddresses>,
+    denied: AtomicU64,
+    too_many_connections: AtomicU64,
+    rate_limited: AtomicU64,
+}
+
+impl ConnectionTracker {
+    pub(crate) fn new(limits: ConnectionLimits) -> Arc<Self> {
+        Arc::new(ConnectionTracker {
+            limits,
+            addresses: Mutex::new(TrackedAddresses {
+                entries: HashMap::new(),
+                last_cleanup: Instant::now(),
+            }),
+            denied: AtomicU64::new(0),
+            too_many_connections: AtomicU64::new(0),
+            rate_limited: AtomicU64::new(0),
+        })
+    }
+
+    pub(crate) fn handshake_timeout(&self) -> Option<Duration> {
+        self.limits.handshake_timeout
+    }
+
+    pub(crate) fn rejected(&self) -> RejectedConnections {
+        RejectedConnections {
+            denied: self.denied.load(Ordering::Relaxed),
+            too_many_connections: self.too_many_connections.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Checks if a new connection from the given address may be accepted. If so, the
+    /// returned slot needs to be kept around for as long as the connection is open.
+    pub(crate) fn try_admit(self: &Arc<Self>, address: IpAddr) -> Result<ConnectionSlot, Rejection> {
+        let address = address.to_canonical();
+        if self.limits.allowlist.iter().any(|network| network.contains(address)) {
+            return Ok(ConnectionSlot { tracked: None });
+        }
+
+        if self.limits.denylist.iter().any(|network| network.contains(address)) {
+            self.denied.fetch_add(1, Ordering::Relaxed);
+            return Err(Rejection::Denied);
+        }
+
+        if self.limits.max_connections_per_ip.is_none() && self.limits.max_connection_rate_per_ip.is_none() {
+            return Ok(ConnectionSlot { tracked: None });
+        }
+
+        let now = Instant::now();
+        let mut addresses = self.addresses.lock().unwrap_or_else(PoisonError::into_inner);
+        if now.duration_since(addresses.last_cleanup) >= RATE_WINDOW {
+            addresses
+                .entries
+                .retain(|_, entry| entry.active > 0 || now.duration_since(entry.window_start) < RATE_WINDOW);
+            addresses.last_cleanup = now;
+        }
+
+        let entry = addresses.entries.entry(address).or_insert(AddressEntry {
+            active: 0,
+            window_start: now,
+            accepted_in_window: 0,
+        });
+
+        if let Some(max) = self.limits.max_connections_per_ip {
+            if entry.active >= max {
+                self.too_many_connections.fetch_add(1, Ordering::Relaxed);
+                return Err(Rejection::TooManyConnections);
+            }
+        }
+
+        if now.duration_since(entry.window_start) >= RATE_WINDOW {
+            entry.window_start = now;
+            entry.accepted_in_window = 0;
+        }
+
+        if let Some(max) = self.limits.max_connection_rate_per_ip {
+            if entry.accepted_in_window >= max {
+                self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                return Err(Rejection::RateLimited);
+            }
+        }
+
+        entry.active += 1;
+        entry.accepted_in_window += 1;
+        Ok(ConnectionSlot {
+            tracked: Some((self.clone(), address)),
+        })
+    }
+
+    fn release(&self, address: IpAddr) {
+        let mut addresses = self.addresses.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = addresses.entries.get_mut(&address) {
+            entry.active = entry.active.saturating_sub(1);
+        }
+    }
+}
+
+/// Reserves a place for a connection of an address, which will be freed once the slot is
+/// dropped.
+pub(crate) struct ConnectionSlot {
+    tracked: Option<(Arc<ConnectionTracker>, IpAddr)>,
+}
+
+impl ConnectionSlot {
+    /// Creates a slot that isn't counted against any limits.
+    pub(crate) fn untracked() -> Self {
+        ConnectionSlot { tracked: None }
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        if let Some((tracker, address)) = self.tracked.take() {
+            tracker.release(address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    pub fn test_network_contains() {
+        let network: IpNetwork = "10.1.0.0/16".parse().unwrap();
+        assert!(network.contains(Ipv4Addr::new(10, 1, 2, 3).into()));
+        assert!(!network.contains(Ipv4Addr::new(10, 2, 0, 1).into()));
+        assert!(network.contains(Ipv4Addr::new(10, 1, 0, 1).to_ipv6_mapped().into()));
+        assert!(!network.contains(Ipv6Addr::LOCALHOST.into()));
+
+        let single: IpNetwork = "127.0.0.1".parse().unwrap();
+        assert!(single.contains(Ipv4Addr::LOCALHOST.into()));
+        assert!(!single.contains(Ipv4Addr::new(127, 0, 0, 2).into()));
+
+        let everything: IpNetwork = "::/0".parse().unwrap();
+        assert!(everything.contains(Ipv6Addr::LOCALHOST.into()));
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("localhost".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    pub fn test_connection_limit() {
+        let tracker = ConnectionTracker::new(ConnectionLimits {
+            max_connections_per_ip: Some(1),
+            ..Default::default()
+        });
+        let address = Ipv4Addr::LOCALHOST.into();
+
+        let slot = tracker.try_admit(address).unwrap();
+        assert_eq!(tracker.try_admit(address).err(), Some(Rejection::TooManyConnections));
+        assert!(tracker.try_admit(Ipv4Addr::new(127, 0, 0, 2).into()).is_ok());
+        drop(slot);
+        assert!(tracker.try_admit(address).is_ok());
+        assert_eq!(tracker.rejected().too_many_connections, 1);
+    }
+
+    #[test]
+    pub fn test_rate_limit() {
+        let tracker = ConnectionTracker::new(ConnectionLimits {
+            max_connection_rate_per_ip: Some(2),
+            ..Default::default()
+        });
+        let address = Ipv4Addr::LOCALHOST.into();
+
+        assert!(tracker.try_admit(address).is_ok());
+        assert!(tracker.try_admit(address).is_ok());
+        assert_eq!(tracker.try_admit(address).err(), Some(Rejection::RateLimited));
+        assert_eq!(tracker.rejected().rate_limited, 1);
+    }
+
+    #[test]
+    pub fn test_allow_and_deny_list() {
+        let tracker = ConnectionTracker::new(ConnectionLimits {
+            max_connections_per_ip: Some(0),
+            allowlist: vec!["10.0.0.5".parse().unwrap()],
+            denylist: vec!["10.0.0.0/8".parse().unwrap()],
+            ..Default::default()
+        });
+
+        assert!(tracker.try_admit(Ipv4Addr::new(10, 0, 0, 5).into()).is_ok());
+        assert_eq!(
+            tracker.try_admit(Ipv4Addr::new(10, 0, 0, 6).into()).err(),
+            Some(Rejection::Denied)
+        );
+        assert_eq!(
+            tracker.try_admit(Ipv4Addr::new(192, 168, 0, 1).into()).err(),
+            Some(Rejection::TooManyConnections)
+        );
+        assert_eq!(tracker.rejected().total(), 2);
+    }
+}
@@ -0,0 +1,341 @@
+use crate::limits::ConnectionSlot;
+use crate::queue::{OutboundPacket, OutboundQueue, QueuedPacket};
+use crate::stats::TrafficStats;
+use crate::{Connection, ConnectionState, DisconnectReason, QueueLimits, STREAM_IDENTIFIER};
+use kanal::{unbounded, Receiver, SendError, Sender};
+use skrillax_stream::handshake::PassiveSecuritySetup;
+use skrillax_stream::packet::{AsPacket, OutgoingPacket};
+use skrillax_stream::stream::{InStreamError, OutStreamError, SilkroadStreamRead, SilkroadStreamWrite, SilkroadTcpExt};
+use skrillax_stream::InputProtocol;
+use std::any::type_name;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::marker::PhantomData;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+/// Options to configure a connection created through [Connection::loopback] or
+/// [Connection::loopback_encrypted].
+#[derive(Copy, Clone, Debug)]
+pub struct LoopbackOptions {
+    /// The address the connection should pretend the client is connecting from.
+    pub remote_addr: SocketAddr,
+    /// Limits for the outbound queue. The inbound side of a loopback connection is not
+    /// bounded, such that injecting packets never blocks the test.
+    pub queue: QueueLimits,
+}
+
+impl Default for LoopbackOptions {
+    fn default() -> Self {
+        LoopbackOptions {
+            remote_addr: SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+            queue: QueueLimits::default(),
+        }
+    }
+}
+
+impl<I: InputProtocol + Send + 'static> Connection<I> {
+    /// Creates a connection that isn't backed by a socket, together with a [LoopbackHandle]
+    /// which takes the place of the client.
+    ///
+    /// There is no security handshake and no encryption; packets are passed along as they
+    /// are, without ever being serialized. This makes it possible to drive anything that
+    /// works with a [Connection] from tests, without opening sockets or implementing the
+    /// client side of the protocol. As nothing goes over the wire, no traffic is recorded.
+    /// Use [Connection::loopback_encrypted] if the encryption should not be skipped.
+    pub fn loopback(options: LoopbackOptions) -> (Connection<I>, LoopbackHandle<I>) {
+        let (connection, inbound, outbound) = Self::unconnected(options);
+        let handle = LoopbackHandle {
+            inbound,
+            outbound,
+            state: connection.state.clone(),
+        };
+
+        (connection, handle)
+    }
+
+    /// Creates a connection like [Connection::loopback], but with the security handshake and
+    /// encryption in place, like for a real client. The packets go through an actual socket
+    /// on the local machine, with the [EncryptedLoopbackHandle] sending and receiving them
+    /// like the client would. Packets sent to the client are decoded using `O`.
+    ///
+    /// Unlike [Connection::loopback], this needs to run inside a tokio runtime. It should only
+    /// be used if the serialization or encryption of the packets matters for the test.
+    pub async fn loopback_encrypted<O: InputProtocol>(
+        options: LoopbackOptions,
+    ) -> Result<(Connection<I>, EncryptedLoopbackHandle<O>), io::Error> {
+        let listener = TcpListener::bind(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0)).await?;
+        let client = TcpStream::connect(listener.local_addr()?).await?;
+        let (socket, _) = listener.accept().await?;
+
+        let (connection, inbound, outbound) = Self::unconnected(options);
+        tokio::spawn(Connection::<I>::handle(
+            socket,
+            connection.identifier,
+            connection.state.clone(),
+            connection.stats.clone(),
+            None,
+            inbound,
+            outbound,
+        ));
+
+        let (mut reader, mut writer) = client.into_silkroad_stream();
+        PassiveSecuritySetup::handle(&mut reader, &mut writer)
+            .await
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let handle = EncryptedLoopbackHandle {
+            reader,
+            writer,
+            _protocol: PhantomData,
+        };
+
+        Ok((connection, handle))
+    }
+
+    /// Creates a connection that is not yet backed by anything, together with the other ends
+    /// of its inbound and outbound queue.
+    fn unconnected(options: LoopbackOptions) -> (Connection<I>, Sender<I::Proto>, Receiver<QueuedPacket>) {
+        let identifier = STREAM_IDENTIFIER.fetch_add(1, Ordering::SeqCst);
+        let (inbound_sender, inbound_receiver) = unbounded();
+        let (outbound, outbound_receiver) = OutboundQueue::new(options.queue.outbound, options.queue.overflow);
+        let state = Arc::new(ConnectionState {
+            cancel: CancellationToken::new(),
+            reason: OnceLock::new(),
+            _slot: ConnectionSlot::untracked(),
+            recorder: None,
+        });
+
+        let connection = Connection {
+            remote_addr: options.remote_addr,
+            identifier,
+            inbound: inbound_receiver,
+            outbound,
+            state,
+            stats: TrafficStats::new(),
+        };
+
+        (connection, inbound_sender, outbound_receiver)
+    }
+}
+
+/// The client side of a loopback connection. Packets injected here will be received by the
+/// [Connection], while packets sent through the [Connection] can be taken out here.
+pub struct LoopbackHandle<I: InputProtocol> {
+    inbound: Sender<I::Proto>,
+    outbound: Receiver<QueuedPacket>,
+    state: Arc<ConnectionState>,
+}
+
+impl<I: InputProtocol> LoopbackHandle<I> {
+    /// Makes the given packet available to [Connection::next] as if the client sent it.
+    pub fn inject(&self, packet: impl Into<I>) -> Result<(), SendError>
+    where
+        I: Into<I::Proto>,
+    {
+        if self.state.cancel.is_cancelled() {
+            return Err(SendError::Closed);
+        }
+
+        let packet: I = packet.into();
+        self.inbound.send(packet.into())
+    }
+
+    /// Takes the oldest packet that was sent to the client, if there is one. Once the
+    /// connection has been disconnected and all remaining packets have been taken, this will
+    /// always return `None`.
+    pub fn next_packet(&self) -> Option<CapturedPacket> {
+        match self.outbound.try_recv() {
+            Ok(Some(QueuedPacket::Packet { packet, droppable })) => Some(CapturedPacket { packet, droppable }),
+            Ok(Some(QueuedPacket::Close)) => {
+                // This is where the connection would close the socket.
+                self.state.cancel.cancel();
+                None
+            },
+            Ok(None) | Err(_) => None,
+        }
+    }
+
+    /// Takes the oldest packet that was sent to the client, expecting it to be of the given
+    /// type.
+    ///
+    /// # Panics
+    /// Panics if there was no packet or it was of a different type.
+    pub fn expect_packet<P: 'static>(&self) -> P {
+        let packet = self
+            .next_packet()
+            .unwrap_or_else(|| panic!("Expected a {} to have been sent, but there was none.", type_name::<P>()));
+        packet
+            .downcast()
+            .unwrap_or_else(|packet| panic!("Expected a {}, but got {:?}.", type_name::<P>(), packet))
+    }
+
+    /// Takes all packets that have been sent to the client so far.
+    pub fn drain(&self) -> Vec<CapturedPacket> {
+        std::iter::from_fn(|| self.next_packet()).collect()
+    }
+
+    /// Closes the connection from the client side, as if the client disconnected. Packets
+    /// that have been injected before can still be received.
+    pub fn close(self) {
+        self.state.close(DisconnectReason::PeerClosed);
+    }
+
+    /// Checks if the connection has been closed, either through [LoopbackHandle::close] or by
+    /// the server disconnecting the client, after all remaining packets have been taken.
+    pub fn is_closed(&self) -> bool {
+        self.state.cancel.is_cancelled()
+    }
+
+    /// Provides the reason the connection was closed with, if it was closed already.
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.state.reason.get().copied()
+    }
+}
+
+/// The client side of a connection created through [Connection::loopback_encrypted]. Unlike
+/// [LoopbackHandle], packets are serialized and encrypted like they would be by the client.
+pub struct EncryptedLoopbackHandle<O: InputProtocol> {
+    reader: SilkroadStreamRead<OwnedReadHalf>,
+    writer: SilkroadStreamWrite<OwnedWriteHalf>,
+    _protocol: PhantomData<O>,
+}
+
+impl<O: InputProtocol> EncryptedLoopbackHandle<O> {
+    /// Sends the packet to the [Connection] as the client, encrypting it if the packet
+    /// requires it.
+    pub async fn inject<P: AsPacket>(&mut self, packet: P) -> Result<(), OutStreamError> {
+        self.writer.write(packet.as_packet()).await?;
+        Ok(())
+    }
+
+    /// Waits for the next packet that was sent to the client and decodes it.
+    pub async fn next_packet(&mut self) -> Result<O::Proto, InStreamError> {
+        self.reader.next_packet::<O>().await
+    }
+}
+
+/// A packet that was sent through a loopback connection.
+pub struct CapturedPacket {
+    packet: Box<dyn OutboundPacket>,
+    droppable: bool,
+}
+
+impl CapturedPacket {
+    /// Checks if this packet is of the given type.
+    pub fn is<P: 'static>(&self) -> bool {
+        self.packet.as_any().is::<P>()
+    }
+
+    /// Turns this packet back into the type it was sent as, or returns it unchanged if it
+    /// is of a different type.
+    pub fn downcast<P: 'static>(self) -> Result<P, CapturedPacket> {
+        if !self.is::<P>() {
+            return Err(self);
+        }
+
+        let packet = self
+            .packet
+            .into_any()
+            .downcast::<P>()
+            .expect("Type of packet should have just been checked");
+        Ok(*packet)
+    }
+
+    /// Serializes the packet like it would have been for sending it over the wire.
+    pub fn as_packet(&self) -> OutgoingPacket {
+        self.packet.as_packet()
+    }
+
+    /// Checks if the packet was sent with [Connection::send_droppable].
+    pub fn is_droppable(&self) -> bool {
+        self.droppable
+    }
+}
+
+impl Debug for CapturedPacket {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CapturedPacket")
+            .field("packet", &self.packet.type_name())
+            .field("droppable", &self.droppable)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use silkroad_base_protocol::{BaseProtocol, IdentityInformation, KeepAlive};
+
+    #[test]
+    fn test_inject_and_capture() {
+        let (connection, handle) = Connection::<BaseProtocol>::loopback(LoopbackOptions::default());
+        handle.inject(KeepAlive).unwrap();
+        assert!(matches!(connection.next(), Ok(Some(packet)) if matches!(*packet, BaseProtocol::KeepAlive(_))));
+        assert!(matches!(connection.next(), Ok(None)));
+
+        connection
+            .send(IdentityInformation::new("AgentServer".to_string(), 0))
+            .unwrap();
+        connection.send_droppable(KeepAlive).unwrap();
+
+        let identity = handle.expect_packet::<IdentityInformation>();
+        assert_eq!(identity.module_name, "AgentServer");
+        let keep_alive = handle.next_packet().unwrap();
+        assert!(keep_alive.is_droppable());
+        assert!(keep_alive.downcast::<IdentityInformation>().is_err());
+        assert!(handle.next_packet().is_none());
+    }
+
+    #[test]
+    fn test_disconnect_flushes_packets() {
+        let (connection, handle) = Connection::<BaseProtocol>::loopback(LoopbackOptions::default());
+        connection.send(KeepAlive).unwrap();
+        connection.disconnect(DisconnectReason::Kicked);
+        assert!(connection.send(KeepAlive).is_err());
+        assert!(!handle.is_closed());
+
+        handle.expect_packet::<KeepAlive>();
+        assert!(handle.next_packet().is_none());
+        assert!(handle.is_closed());
+        assert_eq!(handle.disconnect_reason(), Some(DisconnectReason::Kicked));
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_loopback() {
+        let (connection, mut handle) =
+            Connection::<BaseProtocol>::loopback_encrypted::<BaseProtocol>(LoopbackOptions::default())
+                .await
+                .unwrap();
+        handle.inject(KeepAlive).await.unwrap();
+        let packet = connection.next_async().await.unwrap();
+        assert!(matches!(*packet, BaseProtocol::KeepAlive(_)));
+
+        connection
+            .send(IdentityInformation::new("AgentServer".to_string(), 0))
+            .unwrap();
+        let packet = handle.next_packet().await.unwrap();
+        assert!(
+            matches!(*packet, BaseProtocol::IdentityInformation(ref identity) if identity.module_name == "AgentServer")
+        );
+        assert_eq!(1, connection.traffic().total_received().packets);
+
+        drop(handle);
+        assert!(connection.next_async().await.is_err());
+        assert_eq!(connection.disconnect_reason(), Some(DisconnectReason::PeerClosed));
+    }
+
+    #[test]
+    fn test_client_close() {
+        let (connection, handle) = Connection::<BaseProtocol>::loopback(LoopbackOptions::default());
+        handle.inject(BaseProtocol::KeepAlive(KeepAlive)).unwrap();
+        handle.close();
+        assert!(!connection.is_connected());
+        assert_eq!(connection.disconnect_reason(), Some(DisconnectReason::PeerClosed));
+        assert!(matches!(connection.next(), Ok(Some(packet)) if matches!(*packet, BaseProtocol::KeepAlive(_))));
+        assert!(connection.next().is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::IpNetwork;
+use std::io;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const V2_SIGNATURE: [u8; 12] = [0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A];
+/// The maximum length of a v1 header, including the final CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// Enables the PROXY protocol (v1 and v2) for connections coming from one of the trusted
+/// proxies. Such connections need to start with a PROXY header, which contains the address
+/// of the actual client. This address is then used for the connection instead, including
+/// for the per-address limits.
+///
+/// Connections from other addresses are treated as regular, direct connections.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyProtocolOptions {
+    pub trusted_proxies: Vec<IpNetwork>,
+}
+
+impl ProxyProtocolOptions {
+    pub(crate) fn is_trusted(&self, address: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|network| network.contains(address))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads the PROXY header from the stream, without reading anything after it. Provides the
+/// address of the client, unless the proxy did not know it or the connection was made by the
+/// proxy itself, e.g. for health checks.
+pub(crate) async fn read_proxy_header<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<SocketAddr>> {
+    let mut start = [0u8; 12];
+    reader.read_exact(&mut start).await?;
+    if start == V2_SIGNATURE {
+        read_v2(reader).await
+    } else if start.starts_with(b"PROXY ") {
+        read_v1(reader, &start).await
+    } else {
+        Err(invalid("Connection did not start with a PROXY header."))
+    }
+}
+
+async fn read_v1<R: AsyncRead + Unpin>(reader: &mut R, start: &[u8]) -> io::Result<Option<SocketAddr>> {
+    let mut line = start.to_vec();
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LENGTH {
+            return Err(invalid("PROXY header is too long."));
+        }
+        line.push(reader.read_u8().await?);
+    }
+
+    let line = std::str::from_utf8(&line[..line.len() - 2]).map_err(|_| invalid("PROXY header is not valid text."))?;
+    let parts = line.split(' ').collect::<Vec<_>>();
+    match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", "TCP4" | "TCP6", source, _, source_port, _] => {
+            let ip = source
+                .parse::<IpAddr>()
+                .map_err(|_| invalid("Invalid source address."))?;
+            let port = source_port
+                .parse::<u16>()
+                .map_err(|_| invalid("Invalid source port."))?;
+            Ok(Some(SocketAddr::new(ip, port)))
+        },
+        _ => Err(invalid("Malformed PROXY header.")),
+    }
+}
+
+async fn read_v2<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<SocketAddr>> {
+    let version_command = reader.read_u8().await?;
+    let family = reader.read_u8().await?;
+    let length = reader.read_u16().await? as usize;
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).await?;
+
+    if version_command >> 4 != 2 {
+        return Err(invalid("Unsupported PROXY protocol version."));
+    }
+
+    match version_command & 0x0F {
+        // LOCAL, the connection was established by the proxy itself.
+        0x0 => return Ok(None),
+        0x1 => {},
+        _ => return Err(invalid("Unknown PROXY command.")),
+    }
+
+    // Only the address family matters, not whether it's TCP or UDP. Any TLVs after the
+    // addresses are ignored.
+    match family >> 4 {
+        0x1 if body.len() >= 12 => {
+            let ip = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+            let port = u16::from_be_bytes([body[8], body[9]]);
+            Ok(Some(SocketAddr::new(ip.into(), port)))
+        },
+        0x2 if body.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&body[..16]);
+            let port = u16::from_be_bytes([body[32], body[33]]);
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(octets).into(), port)))
+        },
+        0x1 | 0x2 => Err(invalid("PROXY header is too short for its addresses.")),
+        // Unspecified or unix sockets, which don't carry a usable address.
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn parse(mut data: &[u8]) -> io::Result<Option<SocketAddr>> {
+        let result = read_proxy_header(&mut data).await;
+        // Nothing after the header may be consumed.
+        if result.is_ok() {
+            assert_eq!(data, b"rest");
+        }
+        result
+    }
+
+    #[tokio::test]
+    async fn test_v1() {
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nrest";
+        assert_eq!(parse(header).await.unwrap(), Some("192.168.0.1:56324".parse().unwrap()));
+
+        let header = b"PROXY TCP6 ::1 ::2 4000 15779\r\nrest";
+        assert_eq!(parse(header).await.unwrap(), Some("[::1]:4000".parse().unwrap()));
+
+        assert_eq!(parse(b"PROXY UNKNOWN\r\nrest").await.unwrap(), None);
+        assert!(parse(b"PROXY TCP4 not-an-ip 192.168.0.11 1 2\r\nrest").await.is_err());
+        assert!(parse(b"GET / HTTP/1.1\r\n\r\nrest").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_v2() {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0x00, 0x0C]);
+        header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1F, 0x90, 0x3D, 0x93]);
+        header.extend_from_slice(b"rest");
+        assert_eq!(parse(&header).await.unwrap(), Some("10.0.0.1:8080".parse().unwrap()));
+
+        let mut local = V2_SIGNATURE.to_vec();
+        local.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        local.extend_from_slice(b"rest");
+        assert_eq!(parse(&local).await.unwrap(), None);
+
+        let mut short = V2_SIGNATURE.to_vec();
+        short.extend_from_slice(&[0x21, 0x11, 0x00, 0x04, 10, 0, 0, 1]);
+        short.extend_from_slice(b"rest");
+        assert!(parse(&short).await.is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use kanal::{bounded, Receiver, Sender};
+use skrillax_stream::packet::AsPacket;
+use std::any::Any;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Defines what should happen once a connection reached its outbound queue limit, i.e.
+/// when the client does not receive the packets as fast as we produce them.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(rename_all = "kebab-case"))]
+pub enum OverflowStrategy {
+    /// Drops the oldest packets that have been marked as droppable (see
+    /// [crate::Connection::send_droppable]) to make room for the new packet. If there are no
+    /// droppable packets left in the queue, the client will be disconnected.
+    #[default]
+    DropOldest,
+    /// Lets the caller of [crate::Connection::send_async] wait until there is room in the
+    /// queue again. Synchronous senders, like the systems of the game, can never wait, so
+    /// [crate::Connection::send] disconnects the client instead, like [Self::Disconnect].
+    Block,
+    /// Disconnects the client immediately.
+    Disconnect,
+}
+
+/// Limits for the amount of packets that may be queued for a single connection.
+///
+/// The inbound limit applies backpressure to the client: once the game did not consume
+/// `inbound` packets, we stop reading from the socket until it does. The outbound limit
+/// is handled according to the configured [OverflowStrategy].
+#[derive(Copy, Clone, Debug)]
+pub struct QueueLimits {
+    pub inbound: usize,
+    pub outbound: usize,
+    pub overflow: OverflowStrategy,
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        QueueLimits {
+            inbound: 256,
+            outbound: 4096,
+            overflow: OverflowStrategy::default(),
+        }
+    }
+}
+
+/// A packet waiting to be sent, which still knows its concrete type. This allows the
+/// loopback connection to hand out the packets as they were queued.
+pub(crate) trait OutboundPacket: AsPacket + Send {
+    fn type_name(&self) -> &'static str;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send>;
+}
+
+impl<T: AsPacket + Send + 'static> OutboundPacket for T {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send> {
+        self
+    }
+}
+
+pub(crate) enum QueuedPacket {
+    Packet {
+        packet: Box<dyn OutboundPacket>,
+        droppable: bool,
+    },
+    /// Marks the end of the stream; the connection will be closed once all packets before it
+    /// have been sent.
+    Close,
+}
+
+impl QueuedPacket {
+    pub(crate) fn new(packet: Box<dyn OutboundPacket>, droppable: bool) -> Self {
+        QueuedPacket::Packet { packet, droppable }
+    }
+
+    fn is_droppable(&self) -> bool {
+        matches!(self, QueuedPacket::Packet { droppable: true, .. })
+    }
+}
+
+pub(crate) enum PushError {
+    Closed,
+    Overflow,
+}
+
+#[derive(Clone)]
+pub(crate) struct OutboundQueue {
+    sender: Sender<QueuedPacket>,
+    // We keep a receiver around, such that we can remove older packets from the queue
+    // when it is full.
+    receiver: Receiver<QueuedPacket>,
+    overflow: OverflowStrategy,
+    limit: usize,
+    push_lock: Arc<Mutex<()>>,
+}
+
+impl OutboundQueue {
+    pub(crate) fn new(limit: usize, overflow: OverflowStrategy) -> (Self, Receiver<QueuedPacket>) {
+        // A limit of zero would make this a rendezvous channel, which would mean every
+        // packet is considered an overflow.
+        let limit = limit.max(1);
+        let (sender, receiver) = bounded(limit);
+        let queue = OutboundQueue {
+            sender,
+            receiver: receiver.clone(),
+            overflow,
+            limit,
+            push_lock: Arc::new(Mutex::new(())),
+        };
+        (queue, receiver)
+    }
+
+    /// Queues the packet without ever blocking the caller.
+    pub(crate) fn push(&self, packet: QueuedPacket) -> Result<(), PushError> {
+        match self.overflow {
+            OverflowStrategy::Block | OverflowStrategy::Disconnect => match self.sender.try_send(packet) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(PushError::Overflow),
+                Err(_) => Err(PushError::Closed),
+            },
+            OverflowStrategy::DropOldest => {
+                // Only one sender may rearrange the queue at a time, otherwise we might
+                // reorder packets of concurrent senders.
+                let _guard = self.push_lock.lock().unwrap_or_else(PoisonError::into_inner);
+                if self.sender.is_closed() {
+                    return Err(PushError::Closed);
+                }
+
+                if self.sender.is_full() && !self.evict_droppable()? {
+                    return Err(PushError::Overflow);
+                }
+
+                match self.sender.try_send(packet) {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(PushError::Overflow),
+                    Err(_) => Err(PushError::Closed),
+                }
+            },
+        }
+    }
+
+    /// Queues the packet, waiting for room in the queue if the strategy is
+    /// [OverflowStrategy::Block]. Other strategies behave the same as [OutboundQueue::push].
+    pub(crate) async fn push_async(&self, packet: QueuedPacket) -> Result<(), PushError> {
+        match self.overflow {
+            OverflowStrategy::Block => self.sender.as_async().send(packet).await.map_err(|_| PushError::Closed),
+            _ => self.push(packet),
+        }
+    }
+
+    /// Removes the oldest droppable packets until a quarter of the queue is free again,
+    /// such that we don't have to do this for every single packet of a burst. Returns
+    /// `false` if no packet could be dropped.
+    fn evict_droppable(&self) -> Result<bool, PushError> {
+        let mut pending = Vec::with_capacity(self.limit);
+        while let Some(packet) = self.receiver.try_recv().map_err(|_| PushError::Closed)? {
+            pending.push(packet);
+        }
+
+        let target_free = (self.limit / 4).max(1);
+        let mut to_drop = target_free.saturating_sub(self.limit - pending.len());
+        let before = pending.len();
+        pending.retain(|packet| {
+            if to_drop > 0 && packet.is_droppable() {
+                to_drop -= 1;
+                false
+            } else {
+                true
+            }
+        });
+        let dropped = before - pending.len();
+
+        for packet in pending {
+            // We're the only ones pushing while holding the lock and the queue can only
+            // have shrunk in the meantime, so there is always room for these packets.
+            if !self.sender.try_send(packet).map_err(|_| PushError::Closed)? {
+                return Err(PushError::Overflow);
+            }
+        }
+
+        Ok(dropped > 0 || !self.sender.is_full())
+    }
+
+    /// Queues the marker to close the connection after all currently queued packets. Unlike
+    /// [OutboundQueue::push], this never blocks and will return `false` if the marker could
+    /// not be queued.
+    pub(crate) fn push_close(&self) -> bool {
+        let _guard = self.push_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        matches!(self.sender.try_send(QueuedPacket::Close), Ok(true))
+    }
+
+    pub(crate) fn close(&self) {
+        let _ = self.sender.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use silkroad_base_protocol::IdentityInformation;
+
+    fn packet(id: u8, droppable: bool) -> QueuedPacket {
+        QueuedPacket::new(Box::new(IdentityInformation::new(String::new(), id)), droppable)
+    }
+
+    /// Takes all queued packets, returning their ids in the order they would be sent.
+    fn drain(receiver: &Receiver<QueuedPacket>) -> Vec<u8> {
+        std::iter::from_fn(|| receiver.try_recv().ok().flatten())
+            .map(|queued| match queued {
+                QueuedPacket::Packet { packet, .. } => packet
+                    .as_any()
+                    .downcast_ref::<IdentityInformation>()
+                    .map(|identity| identity.locality)
+                    .expect("Should only contain identity packets"),
+                QueuedPacket::Close => panic!("Should not contain a close marker"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_drop_oldest_droppable() {
+        let (queue, receiver) = OutboundQueue::new(4, OverflowStrategy::DropOldest);
+        assert!(queue.push(packet(1, false)).is_ok());
+        assert!(queue.push(packet(2, true)).is_ok());
+        assert!(queue.push(packet(3, true)).is_ok());
+        assert!(queue.push(packet(4, false)).is_ok());
+        assert!(queue.push(packet(5, false)).is_ok());
+        assert_eq!(vec![1, 3, 4, 5], drain(&receiver));
+    }
+
+    #[test]
+    fn test_drop_oldest_frees_a_quarter() {
+        let (queue, receiver) = OutboundQueue::new(8, OverflowStrategy::DropOldest);
+        for id in 0..8 {
+            assert!(queue.push(packet(id, true)).is_ok());
+        }
+
+        assert!(queue.push(packet(8, false)).is_ok());
+        assert_eq!(vec![2, 3, 4, 5, 6, 7, 8], drain(&receiver));
+    }
+
+    #[test]
+    fn test_drop_oldest_without_droppable() {
+        let (queue, receiver) = OutboundQueue::new(2, OverflowStrategy::DropOldest);
+        assert!(queue.push(packet(1, false)).is_ok());
+        assert!(queue.push(packet(2, false)).is_ok());
+        assert!(matches!(queue.push(packet(3, true)), Err(PushError::Overflow)));
+        assert_eq!(vec![1, 2], drain(&receiver));
+    }
+
+    #[test]
+    fn test_disconnect_on_overflow() {
+        let (queue, receiver) = OutboundQueue::new(1, OverflowStrategy::Disconnect);
+        assert!(queue.push(packet(1, true)).is_ok());
+        assert!(matches!(queue.push(packet(2, true)), Err(PushError::Overflow)));
+        assert_eq!(vec![1], drain(&receiver));
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_room() {
+        let (queue, receiver) = OutboundQueue::new(1, OverflowStrategy::Block);
+        assert!(queue.push(packet(1, false)).is_ok());
+        // Synchronous senders must never wait.
+        assert!(matches!(queue.push(packet(2, false)), Err(PushError::Overflow)));
+
+        let pending = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.push_async(packet(3, false)).await.is_ok() }
+        });
+        assert_eq!(vec![1], drain(&receiver));
+        assert!(pending.await.unwrap());
+        assert_eq!(vec![3], drain(&receiver));
+    }
+}
@@ -0,0 +1,392 @@
+use crate::IpNetwork;
+use kanal::{bounded, Receiver, Sender};
+use skrillax_stream::packet::OutgoingPacket;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{io, thread};
+use tracing::warn;
+
+const MAGIC: &[u8; 7] = b"SKRREC\0";
+const VERSION: u8 = 2;
+/// The amount of packets that may wait to be written to disk, before we start dropping them.
+const QUEUE_SIZE: usize = 8192;
+
+/// Decides which connections should be recorded.
+#[derive(Clone, Debug, Default)]
+pub enum RecordingFilter {
+    #[default]
+    All,
+    /// Only records connections from one of the given networks.
+    Addresses(Vec<IpNetwork>),
+    /// Only records the connections with the given identifiers.
+    Connections(Vec<u64>),
+}
+
+impl RecordingFilter {
+    fn matches(&self, connection: u64, address: SocketAddr) -> bool {
+        match self {
+            RecordingFilter::All => true,
+            RecordingFilter::Addresses(networks) => networks.iter().any(|network| network.contains(address.ip())),
+            RecordingFilter::Connections(connections) => connections.contains(&connection),
+        }
+    }
+}
+
+/// Configures the recording of all packets of a session into a file, which can later be read
+/// again using [RecordingReader].
+#[derive(Clone, Debug)]
+pub struct RecordingOptions {
+    pub path: PathBuf,
+    pub filter: RecordingFilter,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// A packet sent by the client.
+    Inbound,
+    /// A packet sent by the server.
+    Outbound,
+}
+
+/// How a packet was framed on the wire.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Framing {
+    /// The framing is not known. This is the case for packets sent by the client, as the
+    /// stream has already decrypted and reassembled them before we get to see them.
+    Unknown,
+    Simple,
+    Encrypted,
+    /// A packet that was split into multiple parts. The payload of the recording contains
+    /// all parts concatenated.
+    Massive,
+}
+
+impl Framing {
+    pub(crate) fn of(packet: &OutgoingPacket) -> Self {
+        match packet {
+            OutgoingPacket::Simple { .. } => Framing::Simple,
+            OutgoingPacket::Encrypted { .. } => Framing::Encrypted,
+            OutgoingPacket::Massive { .. } => Framing::Massive,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Framing::Unknown => 0,
+            Framing::Simple => 1,
+            Framing::Encrypted => 2,
+            Framing::Massive => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            0 => Ok(Framing::Unknown),
+            1 => Ok(Framing::Simple),
+            2 => Ok(Framing::Encrypted),
+            3 => Ok(Framing::Massive),
+            _ => Err(io::Error::new(ErrorKind::InvalidData, "Unknown packet framing.")),
+        }
+    }
+}
+
+/// A single packet of a recording.
+///
+/// Each packet is stored as the time since the recording started in microseconds (u64), the
+/// identifier of the connection (u64), the direction (u8), the framing (u8), the opcode (u16)
+/// and the length of the payload (u32) followed by the payload itself, all in little endian.
+/// The file starts with a short header containing a magic value and the version of the format.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordedPacket {
+    pub time: Duration,
+    pub connection: u64,
+    pub direction: Direction,
+    pub framing: Framing,
+    pub opcode: u16,
+    pub data: Vec<u8>,
+}
+
+impl RecordedPacket {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let direction: u8 = match self.direction {
+            Direction::Inbound => 0,
+            Direction::Outbound => 1,
+        };
+        let micros = u64::try_from(self.time.as_micros()).unwrap_or(u64::MAX);
+        let length = u32::try_from(self.data.len()).map_err(|_| io::Error::from(ErrorKind::InvalidInput))?;
+
+        writer.write_all(&micros.to_le_bytes())?;
+        writer.write_all(&self.connection.to_le_bytes())?;
+        writer.write_all(&[direction, self.framing.to_byte()])?;
+        writer.write_all(&self.opcode.to_le_bytes())?;
+        writer.write_all(&length.to_le_bytes())?;
+        writer.write_all(&self.data)
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let mut micros = [0u8; 8];
+        match reader.read_exact(&mut micros) {
+            Ok(_) => {},
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let mut connection = [0u8; 8];
+        let mut direction = [0u8; 1];
+        let mut framing = [0u8; 1];
+        let mut opcode = [0u8; 2];
+        let mut length = [0u8; 4];
+        reader.read_exact(&mut connection)?;
+        reader.read_exact(&mut direction)?;
+        reader.read_exact(&mut framing)?;
+        reader.read_exact(&mut opcode)?;
+        reader.read_exact(&mut length)?;
+
+        let direction = match direction[0] {
+            0 => Direction::Inbound,
+            1 => Direction::Outbound,
+            _ => return Err(io::Error::new(ErrorKind::InvalidData, "Unknown packet direction.")),
+        };
+        // The length is not trusted to allocate the data upfront, in case the file is corrupted.
+        let length = u64::from(u32::from_le_bytes(length));
+        let mut data = Vec::new();
+        reader.by_ref().take(length).read_to_end(&mut data)?;
+        if data.len() as u64 != length {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "Packet data is cut off."));
+        }
+
+        Ok(Some(RecordedPacket {
+            time: Duration::from_micros(u64::from_le_bytes(micros)),
+            connection: u64::from_le_bytes(connection),
+            direction,
+            framing: Framing::from_byte(framing[0])?,
+            opcode: u16::from_le_bytes(opcode),
+            data,
+        }))
+    }
+}
+
+/// Writes the packets of all recorded connections of a server into a single file. Writing
+/// happens on a separate thread, such that the connections are never blocked on the disk.
+/// If the disk cannot keep up, packets are dropped from the recording instead.
+pub(crate) struct SessionRecorder {
+    sender: Sender<RecordedPacket>,
+    filter: RecordingFilter,
+    start: Instant,
+    dropped: AtomicU64,
+}
+
+impl SessionRecorder {
+    pub(crate) fn create(options: &RecordingOptions) -> io::Result<Arc<Self>> {
+        let mut writer = BufWriter::new(File::create(&options.path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        let (sender, receiver) = bounded(QUEUE_SIZE);
+        thread::Builder::new()
+            .name("session-recorder".to_string())
+            .spawn(move || Self::write_packets(writer, receiver))?;
+
+        Ok(Arc::new(SessionRecorder {
+            sender,
+            filter: options.filter.clone(),
+            start: Instant::now(),
+            dropped: AtomicU64::new(0),
+        }))
+    }
+
+    fn write_packets(mut writer: BufWriter<File>, receiver: Receiver<RecordedPacket>) {
+        // The loop ends once the server and all its connections are gone.
+        while let Ok(packet) = receiver.recv() {
+            let mut written = packet.write_to(&mut writer);
+            if written.is_ok() && receiver.is_empty() {
+                written = writer.flush();
+            }
+
+            if let Err(e) = written {
+                warn!(%e, "Could not write to session recording, stopping recording.");
+                return;
+            }
+        }
+
+        let _ = writer.flush();
+    }
+
+    /// Provides the amount of packets that could not be recorded, because too many packets
+    /// were still waiting to be written.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, packet: RecordedPacket) {
+        if let Ok(false) = self.sender.try_send(packet) {
+            if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                warn!("Session recording cannot keep up, dropping packets from the recording.");
+            }
+        }
+    }
+
+    pub(crate) fn for_connection(self: &Arc<Self>, connection: u64, address: SocketAddr) -> Option<ConnectionRecorder> {
+        if !self.filter.matches(connection, address) {
+            return None;
+        }
+
+        Some(ConnectionRecorder {
+            recorder: self.clone(),
+            connection,
+        })
+    }
+}
+
+/// Records the packets of a single connection into the recording of the server.
+#[derive(Clone)]
+pub(crate) struct ConnectionRecorder {
+    recorder: Arc<SessionRecorder>,
+    connection: u64,
+}
+
+impl ConnectionRecorder {
+    pub(crate) fn record(&self, direction: Direction, framing: Framing, opcode: u16, data: Vec<u8>) {
+        self.recorder.record(RecordedPacket {
+            time: self.recorder.start.elapsed(),
+            connection: self.connection,
+            direction,
+            framing,
+            opcode,
+            data,
+        });
+    }
+}
+
+/// Reads the packets of a recording created through [RecordingOptions], in the order they
+/// were recorded.
+pub struct RecordingReader<R: Read> {
+    reader: R,
+}
+
+impl RecordingReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "Not a session recording."));
+        }
+
+        if header[MAGIC.len()] != VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported recording version {}.", header[MAGIC.len()]),
+            ));
+        }
+
+        Ok(RecordingReader { reader })
+    }
+}
+
+impl<R: Read> Iterator for RecordingReader<R> {
+    type Item = io::Result<RecordedPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        RecordedPacket::read_from(&mut self.reader).transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_roundtrip() {
+        let packets = vec![
+            RecordedPacket {
+                time: Duration::from_micros(15),
+                connection: 3,
+                direction: Direction::Inbound,
+                framing: Framing::Unknown,
+                opcode: 0x7001,
+                data: vec![1, 2, 3],
+            },
+            RecordedPacket {
+                time: Duration::from_millis(20),
+                connection: 3,
+                direction: Direction::Outbound,
+                framing: Framing::Massive,
+                opcode: 0xB001,
+                data: Vec::new(),
+            },
+        ];
+
+        let mut file = Vec::new();
+        file.extend_from_slice(MAGIC);
+        file.push(VERSION);
+        for packet in packets.iter() {
+            packet.write_to(&mut file).unwrap();
+        }
+
+        let read = RecordingReader::new(Cursor::new(file))
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(read, packets);
+    }
+
+    #[test]
+    fn test_drops_when_full() {
+        let (sender, _receiver) = bounded(1);
+        let recorder = Arc::new(SessionRecorder {
+            sender,
+            filter: RecordingFilter::All,
+            start: Instant::now(),
+            dropped: AtomicU64::new(0),
+        });
+        let connection = recorder
+            .for_connection(1, SocketAddr::from(([127, 0, 0, 1], 15779)))
+            .unwrap();
+
+        connection.record(Direction::Inbound, Framing::Unknown, 0x7001, vec![1]);
+        assert_eq!(recorder.dropped(), 0);
+        connection.record(Direction::Outbound, Framing::Simple, 0xB001, vec![2]);
+        connection.record(Direction::Outbound, Framing::Simple, 0xB001, vec![3]);
+        assert_eq!(recorder.dropped(), 2);
+    }
+
+    #[test]
+    fn test_rejects_cut_off_packets() {
+        let mut file = Vec::new();
+        file.extend_from_slice(MAGIC);
+        file.push(VERSION);
+        RecordedPacket {
+            time: Duration::from_micros(15),
+            connection: 3,
+            direction: Direction::Inbound,
+            framing: Framing::Unknown,
+            opcode: 0x7001,
+            data: vec![1, 2, 3],
+        }
+        .write_to(&mut file)
+        .unwrap();
+        // Claim a length far beyond what is in the file.
+        let length_offset = file.len() - 3 - 4;
+        file[length_offset..length_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut reader = RecordingReader::new(Cursor::new(file)).unwrap();
+        assert!(reader.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_files() {
+        assert!(RecordingReader::new(Cursor::new(b"not a recording".to_vec())).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use bytes::Bytes;
+use skrillax_stream::packet::OutgoingPacket;
+use skrillax_stream::{InputError, InputProtocol};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// Amount of packets and their accumulated size for a single opcode. The size only includes
+/// the payload of the packets, not the header or any padding from encryption.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct OpcodeStats {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// A snapshot of the traffic of either a single [crate::Connection] or all connections of
+/// a [crate::Server] together.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TrafficSnapshot {
+    pub sent: BTreeMap<u16, OpcodeStats>,
+    pub received: BTreeMap<u16, OpcodeStats>,
+    /// Packets received with an opcode the protocol did not know about, per opcode.
+    pub unknown_opcodes: BTreeMap<u16, u64>,
+    /// Packets that should have been sent encrypted, but encryption was not set up.
+    pub encryption_errors: u64,
+    /// Data from the client that could not be turned into packets.
+    pub framing_errors: u64,
+}
+
+impl TrafficSnapshot {
+    pub fn total_sent(&self) -> OpcodeStats {
+        Self::total(&self.sent)
+    }
+
+    pub fn total_received(&self) -> OpcodeStats {
+        Self::total(&self.received)
+    }
+
+    /// Provides the `count` opcodes that were sent with the most bytes, largest first.
+    pub fn top_sent(&self, count: usize) -> Vec<(u16, OpcodeStats)> {
+        Self::top(&self.sent, count)
+    }
+
+    /// Provides the `count` opcodes that were received with the most bytes, largest first.
+    pub fn top_received(&self, count: usize) -> Vec<(u16, OpcodeStats)> {
+        Self::top(&self.received, count)
+    }
+
+    fn total(stats: &BTreeMap<u16, OpcodeStats>) -> OpcodeStats {
+        stats.values().fold(OpcodeStats::default(), |total, stats| OpcodeStats {
+            packets: total.packets + stats.packets,
+            bytes: total.bytes + stats.bytes,
+        })
+    }
+
+    fn top(stats: &BTreeMap<u16, OpcodeStats>, count: usize) -> Vec<(u16, OpcodeStats)> {
+        let mut entries = stats
+            .iter()
+            .map(|(opcode, stats)| (*opcode, *stats))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, stats)| Reverse(stats.bytes));
+        entries.truncate(count);
+        entries
+    }
+}
+
+impl Display for TrafficSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sent = self.total_sent();
+        let received = self.total_received();
+        write!(
+            f,
+            "sent {} packets ({} bytes), received {} packets ({} bytes), ",
+            sent.packets, sent.bytes, received.packets, received.bytes
+        )?;
+        write!(
+            f,
+            "{} unknown opcodes, {} encryption errors, {} framing errors",
+            self.unknown_opcodes.values().sum::<u64>(),
+            self.encryption_errors,
+            self.framing_errors
+        )
+    }
+}
+
+/// A packet and byte counter for a single opcode.
+#[derive(Default)]
+struct AtomicOpcodeStats {
+    packets: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Counters for every possible opcode, which can be updated without taking a lock. Counters
+/// are grouped into pages by the high byte of the opcode, a page only gets allocated once
+/// the first opcode of that page is recorded. As the opcodes of the protocol are clustered,
+/// only a handful of pages are ever needed.
+struct OpcodeCounters {
+    pages: Box<[OnceLock<Box<[AtomicOpcodeStats]>>]>,
+}
+
+impl OpcodeCounters {
+    fn new() -> Self {
+        OpcodeCounters {
+            pages: (0..OPCODE_PAGES).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    fn add(&self, opcode: u16, bytes: usize) {
+        let page = self.pages[usize::from(opcode >> 8)]
+            .get_or_init(|| (0..OPCODE_PAGE_SIZE).map(|_| AtomicOpcodeStats::default()).collect());
+        let counter = &page[usize::from(opcode & 0xFF)];
+        counter.packets.fetch_add(1, Ordering::Relaxed);
+        counter.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> BTreeMap<u16, OpcodeStats> {
+        let mut stats = BTreeMap::new();
+        for (high, page) in self.pages.iter().enumerate() {
+            let Some(page) = page.get() else {
+                continue;
+            };
+
+            for (low, counter) in page.iter().enumerate() {
+                let packets = counter.packets.load(Ordering::Relaxed);
+                if packets > 0 {
+                    let opcode = ((high as u16) << 8) | low as u16;
+                    let bytes = counter.bytes.load(Ordering::Relaxed);
+                    stats.insert(opcode, OpcodeStats { packets, bytes });
+                }
+            }
+        }
+        stats
+    }
+}
+
+const OPCODE_PAGES: usize = 1 << 8;
+const OPCODE_PAGE_SIZE: usize = 1 << 8;
+
+/// Collects the traffic of a connection. Everything recorded is also added to the parent,
+/// if there is one, which is used to keep the statistics of the whole server. All counters
+/// are atomic, such that recording never has to wait for other connections.
+pub(crate) struct TrafficStats {
+    sent: OpcodeCounters,
+    received: OpcodeCounters,
+    // Unknown opcodes should be rare, so we don't bother with counters for every opcode.
+    unknown_opcodes: Mutex<BTreeMap<u16, u64>>,
+    encryption_errors: AtomicU64,
+    framing_errors: AtomicU64,
+    parent: Option<Arc<TrafficStats>>,
+}
+
+impl TrafficStats {
+    fn with_parent(parent: Option<Arc<TrafficStats>>) -> Arc<Self> {
+        Arc::new(TrafficStats {
+            sent: OpcodeCounters::new(),
+            received: OpcodeCounters::new(),
+            unknown_opcodes: Mutex::new(BTreeMap::new()),
+            encryption_errors: AtomicU64::new(0),
+            framing_errors: AtomicU64::new(0),
+            parent,
+        })
+    }
+
+    pub(crate) fn new() -> Arc<Self> {
+        Self::with_parent(None)
+    }
+
+    pub(crate) fn child_of(parent: &Arc<TrafficStats>) -> Arc<Self> {
+        Self::with_parent(Some(parent.clone()))
+    }
+
+    pub(crate) fn snapshot(&self) -> TrafficSnapshot {
+        TrafficSnapshot {
+            sent: self.sent.snapshot(),
+            received: self.received.snapshot(),
+            unknown_opcodes: self
+                .unknown_opcodes
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+            encryption_errors: self.encryption_errors.load(Ordering::Relaxed),
+            framing_errors: self.framing_errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Applies the update to these statistics as well as all of its parents.
+    fn update(&self, update: impl Fn(&TrafficStats)) {
+        update(self);
+        if let Some(parent) = &self.parent {
+            parent.update(update);
+        }
+    }
+
+    /// Records a packet that has been sent, given its opcode and the size of its payload.
+    pub(crate) fn record_sent(&self, opcode: u16, bytes: usize) {
+        self.update(|stats| stats.sent.add(opcode, bytes));
+    }
+
+    pub(crate) fn record_received(&self, opcode: u16, bytes: usize) {
+        self.update(|stats| stats.received.add(opcode, bytes));
+    }
+
+    pub(crate) fn record_unknown_opcode(&self, opcode: u16) {
+        self.update(|stats| {
+            *stats
+                .unknown_opcodes
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(opcode)
+                .or_default() += 1
+        });
+    }
+
+    pub(crate) fn record_encryption_error(&self) {
+        self.update(|stats| {
+            stats.encryption_errors.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    pub(crate) fn record_framing_error(&self) {
+        self.update(|stats| {
+            stats.framing_errors.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+}
+
+/// Provides the opcode and the payload of an outgoing packet. Massive packets consist of
+/// multiple parts.
+pub(crate) fn outgoing_payload(packet: &OutgoingPacket) -> (u16, &[Bytes]) {
+    match packet {
+        OutgoingPacket::Simple { opcode, data, .. } | OutgoingPacket::Encrypted { opcode, data, .. } => {
+            (*opcode, std::slice::from_ref(data))
+        },
+        OutgoingPacket::Massive { opcode, packets, .. } => (*opcode, packets.as_slice()),
+    }
+}
+
+/// Provides the opcode and the size of the payload of an outgoing packet.
+pub(crate) fn outgoing_size(packet: &OutgoingPacket) -> (u16, usize) {
+    let (opcode, payload) = outgoing_payload(packet);
+    (opcode, payload.iter().map(|data| data.len()).sum())
+}
+
+/// A received packet together with its opcode and size, as well as its payload if it was
+/// requested.
+pub(crate) struct MeasuredPacket<P> {
+    pub(crate) opcode: u16,
+    pub(crate) size: usize,
+    pub(crate) data: Option<Vec<u8>>,
+    pub(crate) packet: P,
+}
+
+/// Wraps the input protocol of a connection, such that we know which opcode and size each
+/// received packet had, which the protocol itself no longer carries. If `CAPTURE` is set,
+/// the payload will be kept as well, which is only necessary when recording the connection.
+pub(crate) struct Measured<I: InputProtocol, const CAPTURE: bool>(PhantomData<I>);
+
+impl<I: InputProtocol, const CAPTURE: bool> InputProtocol for Measured<I, CAPTURE> {
+    type Proto = MeasuredPacket<I::Proto>;
+
+    fn create_from(opcode: u16, data: &[u8]) -> Result<(usize, Self::Proto), InputError> {
+        let (size, packet) = I::create_from(opcode, data)?;
+        let data = CAPTURE.then(|| data[..size].to_vec());
+        Ok((
+            size,
+            MeasuredPacket {
+                opcode,
+                size,
+                data,
+                packet,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_records_into_parent() {
+        let server = TrafficStats::new();
+        let first = TrafficStats::child_of(&server);
+        let second = TrafficStats::child_of(&server);
+
+        first.record_received(0x7001, 10);
+        first.record_received(0x7001, 6);
+        second.record_received(0x7001, 4);
+        second.record_received(0x2002, 0);
+        second.record_unknown_opcode(0x1234);
+        first.record_framing_error();
+        first.record_sent(0xB021, 30);
+        second.record_sent(0x3013, 100);
+
+        let first = first.snapshot();
+        assert_eq!(first.received[&0x7001], OpcodeStats { packets: 2, bytes: 16 });
+        assert_eq!(first.framing_errors, 1);
+        assert!(first.unknown_opcodes.is_empty());
+
+        let server = server.snapshot();
+        assert_eq!(server.received[&0x7001], OpcodeStats { packets: 3, bytes: 20 });
+        assert_eq!(server.total_received(), OpcodeStats { packets: 4, bytes: 20 });
+        assert_eq!(server.unknown_opcodes[&0x1234], 1);
+        assert_eq!(server.framing_errors, 1);
+        assert_eq!(server.sent[&0xB021], OpcodeStats { packets: 1, bytes: 30 });
+        assert_eq!(
+            server.top_sent(2),
+            vec![
+                (0x3013, OpcodeStats { packets: 1, bytes: 100 }),
+                (0xB021, OpcodeStats { packets: 1, bytes: 30 })
+            ]
+        );
+        assert_eq!(
+            server.top_received(1),
+            vec![(0x7001, OpcodeStats { packets: 3, bytes: 20 })]
+        );
+    }
+}
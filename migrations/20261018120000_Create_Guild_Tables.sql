@@ -0,0 +1,20 @@
+CREATE TABLE guilds
+(
+    id           SERIAL PRIMARY KEY,
+    name         VARCHAR                  NOT NULL UNIQUE,
+    level        SMALLINT                 NOT NULL DEFAULT 1,
+    notice_title VARCHAR                  NOT NULL DEFAULT '',
+    notice       VARCHAR                  NOT NULL DEFAULT '',
+    created_at   TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE TABLE guild_members
+(
+    character_id INTEGER                  NOT NULL PRIMARY KEY REFERENCES characters (id) ON DELETE CASCADE,
+    guild_id     INTEGER                  NOT NULL REFERENCES guilds (id) ON DELETE CASCADE,
+    rank         SMALLINT                 NOT NULL,
+    permissions  SMALLINT                 NOT NULL DEFAULT 0,
+    joined_at    TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+CREATE INDEX guild_members_guild_id_index ON guild_members (guild_id);
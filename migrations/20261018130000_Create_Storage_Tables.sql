@@ -0,0 +1,14 @@
+CREATE TABLE character_storage_items
+(
+    id            SERIAL PRIMARY KEY,
+    character_id  INTEGER  NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+    item_obj_id   INTEGER  NOT NULL,
+    upgrade_level SMALLINT NOT NULL DEFAULT 0,
+    variance      BIGINT,
+    slot          SMALLINT NOT NULL,
+    amount        SMALLINT NOT NULL DEFAULT 1,
+    CONSTRAINT character_storage_items_slot_uniq UNIQUE (character_id, slot)
+);
+
+ALTER TABLE characters
+    ADD COLUMN storage_gold BIGINT NOT NULL DEFAULT 0;
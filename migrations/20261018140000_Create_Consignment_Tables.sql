@@ -0,0 +1,20 @@
+-- The id of a listing is assigned by the server, which keeps all listings in memory.
+CREATE TABLE consignment_items
+(
+    id            INTEGER PRIMARY KEY,
+    character_id  INTEGER                  NOT NULL REFERENCES characters (id) ON DELETE CASCADE,
+    item_obj_id   INTEGER                  NOT NULL,
+    upgrade_level SMALLINT                 NOT NULL DEFAULT 0,
+    variance      BIGINT,
+    amount        SMALLINT                 NOT NULL DEFAULT 1,
+    price         BIGINT                   NOT NULL,
+    deposit       BIGINT                   NOT NULL,
+    fee           BIGINT                   NOT NULL,
+    -- 0 = listed, 1 = sold, 2 = expired
+    status        SMALLINT                 NOT NULL DEFAULT 0,
+    buyer_id      INTEGER REFERENCES characters (id) ON DELETE SET NULL,
+    registered_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    expires_at    TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX consignment_items_character_id_index ON consignment_items (character_id);
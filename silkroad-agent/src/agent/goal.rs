@@ -6,6 +6,7 @@ use crate::agent::state::{
 use crate::comp::inventory::PlayerInventory;
 use crate::comp::net::Client;
 use crate::comp::pos::Position;
+use crate::comp::skill::SkillCooldownTracker;
 use crate::comp::GameEntity;
 use crate::config::GameConfig;
 use crate::ext::Navmesh;
@@ -16,7 +17,7 @@ use cgmath::num_traits::Pow;
 use cgmath::{InnerSpace, MetricSpace};
 use silkroad_data::skilldata::RefSkillData;
 use silkroad_definitions::inventory::EquipmentSlot;
-use silkroad_game_base::{AttackSkill, GlobalLocation, GlobalPosition, Heading, Vector3Ext};
+use silkroad_game_base::{AttackSkill, GlobalLocation, GlobalPosition, Heading, SkillAttack, Vector3Ext};
 use silkroad_protocol::combat::{DoActionResponseCode, PerformActionResponse};
 
 pub struct AttackingGoal {
@@ -150,6 +151,7 @@ pub(crate) fn apply_goal(
             &Position,
             Option<&Idle>,
             Option<&PlayerInventory>,
+            Option<&SkillCooldownTracker>,
         ),
         Without<Dead>,
     >,
@@ -157,7 +159,8 @@ pub(crate) fn apply_goal(
     settings: Res<GameConfig>,
     navmesh: Res<Navmesh>,
 ) {
-    for (game_entity, mut goal, mut state, position, idle, inventory) in query.iter_mut() {
+    for (game_entity, mut goal, mut state, position, idle, inventory, cooldowns) in query.iter_mut() {
+        let cooldowns = cooldowns.map(|cooldowns| &**cooldowns);
         match &goal.goal {
             AgentGoal::Attacking(args) => {
                 let Ok((target_pos, dead)) = target_query.get(args.target) else {
@@ -174,16 +177,25 @@ pub(crate) fn apply_goal(
                     continue;
                 }
 
-                let skill = args.skill.unwrap_or(match inventory {
-                    Some(inv) => Attack::find_attack_for_player(inv).unwrap(),
-                    None => Attack::find_attack_for_monster(*game_entity).unwrap(),
-                });
+                let skill = match (args.skill, inventory) {
+                    (Some(skill), _) => skill,
+                    (None, Some(inv)) => Attack::find_attack_for_player(inv).unwrap(),
+                    (None, None) => match Attack::find_attack_for_monster(*game_entity, cooldowns) {
+                        Some(skill) => skill,
+                        None => continue,
+                    },
+                };
                 let weapon = inventory.and_then(|inv| inv.get_equipment_item(EquipmentSlot::Weapon));
                 let range = AttackSkill::get_range_for_attack(skill, weapon.map(|item| item.reference));
                 let range_squared = range.pow(2);
                 let range_to_target = position.distance_to(target_pos);
 
                 if range_to_target <= range_squared {
+                    // Wait for the skill to be ready again, instead of trying to use it right away.
+                    if cooldowns.is_some_and(|cooldowns| !cooldowns.is_ready_for(skill)) {
+                        continue;
+                    }
+
                     let target_state = AgentState::PerformSkill(SkillParameter {
                         target: SkillTarget::Entity(args.target),
                         skill,
@@ -312,8 +324,12 @@ pub(crate) fn handle_state_reached_notification(
                     }
 
                     if used_skill {
-                        if let Some(target) = performing.target.entity() {
-                            goal.switch_goal(AgentGoal::attacking(target));
+                        // Only keep attacking the target after an attack, not after buffing it.
+                        match performing.target.entity() {
+                            Some(target) if SkillAttack::from_skill(performing.skill).is_some() => {
+                                goal.switch_goal(AgentGoal::attacking(target))
+                            },
+                            _ => goal.reset(),
                         }
                     }
 
@@ -1,7 +1,7 @@
 use crate::agent::component::AgentGoalReachedEvent;
 use crate::agent::goal::{apply_goal, handle_state_reached_notification};
 use crate::agent::state::{run_transitions, StateTransitionEvent};
-use crate::agent::system::{action, movement, movement_input, pickup, turning};
+use crate::agent::system::{action, incapacitation, movement, movement_input, pickup, tick_cooldowns, turning};
 use bevy::prelude::*;
 
 pub mod component;
@@ -31,7 +31,10 @@ impl Plugin for AgentPlugin {
                     .chain()
                     .in_set(AgentSet::Transition),
             )
-            .add_systems(Update, (pickup, movement, action).in_set(AgentSet::Execute));
+            .add_systems(
+                Update,
+                (pickup, movement, incapacitation, tick_cooldowns, action).in_set(AgentSet::Execute),
+            );
         app.add_event::<StateTransitionEvent>()
             .add_event::<AgentGoalReachedEvent>();
     }
@@ -3,7 +3,7 @@ use crate::agent::goal::{MovingGoal, PickingUpGoal};
 use bevy::prelude::*;
 use cgmath::MetricSpace;
 use silkroad_data::skilldata::RefSkillData;
-use silkroad_game_base::{GlobalLocation, GlobalPosition, Heading};
+use silkroad_game_base::{GlobalLocation, GlobalPosition, Heading, StatusEffect, StatusEffectKind};
 use std::mem;
 
 #[derive(Copy, Clone, PartialEq)]
@@ -119,6 +119,48 @@ impl AsState for PickingUp {
     }
 }
 
+#[derive(Copy, Clone, PartialEq)]
+pub struct IncapacitatedParameter {
+    pub(crate) effect: StatusEffect,
+    /// The location the entity gets knocked back to, if it has been knocked back.
+    pub(crate) knockback: Option<GlobalLocation>,
+}
+
+impl IncapacitatedParameter {
+    pub fn new(effect: StatusEffect, knockback: Option<GlobalLocation>) -> Self {
+        Self { effect, knockback }
+    }
+}
+
+/// The entity suffers from a status effect, like a stun, and cannot act until it wears off.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Incapacitated {
+    pub(crate) parameter: IncapacitatedParameter,
+    pub(crate) timer: Timer,
+    pub(crate) pending_knockback: Option<GlobalLocation>,
+}
+
+impl Incapacitated {
+    pub fn new(parameter: IncapacitatedParameter) -> Self {
+        Self {
+            parameter,
+            timer: Timer::new(parameter.effect.duration, TimerMode::Once),
+            pending_knockback: parameter.knockback,
+        }
+    }
+
+    pub fn is_knocked_down(&self) -> bool {
+        self.parameter.effect.kind == StatusEffectKind::Knockdown
+    }
+}
+
+impl AsState for Incapacitated {
+    fn as_state(&self) -> AgentState {
+        AgentState::Incapacitated(self.parameter)
+    }
+}
+
 #[derive(Copy, Clone, PartialEq)]
 pub enum AgentState {
     Idle,
@@ -127,6 +169,7 @@ pub enum AgentState {
     Sitting,
     PerformingAction(ActionParameter),
     PickingUp(PickupParameter),
+    Incapacitated(IncapacitatedParameter),
     Dead,
 }
 
@@ -138,6 +181,7 @@ pub enum AgentStateType {
     Sitting,
     PerformingAction,
     PickingUp,
+    Incapacitated,
     Dead,
 }
 
@@ -150,6 +194,7 @@ impl From<AgentState> for AgentStateType {
             AgentState::Sitting => AgentStateType::Sitting,
             AgentState::PerformingAction(_) => AgentStateType::PerformingAction,
             AgentState::PickingUp(_) => AgentStateType::PickingUp,
+            AgentState::Incapacitated(_) => AgentStateType::Incapacitated,
             AgentState::Dead => AgentStateType::Dead,
         }
     }
@@ -212,6 +257,8 @@ pub struct PerformingSkill {
     pub(crate) parameter: SkillParameter,
     pub(crate) progress: SkillProgressState,
     pub(crate) timer: Timer,
+    /// Whether the skill follows another one in a chain, which already started the cooldowns.
+    pub(crate) chained: bool,
 }
 
 impl PerformingSkill {
@@ -220,6 +267,14 @@ impl PerformingSkill {
             parameter,
             progress: SkillProgressState::default(),
             timer: Timer::default(),
+            chained: false,
+        }
+    }
+
+    pub fn chained(parameter: SkillParameter) -> Self {
+        Self {
+            chained: true,
+            ..Self::new(parameter)
         }
     }
 }
@@ -368,7 +423,7 @@ impl AgentStateQueue {
 
 impl AgentState {
     fn max_importance() -> u8 {
-        4
+        5
     }
 
     fn importance(&self) -> u8 {
@@ -379,6 +434,7 @@ impl AgentState {
             AgentState::Sitting => 3,
             AgentState::PerformingAction(_) => 3,
             AgentState::PickingUp(_) => 3,
+            AgentState::Incapacitated(_) => 4,
             AgentState::Dead => Self::max_importance(),
         }
     }
@@ -391,6 +447,7 @@ impl AgentState {
             AgentState::Sitting => commands.try_insert(Sitting),
             AgentState::PerformingAction(args) => commands.try_insert(PerformingAction::new(*args)),
             AgentState::PickingUp(args) => commands.try_insert(PickingUp::new(*args)),
+            AgentState::Incapacitated(args) => commands.try_insert(Incapacitated::new(*args)),
             AgentState::Dead => commands.try_insert(Dead),
         }
     }
@@ -403,6 +460,7 @@ impl AgentState {
             (AgentState::Sitting, AgentState::Sitting) => true,
             (AgentState::PerformingAction(param), AgentState::PerformingAction(param2)) => param.eq(param2),
             (AgentState::PickingUp(param), AgentState::PickingUp(param2)) => param.eq(param2),
+            (AgentState::Incapacitated(param), AgentState::Incapacitated(param2)) => param.eq(param2),
             (AgentState::Dead, AgentState::Dead) => true,
             _ => false,
         }
@@ -416,6 +474,7 @@ impl AgentState {
             AgentState::Sitting => "Sitting",
             AgentState::PerformingAction(_) => "PerformingAction",
             AgentState::PickingUp(_) => "PickingUp",
+            AgentState::Incapacitated(_) => "Incapacitated",
             AgentState::Dead => "Dead",
         }
     }
@@ -433,19 +492,21 @@ pub(crate) fn run_transitions(
         Option<&Sitting>,
         Option<&PerformingAction>,
         Option<&PickingUp>,
+        Option<&Incapacitated>,
     )>,
 ) {
     query.par_iter_mut().for_each(
-        |(entity, mut state_queue, dead, idle, skill, moving, sitting, action, pickup)| {
+        |(entity, mut state_queue, dead, idle, skill, moving, sitting, action, pickup, incapacitated)| {
             commands.command_scope(|mut commands| {
-                let current_state = match (dead, idle, skill, moving, sitting, action, pickup) {
-                    (Some(dead), _, _, _, _, _, _) => dead.as_state(),
-                    (_, Some(idle), _, _, _, _, _) => idle.as_state(),
-                    (_, _, Some(skill), _, _, _, _) => skill.as_state(),
-                    (_, _, _, Some(moving), _, _, _) => moving.as_state(),
-                    (_, _, _, _, Some(sitting), _, _) => sitting.as_state(),
-                    (_, _, _, _, _, Some(action), _) => action.as_state(),
-                    (_, _, _, _, _, _, Some(pickup)) => pickup.as_state(),
+                let current_state = match (dead, idle, skill, moving, sitting, action, pickup, incapacitated) {
+                    (Some(dead), _, _, _, _, _, _, _) => dead.as_state(),
+                    (_, Some(idle), _, _, _, _, _, _) => idle.as_state(),
+                    (_, _, Some(skill), _, _, _, _, _) => skill.as_state(),
+                    (_, _, _, Some(moving), _, _, _, _) => moving.as_state(),
+                    (_, _, _, _, Some(sitting), _, _, _) => sitting.as_state(),
+                    (_, _, _, _, _, Some(action), _, _) => action.as_state(),
+                    (_, _, _, _, _, _, Some(pickup), _) => pickup.as_state(),
+                    (_, _, _, _, _, _, _, Some(incapacitated)) => incapacitated.as_state(),
                     _ => {
                         commands.entity(entity).try_insert(Idle);
                         AgentState::Idle
@@ -476,6 +537,7 @@ pub(crate) fn run_transitions(
                                 .remove::<Sitting>()
                                 .remove::<PerformingAction>()
                                 .remove::<PickingUp>()
+                                .remove::<Incapacitated>()
                                 .remove::<Idle>();
 
                             next_state.target.apply_to(&mut entity_commands);
@@ -1,28 +1,36 @@
 use crate::agent::component::{Agent, MovementState};
 use crate::agent::goal::{AgentGoal, GoalTracker};
 use crate::agent::state::{
-    Idle, MovementTarget as AgentMovementTarget, Moving, PerformingSkill, PickingUp, SkillParameter,
-    SkillProgressState, SkillTarget,
+    Dead, Idle, Incapacitated, MovementTarget as AgentMovementTarget, Moving, PerformingSkill, PickingUp,
+    SkillParameter, SkillProgressState, SkillTarget,
 };
 use crate::comp::gold::GoldPouch;
-use crate::comp::inventory::PlayerInventory;
+use crate::comp::inventory::{item_content_data, PlayerInventory};
+use crate::comp::monster::Monster;
 use crate::comp::net::Client;
 use crate::comp::pos::Position;
+use crate::comp::skill::SkillCooldownTracker;
+use crate::comp::visibility::Visibility;
 use crate::comp::{drop, EntityReference, GameEntity, Health, Mana};
-use crate::event::{ConsumeItemEvent, DamageReceiveEvent, SkillDefinition};
+use crate::event::{BuffApplyEvent, ConsumeItemEvent, DamageReceiveEvent, SkillDefinition};
 use crate::ext::{ActionIdCounter, Navmesh};
 use crate::input::PlayerInput;
+use crate::party::PartyMember;
 use crate::world::WorldData;
 use bevy::ecs::query::QueryEntityError;
 use bevy::prelude::*;
 use cgmath::{Array, Deg, InnerSpace, Quaternion, Rotation3, Vector2, Vector3, Zero};
-use silkroad_data::skilldata::SkillParam;
+use silkroad_data::skilldata::{RefSkillData, SkillParam};
 use silkroad_data::DataEntry;
 use silkroad_definitions::type_id::{ObjectItem, ObjectType};
-use silkroad_game_base::{GlobalLocation, Heading, ItemTypeData, LocalLocation, Vector3Ext};
+use silkroad_game_base::{
+    buff_duration, chain_count, chain_targets, GlobalLocation, Heading, Inventory, Item, ItemTypeData, LocalLocation,
+    SkillArea, Vector3Ext,
+};
 use silkroad_protocol::combat::{DoActionResponseCode, PerformActionError, PerformActionResponse};
-use silkroad_protocol::inventory::{InventoryItemContentData, InventoryOperationError, InventoryOperationResult};
+use silkroad_protocol::inventory::{InventoryOperationError, InventoryOperationResult};
 use silkroad_protocol::movement::MovementTarget;
+use std::collections::HashSet;
 use std::ops::Deref;
 use std::time::Duration;
 use tracing::{debug, error, warn};
@@ -54,63 +62,117 @@ pub(crate) fn movement_input(
     }
 }
 
+type PickupPlayerData<'a> = (
+    Entity,
+    &'a Client,
+    &'a GameEntity,
+    &'a Position,
+    Option<&'a PartyMember>,
+    &'a mut PickingUp,
+    &'a mut PlayerInventory,
+    &'a mut GoldPouch,
+);
+
 pub(crate) fn pickup(
-    mut query: Query<(Entity, &Client, &mut PickingUp, &mut PlayerInventory, &mut GoldPouch)>,
+    mut query: Query<PickupPlayerData>,
     time: Res<Time>,
-    target_query: Query<&drop::Drop>,
+    target_query: Query<(&drop::Drop, &Position)>,
     mut cmd: Commands,
 ) {
     let delta = time.delta();
-    for (entity, client, mut pickup, mut inventory, mut gold) in query.iter_mut() {
+    // Drops are only despawned at the end of the tick, so we need to make sure two players don't
+    // pick up the same drop at the same time.
+    let mut picked_up = HashSet::new();
+    for (entity, client, game_entity, position, party, mut pickup, mut inventory, mut gold) in query.iter_mut() {
         if let Some(cooldown) = pickup.cooldown.as_mut() {
             if cooldown.tick(delta).just_finished() {
                 client.send(PerformActionResponse::Stop(PerformActionError::Completed));
                 cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
             }
-        } else {
-            let drop = match target_query.get(pickup.parameter.target) {
-                Ok(drop) => drop,
-                Err(QueryEntityError::NoSuchEntity(_)) => {
-                    client.send(PerformActionResponse::Stop(PerformActionError::InvalidTarget));
-                    cmd.entity(entity).remove::<PickingUp>();
-                    continue;
-                },
-                Err(e) => {
-                    error!("Could not load target pickup item: {:?}", e);
-                    cmd.entity(entity).remove::<PickingUp>();
-                    continue;
-                },
-            };
+            continue;
+        }
 
-            cmd.entity(pickup.parameter.target).despawn();
-            pickup.cooldown = Some(Timer::from_seconds(1.0, TimerMode::Once));
+        let target = pickup.parameter.target;
+        let (drop, drop_position) = match target_query.get(target) {
+            Ok(_) if picked_up.contains(&target) => {
+                client.send(PerformActionResponse::Stop(PerformActionError::InvalidTarget));
+                cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
+                continue;
+            },
+            Ok(drop) => drop,
+            Err(QueryEntityError::NoSuchEntity(_)) => {
+                client.send(PerformActionResponse::Stop(PerformActionError::InvalidTarget));
+                cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
+                continue;
+            },
+            Err(e) => {
+                error!("Could not load target pickup item: {:?}", e);
+                cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
+                continue;
+            },
+        };
 
-            match &drop.item.type_data {
-                ItemTypeData::Gold { amount } => {
-                    gold.gain(u64::from(*amount));
-                    client.send(PerformActionResponse::Do(DoActionResponseCode::Success));
-                },
-                _ => {
-                    if let Some(slot) = inventory.add_item(drop.item) {
-                        client.send(InventoryOperationResult::success_gain_item(
-                            slot,
-                            drop.item.reference.ref_id(),
-                            InventoryItemContentData::Expendable {
-                                stack_size: drop.item.stack_size(),
-                            },
-                        ));
-                    } else {
-                        client.send(InventoryOperationResult::Failure(
-                            InventoryOperationError::InventoryFull,
-                        ));
-                    }
-                    client.send(PerformActionResponse::Stop(PerformActionError::Completed));
-                },
-            }
+        let range = WorldData::characters()
+            .find_id(game_entity.ref_id)
+            .and_then(|character| character.pickup_range)
+            .map(|range| f32::from(range.get()) + EPSYLON)
+            .unwrap_or(0.0);
+        if position.distance_to(drop_position) > range.powi(2) {
+            client.send(PerformActionResponse::Stop(PerformActionError::InvalidDistance));
+            cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
+            continue;
+        }
+
+        if drop.owner_for(game_entity, party.map(|member| member.0)).is_some() {
+            client.send(InventoryOperationResult::Failure(
+                InventoryOperationError::CannotBePicked,
+            ));
+            client.send(PerformActionResponse::Stop(PerformActionError::InvalidTarget));
+            cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
+            continue;
+        }
+
+        match pick_up_item(drop.item, &mut inventory, &mut gold) {
+            Ok(result) => {
+                picked_up.insert(target);
+                cmd.entity(target).despawn();
+                // Setting the cooldown starts the pickup animation, after which we stop.
+                pickup.cooldown = Some(Timer::from_seconds(1.0, TimerMode::Once));
+                client.send(PerformActionResponse::Do(DoActionResponseCode::Success));
+                client.send(result);
+            },
+            Err(error) => {
+                client.send(InventoryOperationResult::Failure(error));
+                client.send(PerformActionResponse::Stop(PerformActionError::Completed));
+                cmd.entity(entity).remove::<PickingUp>().try_insert(Idle);
+            },
         }
     }
 }
 
+/// Moves the dropped item into the inventory, or into the gold pouch if it is gold. Stackable
+/// items are merged with existing stacks where possible.
+fn pick_up_item(
+    item: Item,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+) -> Result<InventoryOperationResult, InventoryOperationError> {
+    if let ItemTypeData::Gold { amount } = item.type_data {
+        gold.gain(u64::from(amount));
+        return Ok(InventoryOperationResult::success_gain_gold(amount));
+    }
+
+    let slot = inventory.add_item(item).ok_or(InventoryOperationError::InventoryFull)?;
+    let slot_item = inventory
+        .get_item_at(slot)
+        .expect("Item should exist in the slot it was just added to");
+    Ok(InventoryOperationResult::success_gain_item(
+        slot,
+        item.reference.ref_id(),
+        item_content_data(slot_item),
+    ))
+}
+
 pub(crate) fn action(
     mut query: Query<(
         Entity,
@@ -119,18 +181,36 @@ pub(crate) fn action(
         Option<&mut Mana>,
         &mut Health,
         Option<&PlayerInventory>,
+        &Position,
+        Option<&Visibility>,
+        Has<Monster>,
+        Option<&mut SkillCooldownTracker>,
+        Option<&Client>,
     )>,
-    target_query: Query<&GameEntity>,
+    target_query: Query<AttackTargetData, With<Health>>,
     time: Res<Time>,
     attack_instance_counter: Res<ActionIdCounter>,
     mut cmd: Commands,
 ) {
     let delta = time.delta();
-    for (entity, game_entity, mut action, mana, mut health, inventory) in query.iter_mut() {
+    for (
+        entity,
+        game_entity,
+        mut action,
+        mana,
+        mut health,
+        inventory,
+        position,
+        visibility,
+        is_monster,
+        mut cooldowns,
+        client,
+    ) in query.iter_mut()
+    {
         if action.timer.tick(delta).just_finished() {
             let Some(next) = action.progress.next() else {
                 if let Some(next_skill) = action.parameter.skill.next_in_chain {
-                    *action = PerformingSkill::new(SkillParameter {
+                    *action = PerformingSkill::chained(SkillParameter {
                         target: action.parameter.target,
                         skill: WorldData::skills()
                             .find_id(next_skill.into())
@@ -144,6 +224,19 @@ pub(crate) fn action(
             };
 
             if next == SkillProgressState::Casting {
+                if !action.chained
+                    && cooldowns
+                        .as_ref()
+                        .is_some_and(|cooldowns| !cooldowns.is_ready_for(action.parameter.skill))
+                {
+                    cmd.entity(entity).remove::<PerformingSkill>();
+                    if let Some(client) = client {
+                        client.send(PerformActionResponse::Stop(PerformActionError::Cooldown));
+                    }
+                    debug!("Cancelling skill as it is still on cooldown.");
+                    continue;
+                }
+
                 if action.parameter.skill.consumed_mp > 0 {
                     let Some(mut mana) = mana else {
                         cmd.entity(entity).remove::<PerformingSkill>();
@@ -211,6 +304,10 @@ pub(crate) fn action(
                         _ => {},
                     };
                 }
+
+                if let Some(cooldowns) = cooldowns.as_mut() {
+                    cooldowns.start_for(action.parameter.skill);
+                }
             }
 
             let time = next.get_time_for(action.parameter.skill).unwrap_or(0);
@@ -218,36 +315,95 @@ pub(crate) fn action(
             action.timer = Timer::new(Duration::from_millis(time as u64), TimerMode::Once);
 
             if next == SkillProgressState::Execution {
-                let attack = action
-                    .parameter
-                    .skill
+                let skill = action.parameter.skill;
+                if skill
                     .params
                     .iter()
-                    .find(|param| matches!(param, SkillParam::Attack { .. }))
-                    .unwrap();
-                match attack {
-                    SkillParam::Attack { .. } => {
-                        let SkillTarget::Entity(target) = action.parameter.target else {
-                            panic!();
-                        };
-                        let target_ = target_query.get(target).unwrap();
-                        cmd.send_event(DamageReceiveEvent {
-                            source: EntityReference(entity, *game_entity),
-                            target: EntityReference(target, *target_),
-                            attack: SkillDefinition {
-                                skill: action.parameter.skill,
-                                instance: attack_instance_counter.next(),
-                            },
-                            amount: 10,
-                        });
-                    },
-                    _ => {},
+                    .any(|param| matches!(param, SkillParam::Attack { .. }))
+                {
+                    let SkillTarget::Entity(target) = action.parameter.target else {
+                        panic!();
+                    };
+                    let Ok((target_entity, target_position, _, _)) = target_query.get(target) else {
+                        cmd.entity(entity).remove::<PerformingSkill>();
+                        continue;
+                    };
+                    let targets = resolve_targets(
+                        skill,
+                        (entity, position.location(), is_monster),
+                        (EntityReference(target, *target_entity), target_position.location()),
+                        visibility,
+                        &target_query,
+                    );
+                    cmd.send_event(DamageReceiveEvent {
+                        source: EntityReference(entity, *game_entity),
+                        targets,
+                        attack: SkillDefinition {
+                            skill,
+                            instance: attack_instance_counter.next(),
+                        },
+                    });
+                } else if buff_duration(skill).is_some() {
+                    let target = match action.parameter.target {
+                        SkillTarget::Entity(target) => target,
+                        _ => entity,
+                    };
+                    cmd.send_event(BuffApplyEvent {
+                        source: EntityReference(entity, *game_entity),
+                        target,
+                        skill: SkillDefinition {
+                            skill,
+                            instance: attack_instance_counter.next(),
+                        },
+                    });
                 }
             }
         }
     }
 }
 
+type AttackTargetData<'a> = (&'a GameEntity, &'a Position, Has<Monster>, Has<Dead>);
+
+/// Finds all entities hit by the skill. Apart from the initial target, this includes all
+/// hostile entities visible to the caster which are inside the area of the skill or which the
+/// skill chains to.
+fn resolve_targets(
+    skill: &RefSkillData,
+    (caster, caster_location, caster_is_monster): (Entity, GlobalLocation, bool),
+    (target, target_location): (EntityReference, GlobalLocation),
+    visibility: Option<&Visibility>,
+    target_query: &Query<AttackTargetData, With<Health>>,
+) -> Vec<EntityReference> {
+    let area = SkillArea::from_skill(skill);
+    let chain = chain_count(skill);
+    let Some(visibility) = visibility.filter(|_| area.is_some() || chain.is_some()) else {
+        return vec![target];
+    };
+
+    let candidates = visibility
+        .entities_in_radius
+        .iter()
+        .filter(|candidate| candidate.0 != caster && candidate.0 != target.0)
+        .filter_map(|candidate| {
+            let (_, position, is_monster, is_dead) = target_query.get(candidate.0).ok()?;
+            (is_monster != caster_is_monster && !is_dead).then_some((*candidate, position.location()))
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(area) = area {
+        let mut targets = vec![target];
+        targets.extend(
+            candidates
+                .into_iter()
+                .filter(|(_, location)| area.contains(caster_location, target_location, *location))
+                .map(|(candidate, _)| candidate),
+        );
+        targets
+    } else {
+        chain_targets((target, target_location), &candidates, chain.unwrap_or(1))
+    }
+}
+
 pub(crate) fn movement(
     mut query: Query<(Entity, &mut Position, &Agent, &Moving, &MovementState)>,
     time: Res<Time>,
@@ -278,6 +434,34 @@ pub(crate) fn movement(
     }
 }
 
+pub(crate) fn tick_cooldowns(mut query: Query<&mut SkillCooldownTracker>, time: Res<Time>) {
+    let delta = time.delta();
+    for mut cooldowns in query.iter_mut() {
+        if !cooldowns.is_empty() {
+            cooldowns.tick(delta);
+        }
+    }
+}
+
+pub(crate) fn incapacitation(
+    mut query: Query<(Entity, &mut Position, &mut Incapacitated)>,
+    time: Res<Time>,
+    mut cmd: Commands,
+    navmesh: Res<Navmesh>,
+) {
+    let delta = time.delta();
+    for (entity, mut pos, mut incapacitated) in query.iter_mut() {
+        if let Some(destination) = incapacitated.pending_knockback.take() {
+            let height = navmesh.height_for(destination.to_local()).unwrap_or(pos.position().0.y);
+            pos.move_to(destination.with_y(height));
+        }
+
+        if incapacitated.timer.tick(delta).just_finished() {
+            cmd.entity(entity).remove::<Incapacitated>().try_insert(Idle);
+        }
+    }
+}
+
 fn get_next_step(
     time_delta: f32,
     current_location: GlobalLocation,
@@ -314,3 +498,49 @@ pub(crate) fn turning(mut query: Query<(&mut Position, &PlayerInput), With<Idle>
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable;
+
+    #[test]
+    fn test_pick_up_merges_stacks() {
+        let mut inventory = Inventory::new(14);
+        let mut gold = GoldPouch::new(0);
+        let item = consumable(10);
+        inventory.add_item(item);
+
+        let result = pick_up_item(item, &mut inventory, &mut gold);
+        assert!(result.is_ok());
+        assert_eq!(20, inventory.get_item_at(13).unwrap().stack_size());
+        assert_eq!(0, inventory.free_slots());
+    }
+
+    #[test]
+    fn test_pick_up_gold() {
+        let mut inventory = Inventory::new(14);
+        let mut gold = GoldPouch::new(100);
+        let item = Item {
+            type_data: ItemTypeData::Gold { amount: 50 },
+            ..consumable(1)
+        };
+
+        let result = pick_up_item(item, &mut inventory, &mut gold);
+        assert!(result.is_ok());
+        assert_eq!(150, gold.amount());
+        assert_eq!(1, inventory.free_slots());
+    }
+
+    #[test]
+    fn test_pick_up_into_full_inventory() {
+        let mut inventory = Inventory::new(14);
+        let mut gold = GoldPouch::new(0);
+        let item = consumable(45);
+        inventory.add_item(item);
+
+        let result = pick_up_item(item, &mut inventory, &mut gold);
+        assert!(matches!(result, Err(InventoryOperationError::InventoryFull)));
+        assert_eq!(45, inventory.get_item_at(13).unwrap().stack_size());
+    }
+}
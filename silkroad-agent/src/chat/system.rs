@@ -8,7 +8,10 @@ use crate::comp::visibility::{Invisible, Visibility};
 use crate::comp::GameEntity;
 use crate::event::SpawnMonster;
 use crate::game::drop::SpawnDrop;
+use crate::guild::{GuildMember, Guilds};
 use crate::input::PlayerInput;
+use crate::party::{Parties, PartyMember};
+use crate::stall::{Stall, StallVisitor};
 use crate::world::{EntityLookup, WorldData};
 use bevy::prelude::*;
 use silkroad_definitions::type_id::{ObjectConsumable, ObjectConsumableCurrency, ObjectItem, ObjectType};
@@ -29,12 +32,27 @@ fn can_send_message(message: &ChatMessage, player: &Player) -> bool {
 }
 
 pub(crate) fn handle_chat(
-    mut query: Query<(Entity, &Client, &GameEntity, &PlayerInput, &Visibility, &Player)>,
+    mut query: Query<(
+        Entity,
+        &Client,
+        &GameEntity,
+        &PlayerInput,
+        &Visibility,
+        &Player,
+        Option<&PartyMember>,
+        Option<&GuildMember>,
+        Option<&StallVisitor>,
+    )>,
     lookup: Res<EntityLookup>,
+    parties: Res<Parties>,
+    guilds: Res<Guilds>,
     others: Query<(&Client, &Player)>,
+    stalls: Query<&Stall>,
     mut cmds: Commands,
 ) {
-    for (entity, client, game_entity, input, visibility, player) in query.iter_mut() {
+    for (entity, client, game_entity, input, visibility, player, party_member, guild_member, stall_visitor) in
+        query.iter_mut()
+    {
         for message in input.chat.iter() {
             let ChatClientProtocol::ChatMessage(message) = message;
 
@@ -116,6 +134,94 @@ pub(crate) fn handle_chat(
                         },
                     }
                 },
+                ChatTarget::Party => {
+                    let Some(party) = party_member.and_then(|member| parties.get(member.0)) else {
+                        client.send(ChatMessageResponse::new(
+                            ChatMessageResult::error(ChatErrorCode::InvalidTarget),
+                            message.target,
+                            message.index,
+                        ));
+                        continue;
+                    };
+
+                    party
+                        .members()
+                        .iter()
+                        .filter(|member| member.0 != entity)
+                        .filter_map(|member| others.get(member.0).ok())
+                        .for_each(|(client, _)| {
+                            client.send(ChatUpdate::new(
+                                ChatSource::party(player.character.name.clone()),
+                                message.message.clone(),
+                            ));
+                        });
+                    client.send(ChatMessageResponse::new(
+                        ChatMessageResult::Success,
+                        message.target,
+                        message.index,
+                    ));
+                },
+                ChatTarget::Guild => {
+                    let Some(guild) = guild_member.and_then(|member| guilds.get(member.0)) else {
+                        client.send(ChatMessageResponse::new(
+                            ChatMessageResult::error(ChatErrorCode::InvalidTarget),
+                            message.target,
+                            message.index,
+                        ));
+                        continue;
+                    };
+
+                    guild
+                        .online_members()
+                        .filter(|member| *member != entity)
+                        .filter_map(|member| others.get(member).ok())
+                        .for_each(|(client, _)| {
+                            client.send(ChatUpdate::new(
+                                ChatSource::guild(player.character.name.clone()),
+                                message.message.clone(),
+                            ));
+                        });
+                    client.send(ChatMessageResponse::new(
+                        ChatMessageResult::Success,
+                        message.target,
+                        message.index,
+                    ));
+                },
+                ChatTarget::Stall => {
+                    // The owner of a stall chats inside their own stall, everyone else in the one
+                    // they're visiting.
+                    let owner = if stalls.contains(entity) {
+                        Some(entity)
+                    } else {
+                        stall_visitor.map(|visitor| visitor.0)
+                    };
+                    let Some((owner, stall)) =
+                        owner.and_then(|owner| stalls.get(owner).ok().map(|stall| (owner, stall)))
+                    else {
+                        client.send(ChatMessageResponse::new(
+                            ChatMessageResult::error(ChatErrorCode::InvalidTarget),
+                            message.target,
+                            message.index,
+                        ));
+                        continue;
+                    };
+
+                    std::iter::once(owner)
+                        .chain(stall.visitors().iter().map(|(visitor, _)| *visitor))
+                        .filter(|member| *member != entity)
+                        .filter_map(|member| others.get(member).ok())
+                        .for_each(|(client, _)| {
+                            client.send(ChatUpdate::new(
+                                ChatSource::stall(player.character.name.clone()),
+                                message.message.clone(),
+                            ));
+                        });
+                    client.send(ChatMessageResponse::new(
+                        ChatMessageResult::Success,
+                        message.target,
+                        message.index,
+                    ));
+                },
                 _ => {},
             }
         }
@@ -171,6 +277,7 @@ pub(crate) fn handle_gm_commands(
                         },
                         position.location(),
                         None,
+                        None,
                     ));
                     client.send(GmResponse::success_message(format!("Dropped 1 of {}", item.common.id)));
                 },
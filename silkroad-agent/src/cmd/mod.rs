@@ -6,7 +6,7 @@ use crate::comp::{EntityReference, GameEntity};
 use crate::ext::Navmesh;
 use crate::game::exp::ReceiveExperienceEvent;
 use crate::game::target::Target;
-use crate::world::WorldData;
+use crate::world::{EntityLookup, WorldData};
 use bevy::app::MainScheduleOrder;
 use bevy::ecs::schedule::ScheduleLabel;
 use bevy::prelude::*;
@@ -14,6 +14,7 @@ use gumdrop::Options;
 use silkroad_game_base::{GlobalLocation, GlobalPosition, MovementSpeed};
 use silkroad_protocol::chat::{ChatSource, ChatUpdate};
 use silkroad_protocol::movement::ChangeSpeed;
+use skrillax_server::DisconnectReason;
 use std::fmt::Display;
 use tracing::{info, warn};
 
@@ -83,6 +84,7 @@ impl Plugin for CommandPlugin {
             .add_event::<CommandInvocation<PrintPos>>()
             .add_event::<CommandInvocation<PrintTarget>>()
             .add_event::<CommandInvocation<TeleportArgs>>()
+            .add_event::<CommandInvocation<KickArgs>>()
             .add_systems(
                 CommandSchedule,
                 (
@@ -95,6 +97,7 @@ impl Plugin for CommandPlugin {
                         handle_print_pos,
                         handle_print_target,
                         handle_teleport,
+                        handle_kick,
                     ),
                     output_results,
                 )
@@ -138,6 +141,8 @@ enum SilkroadCommands {
     Target(PrintTarget),
     #[options(help = "Teleports to the given position")]
     Tp(TeleportArgs),
+    #[options(help = "Disconnects the player with the given name")]
+    Kick(KickArgs),
     #[options(help = "Show the help output")]
     Help(Help),
 }
@@ -219,6 +224,12 @@ fn parse_commands(mut incoming: EventReader<IncomingCommand>, mut cmds: Commands
                     args,
                 });
             },
+            SilkroadCommands::Kick(args) => {
+                cmds.send_event(CommandInvocation {
+                    sender: incoming.sender,
+                    args,
+                });
+            },
             SilkroadCommands::Help(_) => {
                 unreachable!("Help should have already been handled above.")
             },
@@ -525,3 +536,35 @@ fn handle_teleport(
         position.move_to(target);
     }
 }
+
+#[derive(Options, Debug, PartialEq)]
+struct KickArgs {
+    #[options(free)]
+    name: String,
+}
+
+fn handle_kick(
+    mut invocations: EventReader<CommandInvocation<KickArgs>>,
+    mut results: EventWriter<CommandResult>,
+    lookup: Res<EntityLookup>,
+    query: Query<&Client>,
+) {
+    for kick in invocations.read() {
+        let Some(client) = lookup
+            .get_entity_for_name(&kick.args.name)
+            .and_then(|entity| query.get(entity).ok())
+        else {
+            results.send(CommandResult {
+                receiver: kick.sender,
+                outcome: CommandOutcome::ExecutionFailure(format!("There is no player named {}.", kick.args.name)),
+            });
+            continue;
+        };
+
+        client.disconnect(DisconnectReason::Kicked);
+        results.send(CommandResult {
+            receiver: kick.sender,
+            outcome: CommandOutcome::Success(Some(format!("Kicked {}.", kick.args.name))),
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use crate::sync::Reset;
+use bevy::prelude::*;
+use silkroad_game_base::{Buff, BuffList, BuffModifiers};
+use silkroad_protocol::world::ActiveBuffData;
+use std::time::Duration;
+
+/// The buffs currently active on an entity, keeping track of which buffs have begun or ended
+/// since the last synchronization.
+#[derive(Component, Default)]
+pub(crate) struct Buffed {
+    buffs: BuffList,
+    began: Vec<Buff>,
+    ended: Vec<Buff>,
+}
+
+impl Reset for Buffed {
+    fn reset(&mut self) {
+        self.began.clear();
+        self.ended.clear();
+    }
+}
+
+impl Buffed {
+    pub(crate) fn add(&mut self, buff: Buff) {
+        let replaced = self.buffs.add(buff);
+        self.ended.extend(replaced);
+        self.began.push(buff);
+    }
+
+    pub(crate) fn cancel(&mut self, skill: u32) -> bool {
+        match self.buffs.remove_skill(skill) {
+            Some(buff) => {
+                self.ended.push(buff);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Advances all buffs and returns whether any of them expired.
+    pub(crate) fn tick(&mut self, delta: Duration) -> bool {
+        let expired = self.buffs.tick(delta);
+        let any_expired = !expired.is_empty();
+        self.ended.extend(expired);
+        any_expired
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let removed = self.buffs.clear();
+        self.ended.extend(removed);
+    }
+
+    pub(crate) fn has_buffs(&self) -> bool {
+        !self.buffs.is_empty()
+    }
+
+    pub(crate) fn modifiers(&self) -> BuffModifiers {
+        self.buffs.modifiers()
+    }
+
+    pub(crate) fn began(&self) -> impl Iterator<Item = &Buff> {
+        self.began.iter()
+    }
+
+    pub(crate) fn ended(&self) -> impl Iterator<Item = &Buff> {
+        self.ended.iter()
+    }
+
+    pub(crate) fn active_buff_data(&self) -> Vec<ActiveBuffData> {
+        self.buffs
+            .iter()
+            .map(|buff| ActiveBuffData::new(buff.skill, buff.token))
+            .collect()
+    }
+}
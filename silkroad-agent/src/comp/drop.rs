@@ -1,14 +1,31 @@
 use crate::comp::pos::Position;
 use crate::comp::{Despawn, EntityReference, GameEntity};
+use crate::party::PartyId;
 use bevy::prelude::*;
 use silkroad_game_base::Item;
 
 #[derive(Component)]
 pub(crate) struct Drop {
     pub owner: Option<EntityReference>,
+    /// The party sharing this drop, in which case every member of the party counts as an owner.
+    pub party: Option<PartyId>,
     pub item: Item,
 }
 
+impl Drop {
+    /// The owner of the drop as it should be shown to the given player, which is `None` if the
+    /// player may pick up the drop.
+    pub(crate) fn owner_for(&self, player: &GameEntity, party: Option<PartyId>) -> Option<u32> {
+        if party.is_some() && self.party == party {
+            return None;
+        }
+
+        self.owner
+            .map(|owner| owner.1.unique_id)
+            .filter(|id| *id != player.unique_id)
+    }
+}
+
 #[derive(Bundle)]
 pub(crate) struct DropBundle {
     pub(crate) drop: Drop,
@@ -6,7 +6,8 @@ use bevy::prelude::*;
 use silkroad_data::itemdata::RefItemData;
 use silkroad_definitions::type_id::{ObjectItem, ObjectType};
 use silkroad_game_base::{ChangeTracked, Inventory, InventoryChange, Item, ItemTypeData};
-use sqlx::PgPool;
+use silkroad_protocol::inventory::{InventoryItemBindingData, InventoryItemContentData};
+use sqlx::{PgConnection, PgPool};
 use std::ops::{Deref, DerefMut};
 
 #[derive(Component)]
@@ -39,74 +40,109 @@ impl ChangeTracked for PlayerInventory {
 #[async_trait]
 impl ApplyToDatabase for InventoryChange {
     async fn apply(&self, character_id: u32, pool: &PgPool) -> Result<(), sqlx::Error> {
-        match self {
-            InventoryChange::AddItem { slot, item } => {
-                sqlx::query!(
-                    "INSERT INTO character_items(character_id, item_obj_id, upgrade_level, slot, variance, amount) VALUES($1, $2, $3, $4, $5, $6) ON CONFLICT(character_id, slot) DO UPDATE SET item_obj_id = EXCLUDED.item_obj_id, upgrade_level = EXCLUDED.upgrade_level, variance = EXCLUDED.variance, amount = EXCLUDED.amount",
-                    character_id as i32,
-                    item.reference.common.ref_id as i32,
-                    item.type_data.upgrade_level().map(|a| a as i16).unwrap_or(0),
-                    *slot as i16,
-                    item.variance.map(|a| a as i64),
-                    item.type_data.amount() as i16 // This should be fine, since we should never have gold inside an item slot
-                ).execute(pool).await?;
-            },
-            InventoryChange::ChangeTypeData { slot, new_item, .. } => {
-                sqlx::query!(
-                    "UPDATE character_items SET upgrade_level = $1, amount = $2 WHERE character_id = $3 AND slot = $4",
-                    new_item.upgrade_level().map(|a| a as i16).unwrap_or(0),
-                    new_item.amount() as i16, // This should be fine, since we should never have gold inside an item slot
-                    character_id as i32,
-                    *slot as i16,
-                )
-                .execute(pool)
-                .await?;
-            },
-            InventoryChange::MoveItem {
-                source_slot,
-                target_slot,
-            } => {
-                sqlx::query!(
-                    "UPDATE character_items SET slot = $1 WHERE character_id = $2 AND slot = $3",
-                    *target_slot as i16,
-                    character_id as i32,
-                    *source_slot as i16,
-                )
-                .execute(pool)
-                .await?;
-            },
-            InventoryChange::RemoveItem { slot } => {
-                sqlx::query!(
-                    "DELETE FROM character_items WHERE character_id = $1 AND slot = $2",
-                    character_id as i32,
-                    *slot as i16,
-                )
-                .execute(pool)
-                .await?;
-            },
-            InventoryChange::Swap {
-                first_slot,
-                second_slot,
-            } => {
-                sqlx::query!(
+        let mut connection = pool.acquire().await?;
+        apply_inventory_change(self, character_id, &mut connection).await
+    }
+}
+
+/// Applies a single inventory change on the given connection, such that multiple changes can be
+/// grouped inside a transaction.
+pub(crate) async fn apply_inventory_change(
+    change: &InventoryChange,
+    character_id: u32,
+    connection: &mut PgConnection,
+) -> Result<(), sqlx::Error> {
+    match change {
+        InventoryChange::AddItem { slot, item } => {
+            sqlx::query!(
+                "INSERT INTO character_items(character_id, item_obj_id, upgrade_level, slot, variance, amount) VALUES($1, $2, $3, $4, $5, $6) ON CONFLICT(character_id, slot) DO UPDATE SET item_obj_id = EXCLUDED.item_obj_id, upgrade_level = EXCLUDED.upgrade_level, variance = EXCLUDED.variance, amount = EXCLUDED.amount",
+                character_id as i32,
+                item.reference.common.ref_id as i32,
+                item.type_data.upgrade_level().map(|a| a as i16).unwrap_or(0),
+                *slot as i16,
+                item.variance.map(|a| a as i64),
+                item.type_data.amount() as i16 // This should be fine, since we should never have gold inside an item slot
+            ).execute(&mut *connection).await?;
+        },
+        InventoryChange::ChangeTypeData { slot, new_item, .. } => {
+            sqlx::query!(
+                "UPDATE character_items SET upgrade_level = $1, amount = $2 WHERE character_id = $3 AND slot = $4",
+                new_item.upgrade_level().map(|a| a as i16).unwrap_or(0),
+                new_item.amount() as i16, // This should be fine, since we should never have gold inside an item slot
+                character_id as i32,
+                *slot as i16,
+            )
+            .execute(&mut *connection)
+            .await?;
+        },
+        InventoryChange::MoveItem {
+            source_slot,
+            target_slot,
+        } => {
+            sqlx::query!(
+                "UPDATE character_items SET slot = $1 WHERE character_id = $2 AND slot = $3",
+                *target_slot as i16,
+                character_id as i32,
+                *source_slot as i16,
+            )
+            .execute(&mut *connection)
+            .await?;
+        },
+        InventoryChange::RemoveItem { slot } => {
+            sqlx::query!(
+                "DELETE FROM character_items WHERE character_id = $1 AND slot = $2",
+                character_id as i32,
+                *slot as i16,
+            )
+            .execute(&mut *connection)
+            .await?;
+        },
+        InventoryChange::Swap {
+            first_slot,
+            second_slot,
+        } => {
+            sqlx::query!(
                     "UPDATE character_items SET slot = case slot when $2 then $3 when $3 then $2 end WHERE character_id = $1 AND slot in ($2, $3)",
                     character_id as i32,
                     *first_slot as i16,
                     *second_slot as i16,
                 )
-                .execute(pool)
+                .execute(&mut *connection)
                 .await?;
-            },
-        }
-        Ok(())
+        },
+    }
+    Ok(())
+}
+
+/// Creates the representation of the given item as it is sent to the client.
+pub(crate) fn item_content_data(item: &Item) -> InventoryItemContentData {
+    match item.type_data {
+        ItemTypeData::Equipment { upgrade_level } => InventoryItemContentData::Equipment {
+            plus_level: upgrade_level,
+            variance: item.variance.unwrap_or_default(),
+            durability: 1,
+            magic: vec![],
+            bindings_1: InventoryItemBindingData::new(1, 0),
+            bindings_2: InventoryItemBindingData::new(2, 0),
+            bindings_3: InventoryItemBindingData::new(3, 0),
+            bindings_4: InventoryItemBindingData::new(4, 0),
+        },
+        ItemTypeData::Consumable { amount } => InventoryItemContentData::Expendable { stack_size: amount },
+        _ => panic!("Missing inventory type representation."),
     }
 }
 
 impl PlayerInventory {
     fn from_db_inventory(items: &[CharacterItem], size: usize) -> Inventory {
-        let item_map = WorldData::items();
         let mut inventory = Inventory::new(size);
+        Self::load_db_items(&mut inventory, items);
+        inventory
+    }
 
+    /// Puts the items loaded from the database into their slots of the given inventory, without
+    /// recording them as changes.
+    pub(crate) fn load_db_items(inventory: &mut Inventory, items: &[CharacterItem]) {
+        let item_map = WorldData::items();
         for item in items {
             let item_def = item_map.find_id(item.item_obj_id as u32).unwrap();
 
@@ -119,8 +155,6 @@ impl PlayerInventory {
                 },
             );
         }
-
-        inventory
     }
 
     fn item_type_data_for(ref_data: &RefItemData, item: &CharacterItem) -> Option<ItemTypeData> {
@@ -146,3 +180,41 @@ impl PlayerInventory {
         PlayerInventory { inventory }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use silkroad_data::common::{RefCommon, RefOrigin};
+    use silkroad_data::itemdata::{RefBiologicalType, RefItemData};
+    use silkroad_definitions::type_id::{ObjectConsumable, ObjectConsumableRecovery, ObjectItem, ObjectType};
+    use silkroad_game_base::{Item, ItemTypeData};
+
+    /// Creates a stackable item for tests, which stacks up to 50 items.
+    pub(crate) fn consumable(amount: u16) -> Item {
+        let reference = Box::leak(Box::new(RefItemData {
+            common: RefCommon {
+                ref_id: 1,
+                id: "TestItem".to_string(),
+                type_id: ObjectType::Item(ObjectItem::Consumable(ObjectConsumable::Recovery(
+                    ObjectConsumableRecovery::HP,
+                )))
+                .type_id(),
+                country: RefOrigin::Chinese,
+                despawn_time: Default::default(),
+            },
+            price: 100,
+            sell_price: 50,
+            keeping_fee: 10,
+            max_stack_size: 50,
+            range: None,
+            required_level: None,
+            biological_type: RefBiologicalType::Both,
+            stats: Default::default(),
+            params: [0, 0, 0, 0],
+        }));
+        Item {
+            reference,
+            variance: None,
+            type_data: ItemTypeData::Consumable { amount },
+        }
+    }
+}
@@ -44,6 +44,13 @@ impl MasteryKnowledge {
         self.masteries.get(&ref_id).copied()
     }
 
+    /// Returns the masteries ordered by their level, starting with the highest.
+    pub(crate) fn highest(&self) -> impl Iterator<Item = u32> {
+        let mut masteries = self.masteries.iter().collect::<Vec<_>>();
+        masteries.sort_by(|(_, a), (_, b)| b.cmp(a));
+        masteries.into_iter().map(|(ref_id, _)| *ref_id)
+    }
+
     pub(crate) fn total(&self) -> u16 {
         self.masteries.values().map(|v| u16::from(*v)).sum()
     }
@@ -1,3 +1,4 @@
+pub(crate) mod buff;
 pub(crate) mod damage;
 pub(crate) mod drop;
 pub(crate) mod exp;
@@ -1,8 +1,10 @@
 use crate::agent::component::{Agent, MovementState};
 use crate::agent::goal::GoalTracker;
 use crate::agent::state::AgentStateQueue;
+use crate::comp::buff::Buffed;
 use crate::comp::damage::DamageReceiver;
 use crate::comp::pos::Position;
+use crate::comp::skill::SkillCooldownTracker;
 use crate::comp::visibility::Visibility;
 use crate::comp::{GameEntity, Health};
 use bevy::prelude::*;
@@ -38,6 +40,8 @@ pub struct MonsterBundle {
     pub(crate) state_queue: AgentStateQueue,
     pub(crate) movement_state: MovementState,
     pub(crate) damage_receiver: DamageReceiver,
+    pub(crate) buffs: Buffed,
+    pub(crate) cooldowns: SkillCooldownTracker,
 }
 
 #[derive(Bundle)]
@@ -1,6 +1,6 @@
-use crate::protocol::AgentClientProtocol;
 use bevy::prelude::*;
 use derive_more::Deref;
+use silkroad_protocol::agent::AgentClientProtocol;
 use skrillax_protocol::__internal::AsPacket;
 use skrillax_server::Connection;
 use std::time::Instant;
@@ -18,4 +18,10 @@ impl Client {
         // for now. The upside is that this means there's a single point where we handle such errors.
         let _ = self.0.send(packet);
     }
+
+    /// Like [Client::send], but the packet may be dropped if the client cannot keep up with
+    /// the packets we're sending.
+    pub fn send_droppable<T: AsPacket + Send + 'static>(&self, packet: T) {
+        let _ = self.0.send_droppable(packet);
+    }
 }
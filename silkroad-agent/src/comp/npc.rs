@@ -1,29 +1,87 @@
 use crate::agent::component::Agent;
 use crate::comp::pos::Position;
 use crate::comp::GameEntity;
+use crate::world::WorldData;
 use bevy::prelude::*;
+use silkroad_data::characterdata::RefCharacterData;
 use silkroad_game_base::{Heading, LocalPosition};
 
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Component)]
 pub(crate) struct NPC;
 
+/// A service an NPC offers to players talking to it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum NpcFunction {
+    Shop,
+    Storage,
+}
+
+/// The services an NPC offers, determined once when the NPC is spawned.
+#[derive(Component, Default)]
+pub(crate) struct NpcFunctions(Vec<NpcFunction>);
+
+impl NpcFunctions {
+    pub(crate) fn for_npc(character: &RefCharacterData) -> Self {
+        let mut functions = Vec::new();
+        if WorldData::shops().contains_key(&character.common.id) {
+            functions.push(NpcFunction::Shop);
+        }
+        if let Some(function) = Self::function_of_role(&character.common.id) {
+            functions.push(function);
+        }
+        NpcFunctions(functions)
+    }
+
+    /// NPC codes are built as `NPC_<AREA>_<ROLE>[_<VARIANT>]`, where the role describes what
+    /// the NPC does. Services that are not backed by their own reference data, like the storage,
+    /// are derived from the role.
+    fn function_of_role(code: &str) -> Option<NpcFunction> {
+        match code.split('_').nth(2)? {
+            "WAREHOUSE" => Some(NpcFunction::Storage),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn has(&self, function: NpcFunction) -> bool {
+        self.0.contains(&function)
+    }
+}
+
 #[derive(Bundle)]
 pub(crate) struct NpcBundle {
     game_entity: GameEntity,
     npc: NPC,
+    functions: NpcFunctions,
     agent: Agent,
     position: Position,
 }
 
 impl NpcBundle {
-    pub fn new(unique_id: u32, ref_id: u32, position: LocalPosition, agent: Agent) -> Self {
+    pub fn new(unique_id: u32, ref_id: u32, position: LocalPosition, functions: NpcFunctions, agent: Agent) -> Self {
         let position = Position::new(position.to_global(), Heading(0.0)); // TODO: need to get NPC rotation
         Self {
             game_entity: GameEntity { unique_id, ref_id },
             npc: NPC,
+            functions,
             agent,
             position,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_function_of_role() {
+        assert_eq!(
+            Some(NpcFunction::Storage),
+            NpcFunctions::function_of_role("NPC_CH_WAREHOUSE_M")
+        );
+        assert_eq!(None, NpcFunctions::function_of_role("NPC_CH_SMITH"));
+        assert_eq!(None, NpcFunctions::function_of_role("NPC_CH_SMITH_WAREHOUSE"));
+        assert_eq!(None, NpcFunctions::function_of_role("NPC"));
+    }
+}
@@ -1,13 +1,14 @@
 use crate::agent::component::{Agent, MovementState};
 use crate::agent::goal::GoalTracker;
 use crate::agent::state::AgentStateQueue;
+use crate::comp::buff::Buffed;
 use crate::comp::damage::DamageReceiver;
 use crate::comp::exp::{Experienced, Leveled, SP};
 use crate::comp::gold::GoldPouch;
 use crate::comp::inventory::PlayerInventory;
 use crate::comp::mastery::MasteryKnowledge;
 use crate::comp::pos::Position;
-use crate::comp::skill::{Hotbar, SkillBook};
+use crate::comp::skill::{Hotbar, SkillBook, SkillCooldownTracker};
 use crate::comp::visibility::Visibility;
 use crate::comp::{GameEntity, Health, Mana};
 use crate::db::character::CharacterData;
@@ -66,11 +67,6 @@ impl Player {
     }
 }
 
-#[derive(Component)]
-pub(crate) struct Buffed {
-    // pub buffs: Vec<Buff>
-}
-
 #[derive(Bundle)]
 pub(crate) struct PlayerBundle {
     player: Player,
@@ -95,6 +91,7 @@ pub(crate) struct PlayerBundle {
     stat_points: StatPoints,
     masteries: MasteryKnowledge,
     skills: SkillBook,
+    cooldowns: SkillCooldownTracker,
     race: CharacterRace,
     hotbar: Hotbar,
 }
@@ -127,7 +124,7 @@ impl PlayerBundle {
             inventory,
             agent,
             pos,
-            buff: Buffed {},
+            buff: Buffed::default(),
             visibility,
             gold,
             input: Default::default(),
@@ -144,6 +141,7 @@ impl PlayerBundle {
             stat_points,
             masteries: master_knowledge,
             skills,
+            cooldowns: SkillCooldownTracker::default(),
             race,
             hotbar,
         }
@@ -1,12 +1,14 @@
 use crate::persistence::ApplyToDatabase;
 use axum::async_trait;
 use bevy::prelude::*;
+use derive_more::{Deref, DerefMut};
 use silkroad_data::skilldata::RefSkillData;
-use silkroad_game_base::{Change, ChangeTracked, MergeResult};
+use silkroad_game_base::{Change, ChangeTracked, MergeResult, SkillCooldowns};
 use silkroad_protocol::skill::HotbarItem;
 use sqlx::{PgPool, QueryBuilder};
 use std::collections::HashMap;
 use std::mem;
+use std::time::Duration;
 
 #[derive(Component)]
 pub(crate) struct SkillBook {
@@ -36,6 +38,34 @@ impl SkillBook {
     }
 }
 
+/// The cooldowns of the skills an entity has recently used.
+#[derive(Component, Default, Deref, DerefMut)]
+pub(crate) struct SkillCooldownTracker(SkillCooldowns);
+
+/// The cooldowns of characters that have left the game, such that they still apply once the
+/// character joins again. The cooldowns are stored together with the time the character left.
+#[derive(Resource, Default)]
+pub(crate) struct RememberedCooldowns(HashMap<u32, (Duration, SkillCooldowns)>);
+
+impl RememberedCooldowns {
+    pub(crate) fn remember(&mut self, character_id: u32, cooldowns: &SkillCooldowns, now: Duration) {
+        self.0
+            .retain(|_, (left, cooldowns)| cooldowns.longest_remaining() > now.saturating_sub(*left));
+        if !cooldowns.is_empty() {
+            self.0.insert(character_id, (now, cooldowns.clone()));
+        }
+    }
+
+    /// Takes the cooldowns of the character, reduced by the time the character has been gone.
+    pub(crate) fn take(&mut self, character_id: u32, now: Duration) -> SkillCooldowns {
+        let Some((left, mut cooldowns)) = self.0.remove(&character_id) else {
+            return SkillCooldowns::default();
+        };
+        cooldowns.tick(now.saturating_sub(left));
+        cooldowns
+    }
+}
+
 pub(crate) struct LearnedSkill(u32, u8);
 
 impl Change for LearnedSkill {
@@ -185,3 +215,24 @@ impl ApplyToDatabase for HotbarChange {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_remembered_cooldowns() {
+        let mut cooldowns = SkillCooldowns::default();
+        cooldowns.start(1, 10, Duration::from_secs(10), Duration::ZERO);
+        let mut remembered = RememberedCooldowns::default();
+        remembered.remember(1, &cooldowns, Duration::from_secs(100));
+
+        let restored = remembered.take(1, Duration::from_secs(104));
+        assert_eq!(Duration::from_secs(6), restored.longest_remaining());
+        assert!(remembered.take(1, Duration::from_secs(104)).is_empty());
+
+        remembered.remember(1, &cooldowns, Duration::from_secs(100));
+        remembered.remember(2, &SkillCooldowns::default(), Duration::from_secs(120));
+        assert!(remembered.0.is_empty());
+    }
+}
@@ -3,6 +3,7 @@ use config::{ConfigError, FileFormat};
 use log::LevelFilter;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
+use skrillax_server::{ListenAddresses, NetworkConfig};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{ConnectOptions, PgPool};
 use std::ops::RangeInclusive;
@@ -102,13 +103,14 @@ pub(crate) struct DropConfig {
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct GameServerConfig {
     pub(crate) listen_port: u16,
-    pub(crate) listen_address: String,
+    pub(crate) listen_address: ListenAddresses,
     pub(crate) external_address: Option<String>,
     pub(crate) server_id: u16,
     pub(crate) rpc_address: String,
     pub(crate) rpc_port: u16,
     pub(crate) max_player_count: u16,
     pub(crate) database: DbOptions,
+    pub(crate) network: NetworkConfig,
     pub(crate) game: GameConfig,
     pub(crate) region: String,
     pub(crate) name: String,
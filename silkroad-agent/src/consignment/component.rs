@@ -0,0 +1,613 @@
+use crate::comp::inventory::{apply_inventory_change, item_content_data};
+use crate::db::consignment::ConsignmentData;
+use crate::world::WorldData;
+use bevy::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use silkroad_data::DataEntry;
+use silkroad_definitions::type_id::{ObjectItem, ObjectType};
+use silkroad_game_base::{InventoryChange, Item, ItemTypeData};
+use silkroad_protocol::consignment::{
+    ConsignmentCategory, ConsignmentErrorCode, ConsignmentItem, ConsignmentSearchEntry,
+};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::oneshot::Receiver;
+use tokio::time::sleep;
+use tracing::{error, warn};
+
+pub(crate) const MAX_LISTINGS: usize = 10;
+pub(crate) const MAX_DURATION_DAYS: u8 = 7;
+pub(crate) const SEARCH_PAGE_SIZE: usize = 10;
+
+/// The highest price an item can be listed for. This keeps the price, as well as the gold paid
+/// out to the seller, within what the database can store.
+pub(crate) const MAX_PRICE: u64 = i64::MAX as u64 / 2;
+
+/// How often persisting a consignment operation is attempted before giving up on it.
+const PERSIST_ATTEMPTS: u32 = 3;
+const PERSIST_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The share of the price, in percent, which needs to be deposited for every day an item is
+/// listed. The deposit is only returned if the item gets sold.
+const DEPOSIT_PERCENT_PER_DAY: u64 = 1;
+
+/// The share of the price, in percent, which is kept as a fee when an item is sold.
+const FEE_PERCENT: u64 = 3;
+
+pub(crate) fn deposit_for(price: u64, days: u8) -> u64 {
+    (price / 100 * DEPOSIT_PERCENT_PER_DAY * u64::from(days)).max(1)
+}
+
+pub(crate) fn fee_for(price: u64) -> u64 {
+    price / 100 * FEE_PERCENT
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum ListingStatus {
+    Listed,
+    Sold,
+    Expired,
+}
+
+impl ListingStatus {
+    fn from_db(status: i16) -> Option<Self> {
+        match status {
+            0 => Some(ListingStatus::Listed),
+            1 => Some(ListingStatus::Sold),
+            2 => Some(ListingStatus::Expired),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_db(&self) -> i16 {
+        match self {
+            ListingStatus::Listed => 0,
+            ListingStatus::Sold => 1,
+            ListingStatus::Expired => 2,
+        }
+    }
+}
+
+/// An item registered for consignment. The item is no longer part of the inventory of the seller
+/// while it is listed.
+#[derive(Clone)]
+pub(crate) struct Listing {
+    pub(crate) id: u32,
+    pub(crate) seller_id: u32,
+    pub(crate) seller: String,
+    pub(crate) item: Item,
+    pub(crate) price: u64,
+    pub(crate) deposit: u64,
+    pub(crate) fee: u64,
+    pub(crate) status: ListingStatus,
+    pub(crate) expires_at: DateTime<Utc>,
+}
+
+impl Listing {
+    fn is_buyable(&self, now: DateTime<Utc>) -> bool {
+        self.status == ListingStatus::Listed && self.expires_at > now
+    }
+
+    fn matches(&self, category: &ConsignmentCategory) -> bool {
+        let type_id = self.item.reference.common.type_id;
+        [
+            (category.type_id2, type_id.1),
+            (category.type_id3, type_id.2),
+            (category.type_id4, type_id.3),
+        ]
+        .iter()
+        .all(|(wanted, actual)| *wanted == 0 || wanted == actual)
+    }
+
+    /// The gold the seller receives for the sold item, which includes the returned deposit.
+    pub(crate) fn proceeds(&self) -> u64 {
+        self.price - self.fee + self.deposit
+    }
+
+    pub(crate) fn as_protocol(&self) -> ConsignmentItem {
+        ConsignmentItem::new(
+            self.id,
+            self.status.as_db() as u8,
+            self.item.reference.ref_id(),
+            u32::from(self.item.stack_size()),
+            self.price,
+            self.deposit,
+            self.fee,
+            self.expires_at.timestamp() as u32,
+        )
+    }
+
+    pub(crate) fn as_search_entry(&self) -> ConsignmentSearchEntry {
+        ConsignmentSearchEntry {
+            id: self.id,
+            seller: self.seller.clone(),
+            ref_item_id: self.item.reference.ref_id(),
+            content_data: item_content_data(&self.item),
+            price: self.price,
+            end_date: self.expires_at.timestamp() as u32,
+        }
+    }
+}
+
+/// All items registered for consignment. Listings are kept in memory such that purchases are
+/// decided within a single tick, in the order they arrive: once the first buyer got the item, any
+/// other attempt to buy it fails.
+#[derive(Resource, Default)]
+pub(crate) struct Consignments {
+    listings: BTreeMap<u32, Listing>,
+    next_id: u32,
+    purchases: Vec<PendingPurchase>,
+}
+
+impl Consignments {
+    pub(crate) fn from_db(data: Vec<ConsignmentData>) -> Self {
+        let mut consignments = Consignments::default();
+        for entry in data {
+            let id = entry.id as u32;
+            consignments.next_id = consignments.next_id.max(id + 1);
+            let (Some(item), Some(status)) = (item_from_db(&entry), ListingStatus::from_db(entry.status)) else {
+                warn!(id, "Could not load consignment listing");
+                continue;
+            };
+
+            consignments.listings.insert(
+                id,
+                Listing {
+                    id,
+                    seller_id: entry.character_id as u32,
+                    seller: entry.seller,
+                    item,
+                    price: entry.price as u64,
+                    deposit: entry.deposit as u64,
+                    fee: entry.fee as u64,
+                    status,
+                    expires_at: entry.expires_at,
+                },
+            );
+        }
+        consignments
+    }
+
+    pub(crate) fn get(&self, id: u32) -> Option<&Listing> {
+        self.listings.get(&id)
+    }
+
+    pub(crate) fn listings_of(&self, seller_id: u32) -> impl Iterator<Item = &Listing> {
+        self.listings
+            .values()
+            .filter(move |listing| listing.seller_id == seller_id)
+    }
+
+    /// Checks whether a new listing with the given terms can be registered by the seller, and
+    /// returns the deposit it would require.
+    pub(crate) fn check_registration(&self, seller_id: u32, price: u64, days: u8) -> Result<u64, ConsignmentErrorCode> {
+        if price == 0 || price > MAX_PRICE {
+            return Err(ConsignmentErrorCode::InvalidPrice);
+        }
+
+        if days == 0 || days > MAX_DURATION_DAYS {
+            return Err(ConsignmentErrorCode::InvalidDuration);
+        }
+
+        if self.listings_of(seller_id).count() >= MAX_LISTINGS {
+            return Err(ConsignmentErrorCode::TooManyItems);
+        }
+
+        Ok(deposit_for(price, days))
+    }
+
+    /// Registers the item, which must already have been removed from the inventory of the seller.
+    pub(crate) fn register(
+        &mut self,
+        seller_id: u32,
+        seller: String,
+        item: Item,
+        price: u64,
+        days: u8,
+        now: DateTime<Utc>,
+    ) -> &Listing {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.listings.entry(id).or_insert(Listing {
+            id,
+            seller_id,
+            seller,
+            item,
+            price,
+            deposit: deposit_for(price, days),
+            fee: fee_for(price),
+            status: ListingStatus::Listed,
+            expires_at: now + Duration::days(i64::from(days)),
+        })
+    }
+
+    /// Returns a page of items of the given category that can still be bought, as well as the
+    /// total number of pages.
+    pub(crate) fn search(
+        &self,
+        category: &ConsignmentCategory,
+        page: u8,
+        now: DateTime<Utc>,
+    ) -> (Vec<&Listing>, usize) {
+        let matching: Vec<&Listing> = self
+            .listings
+            .values()
+            .filter(|listing| listing.is_buyable(now) && listing.matches(category))
+            .collect();
+        let pages = matching.len().div_ceil(SEARCH_PAGE_SIZE);
+        let entries = matching
+            .into_iter()
+            .skip(usize::from(page) * SEARCH_PAGE_SIZE)
+            .take(SEARCH_PAGE_SIZE)
+            .collect();
+        (entries, pages)
+    }
+
+    /// Checks that the listing can be bought by the given character.
+    pub(crate) fn check_purchase(
+        &self,
+        id: u32,
+        buyer_id: u32,
+        now: DateTime<Utc>,
+    ) -> Result<&Listing, ConsignmentErrorCode> {
+        let listing = self
+            .listings
+            .get(&id)
+            .filter(|listing| listing.is_buyable(now))
+            .ok_or(ConsignmentErrorCode::NotAvailable)?;
+        if listing.seller_id == buyer_id {
+            return Err(ConsignmentErrorCode::OwnItem);
+        }
+        Ok(listing)
+    }
+
+    /// Marks the listing as sold, such that no one else can buy it anymore. The seller collects
+    /// the gold once they settle their listings.
+    pub(crate) fn mark_sold(&mut self, id: u32) {
+        if let Some(listing) = self.listings.get_mut(&id) {
+            listing.status = ListingStatus::Sold;
+        }
+    }
+
+    /// Puts a listing back up for sale, after its sale could not be persisted.
+    pub(crate) fn cancel_sale(&mut self, id: u32) {
+        if let Some(listing) = self
+            .listings
+            .get_mut(&id)
+            .filter(|listing| listing.status == ListingStatus::Sold)
+        {
+            listing.status = ListingStatus::Listed;
+        }
+    }
+
+    /// Keeps track of the purchase until it has been persisted. Until then, the seller cannot
+    /// settle the listing.
+    pub(crate) fn start_purchase(&mut self, purchase: PendingPurchase) {
+        self.purchases.push(purchase);
+    }
+
+    pub(crate) fn is_purchasing(&self, id: u32) -> bool {
+        self.purchases.iter().any(|purchase| purchase.listing == id)
+    }
+
+    /// Takes all purchases which have finished, together with whether they could be persisted.
+    pub(crate) fn finished_purchases(&mut self) -> Vec<(PendingPurchase, bool)> {
+        let mut finished = Vec::new();
+        let mut pending = Vec::with_capacity(self.purchases.len());
+        for mut purchase in self.purchases.drain(..) {
+            let persisted = match purchase.task.try_recv() {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    error!(error = %e, id = purchase.buyer_id, "Could not persist purchase");
+                    false
+                },
+                Err(TryRecvError::Empty) => {
+                    pending.push(purchase);
+                    continue;
+                },
+                Err(TryRecvError::Closed) => {
+                    error!(id = purchase.buyer_id, "Purchase got lost");
+                    false
+                },
+            };
+            finished.push((purchase, persisted));
+        }
+        self.purchases = pending;
+        finished
+    }
+
+    /// Checks that the seller can take back the listing, which is the case as long as it hasn't
+    /// been sold.
+    pub(crate) fn check_cancel(&self, id: u32, seller_id: u32) -> Result<&Listing, ConsignmentErrorCode> {
+        self.listings
+            .get(&id)
+            .filter(|listing| listing.seller_id == seller_id && listing.status != ListingStatus::Sold)
+            .ok_or(ConsignmentErrorCode::NotAvailable)
+    }
+
+    pub(crate) fn remove(&mut self, id: u32) -> Option<Listing> {
+        self.listings.remove(&id)
+    }
+
+    /// Marks all listings which ran out of time as expired and returns their ids.
+    pub(crate) fn expire(&mut self, now: DateTime<Utc>) -> Vec<u32> {
+        self.listings
+            .values_mut()
+            .filter(|listing| listing.status == ListingStatus::Listed && listing.expires_at <= now)
+            .map(|listing| {
+                listing.status = ListingStatus::Expired;
+                listing.id
+            })
+            .collect()
+    }
+}
+
+fn item_from_db(data: &ConsignmentData) -> Option<Item> {
+    let reference = WorldData::items().find_id(data.item_obj_id as u32)?;
+    let type_data = match ObjectType::from_type_id(&reference.common.type_id)? {
+        ObjectType::Item(ObjectItem::Equippable(_)) => ItemTypeData::Equipment {
+            upgrade_level: data.upgrade_level as u8,
+        },
+        // Pets cannot be listed, as they cannot be shown to buyers yet.
+        ObjectType::Item(ObjectItem::Pet(_)) => return None,
+        ObjectType::Item(_) => ItemTypeData::Consumable {
+            amount: data.amount as u16,
+        },
+        _ => return None,
+    };
+    Some(Item {
+        reference,
+        variance: data.variance.map(|variance| variance as u64),
+        type_data,
+    })
+}
+
+/// The change to a listing that needs to be persisted together with the changes of the player.
+#[derive(Clone)]
+pub(crate) enum ListingChange {
+    Registered(Listing),
+    Sold { id: u32, buyer_id: u32 },
+    Removed(Vec<u32>),
+}
+
+/// A purchase that is being written to the database. The buyer already got the item and paid for
+/// it, which is undone if the purchase cannot be persisted. The buyer is only told about the
+/// purchase once it has been persisted.
+pub(crate) struct PendingPurchase {
+    pub(crate) listing: u32,
+    pub(crate) buyer_id: u32,
+    pub(crate) buyer: String,
+    pub(crate) slot: u8,
+    pub(crate) ref_id: u32,
+    pub(crate) price: u64,
+    pub(crate) task: Receiver<Result<(), sqlx::Error>>,
+}
+
+/// Persists a consignment operation of a player. As the operation has already been applied in
+/// memory, failures are retried a few times before giving up.
+pub(crate) async fn persist_consignment(
+    character_id: u32,
+    changes: Vec<InventoryChange>,
+    gold: u64,
+    listing: ListingChange,
+    pool: &PgPool,
+) -> Result<(), sqlx::Error> {
+    let mut attempt = 1;
+    loop {
+        match apply_consignment(character_id, &changes, gold, &listing, pool).await {
+            // A purchase of a listing which isn't listed anymore won't succeed on a later attempt.
+            Err(e) if attempt < PERSIST_ATTEMPTS && !matches!(e, sqlx::Error::RowNotFound) => {
+                warn!(character_id, attempt, error = %e, "Could not persist consignment operation, retrying");
+                sleep(PERSIST_RETRY_DELAY).await;
+                attempt += 1;
+            },
+            result => return result,
+        }
+    }
+}
+
+/// Applies a consignment operation of a player in a single transaction, such that items and gold
+/// can neither be lost nor duplicated. A purchase only succeeds if the listing was still listed,
+/// otherwise the whole transaction is rolled back.
+async fn apply_consignment(
+    character_id: u32,
+    changes: &[InventoryChange],
+    gold: u64,
+    listing: &ListingChange,
+    pool: &PgPool,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    match listing {
+        ListingChange::Registered(listing) => {
+            sqlx::query("INSERT INTO consignment_items(id, character_id, item_obj_id, upgrade_level, variance, amount, price, deposit, fee, status, expires_at) VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)")
+                .bind(listing.id as i32)
+                .bind(listing.seller_id as i32)
+                .bind(listing.item.reference.ref_id() as i32)
+                .bind(i16::from(listing.item.upgrade_level()))
+                .bind(listing.item.variance.map(|variance| variance as i64))
+                .bind(listing.item.stack_size() as i16)
+                .bind(listing.price as i64)
+                .bind(listing.deposit as i64)
+                .bind(listing.fee as i64)
+                .bind(listing.status.as_db())
+                .bind(listing.expires_at)
+                .execute(&mut *transaction)
+                .await?;
+        },
+        ListingChange::Sold { id, buyer_id } => {
+            let result =
+                sqlx::query("UPDATE consignment_items SET status = $1, buyer_id = $2 WHERE id = $3 AND status = $4")
+                    .bind(ListingStatus::Sold.as_db())
+                    .bind(*buyer_id as i32)
+                    .bind(*id as i32)
+                    .bind(ListingStatus::Listed.as_db())
+                    .execute(&mut *transaction)
+                    .await?;
+            if result.rows_affected() != 1 {
+                return Err(sqlx::Error::RowNotFound);
+            }
+        },
+        ListingChange::Removed(ids) => {
+            let ids: Vec<i32> = ids.iter().map(|id| *id as i32).collect();
+            sqlx::query("DELETE FROM consignment_items WHERE id = ANY($1) AND character_id = $2")
+                .bind(ids)
+                .bind(character_id as i32)
+                .execute(&mut *transaction)
+                .await?;
+        },
+    }
+
+    for change in changes.iter() {
+        apply_inventory_change(change, character_id, &mut transaction).await?;
+    }
+    sqlx::query("UPDATE characters SET gold = $1 WHERE id = $2")
+        .bind(gold as i64)
+        .bind(character_id as i32)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn test_fees() {
+        assert_eq!(30, deposit_for(1000, 3));
+        assert_eq!(1, deposit_for(10, 1));
+        assert_eq!(30, fee_for(1000));
+
+        let mut consignments = Consignments::default();
+        let listing = consignments.register(1, "Seller".to_string(), item(5), 1000, 3, now());
+        assert_eq!(1000 - 30 + 30, listing.proceeds());
+        assert_eq!(now() + Duration::days(3), listing.expires_at);
+    }
+
+    #[test]
+    fn test_registration_limits() {
+        let mut consignments = Consignments::default();
+        assert_eq!(
+            Err(ConsignmentErrorCode::InvalidPrice),
+            consignments.check_registration(1, 0, 1)
+        );
+        assert_eq!(
+            Err(ConsignmentErrorCode::InvalidPrice),
+            consignments.check_registration(1, MAX_PRICE + 1, 1)
+        );
+        assert_eq!(
+            Err(ConsignmentErrorCode::InvalidDuration),
+            consignments.check_registration(1, 100, 0)
+        );
+        assert_eq!(
+            Err(ConsignmentErrorCode::InvalidDuration),
+            consignments.check_registration(1, 100, MAX_DURATION_DAYS + 1)
+        );
+
+        for _ in 0..MAX_LISTINGS {
+            consignments.register(1, "Seller".to_string(), item(1), 100, 1, now());
+        }
+        assert_eq!(
+            Err(ConsignmentErrorCode::TooManyItems),
+            consignments.check_registration(1, 100, 1)
+        );
+        assert!(consignments.check_registration(2, 100, 1).is_ok());
+    }
+
+    #[test]
+    fn test_purchase_only_once() {
+        let mut consignments = Consignments::default();
+        let id = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+        assert_eq!(
+            Err(ConsignmentErrorCode::OwnItem),
+            consignments.check_purchase(id, 1, now()).map(|listing| listing.id)
+        );
+        assert!(consignments.check_purchase(id, 2, now()).is_ok());
+
+        consignments.mark_sold(id);
+        assert_eq!(
+            Err(ConsignmentErrorCode::NotAvailable),
+            consignments.check_purchase(id, 3, now()).map(|listing| listing.id)
+        );
+        assert!(consignments.check_cancel(id, 1).is_err());
+    }
+
+    #[test]
+    fn test_failed_purchase() {
+        let mut consignments = Consignments::default();
+        let id = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+        consignments.mark_sold(id);
+        let (sender, task) = tokio::sync::oneshot::channel();
+        consignments.start_purchase(PendingPurchase {
+            listing: id,
+            buyer_id: 2,
+            buyer: "Buyer".to_string(),
+            slot: 13,
+            ref_id: 1,
+            price: 1000,
+            task,
+        });
+        assert!(consignments.is_purchasing(id));
+        assert!(consignments.finished_purchases().is_empty());
+
+        sender.send(Err(sqlx::Error::RowNotFound)).unwrap();
+        let finished = consignments.finished_purchases();
+        assert_eq!(1, finished.len());
+        assert!(!finished[0].1);
+        assert!(!consignments.is_purchasing(id));
+
+        consignments.cancel_sale(id);
+        assert!(consignments.check_purchase(id, 3, now()).is_ok());
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut consignments = Consignments::default();
+        let id = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+        assert!(consignments.expire(now()).is_empty());
+
+        let later = now() + Duration::days(1);
+        assert!(consignments.check_purchase(id, 2, later).is_err());
+        assert_eq!(vec![id], consignments.expire(later));
+        assert_eq!(ListingStatus::Expired, consignments.get(id).unwrap().status);
+        assert!(consignments.check_cancel(id, 1).is_ok());
+        assert!(consignments.check_cancel(id, 2).is_err());
+    }
+
+    #[test]
+    fn test_search() {
+        let mut consignments = Consignments::default();
+        for _ in 0..(SEARCH_PAGE_SIZE + 1) {
+            consignments.register(1, "Seller".to_string(), item(1), 100, 1, now());
+        }
+
+        let type_id = item(1).reference.common.type_id;
+        let category = ConsignmentCategory {
+            type_id2: type_id.1,
+            type_id3: 0,
+            type_id4: 0,
+        };
+        let (entries, pages) = consignments.search(&category, 1, now());
+        assert_eq!(2, pages);
+        assert_eq!(1, entries.len());
+
+        let other = ConsignmentCategory {
+            type_id2: type_id.1 + 1,
+            type_id3: 0,
+            type_id4: 0,
+        };
+        assert_eq!(0, consignments.search(&other, 0, now()).1);
+    }
+}
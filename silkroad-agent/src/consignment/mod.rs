@@ -0,0 +1,36 @@
+mod component;
+mod system;
+
+use crate::consignment::system::{expire_listings, finish_purchases, handle_consignment_input};
+use crate::db::consignment::ConsignmentData;
+use crate::ext::DbPool;
+use crate::tasks::TaskCreator;
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+pub(crate) use component::*;
+use sqlx::PgPool;
+use std::time::Duration;
+
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(crate) struct ConsignmentPlugin;
+
+impl Plugin for ConsignmentPlugin {
+    fn build(&self, app: &mut App) {
+        // The listings are loaded before the app starts, such that they are available from the
+        // very first tick on.
+        let world = app.world();
+        let data = world
+            .resource::<TaskCreator>()
+            .block_on(ConsignmentData::fetch_all(PgPool::clone(world.resource::<DbPool>())))
+            .expect("Should be able to load consignments");
+        app.insert_resource(Consignments::from_db(data)).add_systems(
+            Update,
+            (
+                handle_consignment_input,
+                finish_purchases,
+                expire_listings.run_if(on_timer(EXPIRY_CHECK_INTERVAL)),
+            ),
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use crate::comp::gold::GoldPouch;
+use crate::comp::inventory::{item_content_data, PlayerInventory};
+use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::consignment::component::{persist_consignment, Consignments, ListingChange, ListingStatus, PendingPurchase};
+use crate::db::consignment::ConsignmentData;
+use crate::input::PlayerInput;
+use crate::persistence::{PersistenceCollection, PersistenceQueue};
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use chrono::{DateTime, Utc};
+use silkroad_game_base::{ChangeTracked, Inventory, ItemTypeData};
+use silkroad_protocol::consignment::{
+    ConsignmentBuyResponse, ConsignmentCancelResponse, ConsignmentClientProtocol, ConsignmentErrorCode,
+    ConsignmentOperationResult, ConsignmentRegister, ConsignmentRegisterResponse, ConsignmentResponse,
+    ConsignmentResult, ConsignmentSearchResponse, ConsignmentSearchResult, ConsignmentSettleResponse,
+};
+use silkroad_protocol::inventory::InventoryOperationResult;
+use tracing::error;
+
+pub(crate) fn handle_consignment_input(
+    mut query: Query<(
+        &Client,
+        &Player,
+        &PlayerInput,
+        &mut PlayerInventory,
+        &mut GoldPouch,
+        Option<&mut PersistenceCollection<PlayerInventory>>,
+    )>,
+    mut consignments: ResMut<Consignments>,
+    queue: Res<PersistenceQueue>,
+) {
+    for (client, player, input, mut inventory, mut gold, mut collection) in query.iter_mut() {
+        let character_id = player.character.id;
+        let now = Utc::now();
+        for request in input.consignment.iter() {
+            let mut purchased = None;
+            let change = match request {
+                ConsignmentClientProtocol::ConsignmentList(_) => {
+                    let items = consignments
+                        .listings_of(character_id)
+                        .map(|listing| listing.as_protocol())
+                        .collect();
+                    client.send(ConsignmentResponse::new(ConsignmentResult::success(items)));
+                    continue;
+                },
+                ConsignmentClientProtocol::ConsignmentSearch(search) => {
+                    let (listings, pages) = consignments.search(&search.category, search.page, now);
+                    client.send(ConsignmentSearchResponse::new(ConsignmentSearchResult::Success {
+                        page: search.page,
+                        pages: pages.min(usize::from(u8::MAX)) as u8,
+                        entries: listings.iter().map(|listing| listing.as_search_entry()).collect(),
+                    }));
+                    continue;
+                },
+                ConsignmentClientProtocol::ConsignmentRegister(register) => {
+                    match register_item(register, player, &mut inventory, &mut gold, &mut consignments, now) {
+                        Ok(change) => {
+                            client.send(ConsignmentRegisterResponse::new(ConsignmentOperationResult::Success));
+                            change
+                        },
+                        Err(code) => {
+                            client.send(ConsignmentRegisterResponse::new(ConsignmentOperationResult::error(
+                                code,
+                            )));
+                            continue;
+                        },
+                    }
+                },
+                ConsignmentClientProtocol::ConsignmentCancel(cancel) => {
+                    match cancel_listing(cancel.id, character_id, &mut inventory, &mut consignments) {
+                        Ok(slot) => {
+                            client.send(ConsignmentCancelResponse::new(ConsignmentOperationResult::Success));
+                            notify_received_item(client, &inventory, slot);
+                            ListingChange::Removed(vec![cancel.id])
+                        },
+                        Err(code) => {
+                            client.send(ConsignmentCancelResponse::new(ConsignmentOperationResult::error(code)));
+                            continue;
+                        },
+                    }
+                },
+                ConsignmentClientProtocol::ConsignmentSettle(_) => {
+                    let (settled, slots, complete) =
+                        settle_listings(character_id, &mut inventory, &mut gold, &mut consignments);
+                    let result = if complete {
+                        ConsignmentOperationResult::Success
+                    } else {
+                        ConsignmentOperationResult::error(ConsignmentErrorCode::InventoryFull)
+                    };
+                    client.send(ConsignmentSettleResponse::new(result));
+                    for slot in slots {
+                        notify_received_item(client, &inventory, slot);
+                    }
+                    if settled.is_empty() {
+                        continue;
+                    }
+                    ListingChange::Removed(settled)
+                },
+                ConsignmentClientProtocol::ConsignmentBuy(buy) => {
+                    match purchase(buy.id, character_id, &mut inventory, &mut gold, &mut consignments, now) {
+                        // The buyer is only told about the purchase once it has been persisted.
+                        Ok(slot) => {
+                            let listing = consignments
+                                .get(buy.id)
+                                .expect("Listing should be kept after being sold");
+                            purchased = Some((buy.id, slot, listing.item.reference.ref_id(), listing.price));
+                            ListingChange::Sold {
+                                id: buy.id,
+                                buyer_id: character_id,
+                            }
+                        },
+                        Err(code) => {
+                            client.send(ConsignmentBuyResponse::new(ConsignmentOperationResult::error(code)));
+                            continue;
+                        },
+                    }
+                },
+            };
+
+            // Changes which have been collected before, but not yet persisted, need to be
+            // included as well, otherwise they would be applied in the wrong order.
+            let mut changes = collection
+                .as_mut()
+                .map(|collection| collection.take_changes())
+                .unwrap_or_default();
+            changes.append(&mut inventory.changes());
+            let gold_amount = gold.amount();
+            // A purchase also changes the listing of the seller, so it has to wait for the
+            // registration of the listing to be persisted.
+            let mut characters = vec![character_id];
+            if let ListingChange::Sold { id, .. } = &change {
+                characters.extend(consignments.get(*id).map(|listing| listing.seller_id));
+            }
+            let Some((id, slot, ref_id, price)) = purchased else {
+                queue.spawn(&characters, move |pool| async move {
+                    if let Err(e) = persist_consignment(character_id, changes, gold_amount, change, &pool).await {
+                        error!(character_id, error = %e, "Could not persist consignment operation");
+                    }
+                });
+                continue;
+            };
+
+            let task = queue.create_task(&characters, move |pool| async move {
+                persist_consignment(character_id, changes, gold_amount, change, &pool).await
+            });
+            consignments.start_purchase(PendingPurchase {
+                listing: id,
+                buyer_id: character_id,
+                buyer: player.character.name.clone(),
+                slot,
+                ref_id,
+                price,
+                task,
+            });
+        }
+    }
+}
+
+/// Tells buyers about their purchases once they have been persisted, or undoes the purchases which
+/// could not be persisted.
+pub(crate) fn finish_purchases(
+    mut players: Query<(&Client, &Player, &mut PlayerInventory, &mut GoldPouch)>,
+    lookup: Res<EntityLookup>,
+    mut consignments: ResMut<Consignments>,
+    queue: Res<PersistenceQueue>,
+) {
+    for (purchase, persisted) in consignments.finished_purchases() {
+        // The buyer may have left, or even come back as a new entity, in the meantime.
+        let buyer = lookup
+            .get_entity_for_name(&purchase.buyer)
+            .and_then(|entity| players.get_mut(entity).ok())
+            .filter(|(_, player, ..)| player.character.id == purchase.buyer_id);
+
+        if persisted {
+            if let Some((client, _, inventory, _)) = buyer {
+                client.send(ConsignmentBuyResponse::new(ConsignmentOperationResult::Success));
+                notify_received_item(client, &inventory, purchase.slot);
+            }
+            continue;
+        }
+
+        consignments.cancel_sale(purchase.listing);
+        match buyer {
+            Some((client, _, mut inventory, mut gold)) => {
+                let still_there = inventory
+                    .get_item_at(purchase.slot)
+                    .is_some_and(|item| item.reference.ref_id() == purchase.ref_id);
+                if still_there {
+                    inventory.take_item_at(purchase.slot);
+                }
+                gold.gain(purchase.price);
+                client.send(ConsignmentBuyResponse::new(ConsignmentOperationResult::error(
+                    ConsignmentErrorCode::NotAvailable,
+                )));
+            },
+            None => refund_offline(purchase.buyer_id, purchase.price, &queue),
+        }
+    }
+}
+
+/// Gives the gold for a purchase which could not be persisted back to a buyer that is no longer
+/// online. By then, the gold they had left after the purchase has already been written.
+fn refund_offline(character_id: u32, price: u64, queue: &PersistenceQueue) {
+    queue.spawn(&[character_id], move |pool| async move {
+        let result = sqlx::query("UPDATE characters SET gold = gold + $1 WHERE id = $2")
+            .bind(price as i64)
+            .bind(character_id as i32)
+            .execute(&pool)
+            .await;
+        if let Err(e) = result {
+            error!(error = %e, id = character_id, "Could not refund consignment purchase");
+        }
+    });
+}
+
+fn notify_received_item(client: &Client, inventory: &Inventory, slot: u8) {
+    if let Some(item) = inventory.get_item_at(slot) {
+        client.send(InventoryOperationResult::success_gain_item(
+            slot,
+            item.reference.ref_id(),
+            item_content_data(item),
+        ));
+    }
+}
+
+/// Takes the item out of the inventory and lists it, after taking the deposit.
+fn register_item(
+    request: &ConsignmentRegister,
+    player: &Player,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+    consignments: &mut Consignments,
+    now: DateTime<Utc>,
+) -> Result<ListingChange, ConsignmentErrorCode> {
+    let deposit = consignments.check_registration(player.character.id, request.price, request.duration)?;
+    // Pets cannot be listed, as they cannot be shown to buyers yet.
+    let available = !Inventory::is_equipment_slot(request.slot)
+        && inventory.get_item_at(request.slot).is_some_and(|item| {
+            !matches!(item.type_data, ItemTypeData::COS) && request.amount > 0 && request.amount <= item.stack_size()
+        });
+    if !available {
+        return Err(ConsignmentErrorCode::InvalidItem);
+    }
+
+    if deposit > gold.amount() {
+        return Err(ConsignmentErrorCode::NotEnoughGold);
+    }
+
+    let item = inventory
+        .split_item_at(request.slot, request.amount)
+        .map_err(|_| ConsignmentErrorCode::InvalidItem)?;
+    gold.spend(deposit);
+    let listing = consignments.register(
+        player.character.id,
+        player.character.name.clone(),
+        item,
+        request.price,
+        request.duration,
+        now,
+    );
+    Ok(ListingChange::Registered(listing.clone()))
+}
+
+/// Gives the item of a listing which hasn't been sold back to the seller. The deposit is kept.
+fn cancel_listing(
+    id: u32,
+    seller_id: u32,
+    inventory: &mut Inventory,
+    consignments: &mut Consignments,
+) -> Result<u8, ConsignmentErrorCode> {
+    consignments.check_cancel(id, seller_id)?;
+    if inventory.free_slots() == 0 {
+        return Err(ConsignmentErrorCode::InventoryFull);
+    }
+
+    let listing = consignments.remove(id).ok_or(ConsignmentErrorCode::NotAvailable)?;
+    inventory
+        .add_item(listing.item)
+        .ok_or(ConsignmentErrorCode::InventoryFull)
+}
+
+/// Pays out all sold listings of the seller and returns expired items, as long as they fit into
+/// the inventory. Returns the settled listings, the slots of the returned items and whether all
+/// listings could be settled.
+fn settle_listings(
+    seller_id: u32,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+    consignments: &mut Consignments,
+) -> (Vec<u32>, Vec<u8>, bool) {
+    // A sold listing can only be settled once its purchase has been persisted.
+    let finished: Vec<(u32, ListingStatus)> = consignments
+        .listings_of(seller_id)
+        .filter(|listing| listing.status != ListingStatus::Listed && !consignments.is_purchasing(listing.id))
+        .map(|listing| (listing.id, listing.status))
+        .collect();
+
+    let mut settled = Vec::with_capacity(finished.len());
+    let mut slots = Vec::new();
+    for (id, status) in finished.iter() {
+        if *status == ListingStatus::Expired && inventory.free_slots() == 0 {
+            continue;
+        }
+
+        let Some(listing) = consignments.remove(*id) else {
+            continue;
+        };
+        if *status == ListingStatus::Sold {
+            gold.gain(listing.proceeds());
+        } else if let Some(slot) = inventory.add_item(listing.item) {
+            slots.push(slot);
+        }
+        settled.push(*id);
+    }
+
+    let complete = settled.len() == finished.len();
+    (settled, slots, complete)
+}
+
+/// Buys the listed item. The listing is marked as sold right away, so any other purchase of the
+/// same item, even within the same tick, will fail.
+fn purchase(
+    id: u32,
+    buyer_id: u32,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+    consignments: &mut Consignments,
+    now: DateTime<Utc>,
+) -> Result<u8, ConsignmentErrorCode> {
+    let listing = consignments.check_purchase(id, buyer_id, now)?;
+    if listing.price > gold.amount() {
+        return Err(ConsignmentErrorCode::NotEnoughGold);
+    }
+
+    if inventory.free_slots() == 0 {
+        return Err(ConsignmentErrorCode::InventoryFull);
+    }
+
+    let (item, price) = (listing.item, listing.price);
+    consignments.mark_sold(id);
+    gold.spend(price);
+    inventory.add_item(item).ok_or(ConsignmentErrorCode::InventoryFull)
+}
+
+pub(crate) fn expire_listings(mut consignments: ResMut<Consignments>, queue: Res<PersistenceQueue>) {
+    let expired = consignments.expire(Utc::now());
+    if expired.is_empty() {
+        return;
+    }
+
+    let sellers: Vec<u32> = expired
+        .iter()
+        .filter_map(|id| consignments.get(*id))
+        .map(|listing| listing.seller_id)
+        .collect();
+    let ids: Vec<i32> = expired.into_iter().map(|id| id as i32).collect();
+    queue.spawn(&sellers, move |pool| async move {
+        if let Err(e) = ConsignmentData::expire(&ids, pool).await {
+            error!(error = %e, "Could not expire consignment listings");
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+    use chrono::Duration;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    }
+
+    #[test]
+    fn test_purchase() {
+        let mut consignments = Consignments::default();
+        let id = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+
+        let mut inventory = Inventory::new(45);
+        let mut gold = GoldPouch::new(1500);
+        let slot = purchase(id, 2, &mut inventory, &mut gold, &mut consignments, now()).unwrap();
+        assert_eq!(5, inventory.get_item_at(slot).unwrap().stack_size());
+        assert_eq!(500, gold.amount());
+
+        // A second buyer must not be able to get the same item.
+        let mut other_inventory = Inventory::new(45);
+        let mut other_gold = GoldPouch::new(1500);
+        assert_eq!(
+            Err(ConsignmentErrorCode::NotAvailable),
+            purchase(id, 3, &mut other_inventory, &mut other_gold, &mut consignments, now())
+        );
+        assert_eq!(1500, other_gold.amount());
+    }
+
+    #[test]
+    fn test_purchase_checks() {
+        let mut consignments = Consignments::default();
+        let id = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+
+        let mut inventory = Inventory::new(14);
+        let mut gold = GoldPouch::new(500);
+        assert_eq!(
+            Err(ConsignmentErrorCode::NotEnoughGold),
+            purchase(id, 2, &mut inventory, &mut gold, &mut consignments, now())
+        );
+
+        gold.gain(500);
+        inventory.set_item(13, item(1));
+        assert_eq!(
+            Err(ConsignmentErrorCode::InventoryFull),
+            purchase(id, 2, &mut inventory, &mut gold, &mut consignments, now())
+        );
+        assert_eq!(1000, gold.amount());
+        assert!(consignments.check_purchase(id, 2, now()).is_ok());
+    }
+
+    #[test]
+    fn test_settle() {
+        let mut consignments = Consignments::default();
+        let sold = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+        let expired = consignments
+            .register(1, "Seller".to_string(), item(3), 1000, 1, now())
+            .id;
+        let listed = consignments
+            .register(1, "Seller".to_string(), item(2), 1000, 3, now())
+            .id;
+        consignments.mark_sold(sold);
+        consignments.expire(now() + Duration::days(1));
+
+        let mut inventory = Inventory::new(45);
+        let mut gold = GoldPouch::new(0);
+        let (settled, slots, complete) = settle_listings(1, &mut inventory, &mut gold, &mut consignments);
+        assert!(complete);
+        assert_eq!(vec![sold, expired], settled);
+        assert_eq!(3, inventory.get_item_at(slots[0]).unwrap().stack_size());
+        assert_eq!(1000 - 30 + 10, gold.amount());
+        assert!(consignments.get(listed).is_some());
+        assert_eq!(1, consignments.listings_of(1).count());
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut consignments = Consignments::default();
+        let id = consignments
+            .register(1, "Seller".to_string(), item(5), 1000, 1, now())
+            .id;
+
+        let mut inventory = Inventory::new(45);
+        assert_eq!(
+            Err(ConsignmentErrorCode::NotAvailable),
+            cancel_listing(id, 2, &mut inventory, &mut consignments)
+        );
+        let slot = cancel_listing(id, 1, &mut inventory, &mut consignments).unwrap();
+        assert_eq!(5, inventory.get_item_at(slot).unwrap().stack_size());
+        assert!(consignments.get(id).is_none());
+    }
+}
@@ -128,6 +128,41 @@ impl CharacterItem {
         let character_item_map = all_items.into_iter().into_group_map_by(|item| item.character_id);
         Ok(character_item_map)
     }
+
+    pub async fn fetch_bulk_storage_items<T: Borrow<PgPool>>(
+        character_ids: &[i32],
+        pool: T,
+    ) -> Result<HashMap<i32, Vec<CharacterItem>>, Error> {
+        let all_items: Vec<CharacterItem> = sqlx::query_as::<_, CharacterItem>(
+            "SELECT id, character_id, item_obj_id, upgrade_level, variance, slot, amount FROM character_storage_items WHERE character_id = ANY($1)",
+        )
+        .bind(character_ids)
+        .fetch_all(pool.borrow())
+        .await?;
+
+        let storage_item_map = all_items.into_iter().into_group_map_by(|item| item.character_id);
+        Ok(storage_item_map)
+    }
+}
+
+#[derive(sqlx::FromRow, Copy, Clone)]
+pub struct CharacterStorageGold {
+    pub character_id: i32,
+    pub storage_gold: i64,
+}
+
+impl CharacterStorageGold {
+    pub async fn fetch_for_characters<T: Borrow<PgPool>>(
+        character_ids: &[i32],
+        pool: T,
+    ) -> Result<Vec<CharacterStorageGold>, Error> {
+        sqlx::query_as::<_, CharacterStorageGold>(
+            "SELECT id AS character_id, storage_gold FROM characters WHERE id = ANY($1)",
+        )
+        .bind(character_ids)
+        .fetch_all(pool.borrow())
+        .await
+    }
 }
 
 #[derive(sqlx::FromRow, Copy, Clone)]
@@ -200,3 +235,46 @@ impl CharacterHotbar {
         Ok(entries)
     }
 }
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct CharacterFriend {
+    pub character_id: i32,
+    pub friend_id: i32,
+    pub charname: String,
+    pub character_type: i32,
+    pub group_name: Option<String>,
+}
+
+impl CharacterFriend {
+    pub async fn fetch_for_characters<T: Borrow<PgPool>>(
+        character_ids: &[i32],
+        pool: T,
+    ) -> Result<Vec<CharacterFriend>, Error> {
+        sqlx::query_as::<_, CharacterFriend>(
+            "SELECT f.character_id, f.friend_id, c.charname, c.character_type, g.name AS group_name FROM friends f JOIN characters c ON c.id = f.friend_id LEFT JOIN friends_groups g ON g.id = f.group_id WHERE f.character_id = ANY($1) AND f.status = 'ACCEPTED' AND c.deletion_end IS NULL ORDER BY f.id ASC",
+        )
+        .bind(character_ids)
+        .fetch_all(pool.borrow())
+        .await
+    }
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct CharacterFriendGroup {
+    pub character_id: i32,
+    pub name: String,
+}
+
+impl CharacterFriendGroup {
+    pub async fn fetch_for_characters<T: Borrow<PgPool>>(
+        character_ids: &[i32],
+        pool: T,
+    ) -> Result<Vec<CharacterFriendGroup>, Error> {
+        sqlx::query_as::<_, CharacterFriendGroup>(
+            "SELECT character_id, name FROM friends_groups WHERE character_id = ANY($1) ORDER BY id ASC",
+        )
+        .bind(character_ids)
+        .fetch_all(pool.borrow())
+        .await
+    }
+}
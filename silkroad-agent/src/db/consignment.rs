@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Error, PgPool};
+use std::borrow::Borrow;
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct ConsignmentData {
+    pub id: i32,
+    pub character_id: i32,
+    pub seller: String,
+    pub item_obj_id: i32,
+    pub upgrade_level: i16,
+    pub variance: Option<i64>,
+    pub amount: i16,
+    pub price: i64,
+    pub deposit: i64,
+    pub fee: i64,
+    pub status: i16,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ConsignmentData {
+    pub async fn fetch_all<T: Borrow<PgPool>>(pool: T) -> Result<Vec<ConsignmentData>, Error> {
+        sqlx::query_as::<_, ConsignmentData>(
+            "SELECT i.id, i.character_id, c.charname AS seller, i.item_obj_id, i.upgrade_level, i.variance, i.amount, i.price, i.deposit, i.fee, i.status, i.expires_at FROM consignment_items i JOIN characters c ON c.id = i.character_id ORDER BY i.id ASC",
+        )
+        .fetch_all(pool.borrow())
+        .await
+    }
+
+    /// Marks the given listings as expired, unless they have been sold in the meantime.
+    pub async fn expire<T: Borrow<PgPool>>(ids: &[i32], pool: T) -> Result<(), Error> {
+        sqlx::query("UPDATE consignment_items SET status = 2 WHERE id = ANY($1) AND status = 0")
+            .bind(ids)
+            .execute(pool.borrow())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use sqlx::{Error, PgPool};
+use std::borrow::Borrow;
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct GuildData {
+    pub id: i32,
+    pub name: String,
+    pub level: i16,
+    pub notice_title: String,
+    pub notice: String,
+}
+
+impl GuildData {
+    pub async fn fetch_all<T: Borrow<PgPool>>(pool: T) -> Result<Vec<GuildData>, Error> {
+        sqlx::query_as::<_, GuildData>("SELECT id, name, level, notice_title, notice FROM guilds ORDER BY id ASC")
+            .fetch_all(pool.borrow())
+            .await
+    }
+
+    /// Creates a new guild with the given character as its master and returns the id of the
+    /// guild. The gold of the master is set to the given amount, which is what they have left
+    /// after paying for the guild.
+    pub async fn create<T: Borrow<PgPool>>(
+        name: String,
+        master_id: u32,
+        master_permissions: u8,
+        master_gold: u64,
+        pool: T,
+    ) -> Result<u32, Error> {
+        let mut transaction = pool.borrow().begin().await?;
+        sqlx::query("UPDATE characters SET gold = $1 WHERE id = $2")
+            .bind(master_gold as i64)
+            .bind(master_id as i32)
+            .execute(&mut *transaction)
+            .await?;
+        let (id,): (i32,) = sqlx::query_as("INSERT INTO guilds(name) VALUES($1) RETURNING id")
+            .bind(name)
+            .fetch_one(&mut *transaction)
+            .await?;
+        sqlx::query("INSERT INTO guild_members(character_id, guild_id, rank, permissions) VALUES($1, $2, 0, $3)")
+            .bind(master_id as i32)
+            .bind(id)
+            .bind(master_permissions as i16)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(id as u32)
+    }
+
+    pub async fn update_notice<T: Borrow<PgPool>>(
+        id: u32,
+        title: String,
+        notice: String,
+        pool: T,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE guilds SET notice_title = $1, notice = $2 WHERE id = $3")
+            .bind(title)
+            .bind(notice)
+            .bind(id as i32)
+            .execute(pool.borrow())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn disband<T: Borrow<PgPool>>(id: u32, pool: T) -> Result<(), Error> {
+        sqlx::query("DELETE FROM guilds WHERE id = $1")
+            .bind(id as i32)
+            .execute(pool.borrow())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(sqlx::FromRow, Clone)]
+pub struct GuildMemberData {
+    pub guild_id: i32,
+    pub character_id: i32,
+    pub charname: String,
+    pub character_type: i32,
+    pub level: i16,
+    pub rank: i16,
+    pub permissions: i16,
+}
+
+impl GuildMemberData {
+    pub async fn fetch_all<T: Borrow<PgPool>>(pool: T) -> Result<Vec<GuildMemberData>, Error> {
+        sqlx::query_as::<_, GuildMemberData>(
+            "SELECT m.guild_id, m.character_id, c.charname, c.character_type, c.level, m.rank, m.permissions FROM guild_members m JOIN characters c ON c.id = m.character_id ORDER BY m.joined_at ASC",
+        )
+        .fetch_all(pool.borrow())
+        .await
+    }
+
+    pub async fn add<T: Borrow<PgPool>>(
+        guild_id: u32,
+        character_id: u32,
+        rank: u8,
+        permissions: u8,
+        pool: T,
+    ) -> Result<(), Error> {
+        sqlx::query("INSERT INTO guild_members(character_id, guild_id, rank, permissions) VALUES($1, $2, $3, $4)")
+            .bind(character_id as i32)
+            .bind(guild_id as i32)
+            .bind(rank as i16)
+            .bind(permissions as i16)
+            .execute(pool.borrow())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update<T: Borrow<PgPool>>(character_id: u32, rank: u8, permissions: u8, pool: T) -> Result<(), Error> {
+        sqlx::query("UPDATE guild_members SET rank = $1, permissions = $2 WHERE character_id = $3")
+            .bind(rank as i16)
+            .bind(permissions as i16)
+            .bind(character_id as i32)
+            .execute(pool.borrow())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove<T: Borrow<PgPool>>(character_id: u32, pool: T) -> Result<(), Error> {
+        sqlx::query("DELETE FROM guild_members WHERE character_id = $1")
+            .bind(character_id as i32)
+            .execute(pool.borrow())
+            .await?;
+        Ok(())
+    }
+}
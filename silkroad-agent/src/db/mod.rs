@@ -1,3 +1,5 @@
 pub(crate) mod character;
+pub(crate) mod consignment;
+pub(crate) mod guild;
 pub(crate) mod server;
 pub(crate) mod user;
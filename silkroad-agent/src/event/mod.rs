@@ -4,12 +4,13 @@ use bevy::prelude::*;
 use silkroad_data::skilldata::RefSkillData;
 use silkroad_definitions::TypeId;
 use silkroad_game_base::GlobalLocation;
+use skrillax_server::DisconnectReason;
 
 #[derive(Event)]
 pub(crate) struct ClientConnectedEvent(pub Entity);
 
 #[derive(Event)]
-pub(crate) struct ClientDisconnectedEvent(pub Entity);
+pub(crate) struct ClientDisconnectedEvent(pub Entity, pub DisconnectReason);
 
 #[derive(Event)]
 pub(crate) struct PlayerLevelUp(pub Entity, pub u8);
@@ -31,9 +32,16 @@ pub(crate) struct SkillDefinition {
 #[derive(Event)]
 pub(crate) struct DamageReceiveEvent {
     pub source: EntityReference,
-    pub target: EntityReference,
+    /// All entities hit by the attack, starting with the targeted one.
+    pub targets: Vec<EntityReference>,
     pub attack: SkillDefinition,
-    pub amount: u32,
+}
+
+#[derive(Event)]
+pub(crate) struct BuffApplyEvent {
+    pub source: EntityReference,
+    pub target: Entity,
+    pub skill: SkillDefinition,
 }
 
 #[derive(Event)]
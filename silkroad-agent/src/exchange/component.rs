@@ -0,0 +1,226 @@
+use crate::comp::inventory::{apply_inventory_change, item_content_data};
+use crate::persistence::PersistenceQueue;
+use bevy::prelude::*;
+use silkroad_game_base::{Inventory, InventoryChange, Item};
+use silkroad_protocol::inventory::{InventoryItemData, RentInfo};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::error;
+
+pub(crate) const MAX_EXCHANGE_ITEMS: usize = 12;
+const INVITATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How close both players need to stay to each other for the exchange to remain open.
+pub(crate) const EXCHANGE_DISTANCE_SQUARED: f32 = 100.0 * 100.0;
+
+/// An exchange request, which the invited player has not yet answered.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct ExchangeInvitation {
+    pub(crate) inviter: Entity,
+    pub(crate) timeout: Timer,
+}
+
+impl ExchangeInvitation {
+    pub(crate) fn new(inviter: Entity) -> Self {
+        ExchangeInvitation {
+            inviter,
+            timeout: Timer::new(INVITATION_TIMEOUT, TimerMode::Once),
+        }
+    }
+}
+
+/// The offer of one side of an open exchange. Offered items stay inside the inventory until the
+/// exchange completes, we only keep a copy to verify that they haven't changed in the meantime.
+#[derive(Component)]
+pub(crate) struct Exchange {
+    pub(crate) partner: Entity,
+    items: Vec<(u8, Item)>,
+    gold: u64,
+    confirmed: bool,
+    approved: bool,
+}
+
+impl Exchange {
+    pub(crate) fn new(partner: Entity) -> Self {
+        Exchange {
+            partner,
+            items: Vec::new(),
+            gold: 0,
+            confirmed: false,
+            approved: false,
+        }
+    }
+
+    pub(crate) fn items(&self) -> &[(u8, Item)] {
+        &self.items
+    }
+
+    pub(crate) fn gold(&self) -> u64 {
+        self.gold
+    }
+
+    pub(crate) fn set_gold(&mut self, amount: u64) {
+        self.gold = amount;
+    }
+
+    pub(crate) fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+
+    pub(crate) fn confirm(&mut self) {
+        self.confirmed = true;
+    }
+
+    pub(crate) fn is_approved(&self) -> bool {
+        self.approved
+    }
+
+    pub(crate) fn approve(&mut self) {
+        self.approved = true;
+    }
+
+    /// Resets the agreement of this side when the exchange gets closed. The component is only
+    /// removed at the end of the tick, so this prevents approving the same exchange again.
+    pub(crate) fn abort(&mut self) {
+        self.confirmed = false;
+        self.approved = false;
+    }
+
+    /// Adds the item in the given inventory slot to the offer. Fails if the item is already
+    /// offered or the exchange window is full.
+    pub(crate) fn offer_item(&mut self, slot: u8, item: Item) -> bool {
+        if self.items.len() >= MAX_EXCHANGE_ITEMS || self.items.iter().any(|(offered, _)| *offered == slot) {
+            return false;
+        }
+        self.items.push((slot, item));
+        true
+    }
+
+    /// Removes the item at the given position of the exchange window from the offer.
+    pub(crate) fn withdraw_item(&mut self, exchange_slot: u8) -> Option<(u8, Item)> {
+        let index = exchange_slot as usize;
+        if index >= self.items.len() {
+            return None;
+        }
+        Some(self.items.remove(index))
+    }
+
+    /// Checks that everything offered is still available, i.e. that the items have neither been
+    /// moved nor changed and that there's still enough gold.
+    pub(crate) fn is_available(&self, inventory: &Inventory, gold: u64) -> bool {
+        self.gold <= gold
+            && self.items.iter().all(|(slot, offered)| {
+                inventory.get_item_at(*slot).is_some_and(|item| {
+                    item.reference.ref_id() == offered.reference.ref_id()
+                        && item.variance == offered.variance
+                        && item.type_data == offered.type_data
+                })
+            })
+    }
+
+    /// Checks if the given inventory can hold the items of the partner, once the own offered
+    /// items have been removed.
+    pub(crate) fn can_receive(&self, inventory: &Inventory, partner: &Exchange) -> bool {
+        inventory.free_slots() + self.items.len() >= partner.items.len()
+    }
+
+    pub(crate) fn item_data(&self) -> Vec<InventoryItemData> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, (_, item))| {
+                InventoryItemData::new(
+                    index as u8,
+                    RentInfo::Empty,
+                    item.reference.ref_id(),
+                    item_content_data(item),
+                )
+            })
+            .collect()
+    }
+}
+
+/// The state of one side after an exchange was completed, which needs to be persisted.
+pub(crate) struct ExchangeParticipant {
+    pub(crate) character_id: u32,
+    pub(crate) changes: Vec<InventoryChange>,
+    pub(crate) gold: u64,
+}
+
+/// Persists the result of a completed exchange for both sides in a single transaction, such that
+/// items can neither be lost nor duplicated if the server stops in between. The transaction is
+/// queued behind all changes of both characters that have been queued before.
+pub(crate) fn persist_exchange(participants: [ExchangeParticipant; 2], queue: &PersistenceQueue) {
+    let characters = participants.each_ref().map(|participant| participant.character_id);
+    queue.spawn(&characters, move |pool| async move {
+        if let Err(e) = apply_exchange(&participants, &pool).await {
+            error!(error = %e, ?characters, "Could not persist exchange");
+        }
+    });
+}
+
+async fn apply_exchange(participants: &[ExchangeParticipant; 2], pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    for participant in participants.iter() {
+        for change in participant.changes.iter() {
+            apply_inventory_change(change, participant.character_id, &mut transaction).await?;
+        }
+        sqlx::query("UPDATE characters SET gold = $1 WHERE id = $2")
+            .bind(participant.gold as i64)
+            .bind(participant.character_id as i32)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+
+    #[test]
+    fn test_offer_items() {
+        let mut exchange = Exchange::new(Entity::from_raw(1));
+        assert!(exchange.offer_item(13, item(5)));
+        assert!(!exchange.offer_item(13, item(5)));
+        for slot in 14..(13 + MAX_EXCHANGE_ITEMS as u8) {
+            assert!(exchange.offer_item(slot, item(1)));
+        }
+        assert!(!exchange.offer_item(40, item(1)));
+
+        let (slot, _) = exchange.withdraw_item(0).unwrap();
+        assert_eq!(13, slot);
+        assert!(exchange.withdraw_item(MAX_EXCHANGE_ITEMS as u8).is_none());
+        assert_eq!(MAX_EXCHANGE_ITEMS - 1, exchange.items().len());
+    }
+
+    #[test]
+    fn test_offer_availability() {
+        let mut inventory = Inventory::new(45);
+        let slot = inventory.add_item(item(5)).unwrap();
+
+        let mut exchange = Exchange::new(Entity::from_raw(1));
+        assert!(exchange.offer_item(slot, item(5)));
+        exchange.set_gold(100);
+        assert!(exchange.is_available(&inventory, 100));
+        assert!(!exchange.is_available(&inventory, 50));
+
+        inventory.add_item(item(5));
+        assert!(!exchange.is_available(&inventory, 100));
+    }
+
+    #[test]
+    fn test_receive_space() {
+        let mut inventory = Inventory::new(15);
+        let own = Exchange::new(Entity::from_raw(1));
+        let mut partner = Exchange::new(Entity::from_raw(2));
+        assert!(partner.offer_item(13, item(1)));
+        assert!(partner.offer_item(14, item(1)));
+        assert!(own.can_receive(&inventory, &partner));
+
+        inventory.set_item(13, item(1));
+        assert!(!own.can_receive(&inventory, &partner));
+    }
+}
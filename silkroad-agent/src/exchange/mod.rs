@@ -0,0 +1,21 @@
+mod component;
+mod system;
+
+use crate::exchange::system::{cancel_invalid_exchanges, handle_exchange_input, tick_exchange_invitations};
+use bevy::prelude::*;
+pub(crate) use component::*;
+
+pub(crate) struct ExchangePlugin;
+
+impl Plugin for ExchangePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                cancel_invalid_exchanges,
+                handle_exchange_input,
+                tick_exchange_invitations,
+            ),
+        );
+    }
+}
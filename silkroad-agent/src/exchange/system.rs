@@ -0,0 +1,556 @@
+use crate::agent::state::Dead;
+use crate::comp::gold::GoldPouch;
+use crate::comp::inventory::{item_content_data, PlayerInventory};
+use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::comp::pos::Position;
+use crate::comp::GameEntity;
+use crate::exchange::component::{
+    persist_exchange, Exchange, ExchangeInvitation, ExchangeParticipant, EXCHANGE_DISTANCE_SQUARED,
+};
+use crate::guild::GuildInvitation;
+use crate::input::PlayerInput;
+use crate::party::PartyInvitation;
+use crate::persistence::{PersistenceCollection, PersistenceQueue};
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use silkroad_game_base::{ChangeTracked, Inventory, Item};
+use silkroad_protocol::community::InvitationRequest;
+use silkroad_protocol::exchange::{
+    ExchangeApproveResponse, ExchangeCancelResponse, ExchangeCancelled, ExchangeClientProtocol, ExchangeCompleted,
+    ExchangeConfirmResponse, ExchangeError, ExchangeGoldUpdate, ExchangeItemsUpdate, ExchangePartnerConfirmed,
+    ExchangeResult, ExchangeStartResponse, ExchangeStarted,
+};
+use silkroad_protocol::inventory::{
+    InventoryOperationError, InventoryOperationRequest, InventoryOperationResponseData, InventoryOperationResult,
+};
+use tracing::error;
+
+type ParticipantData<'a> = (
+    &'a Client,
+    &'a GameEntity,
+    &'a Player,
+    &'a Position,
+    &'a mut PlayerInventory,
+    &'a mut GoldPouch,
+    Option<&'a mut Exchange>,
+    Has<ExchangeInvitation>,
+    Has<PartyInvitation>,
+    Has<GuildInvitation>,
+    Has<Dead>,
+);
+
+pub(crate) fn handle_exchange_input(
+    query: Query<(Entity, &PlayerInput, Option<&ExchangeInvitation>)>,
+    mut participants: Query<ParticipantData>,
+    mut collections: Query<&mut PersistenceCollection<PlayerInventory>>,
+    lookup: Res<EntityLookup>,
+    queue: Res<PersistenceQueue>,
+    mut cmd: Commands,
+) {
+    for (entity, input, invitation) in query.iter() {
+        if let (Some(answer), Some(invitation)) = (&input.invitation, invitation) {
+            answer_invitation(entity, invitation.inviter, answer.accepted(), &participants, &mut cmd);
+        }
+
+        for request in input.exchange.iter() {
+            match request {
+                ExchangeClientProtocol::ExchangeStartRequest(start) => {
+                    request_exchange(entity, start.target, &participants, &lookup, &mut cmd);
+                },
+                ExchangeClientProtocol::ExchangeConfirmRequest(_) => {
+                    confirm_exchange(entity, &mut participants);
+                },
+                ExchangeClientProtocol::ExchangeApproveRequest(_) => {
+                    if let Some(partner) = approve_exchange(entity, &mut participants) {
+                        complete_exchange(entity, partner, &mut participants, &mut collections, &queue, &mut cmd);
+                    }
+                },
+                ExchangeClientProtocol::ExchangeCancelRequest(_) => {
+                    cancel_exchange(entity, &mut participants, &mut cmd);
+                },
+            }
+        }
+
+        if let Some(ref operation) = input.inventory {
+            update_offer(entity, operation.data, &mut participants);
+        }
+    }
+}
+
+/// Checks whether the given players are in a state in which they can start exchanging.
+fn check_exchange_pair(
+    first: Entity,
+    second: Entity,
+    participants: &Query<ParticipantData>,
+) -> Result<(), ExchangeError> {
+    let (_, _, _, first_position, _, _, first_exchange, .., first_dead) =
+        participants.get(first).map_err(|_| ExchangeError::InvalidTarget)?;
+    let (_, _, _, second_position, _, _, second_exchange, .., second_dead) =
+        participants.get(second).map_err(|_| ExchangeError::InvalidTarget)?;
+
+    if first_exchange.is_some() {
+        return Err(ExchangeError::AlreadyExchanging);
+    }
+
+    if second_exchange.is_some() {
+        return Err(ExchangeError::TargetBusy);
+    }
+
+    if first_dead || second_dead {
+        return Err(ExchangeError::InvalidTarget);
+    }
+
+    if first_position.distance_to(second_position) > EXCHANGE_DISTANCE_SQUARED {
+        return Err(ExchangeError::TooFarAway);
+    }
+
+    Ok(())
+}
+
+fn request_exchange(
+    entity: Entity,
+    target_id: u32,
+    participants: &Query<ParticipantData>,
+    lookup: &EntityLookup,
+    cmd: &mut Commands,
+) {
+    let Ok((client, game_entity, ..)) = participants.get(entity) else {
+        return;
+    };
+
+    let target = match lookup.get_entity_for_id(target_id).filter(|target| *target != entity) {
+        Some(target) => target,
+        None => {
+            client.send(ExchangeStartResponse::Failure {
+                code: ExchangeError::InvalidTarget,
+            });
+            return;
+        },
+    };
+
+    let Ok((target_client, .., exchange_invitation, party_invitation, guild_invitation, _)) = participants.get(target)
+    else {
+        client.send(ExchangeStartResponse::Failure {
+            code: ExchangeError::InvalidTarget,
+        });
+        return;
+    };
+
+    if exchange_invitation || party_invitation || guild_invitation {
+        client.send(ExchangeStartResponse::Failure {
+            code: ExchangeError::TargetBusy,
+        });
+        return;
+    }
+
+    if let Err(code) = check_exchange_pair(entity, target, participants) {
+        client.send(ExchangeStartResponse::Failure { code });
+        return;
+    }
+
+    cmd.entity(target).try_insert(ExchangeInvitation::new(entity));
+    target_client.send(InvitationRequest::Exchange {
+        inviter: game_entity.unique_id,
+    });
+}
+
+fn answer_invitation(
+    entity: Entity,
+    inviter: Entity,
+    accepted: bool,
+    participants: &Query<ParticipantData>,
+    cmd: &mut Commands,
+) {
+    cmd.entity(entity).remove::<ExchangeInvitation>();
+    let Ok((inviter_client, inviter_game_entity, ..)) = participants.get(inviter) else {
+        return;
+    };
+
+    if !accepted {
+        inviter_client.send(ExchangeStartResponse::Failure {
+            code: ExchangeError::Declined,
+        });
+        return;
+    }
+
+    if let Err(code) = check_exchange_pair(inviter, entity, participants) {
+        inviter_client.send(ExchangeStartResponse::Failure { code });
+        return;
+    }
+
+    let Ok((client, game_entity, ..)) = participants.get(entity) else {
+        return;
+    };
+
+    cmd.entity(inviter).try_insert(Exchange::new(entity));
+    cmd.entity(entity).try_insert(Exchange::new(inviter));
+    inviter_client.send(ExchangeStartResponse::Success {
+        partner: game_entity.unique_id,
+    });
+    client.send(ExchangeStarted {
+        partner: inviter_game_entity.unique_id,
+    });
+}
+
+/// Handles changes to the offer coming from the inventory, i.e. adding or removing items and
+/// changing the offered gold. The offer can no longer be changed once either side confirmed.
+fn update_offer(entity: Entity, operation: InventoryOperationRequest, participants: &mut Query<ParticipantData>) {
+    if !matches!(
+        operation,
+        InventoryOperationRequest::InventoryToExchange { .. }
+            | InventoryOperationRequest::ExchangeToInventory { .. }
+            | InventoryOperationRequest::ExchangeGold { .. }
+    ) {
+        return;
+    }
+
+    let partner = match participants.get(entity) {
+        Ok((_, _, _, _, _, _, Some(exchange), ..)) if !exchange.is_confirmed() => exchange.partner,
+        Ok((client, _, _, _, _, _, exchange, ..)) => {
+            let error = if exchange.is_some() {
+                InventoryOperationError::Busy
+            } else {
+                InventoryOperationError::InvalidTarget
+            };
+            client.send(InventoryOperationResult::Failure(error));
+            return;
+        },
+        Err(_) => return,
+    };
+
+    let partner_confirmed = match participants.get(partner) {
+        Ok((_, _, _, _, _, _, Some(exchange), ..)) => exchange.is_confirmed(),
+        _ => true,
+    };
+
+    let Ok([(client, game_entity, _, _, inventory, gold, Some(mut exchange), ..), (partner_client, ..)]) =
+        participants.get_many_mut([entity, partner])
+    else {
+        return;
+    };
+
+    if partner_confirmed {
+        client.send(InventoryOperationResult::Failure(InventoryOperationError::Busy));
+        return;
+    }
+
+    match operation {
+        InventoryOperationRequest::InventoryToExchange { slot } => {
+            let item = if Inventory::is_equipment_slot(slot) {
+                None
+            } else {
+                inventory.get_item_at(slot).copied()
+            };
+
+            if !item.is_some_and(|item| exchange.offer_item(slot, item)) {
+                client.send(InventoryOperationResult::Failure(
+                    InventoryOperationError::InvalidTarget,
+                ));
+                return;
+            }
+
+            client.send(InventoryOperationResult::Success(
+                InventoryOperationResponseData::InventoryToExchange { slot },
+            ));
+        },
+        InventoryOperationRequest::ExchangeToInventory { exchange_slot } => {
+            if exchange.withdraw_item(exchange_slot).is_none() {
+                client.send(InventoryOperationResult::Failure(
+                    InventoryOperationError::InvalidTarget,
+                ));
+                return;
+            }
+
+            client.send(InventoryOperationResult::Success(
+                InventoryOperationResponseData::ExchangeToInventory { exchange_slot },
+            ));
+        },
+        InventoryOperationRequest::ExchangeGold { amount } => {
+            if amount > gold.amount() {
+                client.send(InventoryOperationResult::Failure(
+                    InventoryOperationError::NotEnoughGold,
+                ));
+                return;
+            }
+
+            exchange.set_gold(amount);
+            client.send(InventoryOperationResult::Success(
+                InventoryOperationResponseData::ExchangeGold { amount },
+            ));
+            let update = ExchangeGoldUpdate {
+                owner: game_entity.unique_id,
+                amount,
+            };
+            client.send(update.clone());
+            partner_client.send(update);
+            return;
+        },
+        _ => return,
+    }
+
+    let update = ExchangeItemsUpdate {
+        owner: game_entity.unique_id,
+        items: exchange.item_data(),
+    };
+    client.send(update.clone());
+    partner_client.send(update);
+}
+
+fn confirm_exchange(entity: Entity, participants: &mut Query<ParticipantData>) {
+    let Ok((client, _, _, _, _, _, exchange, ..)) = participants.get_mut(entity) else {
+        return;
+    };
+
+    let Some(mut exchange) = exchange else {
+        client.send(ExchangeConfirmResponse::new(ExchangeResult::error(
+            ExchangeError::NotExchanging,
+        )));
+        return;
+    };
+
+    exchange.confirm();
+    let partner = exchange.partner;
+    client.send(ExchangeConfirmResponse::new(ExchangeResult::Success));
+
+    if let Ok((partner_client, ..)) = participants.get(partner) {
+        partner_client.send(ExchangePartnerConfirmed);
+    }
+}
+
+/// Approves the exchange for the given player. Returns the partner, if they have approved as
+/// well and the exchange can be completed.
+fn approve_exchange(entity: Entity, participants: &mut Query<ParticipantData>) -> Option<Entity> {
+    let (partner, confirmed) = match participants.get(entity) {
+        Ok((_, _, _, _, _, _, Some(exchange), ..)) => (exchange.partner, exchange.is_confirmed()),
+        Ok((client, ..)) => {
+            client.send(ExchangeApproveResponse::new(ExchangeResult::error(
+                ExchangeError::NotExchanging,
+            )));
+            return None;
+        },
+        Err(_) => return None,
+    };
+
+    let partner_state = match participants.get(partner) {
+        Ok((_, _, _, _, _, _, Some(exchange), ..)) => Some((exchange.is_confirmed(), exchange.is_approved())),
+        _ => None,
+    };
+
+    let Ok((client, _, _, _, _, _, Some(mut exchange), ..)) = participants.get_mut(entity) else {
+        return None;
+    };
+
+    match partner_state {
+        Some((partner_confirmed, partner_approved)) if confirmed && partner_confirmed => {
+            exchange.approve();
+            if partner_approved {
+                // The response is sent once the exchange has been completed.
+                return Some(partner);
+            }
+            client.send(ExchangeApproveResponse::new(ExchangeResult::Success));
+            None
+        },
+        _ => {
+            client.send(ExchangeApproveResponse::new(ExchangeResult::error(
+                ExchangeError::NotConfirmed,
+            )));
+            None
+        },
+    }
+}
+
+fn cancel_exchange(entity: Entity, participants: &mut Query<ParticipantData>, cmd: &mut Commands) {
+    let Ok((client, _, _, _, _, _, exchange, ..)) = participants.get_mut(entity) else {
+        return;
+    };
+
+    let Some(mut exchange) = exchange else {
+        client.send(ExchangeCancelResponse::new(ExchangeResult::error(
+            ExchangeError::NotExchanging,
+        )));
+        return;
+    };
+
+    exchange.abort();
+    let partner = exchange.partner;
+    client.send(ExchangeCancelResponse::new(ExchangeResult::Success));
+    cmd.entity(entity).remove::<Exchange>();
+
+    if let Ok((partner_client, _, _, _, _, _, Some(mut partner_exchange), ..)) = participants.get_mut(partner) {
+        partner_exchange.abort();
+        partner_client.send(ExchangeCancelled);
+        cmd.entity(partner).remove::<Exchange>();
+    }
+}
+
+/// Swaps the offers of both players. Everything happens within this tick, so either both sides
+/// receive the offer of the other or, if anything doesn't add up, nothing changes at all.
+fn complete_exchange(
+    entity: Entity,
+    partner: Entity,
+    participants: &mut Query<ParticipantData>,
+    collections: &mut Query<&mut PersistenceCollection<PlayerInventory>>,
+    queue: &PersistenceQueue,
+    cmd: &mut Commands,
+) {
+    let Ok([own, other]) = participants.get_many_mut([entity, partner]) else {
+        return;
+    };
+    let (client, _, player, _, mut inventory, mut gold, Some(mut exchange), ..) = own else {
+        return;
+    };
+    let (other_client, _, other_player, _, mut other_inventory, mut other_gold, Some(mut other_exchange), ..) = other
+    else {
+        return;
+    };
+
+    let failure = if !exchange.is_available(&inventory, gold.amount())
+        || !other_exchange.is_available(&other_inventory, other_gold.amount())
+    {
+        Some(ExchangeError::InvalidTarget)
+    } else if !exchange.can_receive(&inventory, &other_exchange) {
+        Some(ExchangeError::InventoryFull)
+    } else if !other_exchange.can_receive(&other_inventory, &exchange) {
+        Some(ExchangeError::TargetInventoryFull)
+    } else {
+        None
+    };
+
+    exchange.abort();
+    other_exchange.abort();
+    cmd.entity(entity).remove::<Exchange>();
+    cmd.entity(partner).remove::<Exchange>();
+
+    if let Some(code) = failure {
+        client.send(ExchangeApproveResponse::new(ExchangeResult::error(code)));
+        client.send(ExchangeCancelled);
+        other_client.send(ExchangeCancelled);
+        return;
+    }
+
+    let offered = take_offer(&exchange, &mut inventory);
+    let other_offered = take_offer(&other_exchange, &mut other_inventory);
+    let received = receive_items(other_offered, &mut inventory, player.character.id);
+    let other_received = receive_items(offered, &mut other_inventory, other_player.character.id);
+
+    gold.spend(exchange.gold());
+    gold.gain(other_exchange.gold());
+    other_gold.spend(other_exchange.gold());
+    other_gold.gain(exchange.gold());
+
+    client.send(ExchangeApproveResponse::new(ExchangeResult::Success));
+    client.send(ExchangeCompleted);
+    other_client.send(ExchangeCompleted);
+    notify_received_items(client, &inventory, &received);
+    notify_received_items(other_client, &other_inventory, &other_received);
+
+    // Changes which have been collected before, but not yet persisted, need to be included as
+    // well, otherwise they would be applied after the exchange and in the wrong order.
+    let mut changes = collections
+        .get_mut(entity)
+        .map(|mut collection| collection.take_changes())
+        .unwrap_or_default();
+    changes.append(&mut inventory.changes());
+    let mut other_changes = collections
+        .get_mut(partner)
+        .map(|mut collection| collection.take_changes())
+        .unwrap_or_default();
+    other_changes.append(&mut other_inventory.changes());
+
+    let record = [
+        ExchangeParticipant {
+            character_id: player.character.id,
+            changes,
+            gold: gold.amount(),
+        },
+        ExchangeParticipant {
+            character_id: other_player.character.id,
+            changes: other_changes,
+            gold: other_gold.amount(),
+        },
+    ];
+    persist_exchange(record, queue);
+}
+
+fn take_offer(exchange: &Exchange, inventory: &mut Inventory) -> Vec<Item> {
+    exchange
+        .items()
+        .iter()
+        .filter_map(|(slot, _)| inventory.take_item_at(*slot))
+        .collect()
+}
+
+fn receive_items(items: Vec<Item>, inventory: &mut Inventory, character_id: u32) -> Vec<u8> {
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let slot = inventory.add_item(item);
+            if slot.is_none() {
+                error!(character_id = character_id, "Could not add exchanged item to inventory");
+            }
+            slot
+        })
+        .collect()
+}
+
+fn notify_received_items(client: &Client, inventory: &Inventory, slots: &[u8]) {
+    for slot in slots {
+        if let Some(item) = inventory.get_item_at(*slot) {
+            client.send(InventoryOperationResult::success_gain_item(
+                *slot,
+                item.reference.ref_id(),
+                item_content_data(item),
+            ));
+        }
+    }
+}
+
+pub(crate) fn cancel_invalid_exchanges(
+    mut query: Query<(Entity, &Client, &Position, &mut Exchange, Has<Dead>)>,
+    mut cmd: Commands,
+) {
+    let mut invalid = Vec::new();
+    for (entity, _, position, exchange, dead) in query.iter() {
+        let partner_valid = match query.get(exchange.partner) {
+            Ok((_, _, partner_position, partner_exchange, partner_dead)) => {
+                !partner_dead
+                    && partner_exchange.partner == entity
+                    && position.distance_to(partner_position) <= EXCHANGE_DISTANCE_SQUARED
+            },
+            Err(_) => false,
+        };
+
+        if dead || !partner_valid {
+            invalid.push(entity);
+        }
+    }
+
+    for entity in invalid {
+        if let Ok((_, client, _, mut exchange, _)) = query.get_mut(entity) {
+            exchange.abort();
+            client.send(ExchangeCancelled);
+            cmd.entity(entity).remove::<Exchange>();
+        }
+    }
+}
+
+pub(crate) fn tick_exchange_invitations(
+    mut query: Query<(Entity, &mut ExchangeInvitation)>,
+    clients: Query<&Client>,
+    time: Res<Time>,
+    mut cmd: Commands,
+) {
+    let delta = time.delta();
+    for (entity, mut invitation) in query.iter_mut() {
+        invitation.timeout.tick(delta);
+        if invitation.timeout.just_finished() {
+            cmd.entity(entity).remove::<ExchangeInvitation>();
+            if let Ok(client) = clients.get(invitation.inviter) {
+                client.send(ExchangeStartResponse::Failure {
+                    code: ExchangeError::Declined,
+                });
+            }
+        }
+    }
+}
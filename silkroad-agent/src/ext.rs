@@ -1,10 +1,10 @@
-use crate::protocol::AgentClientProtocol;
 use bevy::prelude::*;
 use derive_more::{Deref, DerefMut, From};
 use id_pool::IdPool;
 use silkroad_data::npc_pos::NpcPosition;
 use silkroad_game_base::LocalLocation;
 use silkroad_navmesh::GlobalNavmesh;
+use silkroad_protocol::agent::AgentClientProtocol;
 use skrillax_server::Server;
 use sqlx::PgPool;
 use std::sync::atomic::{AtomicU32, Ordering};
@@ -0,0 +1,411 @@
+use crate::db::character::{CharacterFriend, CharacterFriendGroup};
+use crate::persistence::ApplyToDatabase;
+use axum::async_trait;
+use bevy::prelude::*;
+use silkroad_game_base::{Change, ChangeTracked, MergeResult};
+use silkroad_protocol::community::{FriendError, FriendListEntry, FriendListGroup, FriendListInfo};
+use sqlx::PgPool;
+use std::mem;
+use std::time::Duration;
+
+pub(crate) const MAX_FRIENDS: usize = 50;
+const MAX_GROUP_NAME_LENGTH: usize = 12;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The group every friend belongs to unless they have been moved into a group of the player.
+pub(crate) const UNASSIGNED_GROUP: u16 = 0;
+
+pub(crate) struct Friend {
+    pub(crate) character_id: u32,
+    pub(crate) name: String,
+    pub(crate) ref_id: u32,
+    group: u16,
+    /// The entity of the friend, if they're currently playing.
+    pub(crate) online: Option<Entity>,
+}
+
+impl Friend {
+    pub(crate) fn new(character_id: u32, name: String, ref_id: u32, online: Option<Entity>) -> Self {
+        Friend {
+            character_id,
+            name,
+            ref_id,
+            group: UNASSIGNED_GROUP,
+            online,
+        }
+    }
+
+    pub(crate) fn as_entry(&self) -> FriendListEntry {
+        FriendListEntry::new(
+            self.character_id,
+            self.name.clone(),
+            self.ref_id,
+            self.group,
+            self.online.is_none(),
+        )
+    }
+}
+
+struct FriendGroup {
+    id: u16,
+    name: String,
+}
+
+/// The friends of a player, sorted into the groups the player created. Groups are identified
+/// by their name in the database and only get an id for the duration of the session.
+#[derive(Component, Default)]
+pub(crate) struct FriendList {
+    last_group_id: u16,
+    groups: Vec<FriendGroup>,
+    friends: Vec<Friend>,
+    changes: Vec<FriendListChange>,
+}
+
+impl FriendList {
+    pub(crate) fn from_db(groups: &[CharacterFriendGroup], friends: &[CharacterFriend]) -> Self {
+        let mut list = FriendList::default();
+        for group in groups {
+            list.insert_group(group.name.clone());
+        }
+
+        for friend in friends {
+            let group = friend
+                .group_name
+                .as_ref()
+                .and_then(|name| list.groups.iter().find(|group| &group.name == name))
+                .map(|group| group.id)
+                .unwrap_or(UNASSIGNED_GROUP);
+            let mut entry = Friend::new(
+                friend.friend_id as u32,
+                friend.charname.clone(),
+                friend.character_type as u32,
+                None,
+            );
+            entry.group = group;
+            list.friends.push(entry);
+        }
+        list
+    }
+
+    pub(crate) fn info(&self) -> FriendListInfo {
+        let groups = std::iter::once(FriendListGroup::not_assigned())
+            .chain(
+                self.groups
+                    .iter()
+                    .map(|group| FriendListGroup::new(group.id, group.name.clone())),
+            )
+            .collect();
+        FriendListInfo::new(groups, self.friends.iter().map(Friend::as_entry).collect())
+    }
+
+    pub(crate) fn friends(&self) -> &[Friend] {
+        &self.friends
+    }
+
+    pub(crate) fn friend(&self, character_id: u32) -> Option<&Friend> {
+        self.friends.iter().find(|friend| friend.character_id == character_id)
+    }
+
+    pub(crate) fn is_friend_with(&self, name: &str) -> bool {
+        self.friends.iter().any(|friend| friend.name == name)
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.friends.len() >= MAX_FRIENDS
+    }
+
+    pub(crate) fn add(&mut self, friend: Friend) {
+        if self.friend(friend.character_id).is_none() {
+            self.changes.push(FriendListChange::Added(friend.character_id));
+            self.friends.push(friend);
+        }
+    }
+
+    pub(crate) fn remove(&mut self, character_id: u32) -> Option<Friend> {
+        let index = self
+            .friends
+            .iter()
+            .position(|friend| friend.character_id == character_id)?;
+        self.changes.push(FriendListChange::Removed(character_id));
+        Some(self.friends.remove(index))
+    }
+
+    /// Updates the entity of the friend with the given character id. Returns `true` if the
+    /// character is a friend of this player.
+    pub(crate) fn set_online(&mut self, character_id: u32, entity: Option<Entity>) -> bool {
+        match self
+            .friends
+            .iter_mut()
+            .find(|friend| friend.character_id == character_id)
+        {
+            Some(friend) => {
+                friend.online = entity;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Marks the friend with the given entity as offline and returns their character id.
+    pub(crate) fn set_offline(&mut self, entity: Entity) -> Option<u32> {
+        let friend = self.friends.iter_mut().find(|friend| friend.online == Some(entity))?;
+        friend.online = None;
+        Some(friend.character_id)
+    }
+
+    pub(crate) fn create_group(&mut self, name: String) -> Result<FriendListGroup, FriendError> {
+        if name.is_empty() || name.chars().count() > MAX_GROUP_NAME_LENGTH {
+            return Err(FriendError::InvalidGroupName);
+        }
+
+        if self.groups.iter().any(|group| group.name == name) {
+            return Err(FriendError::GroupExists);
+        }
+
+        self.changes.push(FriendListChange::GroupCreated(name.clone()));
+        let id = self.insert_group(name.clone());
+        Ok(FriendListGroup::new(id, name))
+    }
+
+    fn insert_group(&mut self, name: String) -> u16 {
+        self.last_group_id += 1;
+        self.groups.push(FriendGroup {
+            id: self.last_group_id,
+            name,
+        });
+        self.last_group_id
+    }
+
+    /// Deletes the group and moves all of its friends back to the unassigned group.
+    pub(crate) fn delete_group(&mut self, id: u16) -> Result<(), FriendError> {
+        let index = self
+            .groups
+            .iter()
+            .position(|group| group.id == id)
+            .ok_or(FriendError::GroupNotFound)?;
+        let group = self.groups.remove(index);
+        self.changes.push(FriendListChange::GroupDeleted(group.name));
+        self.friends
+            .iter_mut()
+            .filter(|friend| friend.group == id)
+            .for_each(|friend| friend.group = UNASSIGNED_GROUP);
+        Ok(())
+    }
+
+    pub(crate) fn move_friend(&mut self, character_id: u32, group: u16) -> Result<(), FriendError> {
+        if group != UNASSIGNED_GROUP && !self.groups.iter().any(|existing| existing.id == group) {
+            return Err(FriendError::GroupNotFound);
+        }
+
+        let friend = self
+            .friends
+            .iter_mut()
+            .find(|friend| friend.character_id == character_id)
+            .ok_or(FriendError::NotFriends)?;
+        friend.group = group;
+        let group = self.group_name(group).map(str::to_string);
+        self.changes.push(FriendListChange::Moved(character_id, group));
+        Ok(())
+    }
+
+    fn group_name(&self, id: u16) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|group| group.id == id)
+            .map(|group| group.name.as_str())
+    }
+}
+
+/// A request to become friends with the `inviter`, which the player has not yet answered.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct FriendInvitation {
+    pub(crate) inviter: Entity,
+    pub(crate) timeout: Timer,
+}
+
+impl FriendInvitation {
+    pub(crate) fn new(inviter: Entity) -> Self {
+        FriendInvitation {
+            inviter,
+            timeout: Timer::new(REQUEST_TIMEOUT, TimerMode::Once),
+        }
+    }
+}
+
+/// A single change to the friend list of a player, which gets persisted as it is.
+pub(crate) enum FriendListChange {
+    /// The character with the given id has become a friend.
+    Added(u32),
+    /// The character with the given id is no longer a friend. Friendships are mutual, so this
+    /// also removes the player from the list of the other character, even if they're offline.
+    Removed(u32),
+    GroupCreated(String),
+    /// The group with the given name was deleted and its friends are no longer assigned to it.
+    GroupDeleted(String),
+    /// The friend with the given character id was moved into the group with the given name,
+    /// or into no group at all.
+    Moved(u32, Option<String>),
+}
+
+impl Change for FriendListChange {
+    fn merge(self, other: Self) -> MergeResult<Self> {
+        match (self, other) {
+            (FriendListChange::Moved(friend, _), FriendListChange::Moved(other_friend, group))
+                if friend == other_friend =>
+            {
+                MergeResult::Merged(FriendListChange::Moved(other_friend, group))
+            },
+            // Changes may depend on the groups or friends created by earlier changes, so we
+            // must keep their order.
+            (left, right) => MergeResult::Incompatible(left, right),
+        }
+    }
+}
+
+impl ChangeTracked for FriendList {
+    type ChangeItem = FriendListChange;
+
+    fn changes(&mut self) -> Vec<Self::ChangeItem> {
+        mem::take(&mut self.changes)
+    }
+}
+
+#[async_trait]
+impl ApplyToDatabase for FriendListChange {
+    async fn apply(&self, character_id: u32, pool: &PgPool) -> Result<(), sqlx::Error> {
+        let character_id = character_id as i32;
+        match self {
+            FriendListChange::Added(friend_id) => {
+                sqlx::query(
+                    "INSERT INTO friends(character_id, friend_id, status) VALUES($1, $2, 'ACCEPTED'::\"FriendStatus\") ON CONFLICT(character_id, friend_id) DO UPDATE SET group_id = NULL, status = EXCLUDED.status",
+                )
+                .bind(character_id)
+                .bind(*friend_id as i32)
+                .execute(pool)
+                .await?;
+            },
+            FriendListChange::Removed(friend_id) => {
+                sqlx::query(
+                    "DELETE FROM friends WHERE (character_id = $1 AND friend_id = $2) OR (character_id = $2 AND friend_id = $1)",
+                )
+                .bind(character_id)
+                .bind(*friend_id as i32)
+                .execute(pool)
+                .await?;
+            },
+            FriendListChange::GroupCreated(name) => {
+                sqlx::query(
+                    "INSERT INTO friends_groups(character_id, name) VALUES($1, $2) ON CONFLICT(character_id, name) DO NOTHING",
+                )
+                .bind(character_id)
+                .bind(name)
+                .execute(pool)
+                .await?;
+            },
+            FriendListChange::GroupDeleted(name) => {
+                let mut transaction = pool.begin().await?;
+                sqlx::query(
+                    "UPDATE friends SET group_id = NULL WHERE group_id IN (SELECT id FROM friends_groups WHERE character_id = $1 AND name = $2)",
+                )
+                .bind(character_id)
+                .bind(name)
+                .execute(&mut *transaction)
+                .await?;
+                sqlx::query("DELETE FROM friends_groups WHERE character_id = $1 AND name = $2")
+                    .bind(character_id)
+                    .bind(name)
+                    .execute(&mut *transaction)
+                    .await?;
+                transaction.commit().await?;
+            },
+            FriendListChange::Moved(friend_id, group) => {
+                sqlx::query(
+                    "UPDATE friends SET group_id = (SELECT id FROM friends_groups WHERE character_id = $1 AND name = $3) WHERE character_id = $1 AND friend_id = $2",
+                )
+                .bind(character_id)
+                .bind(*friend_id as i32)
+                .bind(group)
+                .execute(pool)
+                .await?;
+            },
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use silkroad_game_base::ToOptimizedChange;
+
+    #[test]
+    fn test_groups() {
+        let mut list = FriendList::default();
+        list.add(Friend::new(1, "Tester".to_string(), 1907, None));
+        let group = list.create_group("Guild".to_string()).unwrap();
+        assert!(matches!(
+            list.create_group("Guild".to_string()),
+            Err(FriendError::GroupExists)
+        ));
+        assert!(matches!(
+            list.create_group(String::new()),
+            Err(FriendError::InvalidGroupName)
+        ));
+
+        list.move_friend(1, group.id).unwrap();
+        assert_eq!(list.friend(1).unwrap().as_entry().group_id, group.id);
+        assert!(matches!(list.move_friend(2, group.id), Err(FriendError::NotFriends)));
+        assert!(matches!(list.move_friend(1, 5), Err(FriendError::GroupNotFound)));
+
+        list.delete_group(group.id).unwrap();
+        assert_eq!(list.friend(1).unwrap().as_entry().group_id, UNASSIGNED_GROUP);
+        assert_eq!(list.info().groups.len(), 1);
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut list = FriendList::from_db(
+            &[CharacterFriendGroup {
+                character_id: 1,
+                name: "Guild".to_string(),
+            }],
+            &[],
+        );
+        assert!(list.changes().is_empty());
+
+        list.add(Friend::new(1, "Tester".to_string(), 1907, None));
+        let group = list.create_group("Party".to_string()).unwrap();
+        list.move_friend(1, group.id).unwrap();
+        list.move_friend(1, UNASSIGNED_GROUP).unwrap();
+        list.delete_group(group.id).unwrap();
+        list.remove(1);
+
+        let changes = list.changes().optimize();
+        assert!(matches!(
+            changes.as_slice(),
+            [
+                FriendListChange::Added(1),
+                FriendListChange::GroupCreated(created),
+                FriendListChange::Moved(1, None),
+                FriendListChange::GroupDeleted(deleted),
+                FriendListChange::Removed(1),
+            ] if created == "Party" && deleted == "Party"
+        ));
+    }
+
+    #[test]
+    fn test_online_status() {
+        let mut list = FriendList::default();
+        list.add(Friend::new(1, "Tester".to_string(), 1907, None));
+        let entity = Entity::from_raw(5);
+        assert!(list.set_online(1, Some(entity)));
+        assert!(!list.set_online(2, Some(entity)));
+        assert!(!list.friend(1).unwrap().as_entry().offline);
+        assert_eq!(list.set_offline(entity), Some(1));
+        assert_eq!(list.set_offline(entity), None);
+        assert!(list.is_friend_with("Tester"));
+        assert!(list.remove(1).is_some());
+        assert!(list.friends().is_empty());
+    }
+}
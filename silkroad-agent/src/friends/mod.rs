@@ -0,0 +1,24 @@
+mod component;
+mod system;
+
+use crate::friends::system::{announce_offline, announce_online, handle_friend_input, tick_friend_invitations};
+use crate::persistence::AppPersistanceExt;
+use bevy::prelude::*;
+pub(crate) use component::*;
+
+pub(crate) struct FriendsPlugin;
+
+impl Plugin for FriendsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                handle_friend_input,
+                tick_friend_invitations,
+                announce_online,
+                announce_offline,
+            ),
+        )
+        .track_component::<FriendList>();
+    }
+}
@@ -0,0 +1,293 @@
+use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::comp::GameEntity;
+use crate::event::LoadingFinishedEvent;
+use crate::friends::component::{Friend, FriendInvitation, FriendList};
+use crate::input::PlayerInput;
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use silkroad_protocol::community::{
+    AddFriendResponse, CreateFriendGroupResponse, DeleteFriendGroupResponse, DeleteFriendResponse, FriendError,
+    FriendListClientProtocol, FriendListUpdate, FriendRequest, MoveFriendToGroupResponse,
+};
+
+type TargetData<'a> = (&'a Client, &'a GameEntity, &'a Player, Option<&'a FriendInvitation>);
+
+fn check_friend_target<'a>(
+    entity: Entity,
+    name: &str,
+    targets: &'a Query<TargetData>,
+    lists: &Query<&mut FriendList>,
+    lookup: &EntityLookup,
+) -> Result<(Entity, &'a Client), FriendError> {
+    let own_list = lists.get(entity).map_err(|_| FriendError::InvalidTarget)?;
+    if own_list.is_friend_with(name) {
+        return Err(FriendError::AlreadyFriends);
+    }
+
+    if own_list.is_full() {
+        return Err(FriendError::ListFull);
+    }
+
+    let target = lookup
+        .get_entity_for_name(name)
+        .filter(|target| *target != entity)
+        .ok_or(FriendError::InvalidTarget)?;
+    let (client, _, _, invitation) = targets.get(target).map_err(|_| FriendError::InvalidTarget)?;
+    if invitation.is_some() {
+        return Err(FriendError::TargetBusy);
+    }
+
+    let target_list = lists.get(target).map_err(|_| FriendError::InvalidTarget)?;
+    if target_list.is_full() {
+        return Err(FriendError::TargetListFull);
+    }
+
+    Ok((target, client))
+}
+
+pub(crate) fn handle_friend_input(
+    query: Query<(
+        Entity,
+        &Client,
+        &GameEntity,
+        &Player,
+        &PlayerInput,
+        Option<&FriendInvitation>,
+    )>,
+    targets: Query<TargetData>,
+    mut lists: Query<&mut FriendList>,
+    lookup: Res<EntityLookup>,
+    mut cmd: Commands,
+) {
+    for (entity, client, game_entity, player, input, invitation) in query.iter() {
+        for request in input.friends.iter() {
+            match request {
+                FriendListClientProtocol::AddFriend(add) => {
+                    match check_friend_target(entity, &add.name, &targets, &lists, &lookup) {
+                        Ok((target, target_client)) => {
+                            cmd.entity(target).try_insert(FriendInvitation::new(entity));
+                            target_client.send(FriendRequest {
+                                inviter: game_entity.unique_id,
+                                name: player.character.name.clone(),
+                            });
+                        },
+                        Err(code) => client.send(AddFriendResponse::Failure { code }),
+                    }
+                },
+                FriendListClientProtocol::FriendRequestAnswer(answer) => {
+                    let Some(invitation) = invitation else {
+                        continue;
+                    };
+                    cmd.entity(entity).remove::<FriendInvitation>();
+
+                    let Ok((inviter_client, inviter_entity, inviter_player, _)) = targets.get(invitation.inviter)
+                    else {
+                        continue;
+                    };
+
+                    if inviter_entity.unique_id != answer.inviter {
+                        continue;
+                    }
+
+                    if !answer.accepted {
+                        inviter_client.send(AddFriendResponse::Failure {
+                            code: FriendError::Declined,
+                        });
+                        continue;
+                    }
+
+                    let Ok([mut own_list, mut inviter_list]) = lists.get_many_mut([entity, invitation.inviter]) else {
+                        continue;
+                    };
+
+                    if own_list.is_full() {
+                        inviter_client.send(AddFriendResponse::Failure {
+                            code: FriendError::TargetListFull,
+                        });
+                        continue;
+                    }
+
+                    if inviter_list.is_full() {
+                        inviter_client.send(AddFriendResponse::Failure {
+                            code: FriendError::ListFull,
+                        });
+                        continue;
+                    }
+
+                    let inviter = Friend::new(
+                        inviter_player.character.id,
+                        inviter_player.character.name.clone(),
+                        inviter_entity.ref_id,
+                        Some(invitation.inviter),
+                    );
+                    let own = Friend::new(
+                        player.character.id,
+                        player.character.name.clone(),
+                        game_entity.ref_id,
+                        Some(entity),
+                    );
+                    client.send(FriendListUpdate::Added(inviter.as_entry()));
+                    inviter_client.send(AddFriendResponse::Success(own.as_entry()));
+                    own_list.add(inviter);
+                    inviter_list.add(own);
+                },
+                FriendListClientProtocol::DeleteFriend(delete) => {
+                    let friend_character_id = delete.friend_character_id;
+                    let Some(friend) = lists
+                        .get_mut(entity)
+                        .ok()
+                        .and_then(|mut list| list.remove(friend_character_id))
+                    else {
+                        client.send(DeleteFriendResponse::Failure {
+                            code: FriendError::NotFriends,
+                        });
+                        continue;
+                    };
+
+                    client.send(DeleteFriendResponse::Success { friend_character_id });
+
+                    // If the friend is offline, they will be removed from their list in the
+                    // database once this player's list gets persisted.
+                    let Some(online) = friend.online else {
+                        continue;
+                    };
+
+                    if let Ok(mut list) = lists.get_mut(online) {
+                        list.remove(player.character.id);
+                    }
+
+                    if let Ok((friend_client, ..)) = targets.get(online) {
+                        friend_client.send(FriendListUpdate::Removed {
+                            friend_character_id: player.character.id,
+                        });
+                    }
+                },
+                FriendListClientProtocol::CreateFriendGroup(create) => {
+                    let Ok(mut list) = lists.get_mut(entity) else {
+                        continue;
+                    };
+
+                    match list.create_group(create.name.clone()) {
+                        Ok(group) => client.send(CreateFriendGroupResponse::Success(group)),
+                        Err(code) => client.send(CreateFriendGroupResponse::Failure { code }),
+                    }
+                },
+                FriendListClientProtocol::DeleteFriendGroup(delete) => {
+                    let Ok(mut list) = lists.get_mut(entity) else {
+                        continue;
+                    };
+
+                    match list.delete_group(delete.group_id) {
+                        Ok(_) => client.send(DeleteFriendGroupResponse::Success {
+                            group_id: delete.group_id,
+                        }),
+                        Err(code) => client.send(DeleteFriendGroupResponse::Failure { code }),
+                    }
+                },
+                FriendListClientProtocol::MoveFriendToGroup(movement) => {
+                    let Ok(mut list) = lists.get_mut(entity) else {
+                        continue;
+                    };
+
+                    match list.move_friend(movement.friend_character_id, movement.group_id) {
+                        Ok(_) => client.send(MoveFriendToGroupResponse::Success {
+                            friend_character_id: movement.friend_character_id,
+                            group_id: movement.group_id,
+                        }),
+                        Err(code) => client.send(MoveFriendToGroupResponse::Failure { code }),
+                    }
+                },
+            }
+        }
+    }
+}
+
+pub(crate) fn tick_friend_invitations(
+    mut query: Query<(Entity, &mut FriendInvitation)>,
+    clients: Query<&Client>,
+    time: Res<Time>,
+    mut cmd: Commands,
+) {
+    let delta = time.delta();
+    for (entity, mut invitation) in query.iter_mut() {
+        invitation.timeout.tick(delta);
+        if invitation.timeout.just_finished() {
+            cmd.entity(entity).remove::<FriendInvitation>();
+            if let Ok(client) = clients.get(invitation.inviter) {
+                client.send(AddFriendResponse::Failure {
+                    code: FriendError::Declined,
+                });
+            }
+        }
+    }
+}
+
+/// Marks the friends of a player, who finished loading, as online and lets the friends know
+/// that the player is now online.
+pub(crate) fn announce_online(
+    mut events: EventReader<LoadingFinishedEvent>,
+    players: Query<(&Client, &Player)>,
+    mut lists: Query<&mut FriendList>,
+    lookup: Res<EntityLookup>,
+) {
+    for event in events.read() {
+        let entity = event.0;
+        let Ok((_, player)) = players.get(entity) else {
+            continue;
+        };
+
+        let online_friends = {
+            let Ok(mut list) = lists.get_mut(entity) else {
+                continue;
+            };
+
+            let online_friends = list
+                .friends()
+                .iter()
+                .filter_map(|friend| {
+                    lookup
+                        .get_entity_for_name(&friend.name)
+                        .filter(|friend_entity| players.contains(*friend_entity))
+                        .map(|friend_entity| (friend.character_id, friend_entity))
+                })
+                .collect::<Vec<_>>();
+            for (character_id, friend_entity) in online_friends.iter() {
+                list.set_online(*character_id, Some(*friend_entity));
+            }
+            online_friends
+        };
+
+        for (_, friend_entity) in online_friends {
+            let Ok(mut friend_list) = lists.get_mut(friend_entity) else {
+                continue;
+            };
+
+            if friend_list.set_online(player.character.id, Some(entity)) {
+                if let Ok((client, _)) = players.get(friend_entity) {
+                    client.send(FriendListUpdate::StatusChanged {
+                        friend_character_id: player.character.id,
+                        offline: false,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Lets the friends of a player know that the player went offline.
+pub(crate) fn announce_offline(
+    mut removed: RemovedComponents<FriendList>,
+    mut lists: Query<(&Client, &mut FriendList)>,
+) {
+    for entity in removed.read() {
+        for (client, mut list) in lists.iter_mut() {
+            if let Some(friend_character_id) = list.set_offline(entity) {
+                client.send(FriendListUpdate::StatusChanged {
+                    friend_character_id,
+                    offline: true,
+                });
+            }
+        }
+    }
+}
@@ -1,13 +1,27 @@
 use crate::agent::goal::{AgentGoal, GoalTracker};
+use crate::agent::state::{AgentState, AgentStateQueue, SkillParameter, SkillTarget, Transition, TransitionPriority};
+use crate::comp::buff::Buffed;
 use crate::comp::net::Client;
+use crate::comp::skill::SkillCooldownTracker;
 use crate::input::PlayerInput;
 use crate::world::{EntityLookup, WorldData};
 use bevy::prelude::*;
+use silkroad_game_base::SkillAttack;
 use silkroad_protocol::combat::{ActionTarget, DoActionType, PerformAction, PerformActionError, PerformActionResponse};
 use tracing::warn;
 
-pub(crate) fn handle_action(mut query: Query<(&Client, &PlayerInput, &mut GoalTracker)>, lookup: Res<EntityLookup>) {
-    for (client, input, mut mind) in query.iter_mut() {
+pub(crate) fn handle_action(
+    mut query: Query<(
+        &Client,
+        &PlayerInput,
+        &mut GoalTracker,
+        &mut AgentStateQueue,
+        &mut Buffed,
+        &SkillCooldownTracker,
+    )>,
+    lookup: Res<EntityLookup>,
+) {
+    for (client, input, mut mind, mut state, mut buffs, cooldowns) in query.iter_mut() {
         let Some(ref action) = input.action else {
             continue;
         };
@@ -48,13 +62,50 @@ pub(crate) fn handle_action(mut query: Query<(&Client, &PlayerInput, &mut GoalTr
                             continue;
                         };
 
+                        if !cooldowns.is_ready_for(skill) {
+                            client.send(PerformActionResponse::Stop(PerformActionError::Cooldown));
+                            continue;
+                        }
+
                         mind.switch_goal_notified(AgentGoal::attacking_with(target, skill));
                     },
+                    ActionTarget::None => {
+                        let Some(skill) = WorldData::skills().find_id(*ref_id) else {
+                            client.send(PerformActionResponse::Stop(PerformActionError::NotLearned));
+                            continue;
+                        };
+
+                        if !cooldowns.is_ready_for(skill) {
+                            client.send(PerformActionResponse::Stop(PerformActionError::Cooldown));
+                            continue;
+                        }
+
+                        // Attacks without a target aren't supported yet.
+                        if skill.requires_target || SkillAttack::from_skill(skill).is_some() {
+                            client.send(PerformActionResponse::Stop(PerformActionError::InvalidTarget));
+                            continue;
+                        }
+
+                        // Skills without a target, like most buffs, are cast on ourselves right away.
+                        mind.switch_goal_notified(AgentGoal::none());
+                        state.push(Transition::create(
+                            AgentState::PerformSkill(SkillParameter {
+                                target: SkillTarget::Own,
+                                skill,
+                            }),
+                            TransitionPriority::Default,
+                            true,
+                        ));
+                    },
                     _ => {
                         warn!("Tried to use a skill on unsupported target.")
                     },
                 },
-                DoActionType::CancelBuff { .. } => {},
+                DoActionType::CancelBuff { ref_id, .. } => {
+                    if !buffs.cancel(*ref_id) {
+                        client.send(PerformActionResponse::Stop(PerformActionError::InvalidTarget));
+                    }
+                },
             },
             PerformAction::Stop => {
                 mind.reset();
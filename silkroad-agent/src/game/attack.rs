@@ -3,7 +3,7 @@ use crate::comp::GameEntity;
 use crate::world::WorldData;
 use silkroad_data::skilldata::RefSkillData;
 use silkroad_definitions::inventory::EquipmentSlot;
-use silkroad_game_base::{AttackSkill, AttackSkillError};
+use silkroad_game_base::{AttackSkill, AttackSkillError, SkillAttack, SkillCooldowns};
 
 pub struct Attack;
 
@@ -15,10 +15,23 @@ impl Attack {
         AttackSkill::get_attack_skill(WorldData::skills(), weapon)
     }
 
-    pub(crate) fn find_attack_for_monster(monster: GameEntity) -> Option<&'static RefSkillData> {
-        WorldData::characters()
-            .find_id(monster.ref_id)
-            .and_then(|chardata| chardata.skills.first())
-            .and_then(|skill| WorldData::skills().find_id(*skill))
+    /// Finds the first attack of the monster which isn't on cooldown. If all of them are, the
+    /// first attack is returned, which the monster then has to wait for.
+    pub(crate) fn find_attack_for_monster(
+        monster: GameEntity,
+        cooldowns: Option<&SkillCooldowns>,
+    ) -> Option<&'static RefSkillData> {
+        let attacks: Vec<&'static RefSkillData> = WorldData::characters()
+            .find_id(monster.ref_id)?
+            .skills
+            .iter()
+            .filter_map(|skill| WorldData::skills().find_id(*skill))
+            .filter(|skill| SkillAttack::from_skill(skill).is_some())
+            .collect();
+        attacks
+            .iter()
+            .find(|skill| cooldowns.is_none_or(|cooldowns| cooldowns.is_ready_for(skill)))
+            .or(attacks.first())
+            .copied()
     }
 }
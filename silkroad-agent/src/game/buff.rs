@@ -0,0 +1,119 @@
+use crate::agent::state::Dead;
+use crate::comp::buff::Buffed;
+use crate::comp::monster::Monster;
+use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::comp::{GameEntity, Health, Mana};
+use crate::event::BuffApplyEvent;
+use crate::game::damage::{attack_stats, defense_stats, CombatData};
+use bevy::prelude::*;
+use silkroad_game_base::{buff_duration, can_target, Buff, TargetRelation};
+use silkroad_protocol::character::CharacterStatsMessage;
+use silkroad_protocol::combat::{ActionType, PerformActionError, PerformActionUpdate};
+
+pub(crate) fn apply_buffs(
+    mut reader: EventReader<BuffApplyEvent>,
+    mut target_query: Query<(&GameEntity, &mut Buffed, Option<&Client>, Has<Monster>), Without<Dead>>,
+    source_query: Query<(Option<&Client>, Has<Monster>)>,
+) {
+    for event in reader.read() {
+        let (source_client, source_is_monster) = source_query.get(event.source.0).unwrap_or((None, false));
+        let Some(duration) = buff_duration(event.skill.skill) else {
+            continue;
+        };
+
+        // The skill decides whether it can be used on the caster, its allies or its enemies.
+        let target = target_query
+            .get_mut(event.target)
+            .ok()
+            .filter(|(_, _, _, target_is_monster)| {
+                let is_caster = event.target == event.source.0;
+                let relation = TargetRelation::between(is_caster, source_is_monster, *target_is_monster);
+                can_target(&event.skill.skill.target, relation)
+            });
+        let Some((target, mut buffed, target_client, _)) = target else {
+            if let Some(client) = source_client {
+                client.send(PerformActionUpdate::Failure(PerformActionError::InvalidTarget));
+            }
+            continue;
+        };
+
+        buffed.add(Buff::new(event.skill.skill, event.skill.instance, duration));
+
+        let update = PerformActionUpdate::success(
+            event.skill.skill.ref_id,
+            event.source.1.unique_id,
+            target.unique_id,
+            event.skill.instance,
+            ActionType::None,
+        );
+        if let Some(client) = source_client {
+            client.send(update.clone());
+        }
+        if event.target != event.source.0 {
+            if let Some(client) = target_client {
+                client.send(update);
+            }
+        }
+    }
+}
+
+pub(crate) fn tick_buffs(mut query: Query<&mut Buffed>, time: Res<Time>) {
+    let delta = time.delta();
+    for mut buffed in query.iter_mut() {
+        if !buffed.has_buffs() {
+            continue;
+        }
+
+        // We only want to notify others about buffs that have actually expired, not every tick.
+        if buffed.bypass_change_detection().tick(delta) {
+            buffed.set_changed();
+        }
+    }
+}
+
+pub(crate) fn remove_buffs_on_death(mut query: Query<&mut Buffed, Added<Dead>>) {
+    for mut buffed in query.iter_mut() {
+        buffed.clear();
+    }
+}
+
+pub(crate) fn update_buffed_stats(
+    mut query: Query<(&Client, &Buffed, &mut Health, &mut Mana, CombatData), (Changed<Buffed>, With<Player>)>,
+) {
+    for (client, buffed, mut health, mut mana, combat) in query.iter_mut() {
+        let (_, level, stats, _, _) = combat;
+        let (Some(level), Some(stats)) = (level, stats) else {
+            continue;
+        };
+
+        let modifiers = buffed.modifiers();
+        let max_hp = modifiers.max_health(stats.stats().max_health(level.current_level()));
+        let max_mp = modifiers.max_mana(stats.stats().max_mana(level.current_level()));
+        if health.max_health != max_hp {
+            health.max_health = max_hp;
+            health.current_health = health.current_health.min(max_hp);
+        }
+        if mana.max_mana != max_mp {
+            mana.max_mana = max_mp;
+            mana.current_mana = mana.current_mana.min(max_mp);
+        }
+
+        let attack = attack_stats(combat);
+        let defense = defense_stats(combat);
+        client.send(CharacterStatsMessage::new(
+            attack.physical.min as u32,
+            attack.physical.max as u32,
+            attack.magical.min as u32,
+            attack.magical.max as u32,
+            defense.physical as u16,
+            defense.magical as u16,
+            attack.hit_rate as u16,
+            defense.parry_rate as u16,
+            max_hp,
+            max_mp,
+            stats.stats().strength(),
+            stats.stats().intelligence(),
+        ));
+    }
+}
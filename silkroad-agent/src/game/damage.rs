@@ -1,42 +1,129 @@
 use crate::agent::goal::{AgentGoal, GoalTracker};
-use crate::agent::state::{AgentState, AgentStateQueue, Dead, Transition};
+use crate::agent::state::{AgentState, AgentStateQueue, Dead, Incapacitated, IncapacitatedParameter, Transition};
+use crate::comp::buff::Buffed;
 use crate::comp::damage::{DamageReceiver, Invincible};
+use crate::comp::exp::Leveled;
+use crate::comp::inventory::PlayerInventory;
 use crate::comp::monster::Monster;
 use crate::comp::net::Client;
-use crate::comp::player::Player;
+use crate::comp::player::StatPoints;
+use crate::comp::pos::Position;
 use crate::comp::{Despawn, GameEntity, Health};
 use crate::event::{DamageReceiveEvent, EntityDeath};
+use crate::ext::Navmesh;
+use crate::world::WorldData;
 use bevy::prelude::*;
+use rand::Rng;
+use silkroad_game_base::{
+    calculate_damage, knockback_destination, AttackStats, DamageOutcome, DamageRoll, DefenseStats, SkillAttack,
+    SkillStatusEffects, StatusEffectKind,
+};
 use silkroad_protocol::combat::{
     ActionType, DamageContent, DamageKind, DamageValue, PerEntityDamage, PerformActionError, PerformActionUpdate,
     SkillPartDamage,
 };
 
+/// The components which make up the attack or defense values of an entity.
+pub(crate) type CombatData<'a> = (
+    &'a GameEntity,
+    Option<&'a Leveled>,
+    Option<&'a StatPoints>,
+    Option<&'a PlayerInventory>,
+    Option<&'a Buffed>,
+);
+
+type DamageReceiverData<'a> = (
+    &'a mut Health,
+    &'a mut AgentStateQueue,
+    &'a mut DamageReceiver,
+    Option<&'a Client>,
+    Option<&'a Invincible>,
+    &'a Position,
+    Option<&'a Incapacitated>,
+    CombatData<'a>,
+);
+
 pub(crate) fn handle_damage(
     mut reader: EventReader<DamageReceiveEvent>,
-    mut receiver_query: Query<(
-        &mut Health,
-        &mut AgentStateQueue,
-        &mut DamageReceiver,
-        Option<&Player>,
-        Option<&Client>,
-        Option<&Invincible>,
-    )>,
-    sender_query: Query<(&GameEntity, Option<&Client>)>,
+    mut receiver_query: Query<DamageReceiverData>,
+    sender_query: Query<(Option<&Client>, &Position, CombatData)>,
     mut entity_died: EventWriter<EntityDeath>,
+    navmesh: Res<Navmesh>,
 ) {
+    let mut rng = rand::rng();
     for damage_event in reader.read() {
-        let Ok((mut health, mut controller, mut receiver, player, maybe_client, invincible)) =
-            receiver_query.get_mut(damage_event.target.0)
-        else {
+        let Some(primary_target) = damage_event.targets.first() else {
             continue;
         };
 
-        let (attacker, attacker_client) = sender_query
+        let (attacker_client, attacker_position, attacker) = sender_query
             .get(damage_event.source.0)
             .expect("Sender for damage event should exist");
 
-        if health.is_dead() {
+        let Some(skill_attack) = SkillAttack::from_skill(damage_event.attack.skill) else {
+            continue;
+        };
+        let attack = attack_stats(attacker);
+        let status_effects = SkillStatusEffects::from_skill(damage_event.attack.skill);
+
+        let mut damaged = Vec::with_capacity(damage_event.targets.len());
+        let mut entities = Vec::with_capacity(damage_event.targets.len());
+        for target in damage_event.targets.iter() {
+            let Ok((mut health, mut controller, mut receiver, _, invincible, position, incapacitated, defender)) =
+                receiver_query.get_mut(target.0)
+            else {
+                continue;
+            };
+
+            if health.is_dead() {
+                continue;
+            }
+
+            let knocked_down = incapacitated.is_some_and(|incapacitated| incapacitated.is_knocked_down());
+            let outcome = if invincible.is_none() {
+                let mut defense = defense_stats(defender);
+                defense.knocked_down = knocked_down;
+                calculate_damage(&skill_attack, &attack, &defense, DamageRoll::random(&mut rng))
+            } else {
+                DamageOutcome::Hit {
+                    amount: 0,
+                    critical: false,
+                }
+            };
+            let amount = outcome.amount();
+
+            receiver.record_damage(attacker.0.unique_id, amount as u64);
+            health.reduce(amount);
+            entities.push(PerEntityDamage {
+                target: target.1.unique_id,
+                damage: vec![damage_data(outcome, health.is_dead())],
+            });
+            damaged.push(target.0);
+
+            if health.is_dead() {
+                entity_died.send(EntityDeath {
+                    died: *target,
+                    killer: Some(damage_event.source),
+                });
+                controller.push(Transition::force(AgentState::Dead));
+            } else if invincible.is_none() && matches!(outcome, DamageOutcome::Hit { .. }) {
+                if let Some(effect) = status_effects.roll(rng.random(), knocked_down) {
+                    let knockback = (effect.kind == StatusEffectKind::Knockback).then(|| {
+                        knockback_destination(
+                            attacker_position.location(),
+                            position.location(),
+                            status_effects.knockback_distance(),
+                            |location| navmesh.height_for(location.to_local()).is_some(),
+                        )
+                    });
+                    controller.push(Transition::important(AgentState::Incapacitated(
+                        IncapacitatedParameter::new(effect, knockback),
+                    )));
+                }
+            }
+        }
+
+        if entities.is_empty() {
             // TODO: this might be wrong
             if let Some(client) = attacker_client {
                 client.send(PerformActionUpdate::Failure(PerformActionError::Completed))
@@ -44,67 +131,59 @@ pub(crate) fn handle_damage(
             continue;
         }
 
-        let amount = if invincible.is_none() { damage_event.amount } else { 0 };
-
-        receiver.record_damage(attacker.unique_id, amount as u64);
-        health.reduce(amount);
-        let damage_data = if health.is_dead() {
-            SkillPartDamage::KillingBlow(DamageValue::new(DamageKind::Standard, amount))
-        } else {
-            SkillPartDamage::Default(DamageValue::new(DamageKind::Standard, amount))
-        };
+        let update = PerformActionUpdate::success(
+            damage_event.attack.skill.ref_id,
+            damage_event.source.1.unique_id,
+            primary_target.1.unique_id,
+            damage_event.attack.instance,
+            ActionType::Attack {
+                damage: Some(DamageContent {
+                    damage_instances: 1,
+                    entities,
+                }),
+            },
+        );
         if let Some(client) = attacker_client {
-            client.send(PerformActionUpdate::success(
-                damage_event.attack.skill.ref_id,
-                damage_event.source.1.unique_id,
-                damage_event.target.1.unique_id,
-                damage_event.attack.instance,
-                ActionType::Attack {
-                    damage: Some(DamageContent {
-                        damage_instances: 1,
-                        entities: vec![PerEntityDamage {
-                            target: damage_event.target.1.unique_id,
-                            damage: vec![damage_data],
-                        }],
-                    }),
-                },
-            ));
-        } else if let Some(client) = maybe_client {
-            client.send(PerformActionUpdate::success(
-                damage_event.attack.skill.ref_id,
-                damage_event.source.1.unique_id,
-                damage_event.target.1.unique_id,
-                damage_event.attack.instance,
-                ActionType::Attack {
-                    damage: Some(DamageContent {
-                        damage_instances: 1,
-                        entities: vec![PerEntityDamage {
-                            target: damage_event.target.1.unique_id,
-                            damage: vec![damage_data],
-                        }],
-                    }),
-                },
-            ));
+            client.send(update.clone());
         }
-
-        if health.is_dead() {
-            entity_died.send(EntityDeath {
-                died: damage_event.target,
-                killer: Some(damage_event.source),
-            });
-            controller.push(Transition::force(AgentState::Dead));
+        for target_client in damaged
+            .into_iter()
+            .filter_map(|target| receiver_query.get(target).ok().and_then(|data| data.3))
+        {
+            target_client.send(update.clone());
         }
     }
 }
 
+fn damage_data(outcome: DamageOutcome, killed: bool) -> SkillPartDamage {
+    match outcome {
+        // The client has no separate marking for parried attacks, they show up as blocked.
+        DamageOutcome::Parried | DamageOutcome::Blocked => SkillPartDamage::Blocked,
+        DamageOutcome::Hit { amount, critical } => {
+            let kind = if critical {
+                DamageKind::Critical
+            } else {
+                DamageKind::Standard
+            };
+            if killed {
+                SkillPartDamage::KillingBlow(DamageValue::new(kind, amount))
+            } else {
+                SkillPartDamage::Default(DamageValue::new(kind, amount))
+            }
+        },
+    }
+}
+
 pub(crate) fn attack_player(
     mut query: Query<&mut GoalTracker, With<Monster>>,
     mut events: EventReader<DamageReceiveEvent>,
 ) {
     for event in events.read() {
-        if let Ok(mut goal) = query.get_mut(event.target.0) {
-            if !goal.has_goal() {
-                goal.switch_goal_notified(AgentGoal::attacking(event.source.0));
+        for target in event.targets.iter() {
+            if let Ok(mut goal) = query.get_mut(target.0) {
+                if !goal.has_goal() {
+                    goal.switch_goal_notified(AgentGoal::attacking(event.source.0));
+                }
             }
         }
     }
@@ -115,3 +194,41 @@ pub(crate) fn handle_monster_death(query: Query<Entity, (Added<Dead>, With<Monst
         cmd.entity(entity).try_insert(Despawn::despawn_after_seconds(5));
     }
 }
+
+pub(crate) fn attack_stats((game_entity, level, stats, inventory, buffs): CombatData) -> AttackStats {
+    let mut attack = match (level, stats) {
+        (Some(level), Some(stats)) => AttackStats::for_character(
+            level.current_level(),
+            stats.stats(),
+            inventory.and_then(|inventory| inventory.weapon()),
+        ),
+        _ => WorldData::characters()
+            .find_id(game_entity.ref_id)
+            .map(AttackStats::for_monster)
+            .unwrap_or_default(),
+    };
+    if let Some(buffs) = buffs {
+        buffs.modifiers().apply_to_attack(&mut attack);
+    }
+    attack
+}
+
+pub(crate) fn defense_stats((game_entity, level, stats, inventory, buffs): CombatData) -> DefenseStats {
+    let mut defense = match (level, stats) {
+        (Some(level), Some(stats)) => DefenseStats::for_character(
+            level.current_level(),
+            stats.stats(),
+            inventory
+                .into_iter()
+                .flat_map(|inventory| inventory.equipment_items().map(|(_, item)| item)),
+        ),
+        _ => WorldData::characters()
+            .find_id(game_entity.ref_id)
+            .map(DefenseStats::for_monster)
+            .unwrap_or_default(),
+    };
+    if let Some(buffs) = buffs {
+        buffs.modifiers().apply_to_defense(&mut defense);
+    }
+    defense
+}
@@ -2,6 +2,7 @@ use crate::comp::drop::{Drop, DropBundle};
 use crate::comp::pos::Position;
 use crate::comp::{Despawn, EntityReference, GameEntity};
 use crate::ext::{EntityIdPool, Navmesh};
+use crate::party::PartyId;
 use bevy::prelude::*;
 use derive_more::Constructor;
 use rand::Rng;
@@ -13,6 +14,7 @@ pub(crate) struct SpawnDrop {
     pub item: Item,
     pub relative_position: GlobalLocation,
     pub owner: Option<EntityReference>,
+    pub party: Option<PartyId>,
 }
 
 pub(crate) fn tick_drop(mut cmd: Commands, time: Res<Time>, mut drops: Query<(Entity, &mut Despawn)>) {
@@ -38,6 +40,7 @@ pub(crate) fn create_drops(
         cmd.spawn(DropBundle {
             drop: Drop {
                 owner: spawn.owner,
+                party: spawn.party,
                 item: spawn.item,
             },
             position: Position::new(pos, Heading(rotation)),
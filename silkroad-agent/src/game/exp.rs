@@ -1,3 +1,4 @@
+use crate::comp::buff::Buffed;
 use crate::comp::damage::DamageReceiver;
 use crate::comp::exp::{Experienced, Leveled, SP};
 use crate::comp::player::{Player, StatPoints};
@@ -5,9 +6,11 @@ use crate::comp::pos::Position;
 use crate::comp::{EntityReference, GameEntity, Health, Mana};
 use crate::config::get_config;
 use crate::event::EntityDeath;
+use crate::party::{ExperienceDistribution, Parties, PartyMember};
 use crate::world::{EntityLookup, WorldData};
 use bevy::prelude::*;
 use silkroad_data::characterdata::RefCharacterData;
+use std::collections::HashSet;
 use tracing::warn;
 
 const EXP_RECEIVE_RANGE_SQUARED: f32 = 1000.0 * 1000.0;
@@ -34,7 +37,8 @@ pub(crate) fn distribute_experience(
     mut experience_writer: EventWriter<ReceiveExperienceEvent>,
     dead_query: Query<(&DamageReceiver, &Position)>,
     lookup: Res<EntityLookup>,
-    receiver_query: Query<(&GameEntity, &Position, &Player)>,
+    parties: Res<Parties>,
+    receiver_query: Query<(&GameEntity, &Position, &Player, Option<&PartyMember>)>,
 ) {
     let characters = WorldData::characters();
     let config = get_config();
@@ -44,21 +48,58 @@ pub(crate) fn distribute_experience(
         };
 
         let monster_data = characters.find_id(event.died.1.ref_id).unwrap();
+        let experience_event = |target_entity: Entity, game_entity: &GameEntity, player: &Player, share: usize| {
+            let exp = calculate_exp(monster_data, player) as f32 * config.game.drop.experience;
+            let sp = calculate_sexp(monster_data, player) as f32 * config.game.drop.sp_experience;
+            ReceiveExperienceEvent {
+                source: Some(event.died),
+                target: EntityReference(target_entity, *game_entity),
+                exp: (exp / share as f32) as u64,
+                sp: (sp / share as f32) as u64,
+            }
+        };
 
+        let mut rewarded_parties = HashSet::new();
         for attacker_id in damage_distribution.all_attackers() {
-            if let Some(((game_entity, position, player), target_entity)) = lookup
+            let Some(((game_entity, position, player, party_member), target_entity)) = lookup
                 .get_entity_for_id(attacker_id)
                 .and_then(|entity| receiver_query.get(entity).ok().zip(Some(entity)))
-            {
-                if death_location.distance_to(position) <= EXP_RECEIVE_RANGE_SQUARED {
-                    let event = ReceiveExperienceEvent {
-                        source: Some(event.died),
-                        target: EntityReference(target_entity, *game_entity),
-                        exp: (calculate_exp(monster_data, player) as f32 * config.game.drop.experience) as u64,
-                        sp: (calculate_sexp(monster_data, player) as f32 * config.game.drop.sp_experience) as u64,
-                    };
-                    experience_writer.send(event);
-                }
+            else {
+                continue;
+            };
+
+            let shared_party = party_member.and_then(|member| {
+                parties
+                    .get(member.0)
+                    .filter(|party| party.settings().experience == ExperienceDistribution::Shared)
+                    .map(|party| (member.0, party))
+            });
+
+            match shared_party {
+                Some((party_id, party)) => {
+                    // The whole party is rewarded once, no matter how many of its members attacked.
+                    if !rewarded_parties.insert(party_id) {
+                        continue;
+                    }
+
+                    let receivers = party
+                        .members()
+                        .iter()
+                        .filter_map(|member| receiver_query.get(member.0).ok().zip(Some(member.0)))
+                        .filter(|((_, position, _, _), _)| {
+                            death_location.distance_to(position) <= EXP_RECEIVE_RANGE_SQUARED
+                        })
+                        .collect::<Vec<_>>();
+                    let share = receivers.len();
+                    for ((game_entity, _, player, _), target_entity) in receivers {
+                        experience_writer.send(experience_event(target_entity, game_entity, player, share));
+                    }
+                },
+                None => {
+                    if death_location.distance_to(position) <= EXP_RECEIVE_RANGE_SQUARED {
+                        experience_writer.send(experience_event(target_entity, game_entity, player, 1));
+                    }
+                },
             }
         }
     }
@@ -109,24 +150,26 @@ pub(crate) fn receive_experience(
 
 pub(crate) fn reset_health_mana_on_level(
     mut level_up_events: EventReader<LevelUpEvent>,
-    mut query: Query<(&StatPoints, &mut Health, &mut Mana)>,
+    mut query: Query<(&StatPoints, &mut Health, &mut Mana, Option<&Buffed>)>,
 ) {
     for event in level_up_events.read() {
-        let Ok((stats, mut health, mut mana)) = query.get_mut(event.target.0) else {
+        let Ok((stats, mut health, mut mana, buffs)) = query.get_mut(event.target.0) else {
             continue;
         };
-        health.upgrade(stats.stats().max_health(event.level));
-        mana.upgrade(stats.stats().max_mana(event.level));
+        let modifiers = buffs.map(|buffs| buffs.modifiers()).unwrap_or_default();
+        health.upgrade(modifiers.max_health(stats.stats().max_health(event.level)));
+        mana.upgrade(modifiers.max_mana(stats.stats().max_mana(event.level)));
     }
 }
 
 pub(crate) fn update_max_hp_mp_on_stat_change(
-    mut query: Query<(&StatPoints, &Leveled, &mut Health, &mut Mana), Changed<StatPoints>>,
+    mut query: Query<(&StatPoints, &Leveled, &mut Health, &mut Mana, Option<&Buffed>), Changed<StatPoints>>,
 ) {
-    for (stats, leveled, mut health, mut mana) in query.iter_mut() {
+    for (stats, leveled, mut health, mut mana, buffs) in query.iter_mut() {
         if stats.has_spent_points() {
-            health.increase_max(stats.stats().max_health(leveled.current_level()));
-            mana.increase_max(stats.stats().max_mana(leveled.current_level()));
+            let modifiers = buffs.map(|buffs| buffs.modifiers()).unwrap_or_default();
+            health.increase_max(modifiers.max_health(stats.stats().max_health(leveled.current_level())));
+            mana.increase_max(modifiers.max_mana(stats.stats().max_mana(leveled.current_level())));
         }
     }
 }
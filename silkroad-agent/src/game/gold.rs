@@ -4,6 +4,7 @@ use crate::comp::GameEntity;
 use crate::config::get_config;
 use crate::event::EntityDeath;
 use crate::game::drop::SpawnDrop;
+use crate::party::{ItemDistribution, Parties, PartyMember};
 use crate::world::WorldData;
 use bevy::prelude::*;
 use rand::{rng, Rng};
@@ -28,6 +29,8 @@ pub(crate) fn get_gold_ref_id(amount: u32) -> &'static RefItemData {
 pub(crate) fn drop_gold(
     mut death_events: EventReader<EntityDeath>,
     query: Query<(&GameEntity, &Position), With<Monster>>,
+    killer_query: Query<&PartyMember>,
+    parties: Res<Parties>,
     mut drop_events: EventWriter<SpawnDrop>,
 ) {
     let characters = WorldData::characters();
@@ -43,6 +46,15 @@ pub(crate) fn drop_gold(
             let gold_range = gold.get_for_level(monster_level);
             let amount = rng().random_range(gold_range);
             let amount = (config.game.drop.gold * amount as f32).floor() as u32;
+            let party = event
+                .killer
+                .and_then(|killer| killer_query.get(killer.0).ok())
+                .filter(|member| {
+                    parties
+                        .get(member.0)
+                        .is_some_and(|party| party.settings().items == ItemDistribution::Shared)
+                })
+                .map(|member| member.0);
             drop_events.send(SpawnDrop {
                 item: Item {
                     reference: get_gold_ref_id(amount),
@@ -51,6 +63,7 @@ pub(crate) fn drop_gold(
                 },
                 relative_position: pos.location(),
                 owner: event.killer,
+                party,
             });
         }
     }
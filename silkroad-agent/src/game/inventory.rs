@@ -1,3 +1,4 @@
+use crate::agent::goal::{AgentGoal, GoalTracker};
 use crate::comp::exp::Leveled;
 use crate::comp::gold::GoldPouch;
 use crate::comp::inventory::PlayerInventory;
@@ -7,6 +8,7 @@ use crate::comp::pos::Position;
 use crate::game::drop::SpawnDrop;
 use crate::game::gold::get_gold_ref_id;
 use crate::input::PlayerInput;
+use crate::world::EntityLookup;
 use bevy::prelude::*;
 use silkroad_definitions::type_id::{
     ObjectClothingPart, ObjectClothingType, ObjectConsumable, ObjectConsumableAmmo, ObjectEquippable, ObjectItem,
@@ -27,10 +29,12 @@ pub(crate) fn handle_inventory_input(
         &mut PlayerInventory,
         &mut GoldPouch,
         &Position,
+        &mut GoalTracker,
     )>,
     mut item_spawn: EventWriter<SpawnDrop>,
+    lookup: Res<EntityLookup>,
 ) {
-    for (client, input, level, race, mut inventory, mut gold, position) in query.iter_mut() {
+    for (client, input, level, race, mut inventory, mut gold, position, mut goal) in query.iter_mut() {
         if let Some(ref action) = input.inventory {
             match action.data {
                 InventoryOperationRequest::DropGold { amount } => {
@@ -56,13 +60,24 @@ pub(crate) fn handle_inventory_input(
                         },
                         position.location(),
                         None,
+                        None,
                     ));
 
                     client.send(InventoryOperationResult::Success(
                         InventoryOperationResponseData::DropGold { amount },
                     ));
                 },
-                InventoryOperationRequest::PickupItem { unique_id } => {},
+                InventoryOperationRequest::PickupItem { unique_id } => {
+                    // The pickup itself happens once the player is in range of the drop.
+                    let Some(target) = lookup.get_entity_for_id(unique_id) else {
+                        client.send(InventoryOperationResult::Failure(
+                            InventoryOperationError::InvalidTarget,
+                        ));
+                        continue;
+                    };
+
+                    goal.switch_goal_notified(AgentGoal::picking_up(target));
+                },
                 InventoryOperationRequest::Move { source, target, amount } => {
                     if let Some(source_item) = inventory.get_item_at(source) {
                         if Inventory::is_equipment_slot(target) {
@@ -101,6 +116,20 @@ pub(crate) fn handle_inventory_input(
                     }
                 },
                 InventoryOperationRequest::DropItem { .. } => {},
+                // Changes to an exchange offer are handled by the exchange itself.
+                InventoryOperationRequest::InventoryToExchange { .. }
+                | InventoryOperationRequest::ExchangeToInventory { .. }
+                | InventoryOperationRequest::ExchangeGold { .. } => {},
+                // Trading with NPCs is handled by the shop.
+                InventoryOperationRequest::BuyItem { .. }
+                | InventoryOperationRequest::SellItem { .. }
+                | InventoryOperationRequest::BuyBackItem { .. } => {},
+                // Using the storage is handled by the storage itself.
+                InventoryOperationRequest::StorageMove { .. }
+                | InventoryOperationRequest::InventoryToStorage { .. }
+                | InventoryOperationRequest::StorageToInventory { .. }
+                | InventoryOperationRequest::StorageDepositGold { .. }
+                | InventoryOperationRequest::StorageWithdrawGold { .. } => {},
             }
         }
     }
@@ -1,28 +1,39 @@
 use crate::comp::exp::Leveled;
 use crate::comp::net::Client;
 use crate::comp::player::{Player, StatPoints};
+use crate::comp::skill::{RememberedCooldowns, SkillCooldownTracker};
 use crate::comp::GameEntity;
 use crate::config::GameConfig;
 use crate::event::LoadingFinishedEvent;
+use crate::friends::FriendList;
 use crate::game::daylight::DaylightCycle;
 use bevy::prelude::*;
 use silkroad_game_base::SpawningState;
 use silkroad_protocol::character::CharacterStatsMessage;
 use silkroad_protocol::chat::{ChatSource, ChatUpdate, TextCharacterInitialization};
-use silkroad_protocol::community::{FriendListGroup, FriendListInfo};
-use silkroad_protocol::world::{CelestialUpdate, CharacterFinished};
+use silkroad_protocol::world::{CelestialUpdate, CharacterFinished, CooldownInfo};
 use tracing::debug;
 
 pub(crate) fn load_finished(
     mut reader: EventReader<LoadingFinishedEvent>,
     settings: Res<GameConfig>,
     daycycle: Res<DaylightCycle>,
-    mut query: Query<(&Client, &GameEntity, &mut Player, &Leveled, &StatPoints)>,
+    mut remembered_cooldowns: ResMut<RememberedCooldowns>,
+    time: Res<Time>,
+    mut query: Query<(
+        &Client,
+        &GameEntity,
+        &mut Player,
+        &Leveled,
+        &StatPoints,
+        &FriendList,
+        &mut SkillCooldownTracker,
+    )>,
 ) {
     for event in reader.read() {
-        let (client, game_entity, mut player, level, stat_points) = match query.get_mut(event.0) {
-            Ok(data) => data,
-            _ => continue,
+        let Ok((client, game_entity, mut player, level, stat_points, friends, mut cooldowns)) = query.get_mut(event.0)
+        else {
+            continue;
         };
 
         debug!(id = ?client.0.id(), "Finished loading.");
@@ -36,11 +47,18 @@ pub(crate) fn load_finished(
             hour,
             minute,
         });
-        client.send(CharacterFinished::default());
-        client.send(FriendListInfo {
-            groups: vec![FriendListGroup::not_assigned()],
-            friends: vec![],
+        **cooldowns = remembered_cooldowns.take(player.character.id, time.elapsed());
+        client.send(CharacterFinished {
+            item_cooldowns: Vec::new(),
+            skill_cooldowns: cooldowns
+                .iter()
+                .map(|cooldown| CooldownInfo {
+                    ref_id: cooldown.skill,
+                    cooldown: cooldown.remaining.as_millis() as u32,
+                })
+                .collect(),
         });
+        client.send(friends.info());
 
         if let Some(notice) = &settings.join_notice {
             client.send(ChatUpdate::new(ChatSource::Notice, notice.clone()));
@@ -1,9 +1,12 @@
 use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::comp::skill::{RememberedCooldowns, SkillCooldownTracker};
 use crate::config::GameConfig;
 use crate::event::ClientDisconnectedEvent;
 use crate::input::PlayerInput;
 use bevy::prelude::*;
 use silkroad_protocol::auth::{LogoutFinished, LogoutResponse, LogoutResult};
+use skrillax_server::DisconnectReason;
 use std::time::Duration;
 
 #[derive(Component)]
@@ -37,17 +40,27 @@ pub(crate) fn handle_logout(
     }
 }
 
-pub(crate) fn tick_logout(
-    mut query: Query<(Entity, &Client, &mut Logout)>,
-    time: Res<Time>,
-    mut writer: EventWriter<ClientDisconnectedEvent>,
-) {
+pub(crate) fn tick_logout(mut query: Query<(&Client, &mut Logout)>, time: Res<Time>) {
     let delta = time.delta();
-    for (entity, client, mut logout) in query.iter_mut() {
+    for (client, mut logout) in query.iter_mut() {
         logout.0.tick(delta);
         if logout.0.just_finished() {
             client.send(LogoutFinished);
-            writer.send(ClientDisconnectedEvent(entity));
+            client.disconnect(DisconnectReason::Finished);
+        }
+    }
+}
+
+/// Keeps the cooldowns of players that left, such that logging in again does not reset them.
+pub(crate) fn remember_cooldowns(
+    mut events: EventReader<ClientDisconnectedEvent>,
+    query: Query<(&Player, &SkillCooldownTracker)>,
+    mut remembered: ResMut<RememberedCooldowns>,
+    time: Res<Time>,
+) {
+    for event in events.read() {
+        if let Ok((player, cooldowns)) = query.get(event.0) {
+            remembered.remember(player.character.id, cooldowns, time.elapsed());
         }
     }
 }
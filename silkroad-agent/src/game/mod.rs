@@ -5,13 +5,18 @@ use crate::comp::inventory::PlayerInventory;
 use crate::comp::mastery::MasteryKnowledge;
 use crate::comp::player::StatPoints;
 use crate::comp::pos::Position;
-use crate::comp::skill::{Hotbar, SkillBook};
+use crate::comp::skill::{Hotbar, RememberedCooldowns, SkillBook};
 use crate::comp::{Health, Mana};
+use crate::consignment::ConsignmentPlugin;
 use crate::event::{
-    DamageReceiveEvent, EntityDeath, LoadingFinishedEvent, PlayerLevelUp, SpawnMonster, UniqueKilledEvent,
+    BuffApplyEvent, DamageReceiveEvent, EntityDeath, LoadingFinishedEvent, PlayerLevelUp, SpawnMonster,
+    UniqueKilledEvent,
 };
+use crate::exchange::ExchangePlugin;
 use crate::ext::ActionIdCounter;
+use crate::friends::FriendsPlugin;
 use crate::game::action::handle_action;
+use crate::game::buff::{apply_buffs, remove_buffs_on_death, tick_buffs, update_buffed_stats};
 use crate::game::damage::{attack_player, handle_damage, handle_monster_death};
 use crate::game::daylight::{advance_daylight, DaylightCycle};
 use crate::game::drop::{create_drops, tick_drop, SpawnDrop};
@@ -23,7 +28,7 @@ use crate::game::gold::drop_gold;
 use crate::game::hotbar::update_hotbar;
 use crate::game::inventory::handle_inventory_input;
 use crate::game::join::load_finished;
-use crate::game::logout::{handle_logout, tick_logout};
+use crate::game::logout::{handle_logout, remember_cooldowns, tick_logout};
 use crate::game::mastery::{handle_mastery_levelup, learn_skill};
 use crate::game::movement::movement_monster;
 use crate::game::player_activity::{update_player_activity, PlayerActivity};
@@ -32,13 +37,19 @@ use crate::game::stats::increase_stats;
 use crate::game::target::{deselect_despawned, player_update_target};
 use crate::game::unique::{setup_unique_timers, unique_killed, unique_spawned, update_timers};
 use crate::game::visibility::{clear_visibility, player_visibility_update, visibility_update};
+use crate::guild::GuildPlugin;
+use crate::party::PartyPlugin;
 use crate::persistence::AppPersistanceExt;
+use crate::shop::ShopPlugin;
+use crate::stall::StallPlugin;
+use crate::storage::StoragePlugin;
 use crate::sync::SynchronizationStage;
 use bevy::prelude::*;
 use exp::LevelUpEvent;
 
 mod action;
 pub(crate) mod attack;
+mod buff;
 mod damage;
 mod daylight;
 pub(crate) mod drop;
@@ -62,14 +73,24 @@ pub(crate) struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(ChatPlugin)
+            .add_plugins(PartyPlugin)
+            .add_plugins(FriendsPlugin)
+            .add_plugins(GuildPlugin)
+            .add_plugins(ExchangePlugin)
+            .add_plugins(ShopPlugin)
+            .add_plugins(StoragePlugin)
+            .add_plugins(StallPlugin)
+            .add_plugins(ConsignmentPlugin)
             .insert_resource(PlayerActivity::default())
             .insert_resource(DaylightCycle::official())
             .insert_resource(ActionIdCounter::default())
+            .init_resource::<RememberedCooldowns>()
             .add_event::<PlayerLevelUp>()
             .add_event::<LoadingFinishedEvent>()
             .add_event::<UniqueKilledEvent>()
             .add_event::<SpawnDrop>()
             .add_event::<DamageReceiveEvent>()
+            .add_event::<BuffApplyEvent>()
             .add_event::<EntityDeath>()
             .add_event::<ReceiveExperienceEvent>()
             .add_event::<SpawnMonster>()
@@ -107,11 +128,24 @@ impl Plugin for GamePlugin {
                     update_max_hp_mp_on_stat_change.after(increase_stats),
                 ),
             )
+            .add_systems(
+                Update,
+                (
+                    apply_buffs,
+                    tick_buffs,
+                    remove_buffs_on_death,
+                    update_buffed_stats
+                        .after(apply_buffs)
+                        .after(tick_buffs)
+                        .after(remove_buffs_on_death),
+                ),
+            )
             .add_systems(
                 PostUpdate,
                 (
                     player_visibility_update.before(SynchronizationStage::Distribution),
                     load_finished,
+                    remember_cooldowns,
                     unique_spawned,
                     unique_killed,
                     advance_daylight,
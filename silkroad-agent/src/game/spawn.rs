@@ -1,9 +1,11 @@
 use crate::agent::component::{Agent, MovementState};
 use crate::agent::goal::GoalTracker;
 use crate::agent::state::AgentStateQueue;
+use crate::comp::buff::Buffed;
 use crate::comp::damage::DamageReceiver;
 use crate::comp::monster::{Monster, MonsterAiBundle, MonsterBundle, RandomStroll, SpawnedBy};
 use crate::comp::pos::Position;
+use crate::comp::skill::SkillCooldownTracker;
 use crate::comp::visibility::Visibility;
 use crate::comp::{GameEntity, Health};
 use crate::event::SpawnMonster;
@@ -52,6 +54,8 @@ pub(crate) fn do_spawn_mobs(
             state_queue: AgentStateQueue::default(),
             movement_state: MovementState::default_monster(),
             damage_receiver: DamageReceiver::default(),
+            buffs: Buffed::default(),
+            cooldowns: SkillCooldownTracker::default(),
         });
 
         spawning.insert(MonsterAiBundle {
@@ -1,4 +1,5 @@
 use crate::agent::component::Agent;
+use crate::comp::buff::Buffed;
 use crate::comp::drop::Drop;
 use crate::comp::inventory::PlayerInventory;
 use crate::comp::monster::Monster;
@@ -9,20 +10,23 @@ use crate::comp::pos::Position;
 use crate::comp::visibility::{Invisible, Visibility};
 use crate::comp::{EntityReference, GameEntity};
 use crate::game::player_activity::PlayerActivity;
+use crate::guild::{GuildMember, Guilds};
+use crate::party::{PartyId, PartyMember};
+use crate::stall::Stall;
 use bevy::prelude::*;
 use cgmath::num_traits::Pow;
 use silkroad_data::DataEntry;
 use silkroad_definitions::Region;
 use silkroad_game_base::ItemTypeData;
 use silkroad_navmesh::region::GridRegion;
-use silkroad_protocol::community::GuildInformation;
 use silkroad_protocol::inventory::CharacterSpawnItemData;
 use silkroad_protocol::spawn::{
     DroppedItemSource, EntityTypeSpawnData, GroupEntitySpawnData, GroupEntitySpawnEnd, GroupEntitySpawnStart,
-    GroupSpawnDataContent, GroupSpawnType, ItemSpawnData,
+    GroupSpawnDataContent, GroupSpawnType, ItemSpawnData, StallSpawnData,
 };
 use silkroad_protocol::world::{
-    ActionState, ActiveScroll, AliveState, BodyState, EntityState, InteractOptions, JobType, PlayerKillState, PvpCape,
+    ActionState, ActiveScroll, AliveState, BodyState, EntityState, InteractOptions, InteractionMode, JobType,
+    PlayerKillState, PvpCape,
 };
 use std::collections::{BTreeMap, HashSet};
 use tracing::{instrument, trace};
@@ -86,7 +90,7 @@ pub(crate) fn visibility_update(
 }
 
 pub(crate) fn player_visibility_update(
-    mut query: Query<(&Client, &GameEntity, &mut Visibility)>,
+    mut query: Query<(&Client, &GameEntity, &mut Visibility, Option<&PartyMember>)>,
     lookup: Query<
         (
             &Position,
@@ -96,16 +100,32 @@ pub(crate) fn player_visibility_update(
             Option<&Monster>,
             Option<&Drop>,
             Option<&NPC>,
+            Option<&GuildMember>,
+            Option<&Stall>,
+            Option<&Buffed>,
         ),
         Without<Invisible>,
     >,
+    guilds: Res<Guilds>,
 ) {
-    for (client, player, mut visibility) in query.iter_mut() {
+    for (client, player, mut visibility, party_member) in query.iter_mut() {
         let mut spawns = Vec::new();
         for reference in visibility.added_entities.iter() {
             let added = reference.0;
             let entity = reference.1;
-            if let Ok((pos, inventory_opt, agent_opt, player_opt, monster_opt, item_opt, npc_opt)) = lookup.get(added) {
+            if let Ok((
+                pos,
+                inventory_opt,
+                agent_opt,
+                player_opt,
+                monster_opt,
+                item_opt,
+                npc_opt,
+                guild_opt,
+                stall_opt,
+                buffs_opt,
+            )) = lookup.get(added)
+            {
                 if let Some(player) = player_opt {
                     let agent = agent_opt.unwrap();
                     let items = inventory_opt
@@ -134,25 +154,27 @@ pub(crate) fn player_visibility_update(
                             mask: None,
                             position: pos.as_protocol(),
                             movement: pos.as_standing(),
-                            entity_state: entity_state_from_agent(agent),
+                            entity_state: entity_state_from_agent(agent, buffs_opt),
                             name: player.character.name.clone(),
                             job_type: JobType::None,
                             pk_state: PlayerKillState::None,
                             mounted: false,
                             in_combat: false,
                             active_scroll: ActiveScroll::None,
-                            unknown2: 0,
-                            guild: GuildInformation {
-                                name: "".to_string(),
-                                id: 0,
-                                member: "".to_string(),
-                                last_icon_rev: 0,
-                                union_id: 0,
-                                last_union_icon_rev: 0,
-                                is_friendly: 0,
-                                siege_unknown: 0,
+                            interaction_mode: if stall_opt.is_some() {
+                                InteractionMode::Stall
+                            } else {
+                                InteractionMode::None
                             },
+                            guild: guild_opt
+                                .and_then(|member| guilds.get(member.0))
+                                .map(|guild| guild.information())
+                                .unwrap_or_default(),
                             unknown3: [0; 11],
+                            stall: stall_opt.map(|stall| StallSpawnData {
+                                title: stall.title().to_string(),
+                                decoration: 0,
+                            }),
                             equipment_cooldown: false,
                             unknown4: 0,
                             unknown5: 0,
@@ -166,7 +188,7 @@ pub(crate) fn player_visibility_update(
                             unique_id: entity.unique_id,
                             position: pos.as_protocol(),
                             movement: pos.as_movement(),
-                            entity_state: entity_state_from_agent(agent),
+                            entity_state: entity_state_from_agent(agent, buffs_opt),
                             // Somehow doesn't matter right now *shrug*
                             interaction_options: InteractOptions::None,
                             rarity: monster.rarity,
@@ -174,7 +196,8 @@ pub(crate) fn player_visibility_update(
                         },
                     ));
                 } else if let Some(drop) = item_opt {
-                    let spawn_data = spawndata_from_item(entity, pos, drop, player);
+                    let spawn_data =
+                        spawndata_from_item(entity, pos, drop, player, party_member.map(|member| member.0));
                     spawns.push(GroupSpawnDataContent::Spawn {
                         object_id: entity.ref_id,
                         data: EntityTypeSpawnData::Item(spawn_data),
@@ -187,7 +210,7 @@ pub(crate) fn player_visibility_update(
                             unique_id: entity.unique_id,
                             position: pos.as_protocol(),
                             movement: pos.as_standing(),
-                            entity_state: entity_state_from_agent(agent),
+                            entity_state: entity_state_from_agent(agent, buffs_opt),
                             interaction_options: InteractOptions::None,
                         },
                     ));
@@ -209,7 +232,7 @@ pub(crate) fn player_visibility_update(
     }
 }
 
-fn entity_state_from_agent(agent: &Agent) -> EntityState {
+fn entity_state_from_agent(agent: &Agent, buffs: Option<&Buffed>) -> EntityState {
     EntityState {
         alive: AliveState::Alive,
         unknown1: 0,
@@ -219,20 +242,23 @@ fn entity_state_from_agent(agent: &Agent) -> EntityState {
         walk_speed: agent.walking_speed,
         run_speed: agent.running_speed,
         berserk_speed: agent.berserk_speed,
-        active_buffs: vec![],
+        active_buffs: buffs.map(|buffs| buffs.active_buff_data()).unwrap_or_default(),
     }
 }
 
-fn spawndata_from_item(entity: GameEntity, pos: &Position, drop: &Drop, for_player: &GameEntity) -> ItemSpawnData {
+fn spawndata_from_item(
+    entity: GameEntity,
+    pos: &Position,
+    drop: &Drop,
+    for_player: &GameEntity,
+    party: Option<PartyId>,
+) -> ItemSpawnData {
     match drop.item.type_data {
         ItemTypeData::Equipment { upgrade_level } => ItemSpawnData::Equipment {
             upgrade: upgrade_level,
             unique_id: entity.unique_id,
             position: pos.as_protocol(),
-            owner: drop
-                .owner
-                .map(|owner| owner.1.unique_id)
-                .filter(|id| *id != for_player.unique_id),
+            owner: drop.owner_for(for_player, party),
             rarity: 0,
             source: DroppedItemSource::None,
             source_id: 0,
@@ -240,10 +266,7 @@ fn spawndata_from_item(entity: GameEntity, pos: &Position, drop: &Drop, for_play
         ItemTypeData::COS | ItemTypeData::Consumable { .. } => ItemSpawnData::Consumable {
             unique_id: entity.unique_id,
             position: pos.as_protocol(),
-            owner: drop
-                .owner
-                .map(|owner| owner.1.unique_id)
-                .filter(|id| *id != for_player.unique_id),
+            owner: drop.owner_for(for_player, party),
             rarity: 0,
             source: DroppedItemSource::None,
             source_id: 0,
@@ -252,10 +275,7 @@ fn spawndata_from_item(entity: GameEntity, pos: &Position, drop: &Drop, for_play
             amount,
             unique_id: entity.unique_id,
             position: pos.as_protocol(),
-            owner: drop
-                .owner
-                .map(|owner| owner.1.unique_id)
-                .filter(|id| *id != for_player.unique_id),
+            owner: drop.owner_for(for_player, party),
             rarity: 0,
         },
     }
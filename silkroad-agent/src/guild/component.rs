@@ -0,0 +1,535 @@
+use crate::db::guild::{GuildData, GuildMemberData};
+use crate::persistence::PersistenceQueue;
+use crate::world::WorldData;
+use bevy::prelude::*;
+use derive_more::Deref;
+use silkroad_protocol::community::GuildInformation;
+use silkroad_protocol::guild::{GuildInfo, GuildMemberInfo, GuildPermissions, GuildRank};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::oneshot::error::TryRecvError;
+use tokio::sync::oneshot::Receiver;
+use tracing::error;
+
+pub(crate) const GUILD_CREATION_COST: u64 = 10_000;
+pub(crate) const GUILD_CREATION_MIN_LEVEL: u8 = 20;
+const MAX_GUILD_NAME_LENGTH: usize = 12;
+/// The longest notice title we store for a guild.
+const MAX_NOTICE_TITLE_LENGTH: usize = 64;
+/// The longest notice we store for a guild.
+const MAX_NOTICE_LENGTH: usize = 1024;
+const GUILD_NAME_CONSTRAINT: &str = "guilds_name_key";
+const INVITATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How close a player needs to be to the NPC they're creating a guild at.
+pub(crate) const NPC_INTERACTION_DISTANCE_SQUARED: f32 = 100.0 * 100.0;
+
+/// The maximum amount of members for each guild level, starting at level 1.
+const MEMBER_LIMITS: [usize; 5] = [15, 20, 25, 35, 50];
+
+pub(crate) type GuildId = u32;
+
+pub(crate) fn rank_to_db(rank: GuildRank) -> u8 {
+    match rank {
+        GuildRank::Master => 0,
+        GuildRank::Deputy => 1,
+        GuildRank::Member => 2,
+    }
+}
+
+fn rank_from_db(rank: i16) -> GuildRank {
+    match rank {
+        0 => GuildRank::Master,
+        1 => GuildRank::Deputy,
+        _ => GuildRank::Member,
+    }
+}
+
+pub(crate) fn is_valid_guild_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_GUILD_NAME_LENGTH
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+pub(crate) fn is_valid_notice(title: &str, notice: &str) -> bool {
+    title.chars().count() <= MAX_NOTICE_TITLE_LENGTH && notice.chars().count() <= MAX_NOTICE_LENGTH
+}
+
+/// Guilds can only be created at the guild manager NPCs, like `NPC_CH_GUILD`.
+pub(crate) fn is_guild_manager(ref_id: u32) -> bool {
+    WorldData::characters()
+        .find_id(ref_id)
+        .is_some_and(|character| character.common.id.ends_with("_GUILD"))
+}
+
+pub(crate) struct GuildMemberEntry {
+    pub(crate) character_id: u32,
+    pub(crate) name: String,
+    pub(crate) ref_id: u32,
+    pub(crate) level: u8,
+    pub(crate) rank: GuildRank,
+    pub(crate) permissions: GuildPermissions,
+    /// The entity of the member, if they're currently playing.
+    pub(crate) online: Option<Entity>,
+}
+
+impl GuildMemberEntry {
+    pub(crate) fn info(&self) -> GuildMemberInfo {
+        GuildMemberInfo {
+            character_id: self.character_id,
+            name: self.name.clone(),
+            rank: self.rank,
+            permissions: self.permissions,
+            level: self.level,
+            ref_id: self.ref_id,
+            online: self.online.is_some(),
+        }
+    }
+
+    pub(crate) fn is_master(&self) -> bool {
+        self.rank == GuildRank::Master
+    }
+
+    pub(crate) fn can_invite(&self) -> bool {
+        self.is_master() || self.permissions.can_invite()
+    }
+
+    pub(crate) fn can_kick(&self) -> bool {
+        self.is_master() || self.permissions.can_kick()
+    }
+
+    pub(crate) fn can_edit_notice(&self) -> bool {
+        self.is_master() || self.permissions.can_edit_notice()
+    }
+
+    /// Checks if this member ranks above the other member, e.g. to be allowed to kick them.
+    pub(crate) fn outranks(&self, other: &GuildMemberEntry) -> bool {
+        rank_to_db(self.rank) < rank_to_db(other.rank)
+    }
+}
+
+pub(crate) struct Guild {
+    id: GuildId,
+    name: String,
+    level: u8,
+    pub(crate) notice_title: String,
+    pub(crate) notice: String,
+    members: Vec<GuildMemberEntry>,
+}
+
+impl Guild {
+    pub(crate) fn new(id: GuildId, name: String, master: GuildMemberEntry) -> Self {
+        Guild {
+            id,
+            name,
+            level: 1,
+            notice_title: String::new(),
+            notice: String::new(),
+            members: vec![master],
+        }
+    }
+
+    pub(crate) fn id(&self) -> GuildId {
+        self.id
+    }
+
+    pub(crate) fn info(&self) -> GuildInfo {
+        GuildInfo {
+            id: self.id,
+            name: self.name.clone(),
+            level: self.level,
+            notice_title: self.notice_title.clone(),
+            notice: self.notice.clone(),
+            members: self.members.iter().map(GuildMemberEntry::info).collect(),
+        }
+    }
+
+    /// The guild information shown to other players around a member.
+    pub(crate) fn information(&self) -> GuildInformation {
+        GuildInformation::new(self.name.clone(), self.id, String::new(), 0, 0, 0, 1)
+    }
+
+    pub(crate) fn members(&self) -> &[GuildMemberEntry] {
+        &self.members
+    }
+
+    pub(crate) fn member(&self, character_id: u32) -> Option<&GuildMemberEntry> {
+        self.members.iter().find(|member| member.character_id == character_id)
+    }
+
+    pub(crate) fn member_mut(&mut self, character_id: u32) -> Option<&mut GuildMemberEntry> {
+        self.members
+            .iter_mut()
+            .find(|member| member.character_id == character_id)
+    }
+
+    pub(crate) fn member_by_name(&self, name: &str) -> Option<&GuildMemberEntry> {
+        self.members.iter().find(|member| member.name == name)
+    }
+
+    pub(crate) fn online_members(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.members.iter().filter_map(|member| member.online)
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        let limit = MEMBER_LIMITS[(self.level.max(1) as usize - 1).min(MEMBER_LIMITS.len() - 1)];
+        self.members.len() >= limit
+    }
+
+    pub(crate) fn add_member(&mut self, member: GuildMemberEntry) {
+        if self.member(member.character_id).is_none() {
+            self.members.push(member);
+        }
+    }
+
+    pub(crate) fn remove_member(&mut self, character_id: u32) -> Option<GuildMemberEntry> {
+        let index = self
+            .members
+            .iter()
+            .position(|member| member.character_id == character_id)?;
+        Some(self.members.remove(index))
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct Guilds {
+    guilds: HashMap<GuildId, Guild>,
+    /// Names of guilds which are currently being created.
+    reserved_names: HashSet<String>,
+    creations: Vec<GuildCreation>,
+}
+
+impl Guilds {
+    pub(crate) fn from_db(guilds: Vec<GuildData>, members: Vec<GuildMemberData>) -> Self {
+        let mut guilds = guilds
+            .into_iter()
+            .map(|guild| {
+                let id = guild.id as GuildId;
+                let guild = Guild {
+                    id,
+                    name: guild.name,
+                    level: guild.level as u8,
+                    notice_title: guild.notice_title,
+                    notice: guild.notice,
+                    members: Vec::new(),
+                };
+                (id, guild)
+            })
+            .collect::<HashMap<_, _>>();
+
+        for member in members {
+            if let Some(guild) = guilds.get_mut(&(member.guild_id as GuildId)) {
+                guild.members.push(GuildMemberEntry {
+                    character_id: member.character_id as u32,
+                    name: member.charname,
+                    ref_id: member.character_type as u32,
+                    level: member.level as u8,
+                    rank: rank_from_db(member.rank),
+                    permissions: GuildPermissions(member.permissions as u8),
+                    online: None,
+                });
+            }
+        }
+
+        Guilds {
+            guilds,
+            reserved_names: HashSet::new(),
+            creations: Vec::new(),
+        }
+    }
+
+    pub(crate) fn is_name_taken(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.reserved_names.contains(&name) || self.guilds.values().any(|guild| guild.name.to_lowercase() == name)
+    }
+
+    pub(crate) fn reserve_name(&mut self, name: &str) {
+        self.reserved_names.insert(name.to_lowercase());
+    }
+
+    pub(crate) fn release_name(&mut self, name: &str) {
+        self.reserved_names.remove(&name.to_lowercase());
+    }
+
+    /// Keeps track of the guild being created, reserving its name until it is done.
+    pub(crate) fn start_creation(&mut self, creation: GuildCreation) {
+        self.reserve_name(&creation.name);
+        self.creations.push(creation);
+    }
+
+    pub(crate) fn is_creating(&self, character_id: u32) -> bool {
+        self.creations
+            .iter()
+            .any(|creation| creation.master.character_id == character_id)
+    }
+
+    /// Takes all guild creations which have finished, together with their outcome.
+    pub(crate) fn finished_creations(&mut self) -> Vec<(GuildCreation, CreationResult)> {
+        let mut finished = Vec::new();
+        let mut pending = Vec::with_capacity(self.creations.len());
+        for mut creation in self.creations.drain(..) {
+            let result = match creation.task.try_recv() {
+                Ok(Ok(id)) => CreationResult::Created(id),
+                Ok(Err(sqlx::Error::Database(e)))
+                    if e.is_unique_violation() && e.constraint() == Some(GUILD_NAME_CONSTRAINT) =>
+                {
+                    CreationResult::NameTaken
+                },
+                Ok(Err(e)) => {
+                    error!(error = %e, id = creation.master.character_id, "Could not create guild");
+                    CreationResult::Failed
+                },
+                Err(TryRecvError::Empty) => {
+                    pending.push(creation);
+                    continue;
+                },
+                Err(TryRecvError::Closed) => {
+                    error!(id = creation.master.character_id, "Guild creation got lost");
+                    CreationResult::Failed
+                },
+            };
+            finished.push((creation, result));
+        }
+        self.creations = pending;
+        finished
+    }
+
+    pub(crate) fn get(&self, id: GuildId) -> Option<&Guild> {
+        self.guilds.get(&id)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: GuildId) -> Option<&mut Guild> {
+        self.guilds.get_mut(&id)
+    }
+
+    pub(crate) fn insert(&mut self, guild: Guild) {
+        self.release_name(&guild.name);
+        self.guilds.insert(guild.id, guild);
+    }
+
+    pub(crate) fn remove(&mut self, id: GuildId) -> Option<Guild> {
+        self.guilds.remove(&id)
+    }
+
+    pub(crate) fn find_by_character(&self, character_id: u32) -> Option<GuildId> {
+        self.guilds
+            .values()
+            .find(|guild| guild.member(character_id).is_some())
+            .map(|guild| guild.id)
+    }
+
+    pub(crate) fn find_by_online_entity(&mut self, entity: Entity) -> Option<(&mut Guild, u32)> {
+        self.guilds.values_mut().find_map(|guild| {
+            let character_id = guild
+                .members
+                .iter()
+                .find(|member| member.online == Some(entity))?
+                .character_id;
+            Some((guild, character_id))
+        })
+    }
+}
+
+#[derive(Component, Copy, Clone, Deref)]
+pub(crate) struct GuildMember(pub(crate) GuildId);
+
+/// An invitation into a guild, which the invited player has not yet answered.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct GuildInvitation {
+    pub(crate) inviter: Entity,
+    pub(crate) guild: GuildId,
+    pub(crate) timeout: Timer,
+}
+
+impl GuildInvitation {
+    pub(crate) fn new(inviter: Entity, guild: GuildId) -> Self {
+        GuildInvitation {
+            inviter,
+            guild,
+            timeout: Timer::new(INVITATION_TIMEOUT, TimerMode::Once),
+        }
+    }
+}
+
+/// A guild that is currently being inserted into the database, which is where it gets its id.
+/// It is not attached to the master, as the guild exists even if they leave the game before it
+/// is done.
+pub(crate) struct GuildCreation {
+    pub(crate) name: String,
+    pub(crate) master: GuildMemberEntry,
+    pub(crate) task: Receiver<Result<GuildId, sqlx::Error>>,
+}
+
+impl GuildCreation {
+    /// Inserts the guild and takes the cost from the master in a single transaction, after all
+    /// earlier changes of the master have been written. `gold` is the gold the master has left
+    /// after paying for the guild.
+    pub(crate) fn start(name: String, master: GuildMemberEntry, gold: u64, queue: &PersistenceQueue) -> Self {
+        let guild_name = name.clone();
+        let master_id = master.character_id;
+        let task = queue.create_task(&[master_id], move |pool| async move {
+            GuildData::create(guild_name, master_id, GuildPermissions::all().0, gold, pool).await
+        });
+        GuildCreation { name, master, task }
+    }
+}
+
+pub(crate) enum CreationResult {
+    Created(GuildId),
+    NameTaken,
+    Failed,
+}
+
+/// A change to a guild that needs to be written to the database.
+#[derive(Event, Clone)]
+pub(crate) enum GuildChange {
+    MemberAdded {
+        guild: GuildId,
+        character_id: u32,
+        rank: GuildRank,
+        permissions: GuildPermissions,
+    },
+    MemberUpdated {
+        character_id: u32,
+        rank: GuildRank,
+        permissions: GuildPermissions,
+    },
+    MemberRemoved {
+        character_id: u32,
+    },
+    NoticeChanged {
+        guild: GuildId,
+        title: String,
+        notice: String,
+        members: Vec<u32>,
+    },
+    Disbanded {
+        guild: GuildId,
+        members: Vec<u32>,
+    },
+}
+
+impl GuildChange {
+    /// The characters whose earlier changes need to be written before this change.
+    pub(crate) fn characters(&self) -> Vec<u32> {
+        match self {
+            GuildChange::MemberAdded { character_id, .. }
+            | GuildChange::MemberUpdated { character_id, .. }
+            | GuildChange::MemberRemoved { character_id } => vec![*character_id],
+            GuildChange::NoticeChanged { members, .. } | GuildChange::Disbanded { members, .. } => members.clone(),
+        }
+    }
+
+    pub(crate) async fn apply(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        match self {
+            GuildChange::MemberAdded {
+                guild,
+                character_id,
+                rank,
+                permissions,
+            } => GuildMemberData::add(*guild, *character_id, rank_to_db(*rank), permissions.0, pool).await,
+            GuildChange::MemberUpdated {
+                character_id,
+                rank,
+                permissions,
+            } => GuildMemberData::update(*character_id, rank_to_db(*rank), permissions.0, pool).await,
+            GuildChange::MemberRemoved { character_id } => GuildMemberData::remove(*character_id, pool).await,
+            GuildChange::NoticeChanged {
+                guild, title, notice, ..
+            } => GuildData::update_notice(*guild, title.clone(), notice.clone(), pool).await,
+            GuildChange::Disbanded { guild, .. } => GuildData::disband(*guild, pool).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::sync::oneshot;
+
+    fn member(character_id: u32, rank: GuildRank) -> GuildMemberEntry {
+        GuildMemberEntry {
+            character_id,
+            name: format!("Member{character_id}"),
+            ref_id: 1907,
+            level: 20,
+            rank,
+            permissions: GuildPermissions::none(),
+            online: None,
+        }
+    }
+
+    #[test]
+    fn test_ranks() {
+        let master = member(1, GuildRank::Master);
+        let deputy = member(2, GuildRank::Deputy);
+        let regular = member(3, GuildRank::Member);
+        assert!(master.can_kick() && master.can_invite() && master.can_edit_notice());
+        assert!(!deputy.can_kick());
+        assert!(master.outranks(&deputy));
+        assert!(deputy.outranks(&regular));
+        assert!(!regular.outranks(&deputy));
+        assert_eq!(rank_from_db(rank_to_db(GuildRank::Deputy) as i16), GuildRank::Deputy);
+    }
+
+    #[test]
+    fn test_members() {
+        let mut guilds = Guilds::default();
+        guilds.reserve_name("Guild");
+        assert!(guilds.is_name_taken("guild"));
+
+        let mut guild = Guild::new(1, "Guild".to_string(), member(1, GuildRank::Master));
+        for id in 2..=15 {
+            guild.add_member(member(id, GuildRank::Member));
+        }
+        assert!(guild.is_full());
+        assert!(guild.remove_member(15).is_some());
+        assert!(!guild.is_full());
+        assert_eq!(
+            guild.member_by_name("Member2").map(|member| member.character_id),
+            Some(2)
+        );
+
+        guilds.insert(guild);
+        assert!(guilds.is_name_taken("GUILD"));
+        assert_eq!(guilds.find_by_character(3), Some(1));
+        assert_eq!(guilds.find_by_character(15), None);
+    }
+
+    #[test]
+    fn test_creations() {
+        let mut guilds = Guilds::default();
+        let (sender, task) = oneshot::channel();
+        guilds.start_creation(GuildCreation {
+            name: "Guild".to_string(),
+            master: member(1, GuildRank::Master),
+            task,
+        });
+        assert!(guilds.is_name_taken("guild"));
+        assert!(guilds.is_creating(1));
+        assert!(guilds.finished_creations().is_empty());
+
+        sender.send(Ok(7)).unwrap();
+        let finished = guilds.finished_creations();
+        assert!(matches!(finished.as_slice(), [(_, CreationResult::Created(7))]));
+        assert!(!guilds.is_creating(1));
+    }
+
+    #[test]
+    fn test_notice_lengths() {
+        assert!(is_valid_notice("", ""));
+        assert!(is_valid_notice(&"ä".repeat(MAX_NOTICE_TITLE_LENGTH), "Welcome"));
+        assert!(!is_valid_notice(&"a".repeat(MAX_NOTICE_TITLE_LENGTH + 1), ""));
+        assert!(!is_valid_notice("", &"a".repeat(MAX_NOTICE_LENGTH + 1)));
+    }
+
+    #[test]
+    fn test_guild_names() {
+        assert!(is_valid_guild_name("Knights_1"));
+        assert!(!is_valid_guild_name(""));
+        assert!(!is_valid_guild_name("Knights of Ni"));
+        assert!(!is_valid_guild_name("ThisNameIsTooLong"));
+    }
+}
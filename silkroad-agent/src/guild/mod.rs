@@ -0,0 +1,34 @@
+mod component;
+mod system;
+
+use crate::guild::system::{
+    attach_guild_members, finish_guild_creation, handle_guild_creation, handle_guild_input, load_guilds,
+    persist_guild_changes, remove_despawned_guild_members, send_guild_info, tick_guild_invitations,
+    update_guild_member_levels,
+};
+use bevy::prelude::*;
+pub(crate) use component::*;
+
+pub(crate) struct GuildPlugin;
+
+impl Plugin for GuildPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Guilds>()
+            .add_event::<GuildChange>()
+            .add_systems(Startup, load_guilds)
+            .add_systems(
+                Update,
+                (
+                    handle_guild_creation,
+                    finish_guild_creation,
+                    handle_guild_input,
+                    tick_guild_invitations,
+                    attach_guild_members,
+                    send_guild_info,
+                    remove_despawned_guild_members,
+                    update_guild_member_levels,
+                ),
+            )
+            .add_systems(PostUpdate, persist_guild_changes);
+    }
+}
@@ -0,0 +1,746 @@
+use crate::comp::exp::Leveled;
+use crate::comp::gold::GoldPouch;
+use crate::comp::net::Client;
+use crate::comp::npc::NPC;
+use crate::comp::player::Player;
+use crate::comp::pos::Position;
+use crate::comp::visibility::Visibility;
+use crate::comp::GameEntity;
+use crate::db::guild::{GuildData, GuildMemberData};
+use crate::event::LoadingFinishedEvent;
+use crate::exchange::ExchangeInvitation;
+use crate::ext::DbPool;
+use crate::game::exp::LevelUpEvent;
+use crate::guild::component::{
+    is_guild_manager, is_valid_guild_name, is_valid_notice, CreationResult, Guild, GuildChange, GuildCreation,
+    GuildInvitation, GuildMember, GuildMemberEntry, Guilds, GUILD_CREATION_COST, GUILD_CREATION_MIN_LEVEL,
+    NPC_INTERACTION_DISTANCE_SQUARED,
+};
+use crate::input::PlayerInput;
+use crate::party::PartyInvitation;
+use crate::persistence::PersistenceQueue;
+use crate::tasks::TaskCreator;
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use silkroad_protocol::community::{GuildInformation, InvitationRequest};
+use silkroad_protocol::guild::{
+    EntityGuildUpdate, GuildAuthorityResponse, GuildClientProtocol, GuildCreateResponse, GuildDisbandResponse,
+    GuildError, GuildInviteResponse, GuildKickResponse, GuildLeaveReason, GuildLeaveResponse, GuildNoticeResponse,
+    GuildPermissions, GuildRank, GuildResult, GuildUpdate,
+};
+use sqlx::PgPool;
+use tracing::error;
+
+type TargetData<'a> = (
+    &'a Client,
+    &'a GameEntity,
+    &'a Player,
+    &'a Leveled,
+    &'a Visibility,
+    Has<GuildMember>,
+    Has<GuildInvitation>,
+    Has<PartyInvitation>,
+    Has<ExchangeInvitation>,
+);
+
+fn send_to_members(guild: &Guild, clients: &Query<&Client>, update: GuildUpdate) {
+    guild
+        .online_members()
+        .filter_map(|member| clients.get(member).ok())
+        .for_each(|client| client.send(update.clone()));
+}
+
+/// Updates the guild shown for the player, both for the player itself and everyone around them.
+fn announce_guild(
+    client: &Client,
+    game_entity: &GameEntity,
+    visibility: &Visibility,
+    clients: &Query<&Client>,
+    guild: GuildInformation,
+) {
+    let update = EntityGuildUpdate {
+        unique_id: game_entity.unique_id,
+        guild,
+    };
+    visibility
+        .entities_in_radius
+        .iter()
+        .filter_map(|reference| clients.get(reference.0).ok())
+        .for_each(|other| other.send(update.clone()));
+    client.send(update);
+}
+
+/// Removes the member from the guild and notifies the remaining members as well as the removed
+/// member, if they're online.
+fn remove_member(
+    guild: &mut Guild,
+    character_id: u32,
+    reason: GuildLeaveReason,
+    targets: &Query<TargetData>,
+    clients: &Query<&Client>,
+    changes: &mut EventWriter<GuildChange>,
+    cmd: &mut Commands,
+) {
+    let Some(removed) = guild.remove_member(character_id) else {
+        return;
+    };
+
+    let update = GuildUpdate::MemberLeft { character_id, reason };
+    send_to_members(guild, clients, update.clone());
+    if let Some(online) = removed.online {
+        if let Some(mut entity) = cmd.get_entity(online) {
+            entity.remove::<GuildMember>();
+        }
+
+        if let Ok((client, game_entity, _, _, visibility, ..)) = targets.get(online) {
+            client.send(update);
+            announce_guild(client, game_entity, visibility, clients, GuildInformation::default());
+        }
+    }
+    changes.send(GuildChange::MemberRemoved { character_id });
+}
+
+fn set_authority(
+    guild: &mut Guild,
+    character_id: u32,
+    rank: GuildRank,
+    permissions: GuildPermissions,
+    clients: &Query<&Client>,
+    changes: &mut EventWriter<GuildChange>,
+) {
+    let Some(member) = guild.member_mut(character_id) else {
+        return;
+    };
+    member.rank = rank;
+    member.permissions = permissions;
+
+    send_to_members(
+        guild,
+        clients,
+        GuildUpdate::MemberAuthority {
+            character_id,
+            rank,
+            permissions,
+        },
+    );
+    changes.send(GuildChange::MemberUpdated {
+        character_id,
+        rank,
+        permissions,
+    });
+}
+
+/// Checks whether the player with the given id can be invited into a guild.
+fn check_invitation_target<'a>(
+    inviter: Entity,
+    target_id: u32,
+    targets: &'a Query<TargetData>,
+    lookup: &EntityLookup,
+) -> Result<(Entity, &'a Client), GuildError> {
+    let target = lookup
+        .get_entity_for_id(target_id)
+        .filter(|target| *target != inviter)
+        .ok_or(GuildError::InvalidTarget)?;
+    let (client, _, _, _, _, in_guild, guild_invitation, party_invitation, exchange_invitation) =
+        targets.get(target).map_err(|_| GuildError::InvalidTarget)?;
+
+    if in_guild {
+        return Err(GuildError::TargetAlreadyInGuild);
+    }
+
+    if guild_invitation || party_invitation || exchange_invitation {
+        return Err(GuildError::TargetBusy);
+    }
+
+    Ok((target, client))
+}
+
+pub(crate) fn load_guilds(task_creator: Res<TaskCreator>, pool: Res<DbPool>, mut guilds: ResMut<Guilds>) {
+    let (guild_data, member_data) = task_creator
+        .block_on(async {
+            let guilds = GuildData::fetch_all(PgPool::clone(&pool)).await?;
+            let members = GuildMemberData::fetch_all(PgPool::clone(&pool)).await?;
+            Ok::<_, sqlx::Error>((guilds, members))
+        })
+        .expect("Should be able to load guilds");
+    *guilds = Guilds::from_db(guild_data, member_data);
+}
+
+pub(crate) fn handle_guild_creation(
+    mut query: Query<(
+        &Client,
+        &GameEntity,
+        &Player,
+        &PlayerInput,
+        &Position,
+        &Leveled,
+        &mut GoldPouch,
+        Has<GuildMember>,
+    )>,
+    npcs: Query<(&GameEntity, &Position), With<NPC>>,
+    lookup: Res<EntityLookup>,
+    mut guilds: ResMut<Guilds>,
+    queue: Res<PersistenceQueue>,
+) {
+    for (client, game_entity, player, input, position, level, mut gold, in_guild) in query.iter_mut() {
+        for request in input.guild.iter() {
+            let GuildClientProtocol::GuildCreateRequest(create) = request else {
+                continue;
+            };
+
+            if in_guild || guilds.is_creating(player.character.id) {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::AlreadyInGuild)));
+                continue;
+            }
+
+            if level.current_level() < GUILD_CREATION_MIN_LEVEL {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::LevelTooLow)));
+                continue;
+            }
+
+            let Some((npc, npc_position)) = lookup.get_entity_for_id(create.npc).and_then(|npc| npcs.get(npc).ok())
+            else {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::InvalidTarget)));
+                continue;
+            };
+
+            if !is_guild_manager(npc.ref_id) {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::InvalidTarget)));
+                continue;
+            }
+
+            if position.distance_to(npc_position) > NPC_INTERACTION_DISTANCE_SQUARED {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::TooFarAway)));
+                continue;
+            }
+
+            if !is_valid_guild_name(&create.name) {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::InvalidName)));
+                continue;
+            }
+
+            if guilds.is_name_taken(&create.name) {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::NameTaken)));
+                continue;
+            }
+
+            if gold.amount() < GUILD_CREATION_COST {
+                client.send(GuildCreateResponse::new(GuildResult::error(GuildError::NotEnoughGold)));
+                continue;
+            }
+
+            // The gold is taken right away and refunded if the guild could not be created.
+            gold.spend(GUILD_CREATION_COST);
+            let master = GuildMemberEntry {
+                character_id: player.character.id,
+                name: player.character.name.clone(),
+                ref_id: game_entity.ref_id,
+                level: level.current_level(),
+                rank: GuildRank::Master,
+                permissions: GuildPermissions::all(),
+                online: None,
+            };
+            guilds.start_creation(GuildCreation::start(create.name.clone(), master, gold.amount(), &queue));
+            break;
+        }
+    }
+}
+
+/// Gives the gold for a failed guild creation back to a master that is no longer online.
+fn refund_offline(character_id: u32, queue: &PersistenceQueue) {
+    queue.spawn(&[character_id], move |pool| async move {
+        let result = sqlx::query("UPDATE characters SET gold = gold + $1 WHERE id = $2")
+            .bind(GUILD_CREATION_COST as i64)
+            .bind(character_id as i32)
+            .execute(&pool)
+            .await;
+        if let Err(e) = result {
+            error!(error = %e, id = character_id, "Could not refund guild creation");
+        }
+    });
+}
+
+pub(crate) fn finish_guild_creation(
+    mut players: Query<(&Client, &GameEntity, &Player, &Visibility, &mut GoldPouch)>,
+    clients: Query<&Client>,
+    lookup: Res<EntityLookup>,
+    mut guilds: ResMut<Guilds>,
+    queue: Res<PersistenceQueue>,
+    mut cmd: Commands,
+) {
+    for (creation, result) in guilds.finished_creations() {
+        let GuildCreation { name, mut master, .. } = creation;
+        let character_id = master.character_id;
+        // The master may have left, or even come back as a new entity, in the meantime.
+        let online = lookup.get_entity_for_name(&master.name).filter(|entity| {
+            players
+                .get(*entity)
+                .is_ok_and(|(_, _, player, ..)| player.character.id == character_id)
+        });
+
+        let error = match result {
+            CreationResult::Created(id) => {
+                master.online = online;
+                let guild = Guild::new(id, name, master);
+                if let Some(entity) = online {
+                    if let Ok((client, game_entity, _, visibility, _)) = players.get(entity) {
+                        client.send(GuildCreateResponse::new(GuildResult::Success));
+                        client.send(guild.info());
+                        announce_guild(client, game_entity, visibility, &clients, guild.information());
+                    }
+                    cmd.entity(entity).try_insert(GuildMember(id));
+                }
+                guilds.insert(guild);
+                continue;
+            },
+            CreationResult::NameTaken => GuildError::NameTaken,
+            // There is no error for a failure on our side, so this is the closest fit.
+            CreationResult::Failed => GuildError::InvalidTarget,
+        };
+
+        guilds.release_name(&name);
+        match online.and_then(|entity| players.get_mut(entity).ok()) {
+            Some((client, _, _, _, mut gold)) => {
+                gold.gain(GUILD_CREATION_COST);
+                client.send(GuildCreateResponse::new(GuildResult::error(error)));
+            },
+            None => refund_offline(character_id, &queue),
+        }
+    }
+}
+
+pub(crate) fn handle_guild_input(
+    query: Query<(Entity, &PlayerInput, Option<&GuildMember>, Option<&GuildInvitation>)>,
+    targets: Query<TargetData>,
+    clients: Query<&Client>,
+    lookup: Res<EntityLookup>,
+    mut guilds: ResMut<Guilds>,
+    mut changes: EventWriter<GuildChange>,
+    mut cmd: Commands,
+) {
+    for (entity, input, member, invitation) in query.iter() {
+        let Ok((client, game_entity, player, level, visibility, ..)) = targets.get(entity) else {
+            continue;
+        };
+        let character_id = player.character.id;
+
+        for request in input.guild.iter() {
+            match request {
+                // Creating a guild is handled separately, as it requires a trip to the database.
+                GuildClientProtocol::GuildCreateRequest(_) => {},
+                GuildClientProtocol::GuildDisbandRequest(_) => {
+                    let Some(id) = member.map(|member| member.0) else {
+                        client.send(GuildDisbandResponse::new(GuildResult::error(GuildError::NotInGuild)));
+                        continue;
+                    };
+
+                    let is_master = guilds
+                        .get(id)
+                        .and_then(|guild| guild.member(character_id))
+                        .is_some_and(GuildMemberEntry::is_master);
+                    if !is_master {
+                        client.send(GuildDisbandResponse::new(GuildResult::error(GuildError::NoPermission)));
+                        continue;
+                    }
+
+                    let Some(guild) = guilds.remove(id) else {
+                        continue;
+                    };
+
+                    send_to_members(&guild, &clients, GuildUpdate::Disbanded);
+                    for online in guild.online_members() {
+                        if let Some(mut entity) = cmd.get_entity(online) {
+                            entity.remove::<GuildMember>();
+                        }
+
+                        if let Ok((client, game_entity, _, _, visibility, ..)) = targets.get(online) {
+                            announce_guild(client, game_entity, visibility, &clients, GuildInformation::default());
+                        }
+                    }
+                    changes.send(GuildChange::Disbanded {
+                        guild: id,
+                        members: guild.members().iter().map(|member| member.character_id).collect(),
+                    });
+                    client.send(GuildDisbandResponse::new(GuildResult::Success));
+                },
+                GuildClientProtocol::GuildLeaveRequest(_) => {
+                    let Some(guild) = member.and_then(|member| guilds.get_mut(member.0)) else {
+                        client.send(GuildLeaveResponse::new(GuildResult::error(GuildError::NotInGuild)));
+                        continue;
+                    };
+
+                    if guild.member(character_id).is_some_and(GuildMemberEntry::is_master) {
+                        client.send(GuildLeaveResponse::new(GuildResult::error(
+                            GuildError::MasterCannotLeave,
+                        )));
+                        continue;
+                    }
+
+                    remove_member(
+                        guild,
+                        character_id,
+                        GuildLeaveReason::Left,
+                        &targets,
+                        &clients,
+                        &mut changes,
+                        &mut cmd,
+                    );
+                    client.send(GuildLeaveResponse::new(GuildResult::Success));
+                },
+                GuildClientProtocol::GuildInviteRequest(invite) => {
+                    let Some(guild) = member.and_then(|member| guilds.get(member.0)) else {
+                        client.send(GuildInviteResponse::new(GuildResult::error(GuildError::NotInGuild)));
+                        continue;
+                    };
+
+                    if !guild.member(character_id).is_some_and(GuildMemberEntry::can_invite) {
+                        client.send(GuildInviteResponse::new(GuildResult::error(GuildError::NoPermission)));
+                        continue;
+                    }
+
+                    if guild.is_full() {
+                        client.send(GuildInviteResponse::new(GuildResult::error(GuildError::GuildFull)));
+                        continue;
+                    }
+
+                    match check_invitation_target(entity, invite.target, &targets, &lookup) {
+                        Ok((target, target_client)) => {
+                            cmd.entity(target).try_insert(GuildInvitation::new(entity, guild.id()));
+                            target_client.send(InvitationRequest::GuildInvitation {
+                                inviter: game_entity.unique_id,
+                            });
+                        },
+                        Err(error) => client.send(GuildInviteResponse::new(GuildResult::error(error))),
+                    }
+                },
+                GuildClientProtocol::GuildKickRequest(kick) => {
+                    let Some(guild) = member.and_then(|member| guilds.get_mut(member.0)) else {
+                        client.send(GuildKickResponse::new(GuildResult::error(GuildError::NotInGuild)));
+                        continue;
+                    };
+
+                    let Some(own) = guild.member(character_id).filter(|own| own.can_kick()) else {
+                        client.send(GuildKickResponse::new(GuildResult::error(GuildError::NoPermission)));
+                        continue;
+                    };
+
+                    // Members can only kick those below their own rank, which also keeps them
+                    // from kicking themselves or the master.
+                    let Some(target) = guild
+                        .member_by_name(&kick.name)
+                        .filter(|target| own.outranks(target))
+                        .map(|target| target.character_id)
+                    else {
+                        client.send(GuildKickResponse::new(GuildResult::error(GuildError::InvalidTarget)));
+                        continue;
+                    };
+
+                    remove_member(
+                        guild,
+                        target,
+                        GuildLeaveReason::Kicked,
+                        &targets,
+                        &clients,
+                        &mut changes,
+                        &mut cmd,
+                    );
+                    client.send(GuildKickResponse::new(GuildResult::Success));
+                },
+                GuildClientProtocol::GuildNoticeRequest(notice) => {
+                    let Some(guild) = member.and_then(|member| guilds.get_mut(member.0)) else {
+                        client.send(GuildNoticeResponse::new(GuildResult::error(GuildError::NotInGuild)));
+                        continue;
+                    };
+
+                    if !guild
+                        .member(character_id)
+                        .is_some_and(GuildMemberEntry::can_edit_notice)
+                    {
+                        client.send(GuildNoticeResponse::new(GuildResult::error(GuildError::NoPermission)));
+                        continue;
+                    }
+
+                    // There is no error for a notice that is too long, so this is the closest fit.
+                    if !is_valid_notice(&notice.title, &notice.content) {
+                        client.send(GuildNoticeResponse::new(GuildResult::error(GuildError::InvalidTarget)));
+                        continue;
+                    }
+
+                    guild.notice_title = notice.title.clone();
+                    guild.notice = notice.content.clone();
+                    send_to_members(
+                        guild,
+                        &clients,
+                        GuildUpdate::Notice {
+                            title: notice.title.clone(),
+                            content: notice.content.clone(),
+                        },
+                    );
+                    changes.send(GuildChange::NoticeChanged {
+                        guild: guild.id(),
+                        title: notice.title.clone(),
+                        notice: notice.content.clone(),
+                        members: guild.members().iter().map(|member| member.character_id).collect(),
+                    });
+                    client.send(GuildNoticeResponse::new(GuildResult::Success));
+                },
+                GuildClientProtocol::GuildAuthorityRequest(authority) => {
+                    let Some(guild) = member.and_then(|member| guilds.get_mut(member.0)) else {
+                        client.send(GuildAuthorityResponse::new(GuildResult::error(GuildError::NotInGuild)));
+                        continue;
+                    };
+
+                    if !guild.member(character_id).is_some_and(GuildMemberEntry::is_master) {
+                        client.send(GuildAuthorityResponse::new(GuildResult::error(
+                            GuildError::NoPermission,
+                        )));
+                        continue;
+                    }
+
+                    let Some(target) = guild
+                        .member_by_name(&authority.name)
+                        .map(|target| target.character_id)
+                        .filter(|target| *target != character_id)
+                    else {
+                        client.send(GuildAuthorityResponse::new(GuildResult::error(
+                            GuildError::InvalidTarget,
+                        )));
+                        continue;
+                    };
+
+                    if authority.rank == GuildRank::Master {
+                        set_authority(
+                            guild,
+                            character_id,
+                            GuildRank::Deputy,
+                            GuildPermissions::all(),
+                            &clients,
+                            &mut changes,
+                        );
+                        set_authority(
+                            guild,
+                            target,
+                            GuildRank::Master,
+                            GuildPermissions::all(),
+                            &clients,
+                            &mut changes,
+                        );
+                    } else {
+                        set_authority(
+                            guild,
+                            target,
+                            authority.rank,
+                            authority.permissions,
+                            &clients,
+                            &mut changes,
+                        );
+                    }
+                    client.send(GuildAuthorityResponse::new(GuildResult::Success));
+                },
+            }
+        }
+
+        let (Some(response), Some(invitation)) = (input.invitation, invitation) else {
+            continue;
+        };
+        cmd.entity(entity).remove::<GuildInvitation>();
+
+        let respond = |result: GuildResult| {
+            if let Ok(inviter_client) = clients.get(invitation.inviter) {
+                inviter_client.send(GuildInviteResponse::new(result));
+            }
+        };
+
+        if !response.accepted() {
+            respond(GuildResult::error(GuildError::Declined));
+            continue;
+        }
+
+        if member.is_some() {
+            respond(GuildResult::error(GuildError::TargetAlreadyInGuild));
+            continue;
+        }
+
+        let Some(guild) = guilds.get_mut(invitation.guild) else {
+            respond(GuildResult::error(GuildError::InvalidTarget));
+            continue;
+        };
+
+        // The inviter may have left the guild or lost the permission to invite in the meantime.
+        let inviter_can_invite = targets
+            .get(invitation.inviter)
+            .ok()
+            .and_then(|(_, _, inviter, ..)| guild.member(inviter.character.id))
+            .is_some_and(GuildMemberEntry::can_invite);
+        if !inviter_can_invite {
+            respond(GuildResult::error(GuildError::NoPermission));
+            continue;
+        }
+
+        if guild.is_full() {
+            respond(GuildResult::error(GuildError::GuildFull));
+            continue;
+        }
+
+        let entry = GuildMemberEntry {
+            character_id,
+            name: player.character.name.clone(),
+            ref_id: game_entity.ref_id,
+            level: level.current_level(),
+            rank: GuildRank::Member,
+            permissions: GuildPermissions::none(),
+            online: Some(entity),
+        };
+        send_to_members(guild, &clients, GuildUpdate::MemberJoined(entry.info()));
+        guild.add_member(entry);
+        cmd.entity(entity).try_insert(GuildMember(guild.id()));
+
+        client.send(guild.info());
+        announce_guild(client, game_entity, visibility, &clients, guild.information());
+        changes.send(GuildChange::MemberAdded {
+            guild: guild.id(),
+            character_id,
+            rank: GuildRank::Member,
+            permissions: GuildPermissions::none(),
+        });
+        respond(GuildResult::Success);
+    }
+}
+
+pub(crate) fn tick_guild_invitations(
+    mut query: Query<(Entity, &mut GuildInvitation)>,
+    clients: Query<&Client>,
+    time: Res<Time>,
+    mut cmd: Commands,
+) {
+    let delta = time.delta();
+    for (entity, mut invitation) in query.iter_mut() {
+        invitation.timeout.tick(delta);
+        if invitation.timeout.just_finished() {
+            cmd.entity(entity).remove::<GuildInvitation>();
+            if let Ok(client) = clients.get(invitation.inviter) {
+                client.send(GuildInviteResponse::new(GuildResult::error(GuildError::Declined)));
+            }
+        }
+    }
+}
+
+pub(crate) fn persist_guild_changes(mut changes: EventReader<GuildChange>, queue: Res<PersistenceQueue>) {
+    for change in changes.read() {
+        let change = change.clone();
+        queue.spawn(&change.characters(), move |pool| async move {
+            if let Err(e) = change.apply(&pool).await {
+                error!(error = %e, "Could not apply guild change");
+            }
+        });
+    }
+}
+
+/// Links newly spawned players to their guild and lets the other members know that they're now
+/// online.
+pub(crate) fn attach_guild_members(
+    query: Query<(Entity, &Player, &Leveled), Added<Player>>,
+    clients: Query<&Client>,
+    mut guilds: ResMut<Guilds>,
+    mut cmd: Commands,
+) {
+    for (entity, player, level) in query.iter() {
+        let character_id = player.character.id;
+        let Some(guild) = guilds.find_by_character(character_id).and_then(|id| guilds.get_mut(id)) else {
+            continue;
+        };
+
+        if let Some(member) = guild.member_mut(character_id) {
+            member.online = Some(entity);
+            member.level = level.current_level();
+        }
+        cmd.entity(entity).try_insert(GuildMember(guild.id()));
+
+        let update = GuildUpdate::MemberStatus {
+            character_id,
+            online: true,
+        };
+        guild
+            .online_members()
+            .filter(|member| *member != entity)
+            .filter_map(|member| clients.get(member).ok())
+            .for_each(|client| client.send(update.clone()));
+    }
+}
+
+pub(crate) fn send_guild_info(
+    mut events: EventReader<LoadingFinishedEvent>,
+    query: Query<(&Client, &GuildMember)>,
+    guilds: Res<Guilds>,
+) {
+    for event in events.read() {
+        let Ok((client, member)) = query.get(event.0) else {
+            continue;
+        };
+
+        if let Some(guild) = guilds.get(member.0) {
+            client.send(guild.info());
+        }
+    }
+}
+
+/// Marks players as offline in their guild once they're gone, e.g. because they disconnected.
+pub(crate) fn remove_despawned_guild_members(
+    mut removed: RemovedComponents<GuildMember>,
+    still_members: Query<(), With<GuildMember>>,
+    clients: Query<&Client>,
+    mut guilds: ResMut<Guilds>,
+) {
+    for entity in removed.read() {
+        if still_members.contains(entity) {
+            continue;
+        }
+
+        // If the member left regularly, it has already been removed from the guild.
+        let Some((guild, character_id)) = guilds.find_by_online_entity(entity) else {
+            continue;
+        };
+
+        if let Some(member) = guild.member_mut(character_id) {
+            member.online = None;
+        }
+        send_to_members(
+            guild,
+            &clients,
+            GuildUpdate::MemberStatus {
+                character_id,
+                online: false,
+            },
+        );
+    }
+}
+
+pub(crate) fn update_guild_member_levels(
+    mut events: EventReader<LevelUpEvent>,
+    members: Query<(&Player, &GuildMember)>,
+    clients: Query<&Client>,
+    mut guilds: ResMut<Guilds>,
+) {
+    for event in events.read() {
+        let Ok((player, member)) = members.get(event.target.0) else {
+            continue;
+        };
+
+        let Some(guild) = guilds.get_mut(member.0) else {
+            continue;
+        };
+
+        let character_id = player.character.id;
+        if let Some(entry) = guild.member_mut(character_id) {
+            entry.level = event.level;
+        }
+        send_to_members(
+            guild,
+            &clients,
+            GuildUpdate::MemberLevel {
+                character_id,
+                level: event.level,
+            },
+        );
+    }
+}
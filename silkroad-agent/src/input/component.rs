@@ -4,10 +4,17 @@ use silkroad_protocol::auth::{AuthRequest, LogoutRequest};
 use silkroad_protocol::character::{CharacterJoinRequest, CharacterListRequestAction};
 use silkroad_protocol::chat::ChatClientProtocol;
 use silkroad_protocol::combat::PerformAction;
+use silkroad_protocol::community::{FriendListClientProtocol, InvitationResponse};
+use silkroad_protocol::consignment::ConsignmentClientProtocol;
+use silkroad_protocol::exchange::ExchangeClientProtocol;
 use silkroad_protocol::gm::GmCommand;
+use silkroad_protocol::guild::GuildClientProtocol;
 use silkroad_protocol::inventory::InventoryOperation;
 use silkroad_protocol::movement::{MovementTarget, Rotation};
+use silkroad_protocol::party::PartyClientProtocol;
 use silkroad_protocol::skill::{HotbarItem, LearnSkill, LevelUpMastery};
+use silkroad_protocol::stall::StallClientProtocol;
+use silkroad_protocol::storage::StorageOpenRequest;
 use silkroad_protocol::world::{TargetEntity, UnTargetEntity};
 use std::mem;
 
@@ -26,6 +33,14 @@ pub(crate) struct PlayerInput {
     pub skill_add: Option<LearnSkill>,
     pub increase_stats: Vec<StatType>,
     pub hotbar: Option<Vec<HotbarItem>>,
+    pub party: Vec<PartyClientProtocol>,
+    pub friends: Vec<FriendListClientProtocol>,
+    pub guild: Vec<GuildClientProtocol>,
+    pub exchange: Vec<ExchangeClientProtocol>,
+    pub storage: Option<StorageOpenRequest>,
+    pub stall: Vec<StallClientProtocol>,
+    pub consignment: Vec<ConsignmentClientProtocol>,
+    pub invitation: Option<InvitationResponse>,
 }
 
 impl PlayerInput {
@@ -3,18 +3,21 @@ use crate::config::GameConfig;
 use crate::event::{ClientDisconnectedEvent, LoadingFinishedEvent};
 use crate::input::{LoginInput, PlayerInput};
 use crate::mall::event::MallOpenRequestEvent;
-use crate::protocol::AgentClientProtocol;
 use bevy::prelude::*;
 use silkroad_game_base::StatType;
+use silkroad_protocol::agent::AgentClientProtocol;
 use silkroad_protocol::auth::AuthProtocol;
 use silkroad_protocol::character::CharselectClientProtocol;
 use silkroad_protocol::combat::CombatClientProtocol;
+use silkroad_protocol::community::InvitationClientProtocol;
 use silkroad_protocol::general::{BaseProtocol, IdentityInformation};
 use silkroad_protocol::gm::GmClientProtocol;
-use silkroad_protocol::inventory::{ConsignmentResponse, InventoryClientProtocol};
+use silkroad_protocol::inventory::InventoryClientProtocol;
 use silkroad_protocol::movement::MovementClientProtocol;
 use silkroad_protocol::skill::SkillClientProtocol;
+use silkroad_protocol::storage::StorageClientProtocol;
 use silkroad_protocol::world::{GameGuideResponse, StatClientProtocol, WorldClientProtocol};
+use skrillax_server::DisconnectReason;
 use std::time::Instant;
 
 pub(crate) fn reset(mut player_input: Query<&mut PlayerInput>, mut login_input: Query<&mut LoginInput>) {
@@ -53,7 +56,12 @@ pub(crate) fn receive_game_inputs(
                         AgentClientProtocol::MovementClientProtocol(MovementClientProtocol::Rotation(rotate)) => {
                             input.rotation = Some(rotate);
                         },
-                        AgentClientProtocol::FriendListClientProtocol(friend_list) => {},
+                        AgentClientProtocol::FriendListClientProtocol(friend_list) => {
+                            input.friends.push(friend_list);
+                        },
+                        AgentClientProtocol::GuildClientProtocol(guild) => {
+                            input.guild.push(guild);
+                        },
                         AgentClientProtocol::SkillClientProtocol(SkillClientProtocol::LearnSkill(skill)) => {
                             input.skill_add = Some(skill);
                         },
@@ -91,9 +99,6 @@ pub(crate) fn receive_game_inputs(
                             InventoryClientProtocol::InventoryOperation(inventory) => {
                                 input.inventory = Some(inventory);
                             },
-                            InventoryClientProtocol::ConsignmentList(_) => {
-                                client.send(ConsignmentResponse::success_empty());
-                            },
                         },
                         AgentClientProtocol::AuthProtocol(AuthProtocol::LogoutRequest(logout)) => {
                             input.logout = Some(logout);
@@ -101,6 +106,28 @@ pub(crate) fn receive_game_inputs(
                         AgentClientProtocol::GmClientProtocol(GmClientProtocol::GmCommand(command)) => {
                             input.gm = Some(command);
                         },
+                        AgentClientProtocol::PartyClientProtocol(party) => {
+                            input.party.push(party);
+                        },
+                        AgentClientProtocol::ExchangeClientProtocol(exchange) => {
+                            input.exchange.push(exchange);
+                        },
+                        AgentClientProtocol::StorageClientProtocol(StorageClientProtocol::StorageOpenRequest(
+                            request,
+                        )) => {
+                            input.storage = Some(request);
+                        },
+                        AgentClientProtocol::StallClientProtocol(stall) => {
+                            input.stall.push(stall);
+                        },
+                        AgentClientProtocol::ConsignmentClientProtocol(consignment) => {
+                            input.consignment.push(consignment);
+                        },
+                        AgentClientProtocol::InvitationClientProtocol(
+                            InvitationClientProtocol::InvitationResponse(response),
+                        ) => {
+                            input.invitation = Some(response);
+                        },
                         _ => {},
                     }
                 },
@@ -108,7 +135,8 @@ pub(crate) fn receive_game_inputs(
                     break;
                 },
                 Err(_) => {
-                    disconnect_events.send(ClientDisconnectedEvent(entity));
+                    let reason = client.disconnect_reason().unwrap_or(DisconnectReason::PeerClosed);
+                    disconnect_events.send(ClientDisconnectedEvent(entity, reason));
                     break;
                 },
             }
@@ -120,7 +148,8 @@ pub(crate) fn receive_game_inputs(
         }
 
         if last_tick_time.duration_since(last_action.0).as_secs() > settings.client_timeout.into() {
-            disconnect_events.send(ClientDisconnectedEvent(entity));
+            // The disconnect event will be sent once we notice the connection being closed.
+            client.disconnect(DisconnectReason::Timeout);
         }
     }
 }
@@ -160,7 +189,8 @@ pub(crate) fn receive_login_inputs(
                     break;
                 },
                 Err(_) => {
-                    disconnect_events.send(ClientDisconnectedEvent(entity));
+                    let reason = client.disconnect_reason().unwrap_or(DisconnectReason::PeerClosed);
+                    disconnect_events.send(ClientDisconnectedEvent(entity, reason));
                     break;
                 },
             }
@@ -172,7 +202,8 @@ pub(crate) fn receive_login_inputs(
         }
 
         if last_tick_time.duration_since(last_action.0).as_secs() > settings.client_timeout.into() {
-            disconnect_events.send(ClientDisconnectedEvent(entity));
+            // The disconnect event will be sent once we notice the connection being closed.
+            client.disconnect(DisconnectReason::Timeout);
         }
     }
 }
@@ -180,3 +211,75 @@ pub(crate) fn receive_login_inputs(
 fn send_identity_information(client: &Client) {
     client.send(IdentityInformation::new("AgentServer".to_string(), 0))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use silkroad_protocol::movement::Rotation;
+    use skrillax_server::{Connection, LoopbackHandle, LoopbackOptions};
+
+    fn setup() -> (World, LoopbackHandle<AgentClientProtocol>, Entity) {
+        let (connection, handle) = Connection::<AgentClientProtocol>::loopback(LoopbackOptions::default());
+        let mut world = World::new();
+        world.init_resource::<Time<Real>>();
+        world.insert_resource(GameConfig {
+            client_timeout: 30,
+            ..Default::default()
+        });
+        world.init_resource::<Events<LoadingFinishedEvent>>();
+        world.init_resource::<Events<ClientDisconnectedEvent>>();
+        world.init_resource::<Events<MallOpenRequestEvent>>();
+        let entity = world
+            .spawn((
+                Client(connection),
+                LastAction(Instant::now()),
+                PlayerInput::default(),
+                LoginInput::default(),
+            ))
+            .id();
+        (world, handle, entity)
+    }
+
+    #[test]
+    fn test_identity_during_login() {
+        let (mut world, handle, _) = setup();
+        handle
+            .inject(AgentClientProtocol::BaseProtocol(BaseProtocol::IdentityInformation(
+                IdentityInformation::new("SR_Client".to_string(), 0),
+            )))
+            .unwrap();
+        world.run_system_once(receive_login_inputs).unwrap();
+
+        let identity = handle.expect_packet::<IdentityInformation>();
+        assert_eq!("AgentServer", identity.module_name);
+        assert!(handle.next_packet().is_none());
+    }
+
+    #[test]
+    fn test_game_input() {
+        let (mut world, handle, entity) = setup();
+        handle
+            .inject(AgentClientProtocol::MovementClientProtocol(
+                MovementClientProtocol::Rotation(Rotation { heading: 100 }),
+            ))
+            .unwrap();
+        world.run_system_once(receive_game_inputs).unwrap();
+
+        let input = world.get::<PlayerInput>(entity).unwrap();
+        assert_eq!(Some(100), input.rotation.as_ref().map(|rotation| rotation.heading));
+        assert!(world.resource::<Events<ClientDisconnectedEvent>>().is_empty());
+    }
+
+    #[test]
+    fn test_client_disconnect() {
+        let (mut world, handle, entity) = setup();
+        handle.close();
+        world.run_system_once(receive_game_inputs).unwrap();
+
+        let disconnects = world.resource::<Events<ClientDisconnectedEvent>>();
+        let event = disconnects.iter_current_update_events().next().unwrap();
+        assert_eq!(entity, event.0);
+        assert_eq!(DisconnectReason::PeerClosed, event.1);
+    }
+}
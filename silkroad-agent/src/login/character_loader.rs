@@ -1,9 +1,11 @@
 use crate::db::character::{
-    CharacterData, CharacterHotbar, CharacterItem, CharacterMastery, CharacterSkill, HotbarEntry,
+    CharacterData, CharacterFriend, CharacterFriendGroup, CharacterHotbar, CharacterItem, CharacterMastery,
+    CharacterSkill, CharacterStorageGold, HotbarEntry,
 };
 use itertools::Itertools;
 use sqlx::PgPool;
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use tracing::{debug, instrument};
 
 #[derive(Clone)]
@@ -13,6 +15,10 @@ pub struct DbCharacter {
     pub(crate) masteries: Vec<CharacterMastery>,
     pub(crate) skills: Vec<CharacterSkill>,
     pub(crate) hotbar: Vec<HotbarEntry>,
+    pub(crate) friends: Vec<CharacterFriend>,
+    pub(crate) friend_groups: Vec<CharacterFriendGroup>,
+    pub(crate) storage_items: Vec<CharacterItem>,
+    pub(crate) storage_gold: u64,
 }
 
 impl DbCharacter {
@@ -42,6 +48,29 @@ impl DbCharacter {
             .into_iter()
             .into_group_map_by(|e| e.character_id);
 
+        let mut friends = CharacterFriend::fetch_for_characters(&character_ids, pool.borrow())
+            .await
+            .unwrap()
+            .into_iter()
+            .into_group_map_by(|f| f.character_id);
+
+        let mut friend_groups = CharacterFriendGroup::fetch_for_characters(&character_ids, pool.borrow())
+            .await
+            .unwrap()
+            .into_iter()
+            .into_group_map_by(|g| g.character_id);
+
+        let mut storage_items = CharacterItem::fetch_bulk_storage_items(&character_ids, pool.borrow())
+            .await
+            .unwrap();
+
+        let storage_gold: HashMap<i32, i64> = CharacterStorageGold::fetch_for_characters(&character_ids, pool.borrow())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|storage| (storage.character_id, storage.storage_gold))
+            .collect();
+
         let mut all_characters = Vec::new();
 
         for character in characters {
@@ -49,6 +78,10 @@ impl DbCharacter {
             let masteries = character_masteries.remove(&character.id).unwrap_or_default();
             let skills = character_skills.remove(&character.id).unwrap_or_default();
             let hotbar = hotbar_entries.remove(&character.id).unwrap_or_default();
+            let friends = friends.remove(&character.id).unwrap_or_default();
+            let friend_groups = friend_groups.remove(&character.id).unwrap_or_default();
+            let storage_items = storage_items.remove(&character.id).unwrap_or_default();
+            let storage_gold = storage_gold.get(&character.id).copied().unwrap_or_default() as u64;
 
             all_characters.push(DbCharacter {
                 character_data: character,
@@ -56,6 +89,10 @@ impl DbCharacter {
                 masteries,
                 skills,
                 hotbar,
+                friends,
+                friend_groups,
+                storage_items,
+                storage_gold,
             });
         }
 
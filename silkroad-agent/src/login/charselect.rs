@@ -1,6 +1,6 @@
 use crate::agent::component::Agent;
 use crate::comp::gold::GoldPouch;
-use crate::comp::inventory::PlayerInventory;
+use crate::comp::inventory::{item_content_data, PlayerInventory};
 use crate::comp::net::Client;
 use crate::comp::player::{Player, PlayerBundle};
 use crate::comp::pos::Position;
@@ -10,6 +10,7 @@ use crate::comp::{GameEntity, Playing};
 use crate::config::GameConfig;
 use crate::db::character::{CharacterData, CharacterItem, DbRace};
 use crate::ext::{DbPool, EntityIdPool};
+use crate::friends::FriendList;
 use crate::input::LoginInput;
 use crate::login::character_loader::DbCharacter;
 use crate::login::job_distribution::JobDistribution;
@@ -18,21 +19,20 @@ use crate::login::{
 };
 use crate::population::{LoginQueue, ReservationError};
 use crate::server_plugin::ServerId;
+use crate::storage::PlayerStorage;
 use crate::tasks::TaskCreator;
 use crate::world::WorldData;
 use bevy::prelude::*;
 use cgmath::Vector3;
 use chrono::{TimeZone, Utc};
 use silkroad_data::DataEntry;
-use silkroad_game_base::{Heading, ItemTypeData, LocalPosition};
+use silkroad_game_base::{Heading, LocalPosition};
 use silkroad_protocol::auth::{AuthResponse, AuthResult, AuthResultError, UnknownLargePacket};
 use silkroad_protocol::character::{
     CharacterJoinResponse, CharacterListAction, CharacterListContent, CharacterListError, CharacterListRequestAction,
     CharacterListResponse, CharacterListResult, MacroStatus, UnknownPacket, UnknownPacket2, MACRO_POTION,
 };
-use silkroad_protocol::inventory::{
-    BagContent, InventoryItemBindingData, InventoryItemContentData, InventoryItemData, RentInfo,
-};
+use silkroad_protocol::inventory::{BagContent, InventoryItemData, RentInfo};
 use silkroad_protocol::skill::{HotbarItem, MasteryData, SkillData};
 use silkroad_protocol::spawn::{CharacterSpawn, CharacterSpawnEnd, CharacterSpawnStart, JobInformation};
 use silkroad_protocol::world::{ActionState, AliveState, BodyState, EntityState};
@@ -239,6 +239,8 @@ pub(crate) fn handle_join(
                             Visibility::with_radius(500.),
                             hotbar,
                         ))
+                        .insert(FriendList::from_db(&character.friend_groups, &character.friends))
+                        .insert(PlayerStorage::from_db(&character.storage_items, character.storage_gold))
                         .remove::<CharacterSelect>()
                         .remove::<LoginInput>();
                 },
@@ -336,6 +338,7 @@ fn send_spawn(
         walk_speed: 16.0,
         run_speed: 50.0,
         berserk_speed: 100.0,
+        // Buffs are not persisted, so a character that just joined cannot have any.
         active_buffs: vec![],
     };
 
@@ -345,20 +348,7 @@ fn send_spawn(
             slot: *slot,
             rent_data: RentInfo::Empty,
             item_id: item.reference.ref_id(),
-            content_data: match item.type_data {
-                ItemTypeData::Equipment { upgrade_level } => InventoryItemContentData::Equipment {
-                    plus_level: upgrade_level,
-                    variance: item.variance.unwrap_or_default(),
-                    durability: 1,
-                    magic: vec![],
-                    bindings_1: InventoryItemBindingData::new(1, 0),
-                    bindings_2: InventoryItemBindingData::new(2, 0),
-                    bindings_3: InventoryItemBindingData::new(3, 0),
-                    bindings_4: InventoryItemBindingData::new(4, 0),
-                },
-                ItemTypeData::Consumable { amount } => InventoryItemContentData::Expendable { stack_size: amount },
-                _ => panic!("Missing inventory type representation."),
-            },
+            content_data: item_content_data(item),
         })
         .collect();
 
@@ -551,5 +541,9 @@ pub(crate) fn create_character_from(
         masteries: vec![],
         skills: vec![],
         hotbar: vec![], // TODO fill with default actions
+        friends: vec![],
+        friend_groups: vec![],
+        storage_items: vec![],
+        storage_gold: 0,
     }
 }
@@ -5,18 +5,25 @@ mod chat;
 mod cmd;
 mod comp;
 mod config;
+mod consignment;
 mod db;
 mod event;
+mod exchange;
 mod ext;
+mod friends;
 mod game;
+mod guild;
 mod input;
 mod login;
 mod mall;
 mod net;
+mod party;
 mod persistence;
 mod population;
-mod protocol;
 mod server_plugin;
+mod shop;
+mod stall;
+mod storage;
 mod sync;
 mod tasks;
 mod world;
@@ -45,7 +52,7 @@ use rand::{rng, Rng};
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{error, info};
 
 fn main() {
     tracing_subscriber::fmt::init();
@@ -58,6 +65,14 @@ fn main() {
     }
     .expect("External address should be 'ip:port'.");
 
+    let listen_addrs = match configuration.listen_address.socket_addrs(configuration.listen_port) {
+        Ok(addrs) => addrs,
+        Err(e) => {
+            error!(error = %e, "Invalid listen address");
+            return;
+        },
+    };
+
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .worker_threads(4)
@@ -97,10 +112,6 @@ fn main() {
         configuration.rpc_port,
     ));
 
-    let listen_addr = format!("{}:{}", configuration.listen_address, configuration.listen_port)
-        .parse()
-        .expect("Just created address should be in a valid format");
-
     info!("Listening for clients");
     App::new()
         .add_plugins(TimePlugin)
@@ -108,7 +119,12 @@ fn main() {
         .insert_resource::<TaskCreator>(runtime.clone().into())
         .insert_resource::<DbPool>(db_pool.into())
         .add_plugins(ServerPlugin::new(configuration.game.clone(), server_id))
-        .add_plugins(NetworkPlugin::new(listen_addr, runtime))
+        .add_plugins(NetworkPlugin::new(
+            listen_addrs,
+            configuration.network.server_options(),
+            configuration.network.traffic_report_interval(),
+            runtime,
+        ))
         .add_plugins(ReceivePlugin)
         .add_plugins(SynchronizationPlugin)
         .add_plugins(AgentPlugin)
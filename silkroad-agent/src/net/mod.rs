@@ -1,16 +1,19 @@
 use crate::event::{ClientConnectedEvent, ClientDisconnectedEvent};
 use crate::ext::ServerResource;
-use crate::net::net::{accept, connected, disconnected};
+use crate::net::net::{accept, connected, disconnected, report_traffic, TrafficReport};
 use bevy::prelude::*;
-use skrillax_server::Server;
+use skrillax_server::{Server, ServerOptions};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
 mod net;
 
 pub struct NetworkPlugin {
-    server: SocketAddr,
+    server: Vec<SocketAddr>,
+    options: ServerOptions,
+    traffic_report: Option<Duration>,
     runtime: Arc<Runtime>,
 }
 
@@ -19,7 +22,7 @@ impl Plugin for NetworkPlugin {
         // Need to run this inside a `block_on` to ensure we're inside tokio and can
         // `spawn()` more tasks.
         let server = self.runtime.block_on(async {
-            Server::new(self.server)
+            Server::new(&self.server, self.options.clone())
                 .expect("Should be able to create the server")
                 .into()
         });
@@ -28,11 +31,26 @@ impl Plugin for NetworkPlugin {
             .add_systems(First, (accept, disconnected, connected))
             .add_event::<ClientDisconnectedEvent>()
             .add_event::<ClientConnectedEvent>();
+
+        if let Some(interval) = self.traffic_report {
+            app.insert_resource(TrafficReport(Timer::new(interval, TimerMode::Repeating)))
+                .add_systems(Last, report_traffic);
+        }
     }
 }
 
 impl NetworkPlugin {
-    pub fn new(server: SocketAddr, runtime: Arc<Runtime>) -> Self {
-        Self { server, runtime }
+    pub fn new(
+        server: Vec<SocketAddr>,
+        options: ServerOptions,
+        traffic_report: Option<Duration>,
+        runtime: Arc<Runtime>,
+    ) -> Self {
+        Self {
+            server,
+            options,
+            traffic_report,
+            runtime,
+        }
     }
 }
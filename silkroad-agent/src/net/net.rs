@@ -39,7 +39,7 @@ pub(crate) fn disconnected(
 ) {
     for event in events.read() {
         let entity = event.0;
-        debug!("Handling client disconnect.");
+        debug!(reason = %event.1, "Handling client disconnect.");
         if let Ok(player) = query.get(event.0) {
             let id = player.character.id;
             task_creator.spawn(CharacterData::update_last_played_of(id, pool.clone()));
@@ -55,3 +55,14 @@ pub(crate) fn connected(mut events: EventReader<ClientConnectedEvent>) {
         // ..
     }
 }
+
+#[derive(Resource)]
+pub(crate) struct TrafficReport(pub(crate) Timer);
+
+pub(crate) fn report_traffic(mut report: ResMut<TrafficReport>, network: Res<ServerResource>, time: Res<Time<Real>>) {
+    if !report.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    network.report_traffic();
+}
@@ -0,0 +1,255 @@
+use crate::comp::EntityReference;
+use bevy::prelude::*;
+use derive_more::Deref;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub(crate) const MAX_PARTY_MEMBERS: usize = 8;
+const INVITATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) type PartyId = u32;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum ExperienceDistribution {
+    /// Each member receives experience for their own share of the damage dealt.
+    Individual,
+    /// The experience is split evenly between all members close to the kill.
+    Shared,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum ItemDistribution {
+    /// Drops belong to the member who killed the monster.
+    Individual,
+    /// Drops belong to the whole party and may be picked up by any member.
+    Shared,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) struct PartySettings {
+    pub(crate) experience: ExperienceDistribution,
+    pub(crate) items: ItemDistribution,
+    pub(crate) anyone_can_invite: bool,
+}
+
+impl From<silkroad_protocol::party::PartySettings> for PartySettings {
+    fn from(settings: silkroad_protocol::party::PartySettings) -> Self {
+        PartySettings {
+            experience: if settings.experience_shared() {
+                ExperienceDistribution::Shared
+            } else {
+                ExperienceDistribution::Individual
+            },
+            items: if settings.items_shared() {
+                ItemDistribution::Shared
+            } else {
+                ItemDistribution::Individual
+            },
+            anyone_can_invite: settings.anyone_can_invite(),
+        }
+    }
+}
+
+impl From<PartySettings> for silkroad_protocol::party::PartySettings {
+    fn from(settings: PartySettings) -> Self {
+        silkroad_protocol::party::PartySettings::new(
+            settings.experience == ExperienceDistribution::Shared,
+            settings.items == ItemDistribution::Shared,
+            settings.anyone_can_invite,
+        )
+    }
+}
+
+pub(crate) struct Party {
+    leader: EntityReference,
+    members: Vec<EntityReference>,
+    settings: PartySettings,
+}
+
+impl Party {
+    fn new(leader: EntityReference, settings: PartySettings) -> Self {
+        Party {
+            leader,
+            members: vec![leader],
+            settings,
+        }
+    }
+
+    pub(crate) fn leader(&self) -> EntityReference {
+        self.leader
+    }
+
+    pub(crate) fn is_leader(&self, entity: Entity) -> bool {
+        self.leader.0 == entity
+    }
+
+    pub(crate) fn members(&self) -> &[EntityReference] {
+        &self.members
+    }
+
+    pub(crate) fn member(&self, unique_id: u32) -> Option<EntityReference> {
+        self.members
+            .iter()
+            .find(|member| member.1.unique_id == unique_id)
+            .copied()
+    }
+
+    pub(crate) fn settings(&self) -> PartySettings {
+        self.settings
+    }
+
+    pub(crate) fn contains(&self, entity: Entity) -> bool {
+        self.members.iter().any(|member| member.0 == entity)
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.members.len() >= MAX_PARTY_MEMBERS
+    }
+
+    pub(crate) fn can_invite(&self, entity: Entity) -> bool {
+        self.is_leader(entity) || (self.settings.anyone_can_invite && self.contains(entity))
+    }
+
+    pub(crate) fn add_member(&mut self, member: EntityReference) {
+        if !self.contains(member.0) {
+            self.members.push(member);
+        }
+    }
+
+    /// Removes the member from the party. If the member was the leader, the member who joined
+    /// the party the earliest becomes the new leader.
+    pub(crate) fn remove_member(&mut self, entity: Entity) -> bool {
+        let before = self.members.len();
+        self.members.retain(|member| member.0 != entity);
+        if self.leader.0 == entity {
+            if let Some(next) = self.members.first() {
+                self.leader = *next;
+            }
+        }
+        before != self.members.len()
+    }
+
+    pub(crate) fn set_leader(&mut self, entity: Entity) -> bool {
+        match self.members.iter().find(|member| member.0 == entity) {
+            Some(member) => {
+                self.leader = *member;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// A party needs at least two members, otherwise it will be dismissed.
+    pub(crate) fn should_dismiss(&self) -> bool {
+        self.members.len() < 2
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct Parties {
+    last_id: PartyId,
+    parties: HashMap<PartyId, Party>,
+}
+
+impl Parties {
+    pub(crate) fn create(&mut self, leader: EntityReference, settings: PartySettings) -> PartyId {
+        self.last_id = self.last_id.wrapping_add(1).max(1);
+        self.parties.insert(self.last_id, Party::new(leader, settings));
+        self.last_id
+    }
+
+    pub(crate) fn get(&self, id: PartyId) -> Option<&Party> {
+        self.parties.get(&id)
+    }
+
+    pub(crate) fn get_mut(&mut self, id: PartyId) -> Option<&mut Party> {
+        self.parties.get_mut(&id)
+    }
+
+    pub(crate) fn remove(&mut self, id: PartyId) -> Option<Party> {
+        self.parties.remove(&id)
+    }
+
+    pub(crate) fn find_party_of(&self, entity: Entity) -> Option<PartyId> {
+        self.parties
+            .iter()
+            .find(|(_, party)| party.contains(entity))
+            .map(|(id, _)| *id)
+    }
+}
+
+#[derive(Component, Copy, Clone, Deref)]
+pub(crate) struct PartyMember(pub(crate) PartyId);
+
+/// An invitation into a party, which the invited player has not yet answered. If `party` is
+/// `None`, the party will be created once the invitation is accepted.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct PartyInvitation {
+    pub(crate) inviter: Entity,
+    pub(crate) party: Option<PartyId>,
+    pub(crate) settings: PartySettings,
+    pub(crate) timeout: Timer,
+}
+
+impl PartyInvitation {
+    pub(crate) fn new(inviter: Entity, party: Option<PartyId>, settings: PartySettings) -> Self {
+        PartyInvitation {
+            inviter,
+            party,
+            settings,
+            timeout: Timer::new(INVITATION_TIMEOUT, TimerMode::Once),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::GameEntity;
+
+    fn reference(id: u32) -> EntityReference {
+        EntityReference(
+            Entity::from_raw(id),
+            GameEntity {
+                unique_id: id,
+                ref_id: 1907,
+            },
+        )
+    }
+
+    fn settings() -> PartySettings {
+        PartySettings {
+            experience: ExperienceDistribution::Shared,
+            items: ItemDistribution::Individual,
+            anyone_can_invite: false,
+        }
+    }
+
+    #[test]
+    fn test_settings_conversion() {
+        let protocol: silkroad_protocol::party::PartySettings = settings().into();
+        assert_eq!(protocol, silkroad_protocol::party::PartySettings(0b001));
+        assert_eq!(PartySettings::from(protocol), settings());
+    }
+
+    #[test]
+    fn test_leader_moves_on() {
+        let leader = reference(1);
+        let member = reference(2);
+        let mut parties = Parties::default();
+        let id = parties.create(leader, settings());
+        let party = parties.get_mut(id).unwrap();
+        party.add_member(member);
+        assert!(party.can_invite(leader.0));
+        assert!(!party.can_invite(member.0));
+        assert_eq!(party.member(2).map(|member| member.0), Some(member.0));
+
+        assert!(party.remove_member(leader.0));
+        assert!(party.is_leader(member.0));
+        assert!(!party.can_invite(leader.0));
+        assert!(party.should_dismiss());
+        assert_eq!(parties.find_party_of(member.0), Some(id));
+        assert_eq!(parties.find_party_of(leader.0), None);
+    }
+}
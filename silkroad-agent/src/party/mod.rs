@@ -0,0 +1,37 @@
+mod component;
+mod system;
+
+use crate::party::system::{
+    handle_party_input, remove_despawned_members, tick_invitations, update_member_bars, update_member_levels,
+    update_member_positions,
+};
+use bevy::prelude::*;
+use bevy::time::common_conditions::on_timer;
+pub(crate) use component::*;
+use std::time::Duration;
+
+const POSITION_UPDATE_INTERVAL: Duration = Duration::from_secs(2);
+
+pub(crate) struct PartyPlugin;
+
+impl Plugin for PartyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Parties>()
+            .add_systems(
+                Update,
+                (
+                    handle_party_input,
+                    tick_invitations,
+                    remove_despawned_members,
+                    update_member_levels,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    update_member_bars,
+                    update_member_positions.run_if(on_timer(POSITION_UPDATE_INTERVAL)),
+                ),
+            );
+    }
+}
@@ -0,0 +1,515 @@
+use crate::comp::exp::Leveled;
+use crate::comp::mastery::MasteryKnowledge;
+use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::comp::pos::Position;
+use crate::comp::{EntityReference, GameEntity, Health, Mana};
+use crate::exchange::ExchangeInvitation;
+use crate::game::exp::LevelUpEvent;
+use crate::guild::GuildInvitation;
+use crate::input::PlayerInput;
+use crate::party::component::{Parties, Party, PartyId, PartyInvitation, PartyMember};
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use silkroad_protocol::community::InvitationRequest;
+use silkroad_protocol::party::{
+    PartyClientProtocol, PartyCreateResponse, PartyError, PartyInfo, PartyInviteResponse, PartyKickResponse,
+    PartyLeaderTransferResponse, PartyLeaveReason, PartyLeaveResponse, PartyMemberBars, PartyMemberInfo,
+    PartyMemberPosition, PartyMemberUpdate, PartyResult, PartyUpdate,
+};
+
+type MemberData<'a> = (
+    &'a GameEntity,
+    &'a Player,
+    &'a Leveled,
+    &'a Health,
+    &'a Mana,
+    &'a Position,
+    &'a MasteryKnowledge,
+);
+
+type TargetData<'a> = (
+    &'a GameEntity,
+    Option<&'a PartyInvitation>,
+    Has<GuildInvitation>,
+    Has<ExchangeInvitation>,
+);
+
+fn member_info((game_entity, player, level, health, mana, position, masteries): MemberData) -> PartyMemberInfo {
+    let mut highest = masteries.highest();
+    PartyMemberInfo::new(
+        game_entity.unique_id,
+        player.character.name.clone(),
+        game_entity.ref_id,
+        level.current_level(),
+        PartyMemberBars::new(
+            health.current_health,
+            health.max_health,
+            mana.current_mana,
+            mana.max_mana,
+        ),
+        member_position(position),
+        [highest.next().unwrap_or(0), highest.next().unwrap_or(0)],
+    )
+}
+
+fn member_position(position: &Position) -> PartyMemberPosition {
+    let local = position.position().to_local();
+    // The height is unsigned in the protocol, so anything below the ground level of the region is
+    // sent as being on the ground instead.
+    let height = local.1.y.clamp(0.0, f32::from(u16::MAX)) as u16;
+    PartyMemberPosition::new(local.0.id(), local.1.x as u16, height, local.1.z as u16)
+}
+
+fn party_info(id: PartyId, party: &Party, members: &Query<MemberData>) -> PartyInfo {
+    PartyInfo::new(
+        id,
+        party.leader().1.unique_id,
+        party.settings().into(),
+        party
+            .members()
+            .iter()
+            .filter_map(|member| members.get(member.0).ok())
+            .map(member_info)
+            .collect(),
+    )
+}
+
+fn send_to_members(party: &Party, clients: &Query<&Client>, update: PartyUpdate) {
+    party
+        .members()
+        .iter()
+        .filter_map(|member| clients.get(member.0).ok())
+        .for_each(|client| client.send(update.clone()));
+}
+
+fn send_to_others(party: &Party, entity: Entity, clients: &Query<&Client>, update: PartyUpdate) {
+    party
+        .members()
+        .iter()
+        .filter(|member| member.0 != entity)
+        .filter_map(|member| clients.get(member.0).ok())
+        .for_each(|client| client.send(update.clone()));
+}
+
+/// Removes the member from its party and notifies the remaining members. If the party is left
+/// with a single member, it will be dismissed.
+fn leave_party(
+    parties: &mut Parties,
+    id: PartyId,
+    member: EntityReference,
+    reason: PartyLeaveReason,
+    clients: &Query<&Client>,
+    cmd: &mut Commands,
+) {
+    let Some(party) = parties.get_mut(id).filter(|party| party.contains(member.0)) else {
+        return;
+    };
+
+    send_to_members(
+        party,
+        clients,
+        PartyUpdate::MemberLeft {
+            member: member.1.unique_id,
+            reason,
+        },
+    );
+
+    let previous_leader = party.leader();
+    party.remove_member(member.0);
+    if let Some(mut entity) = cmd.get_entity(member.0) {
+        entity.remove::<PartyMember>();
+    }
+
+    if party.should_dismiss() {
+        send_to_members(party, clients, PartyUpdate::Dismissed);
+        for remaining in party.members() {
+            if let Some(mut entity) = cmd.get_entity(remaining.0) {
+                entity.remove::<PartyMember>();
+            }
+        }
+        parties.remove(id);
+    } else if previous_leader != party.leader() {
+        send_to_members(
+            party,
+            clients,
+            PartyUpdate::LeaderChanged {
+                leader: party.leader().1.unique_id,
+            },
+        );
+    }
+}
+
+pub(crate) fn handle_party_input(
+    query: Query<(Entity, &Client, &GameEntity, &PlayerInput, Option<&PartyInvitation>)>,
+    targets: Query<TargetData, With<Player>>,
+    members: Query<MemberData>,
+    clients: Query<&Client>,
+    lookup: Res<EntityLookup>,
+    mut parties: ResMut<Parties>,
+    mut cmd: Commands,
+) {
+    for (entity, client, game_entity, input, invitation) in query.iter() {
+        let own_reference = EntityReference(entity, *game_entity);
+        for request in input.party.iter() {
+            match request {
+                PartyClientProtocol::PartyCreateRequest(create) => {
+                    if parties.find_party_of(entity).is_some() {
+                        client.send(PartyCreateResponse::new(PartyResult::error(PartyError::AlreadyInParty)));
+                        continue;
+                    }
+
+                    match check_invitation_target(entity, create.target, &targets, &clients, &lookup, &parties) {
+                        Ok((target, target_client)) => {
+                            let settings = create.settings.into();
+                            cmd.entity(target)
+                                .try_insert(PartyInvitation::new(entity, None, settings));
+                            target_client.send(InvitationRequest::PartyCreation {
+                                inviter: game_entity.unique_id,
+                                settings: create.settings,
+                            });
+                        },
+                        Err(error) => client.send(PartyCreateResponse::new(PartyResult::error(error))),
+                    }
+                },
+                PartyClientProtocol::PartyInviteRequest(invite) => {
+                    let Some((id, party)) = parties
+                        .find_party_of(entity)
+                        .and_then(|id| parties.get(id).map(|party| (id, party)))
+                    else {
+                        client.send(PartyInviteResponse::new(PartyResult::error(PartyError::NotInParty)));
+                        continue;
+                    };
+
+                    if !party.can_invite(entity) {
+                        client.send(PartyInviteResponse::new(PartyResult::error(PartyError::NotLeader)));
+                        continue;
+                    }
+
+                    if party.is_full() {
+                        client.send(PartyInviteResponse::new(PartyResult::error(PartyError::PartyFull)));
+                        continue;
+                    }
+
+                    let settings = party.settings();
+                    match check_invitation_target(entity, invite.target, &targets, &clients, &lookup, &parties) {
+                        Ok((target, target_client)) => {
+                            cmd.entity(target)
+                                .try_insert(PartyInvitation::new(entity, Some(id), settings));
+                            target_client.send(InvitationRequest::PartyInvitation {
+                                inviter: game_entity.unique_id,
+                                settings: settings.into(),
+                            });
+                        },
+                        Err(error) => client.send(PartyInviteResponse::new(PartyResult::error(error))),
+                    }
+                },
+                PartyClientProtocol::PartyLeaveRequest(_) => {
+                    let Some(id) = parties.find_party_of(entity) else {
+                        client.send(PartyLeaveResponse::new(PartyResult::error(PartyError::NotInParty)));
+                        continue;
+                    };
+
+                    leave_party(
+                        &mut parties,
+                        id,
+                        own_reference,
+                        PartyLeaveReason::Left,
+                        &clients,
+                        &mut cmd,
+                    );
+                    client.send(PartyLeaveResponse::new(PartyResult::Success));
+                },
+                PartyClientProtocol::PartyKickRequest(kick) => {
+                    let Some((id, party)) = parties
+                        .find_party_of(entity)
+                        .and_then(|id| parties.get(id).map(|party| (id, party)))
+                    else {
+                        client.send(PartyKickResponse::new(PartyResult::error(PartyError::NotInParty)));
+                        continue;
+                    };
+
+                    if !party.is_leader(entity) {
+                        client.send(PartyKickResponse::new(PartyResult::error(PartyError::NotLeader)));
+                        continue;
+                    }
+
+                    let Some(member) = party.member(kick.member).filter(|member| member.0 != entity) else {
+                        client.send(PartyKickResponse::new(PartyResult::error(PartyError::InvalidTarget)));
+                        continue;
+                    };
+
+                    leave_party(&mut parties, id, member, PartyLeaveReason::Kicked, &clients, &mut cmd);
+                    client.send(PartyKickResponse::new(PartyResult::Success));
+                },
+                PartyClientProtocol::PartyLeaderTransferRequest(transfer) => {
+                    let Some(party) = parties.find_party_of(entity).and_then(|id| parties.get_mut(id)) else {
+                        client.send(PartyLeaderTransferResponse::new(PartyResult::error(
+                            PartyError::NotInParty,
+                        )));
+                        continue;
+                    };
+
+                    if !party.is_leader(entity) {
+                        client.send(PartyLeaderTransferResponse::new(PartyResult::error(
+                            PartyError::NotLeader,
+                        )));
+                        continue;
+                    }
+
+                    let Some(member) = party.member(transfer.member) else {
+                        client.send(PartyLeaderTransferResponse::new(PartyResult::error(
+                            PartyError::InvalidTarget,
+                        )));
+                        continue;
+                    };
+
+                    party.set_leader(member.0);
+                    send_to_members(
+                        party,
+                        &clients,
+                        PartyUpdate::LeaderChanged {
+                            leader: member.1.unique_id,
+                        },
+                    );
+                    client.send(PartyLeaderTransferResponse::new(PartyResult::Success));
+                },
+            }
+        }
+
+        let (Some(response), Some(invitation)) = (input.invitation, invitation) else {
+            continue;
+        };
+        cmd.entity(entity).remove::<PartyInvitation>();
+
+        let inviter_client = clients.get(invitation.inviter).ok();
+        let respond = |result: PartyResult| {
+            if let Some(inviter_client) = inviter_client {
+                match invitation.party {
+                    None => inviter_client.send(PartyCreateResponse::new(result)),
+                    Some(_) => inviter_client.send(PartyInviteResponse::new(result)),
+                }
+            }
+        };
+
+        if !response.accepted() {
+            respond(PartyResult::error(PartyError::Declined));
+            continue;
+        }
+
+        if parties.find_party_of(entity).is_some() {
+            respond(PartyResult::error(PartyError::TargetAlreadyInParty));
+            continue;
+        }
+
+        let Ok(inviter) = targets.get(invitation.inviter) else {
+            continue;
+        };
+        let inviter = EntityReference(invitation.inviter, *inviter.0);
+
+        let (id, created) = match invitation.party.or_else(|| parties.find_party_of(inviter.0)) {
+            Some(id) => (id, false),
+            None => (parties.create(inviter, invitation.settings), true),
+        };
+
+        let Some(party) = parties.get_mut(id) else {
+            respond(PartyResult::error(PartyError::InvalidTarget));
+            continue;
+        };
+
+        // The inviter may have left the party or lost the permission to invite since sending the
+        // invitation.
+        if !created && !party.contains(inviter.0) {
+            respond(PartyResult::error(PartyError::InvalidTarget));
+            continue;
+        }
+
+        if !created && !party.can_invite(inviter.0) {
+            respond(PartyResult::error(PartyError::NotLeader));
+            continue;
+        }
+
+        if party.is_full() {
+            respond(PartyResult::error(PartyError::PartyFull));
+            continue;
+        }
+
+        if !created {
+            if let Ok(data) = members.get(entity) {
+                send_to_members(party, &clients, PartyUpdate::MemberJoined(member_info(data)));
+            }
+        }
+        party.add_member(own_reference);
+        cmd.entity(entity).try_insert(PartyMember(id));
+
+        let info = party_info(id, party, &members);
+        client.send(info.clone());
+        if created {
+            cmd.entity(inviter.0).try_insert(PartyMember(id));
+            if let Some(inviter_client) = inviter_client {
+                inviter_client.send(info);
+            }
+        }
+
+        respond(PartyResult::Success);
+    }
+}
+
+/// Checks whether the player with the given id can be invited into a party.
+fn check_invitation_target<'a>(
+    inviter: Entity,
+    target_id: u32,
+    targets: &Query<TargetData, With<Player>>,
+    clients: &'a Query<&Client>,
+    lookup: &EntityLookup,
+    parties: &Parties,
+) -> Result<(Entity, &'a Client), PartyError> {
+    let target = lookup
+        .get_entity_for_id(target_id)
+        .filter(|target| *target != inviter)
+        .ok_or(PartyError::InvalidTarget)?;
+    let (_, pending_invitation, guild_invitation, exchange_invitation) =
+        targets.get(target).map_err(|_| PartyError::InvalidTarget)?;
+    let client = clients.get(target).map_err(|_| PartyError::InvalidTarget)?;
+
+    if parties.find_party_of(target).is_some() {
+        return Err(PartyError::TargetAlreadyInParty);
+    }
+
+    if pending_invitation.is_some() || guild_invitation || exchange_invitation {
+        return Err(PartyError::TargetBusy);
+    }
+
+    Ok((target, client))
+}
+
+pub(crate) fn tick_invitations(
+    mut query: Query<(Entity, &mut PartyInvitation)>,
+    clients: Query<&Client>,
+    time: Res<Time>,
+    mut cmd: Commands,
+) {
+    let delta = time.delta();
+    for (entity, mut invitation) in query.iter_mut() {
+        invitation.timeout.tick(delta);
+        if invitation.timeout.just_finished() {
+            cmd.entity(entity).remove::<PartyInvitation>();
+            if let Ok(client) = clients.get(invitation.inviter) {
+                let result = PartyResult::error(PartyError::Declined);
+                match invitation.party {
+                    None => client.send(PartyCreateResponse::new(result)),
+                    Some(_) => client.send(PartyInviteResponse::new(result)),
+                }
+            }
+        }
+    }
+}
+
+/// Removes players from their party once they're gone, e.g. because they disconnected.
+pub(crate) fn remove_despawned_members(
+    mut removed: RemovedComponents<PartyMember>,
+    still_members: Query<(), With<PartyMember>>,
+    clients: Query<&Client>,
+    mut parties: ResMut<Parties>,
+    mut cmd: Commands,
+) {
+    for entity in removed.read() {
+        if still_members.contains(entity) {
+            continue;
+        }
+
+        // If the member left regularly, it has already been removed from the party.
+        let Some(id) = parties.find_party_of(entity) else {
+            continue;
+        };
+
+        let Some(member) = parties
+            .get(id)
+            .and_then(|party| party.members().iter().find(|member| member.0 == entity).copied())
+        else {
+            continue;
+        };
+
+        leave_party(
+            &mut parties,
+            id,
+            member,
+            PartyLeaveReason::Disconnected,
+            &clients,
+            &mut cmd,
+        );
+    }
+}
+
+pub(crate) fn update_member_bars(
+    query: Query<(Entity, &GameEntity, &PartyMember, &Health, &Mana), Or<(Changed<Health>, Changed<Mana>)>>,
+    clients: Query<&Client>,
+    parties: Res<Parties>,
+) {
+    for (entity, game_entity, member, health, mana) in query.iter() {
+        let Some(party) = parties.get(member.0) else {
+            continue;
+        };
+
+        let bars = PartyMemberBars::new(
+            health.current_health,
+            health.max_health,
+            mana.current_mana,
+            mana.max_mana,
+        );
+        send_to_others(
+            party,
+            entity,
+            &clients,
+            PartyUpdate::MemberUpdate {
+                member: game_entity.unique_id,
+                update: PartyMemberUpdate::Bars(bars),
+            },
+        );
+    }
+}
+
+pub(crate) fn update_member_levels(
+    mut level_ups: EventReader<LevelUpEvent>,
+    query: Query<&PartyMember>,
+    clients: Query<&Client>,
+    parties: Res<Parties>,
+) {
+    for event in level_ups.read() {
+        let Some(party) = query.get(event.target.0).ok().and_then(|member| parties.get(member.0)) else {
+            continue;
+        };
+
+        send_to_others(
+            party,
+            event.target.0,
+            &clients,
+            PartyUpdate::MemberUpdate {
+                member: event.target.1.unique_id,
+                update: PartyMemberUpdate::Level(event.level),
+            },
+        );
+    }
+}
+
+pub(crate) fn update_member_positions(
+    query: Query<(Entity, &GameEntity, &PartyMember, &Position)>,
+    clients: Query<&Client>,
+    parties: Res<Parties>,
+) {
+    for (entity, game_entity, member, position) in query.iter() {
+        let Some(party) = parties.get(member.0) else {
+            continue;
+        };
+
+        send_to_others(
+            party,
+            entity,
+            &clients,
+            PartyUpdate::MemberUpdate {
+                member: game_entity.unique_id,
+                update: PartyMemberUpdate::Position(member_position(position)),
+            },
+        );
+    }
+}
@@ -12,6 +12,13 @@ pub trait ApplyToDatabase: Send + Sync {
     async fn apply(&self, character_id: u32, pool: &PgPool) -> Result<(), sqlx::Error>;
 }
 
+#[async_trait]
+impl ApplyToDatabase for Box<dyn ApplyToDatabase> {
+    async fn apply(&self, character_id: u32, pool: &PgPool) -> Result<(), sqlx::Error> {
+        self.as_ref().apply(character_id, pool).await
+    }
+}
+
 pub struct PositionChange(GlobalPosition, Heading);
 
 impl ChangeProvided for Position {
@@ -8,24 +8,31 @@ use bevy::ecs::component::ComponentId;
 use bevy::prelude::*;
 use bevy::ptr::Ptr;
 use bevy::time::common_conditions::on_timer;
-use futures::future::join_all;
+pub(crate) use queue::PersistenceQueue;
 use silkroad_game_base::{ChangeProvided, ChangeTracked, ToOptimizedChange};
 use sqlx::PgPool;
 use std::mem;
-use std::ops::Deref;
 use std::time::Duration;
-use tracing::error;
 
 mod apply;
+mod queue;
 
 #[derive(Component)]
 pub struct Persistable;
 
 #[derive(Component)]
-struct PersistenceCollection<T: ChangeTracked + Component> {
+pub(crate) struct PersistenceCollection<T: ChangeTracked + Component> {
     changes: Vec<T::ChangeItem>,
 }
 
+impl<T: ChangeTracked + Component> PersistenceCollection<T> {
+    /// Takes the collected changes which have not yet been applied, for cases where the changes
+    /// need to be persisted together with other changes instead of periodically.
+    pub(crate) fn take_changes(&mut self) -> Vec<T::ChangeItem> {
+        mem::take(&mut self.changes)
+    }
+}
+
 impl<T: ChangeTracked + Component> Default for PersistenceCollection<T> {
     fn default() -> Self {
         PersistenceCollection { changes: Vec::new() }
@@ -49,7 +56,14 @@ impl Plugin for PersistencePlugin {
             .get_resource::<GameConfig>()
             .expect("Game config should exist.")
             .persist_interval;
+        let queue = {
+            let world = app.world();
+            let task_creator = world.get_resource::<TaskCreator>().expect("Task creator should exist.");
+            let pool = world.get_resource::<DbPool>().expect("Database pool should exist.");
+            PersistenceQueue::new(task_creator, PgPool::clone(pool))
+        };
         app.init_resource::<PersistedComponents>()
+            .insert_resource(queue)
             .add_systems(PostUpdate, apply_changes_combined)
             .add_systems(
                 PostUpdate,
@@ -134,46 +148,27 @@ fn collect_changes<T: ChangeTracked + Component>(mut query: Query<(&mut T, &mut
 
 fn apply_changes<T: ChangeTracked + Component>(
     mut query: Query<(&Player, &mut PersistenceCollection<T>)>,
-    task_creator: Res<TaskCreator>,
-    pool: Res<DbPool>,
+    queue: Res<PersistenceQueue>,
 ) where
     T::ChangeItem: ApplyToDatabase,
 {
     for (player, mut changes) in query.iter_mut() {
         let changes = mem::take(&mut changes.changes);
-        let optimized = changes.optimize();
-        let character_id = player.character.id;
-        let pool = pool.deref().deref().clone();
-        task_creator.spawn(async move {
-            let results = join_all(optimized.iter().map(|c| c.apply(character_id, &pool))).await;
-            for e in results.into_iter().filter_map(|res| res.err()) {
-                error!(error = %e, character_id = character_id, "Could not apply update");
-            }
-        });
+        queue.apply(player.character.id, changes.optimize());
     }
 }
 
 fn apply_changes_exit<T: ChangeTracked + Component>(
     mut query: Query<(&Player, &mut PersistenceCollection<T>)>,
     mut event_reader: EventReader<ClientDisconnectedEvent>,
-    task_creator: Res<TaskCreator>,
-    pool: Res<DbPool>,
+    queue: Res<PersistenceQueue>,
 ) where
     T::ChangeItem: ApplyToDatabase,
 {
     for event in event_reader.read() {
         if let Ok((player, mut changes)) = query.get_mut(event.0) {
             let changes = mem::take(&mut changes.changes);
-            let optimized = changes.optimize();
-            let character_id = player.character.id;
-            let pool = pool.deref().deref().clone();
-            task_creator.spawn(async move {
-                for change in optimized {
-                    if let Err(e) = change.apply(character_id, &pool).await {
-                        error!(error = %e, character_id = character_id, "Could not apply update");
-                    }
-                }
-            });
+            queue.apply(player.character.id, changes.optimize());
         }
     }
 }
@@ -181,8 +176,7 @@ fn apply_changes_exit<T: ChangeTracked + Component>(
 fn apply_changes_combined(
     components: Res<PersistedComponents>,
     mut disconnections: EventReader<ClientDisconnectedEvent>,
-    task_creator: Res<TaskCreator>,
-    db_pool: Res<DbPool>,
+    queue: Res<PersistenceQueue>,
     query: Query<(EntityRef, &Player)>,
 ) {
     if components.0.is_empty() {
@@ -194,15 +188,13 @@ fn apply_changes_combined(
             continue;
         };
 
-        let character_id = player.character.id;
-        execute_db_update(&components, &task_creator, &db_pool, entity, character_id);
+        execute_db_update(&components, &queue, entity, player.character.id);
     }
 }
 
 fn apply_changes_periodically(
     components: Res<PersistedComponents>,
-    task_creator: Res<TaskCreator>,
-    db_pool: Res<DbPool>,
+    queue: Res<PersistenceQueue>,
     query: Query<(EntityRef, &Player)>,
 ) {
     if components.0.is_empty() {
@@ -210,30 +202,15 @@ fn apply_changes_periodically(
     }
 
     for (entity, player) in query.iter() {
-        let character_id = player.character.id;
-        execute_db_update(&components, &task_creator, &db_pool, entity, character_id);
+        execute_db_update(&components, &queue, entity, player.character.id);
     }
 }
 
-fn execute_db_update(
-    components: &PersistedComponents,
-    task_creator: &TaskCreator,
-    db_pool: &PgPool,
-    entity: EntityRef,
-    character_id: u32,
-) {
-    for config in components.0.iter() {
-        let Ok(ptr) = entity.get_by_id(config.component) else {
-            continue;
-        };
-
-        let change = (config.change_provider)(ptr);
-        let pool = db_pool.clone();
-
-        task_creator.spawn(async move {
-            if let Err(e) = change.apply(character_id, &pool).await {
-                error!(error = %e, character_id = character_id, "Could not apply update");
-            }
-        });
-    }
+fn execute_db_update(components: &PersistedComponents, queue: &PersistenceQueue, entity: EntityRef, character_id: u32) {
+    let changes = components
+        .0
+        .iter()
+        .filter_map(|config| entity.get_by_id(config.component).ok().map(config.change_provider))
+        .collect::<Vec<_>>();
+    queue.apply(character_id, changes);
 }
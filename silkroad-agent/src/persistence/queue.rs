@@ -0,0 +1,206 @@
+use crate::persistence::ApplyToDatabase;
+use crate::tasks::TaskCreator;
+use bevy::prelude::*;
+use futures::future::BoxFuture;
+use sqlx::PgPool;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
+use tracing::error;
+
+/// How many workers apply changes to the database concurrently.
+const WORKERS: usize = 16;
+
+type Job = BoxFuture<'static, ()>;
+
+/// Applies changes to the database in the order they were queued, per character.
+///
+/// Each character is assigned to one of several workers, which run the tasks queued for them
+/// one after another. Tasks of different characters may thus still run in parallel, unless
+/// they happen to share a worker. Tasks involving multiple characters, like an exchange, wait
+/// for all earlier tasks of each of those characters and finish before any of their later
+/// tasks start.
+#[derive(Resource)]
+pub(crate) struct PersistenceQueue {
+    workers: Mutex<Vec<mpsc::UnboundedSender<Job>>>,
+    pool: PgPool,
+}
+
+impl PersistenceQueue {
+    pub(crate) fn new(task_creator: &TaskCreator, pool: PgPool) -> Self {
+        let workers = (0..WORKERS)
+            .map(|_| {
+                let (sender, mut receiver) = mpsc::unbounded_channel::<Job>();
+                task_creator.spawn(async move {
+                    while let Some(job) = receiver.recv().await {
+                        job.await;
+                    }
+                });
+                sender
+            })
+            .collect();
+        PersistenceQueue {
+            workers: Mutex::new(workers),
+            pool,
+        }
+    }
+
+    fn worker(character_id: u32) -> usize {
+        character_id as usize % WORKERS
+    }
+
+    /// Queues the changes to be applied, in order, for the given character.
+    pub(crate) fn apply<C: ApplyToDatabase + 'static>(&self, character_id: u32, changes: Vec<C>) {
+        if changes.is_empty() {
+            return;
+        }
+
+        self.spawn(&[character_id], move |pool| async move {
+            for change in changes {
+                if let Err(e) = change.apply(character_id, &pool).await {
+                    error!(error = %e, character_id = character_id, "Could not apply update");
+                }
+            }
+        });
+    }
+
+    /// Queues a task that changes the data of all the given characters.
+    pub(crate) fn spawn<F, Fut>(&self, characters: &[u32], task: F)
+    where
+        F: FnOnce(PgPool) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut workers = characters.iter().map(|id| Self::worker(*id)).collect::<Vec<_>>();
+        workers.sort_unstable();
+        workers.dedup();
+        let Some((&worker, others)) = workers.split_first() else {
+            return;
+        };
+
+        // The other workers are held at this point in their queue until the task is done. As
+        // all jobs of a task are queued while holding the lock, tasks are queued in the same
+        // order on every worker, even if systems queue tasks concurrently. The oldest task is
+        // thus always first in line on all of its workers, so they can never wait on each other.
+        let senders = self.workers.lock().expect("Worker mutex should not be poisoned");
+        let mut reached = Vec::with_capacity(others.len());
+        let mut done = Vec::with_capacity(others.len());
+        for other in others {
+            let (reached_sender, reached_receiver) = oneshot::channel::<()>();
+            let (done_sender, done_receiver) = oneshot::channel::<()>();
+            reached.push(reached_receiver);
+            done.push(done_sender);
+            Self::queue(
+                &senders,
+                *other,
+                Box::pin(async move {
+                    let _ = reached_sender.send(());
+                    let _ = done_receiver.await;
+                }),
+            );
+        }
+
+        let pool = self.pool.clone();
+        Self::queue(
+            &senders,
+            worker,
+            Box::pin(async move {
+                for other in reached {
+                    let _ = other.await;
+                }
+                task(pool).await;
+                drop(done);
+            }),
+        );
+    }
+
+    /// Same as [Self::spawn], but provides the result of the task once it is done.
+    pub(crate) fn create_task<F, Fut>(&self, characters: &[u32], task: F) -> oneshot::Receiver<Fut::Output>
+    where
+        F: FnOnce(PgPool) -> Fut + Send + 'static,
+        Fut: Future + Send + 'static,
+        Fut::Output: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.spawn(characters, move |pool| async move {
+            let _ = sender.send(task(pool).await);
+        });
+        receiver
+    }
+
+    fn queue(senders: &[mpsc::UnboundedSender<Job>], worker: usize, job: Job) {
+        if senders[worker].send(job).is_err() {
+            error!(worker, "Persistence worker is gone, dropping update");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::runtime::Runtime;
+    use tokio::time::sleep;
+
+    #[test]
+    fn test_keeps_order_per_character() {
+        let runtime = Arc::new(Runtime::new().unwrap());
+        let task_creator = TaskCreator::from(runtime.clone());
+        let _guard = runtime.enter();
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/test").unwrap();
+        let queue = PersistenceQueue::new(&task_creator, pool);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let record = |name: &'static str, delay: u64| {
+            let order = order.clone();
+            move |_| async move {
+                sleep(Duration::from_millis(delay)).await;
+                order.lock().unwrap().push(name);
+            }
+        };
+
+        queue.spawn(&[1], record("first", 50));
+        queue.spawn(&[2], record("second", 20));
+        queue.spawn(&[1, 2], record("both", 0));
+        let third = queue.create_task(&[2], record("third", 0));
+        let fourth = queue.create_task(&[1], record("fourth", 30));
+
+        runtime.block_on(third).unwrap();
+        runtime.block_on(fourth).unwrap();
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["second", "first", "both", "third", "fourth"]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_spawns_finish() {
+        let runtime = Arc::new(Runtime::new().unwrap());
+        let task_creator = TaskCreator::from(runtime.clone());
+        let _guard = runtime.enter();
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/test").unwrap();
+        let queue = Arc::new(PersistenceQueue::new(&task_creator, pool));
+
+        let spawners = [[1, 2], [2, 1]]
+            .into_iter()
+            .map(|characters| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    (0..200)
+                        .map(|_| queue.create_task(&characters, |_| async {}))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for spawner in spawners {
+            for task in spawner.join().unwrap() {
+                runtime
+                    .block_on(tokio::time::timeout(Duration::from_secs(5), task))
+                    .expect("Task should not be stuck")
+                    .unwrap();
+            }
+        }
+    }
+}
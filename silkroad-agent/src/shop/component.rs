@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use silkroad_game_base::Item;
+use std::collections::VecDeque;
+
+/// How many sold items are remembered to be bought back.
+pub(crate) const MAX_BUY_BACK_ITEMS: usize = 5;
+
+/// How close a player needs to be to the NPC they're trading with.
+pub(crate) const SHOP_DISTANCE_SQUARED: f32 = 100.0 * 100.0;
+
+pub(crate) struct BuyBackEntry {
+    pub(crate) item: Item,
+    pub(crate) price: u64,
+}
+
+/// The items a player sold to NPCs during this session, which can be bought back for the price
+/// they were sold for. The most recently sold item is always at the front.
+#[derive(Component, Default)]
+pub(crate) struct BuyBackList {
+    entries: VecDeque<BuyBackEntry>,
+}
+
+impl BuyBackList {
+    /// Remembers the sold item and returns its position in the list. If the list is full, the
+    /// oldest item is dropped.
+    pub(crate) fn add(&mut self, item: Item, price: u64) -> u8 {
+        self.entries.push_front(BuyBackEntry { item, price });
+        self.entries.truncate(MAX_BUY_BACK_ITEMS);
+        0
+    }
+
+    pub(crate) fn get(&self, slot: u8) -> Option<&BuyBackEntry> {
+        self.entries.get(slot as usize)
+    }
+
+    pub(crate) fn remove(&mut self, slot: u8) -> Option<BuyBackEntry> {
+        self.entries.remove(slot as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable;
+
+    #[test]
+    fn test_buy_back_order() {
+        let mut list = BuyBackList::default();
+        for amount in 1..=(MAX_BUY_BACK_ITEMS as u16 + 1) {
+            assert_eq!(0, list.add(consumable(amount), 10));
+        }
+        assert!(list.get(MAX_BUY_BACK_ITEMS as u8).is_none());
+        assert_eq!(MAX_BUY_BACK_ITEMS as u16 + 1, list.get(0).unwrap().item.stack_size());
+        assert_eq!(2, list.get(MAX_BUY_BACK_ITEMS as u8 - 1).unwrap().item.stack_size());
+
+        let removed = list.remove(1).unwrap();
+        assert_eq!(MAX_BUY_BACK_ITEMS as u16, removed.item.stack_size());
+        assert!(list.get(MAX_BUY_BACK_ITEMS as u8 - 1).is_none());
+        assert!(list.remove(MAX_BUY_BACK_ITEMS as u8).is_none());
+    }
+}
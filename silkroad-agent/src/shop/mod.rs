@@ -0,0 +1,13 @@
+mod component;
+mod system;
+
+use crate::shop::system::{attach_buy_back_list, handle_shop_input};
+use bevy::prelude::*;
+
+pub(crate) struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (attach_buy_back_list, handle_shop_input));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::comp::gold::GoldPouch;
+use crate::comp::inventory::PlayerInventory;
+use crate::comp::net::Client;
+use crate::comp::npc::NPC;
+use crate::comp::player::Player;
+use crate::comp::pos::Position;
+use crate::comp::GameEntity;
+use crate::input::PlayerInput;
+use crate::shop::component::{BuyBackList, SHOP_DISTANCE_SQUARED};
+use crate::world::{EntityLookup, WorldData};
+use bevy::prelude::*;
+use silkroad_data::shop::{Shop, ShopGood};
+use silkroad_data::DataEntry;
+use silkroad_definitions::type_id::{ObjectItem, ObjectType};
+use silkroad_game_base::{Inventory, Item, ItemTypeData};
+use silkroad_protocol::inventory::{
+    InventoryOperationError, InventoryOperationRequest, InventoryOperationResponseData, InventoryOperationResult,
+};
+use std::cmp::max;
+use tracing::error;
+
+pub(crate) fn attach_buy_back_list(query: Query<Entity, Added<Player>>, mut cmd: Commands) {
+    for entity in query.iter() {
+        cmd.entity(entity).try_insert(BuyBackList::default());
+    }
+}
+
+pub(crate) fn handle_shop_input(
+    mut query: Query<(
+        &Client,
+        &PlayerInput,
+        &Position,
+        &mut PlayerInventory,
+        &mut GoldPouch,
+        &mut BuyBackList,
+    )>,
+    npcs: Query<(&GameEntity, &Position), With<NPC>>,
+    lookup: Res<EntityLookup>,
+) {
+    for (client, input, position, mut inventory, mut gold, mut buy_back) in query.iter_mut() {
+        let Some(ref action) = input.inventory else {
+            continue;
+        };
+
+        let npc = match action.data {
+            InventoryOperationRequest::BuyItem { npc, .. }
+            | InventoryOperationRequest::SellItem { npc, .. }
+            | InventoryOperationRequest::BuyBackItem { npc, .. } => npc,
+            _ => continue,
+        };
+
+        let Some(shop) = find_shop(npc, position, &npcs, &lookup) else {
+            client.send(InventoryOperationResult::Failure(
+                InventoryOperationError::InvalidTarget,
+            ));
+            continue;
+        };
+
+        let result = match action.data {
+            InventoryOperationRequest::BuyItem { tab, slot, amount, .. } => {
+                buy_item(shop, tab, slot, amount, &mut inventory, &mut gold)
+            },
+            InventoryOperationRequest::SellItem { slot, amount, npc } => {
+                sell_item(slot, amount, npc, &mut inventory, &mut gold, &mut buy_back)
+            },
+            InventoryOperationRequest::BuyBackItem { buyback_slot, .. } => {
+                buy_back_item(buyback_slot, &mut inventory, &mut gold, &mut buy_back)
+            },
+            _ => continue,
+        };
+
+        match result {
+            Ok(data) => client.send(InventoryOperationResult::Success(data)),
+            Err(error) => client.send(InventoryOperationResult::Failure(error)),
+        }
+    }
+}
+
+/// Finds the shop of the NPC with the given id, if the player is close enough to trade with it.
+fn find_shop(
+    npc: u32,
+    position: &Position,
+    npcs: &Query<(&GameEntity, &Position), With<NPC>>,
+    lookup: &EntityLookup,
+) -> Option<&'static Shop> {
+    let (npc, npc_position) = lookup.get_entity_for_id(npc).and_then(|npc| npcs.get(npc).ok())?;
+    if position.distance_to(npc_position) > SHOP_DISTANCE_SQUARED {
+        return None;
+    }
+
+    let character = WorldData::characters().find_id(npc.ref_id)?;
+    WorldData::shops().get(&character.common.id)
+}
+
+/// Creates the item sold by the shop, without any amount applied yet.
+fn item_for_good(good: &ShopGood) -> Option<Item> {
+    let reference = WorldData::items().find_code(&good.item)?;
+    let type_data = match ObjectType::from_type_id(&reference.common.type_id)? {
+        ObjectType::Item(ObjectItem::Equippable(_)) => ItemTypeData::Equipment {
+            upgrade_level: good.upgrade_level,
+        },
+        // Pets cannot be represented in the inventory yet, so they cannot be bought either.
+        ObjectType::Item(ObjectItem::Pet(_)) => return None,
+        ObjectType::Item(_) => ItemTypeData::Consumable { amount: 1 },
+        _ => return None,
+    };
+    Some(Item {
+        reference,
+        variance: Some(good.variance).filter(|variance| *variance != 0),
+        type_data,
+    })
+}
+
+fn buy_item(
+    shop: &Shop,
+    tab: u8,
+    slot: u8,
+    amount: u16,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+) -> Result<InventoryOperationResponseData, InventoryOperationError> {
+    let item = shop
+        .find_good(tab, slot)
+        .and_then(item_for_good)
+        .ok_or(InventoryOperationError::InvalidTarget)?;
+
+    // Buying more than fits into a single stack results in multiple stacks.
+    let stacks = item.into_stacks(max(1, amount));
+    let amount: u16 = stacks.iter().map(Item::stack_size).sum();
+    let price = item.reference.price * u64::from(amount);
+    if price > gold.amount() {
+        return Err(InventoryOperationError::NotEnoughGold);
+    }
+
+    if stacks.len() > inventory.free_slots() {
+        return Err(InventoryOperationError::InventoryFull);
+    }
+
+    gold.spend(price);
+    let mut target_slots = Vec::with_capacity(stacks.len());
+    for stack in stacks {
+        match inventory.add_item(stack) {
+            Some(target) if !target_slots.contains(&target) => target_slots.push(target),
+            Some(_) => {},
+            None => error!(item = item.reference.ref_id(), "Could not add bought item to inventory"),
+        }
+    }
+
+    Ok(InventoryOperationResponseData::BuyItem {
+        tab,
+        slot,
+        target_slots,
+        amount,
+    })
+}
+
+fn sell_item(
+    slot: u8,
+    amount: u16,
+    npc: u32,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+    buy_back: &mut BuyBackList,
+) -> Result<InventoryOperationResponseData, InventoryOperationError> {
+    if Inventory::is_equipment_slot(slot) {
+        return Err(InventoryOperationError::InvalidTarget);
+    }
+
+    let item = inventory
+        .split_item_at(slot, max(1, amount))
+        .map_err(|_| InventoryOperationError::InvalidTarget)?;
+    let price = item.reference.sell_price * u64::from(item.stack_size());
+    gold.gain(price);
+    let buyback_slot = buy_back.add(item, price);
+
+    Ok(InventoryOperationResponseData::SellItem {
+        slot,
+        amount: item.stack_size(),
+        npc,
+        buyback_slot,
+    })
+}
+
+fn buy_back_item(
+    buyback_slot: u8,
+    inventory: &mut Inventory,
+    gold: &mut GoldPouch,
+    buy_back: &mut BuyBackList,
+) -> Result<InventoryOperationResponseData, InventoryOperationError> {
+    let entry = buy_back
+        .get(buyback_slot)
+        .ok_or(InventoryOperationError::InvalidTarget)?;
+
+    if entry.price > gold.amount() {
+        return Err(InventoryOperationError::NotEnoughGold);
+    }
+
+    if inventory.free_slots() == 0 {
+        return Err(InventoryOperationError::InventoryFull);
+    }
+
+    let entry = buy_back
+        .remove(buyback_slot)
+        .expect("Buy back entry should exist after checking it");
+    let target_slot = inventory
+        .add_item(entry.item)
+        .ok_or(InventoryOperationError::InventoryFull)?;
+    gold.spend(entry.price);
+
+    Ok(InventoryOperationResponseData::BuyBackItem {
+        target_slot,
+        buyback_slot,
+        amount: entry.item.stack_size(),
+    })
+}
@@ -0,0 +1,243 @@
+use crate::comp::inventory::item_content_data;
+use bevy::prelude::*;
+use silkroad_game_base::{Inventory, Item};
+use silkroad_protocol::inventory::RentInfo;
+use silkroad_protocol::stall::{StallError, StallItemData};
+use std::collections::BTreeMap;
+
+pub(crate) const MAX_STALL_ITEMS: u8 = 10;
+
+/// The longest title the client lets players enter for their stall.
+const MAX_TITLE_LENGTH: usize = 30;
+/// The longest greeting the client lets players enter for their stall.
+const MAX_GREETING_LENGTH: usize = 100;
+
+pub(crate) fn is_valid_title(title: &str) -> bool {
+    title.chars().count() <= MAX_TITLE_LENGTH
+}
+
+pub(crate) fn is_valid_greeting(greeting: &str) -> bool {
+    greeting.chars().count() <= MAX_GREETING_LENGTH
+}
+
+/// How close a player needs to be to a stall to browse it.
+pub(crate) const STALL_DISTANCE_SQUARED: f32 = 100.0 * 100.0;
+
+/// An item offered inside a stall. The item stays inside the inventory of the owner until it is
+/// bought, we only keep a copy to verify that it hasn't changed in the meantime.
+#[derive(Copy, Clone)]
+pub(crate) struct StallItem {
+    pub(crate) inventory_slot: u8,
+    pub(crate) item: Item,
+    pub(crate) amount: u16,
+    pub(crate) price: u64,
+}
+
+impl StallItem {
+    /// Checks that the offered amount of the item is still inside the given inventory.
+    pub(crate) fn is_available(&self, inventory: &Inventory) -> bool {
+        inventory.get_item_at(self.inventory_slot).is_some_and(|item| {
+            item.reference.ref_id() == self.item.reference.ref_id()
+                && item.variance == self.item.variance
+                && item.upgrade_level() == self.item.upgrade_level()
+                && item.stack_size() >= self.amount
+        })
+    }
+}
+
+/// The stall a player has opened. Items can only be changed while the stall is not open for
+/// business, otherwise visitors could end up buying something different from what they saw.
+#[derive(Component)]
+pub(crate) struct Stall {
+    title: String,
+    greeting: String,
+    open: bool,
+    items: BTreeMap<u8, StallItem>,
+    visitors: Vec<(Entity, u32)>,
+}
+
+impl Stall {
+    pub(crate) fn new(title: String) -> Self {
+        Stall {
+            title,
+            greeting: String::new(),
+            open: false,
+            items: BTreeMap::new(),
+            visitors: Vec::new(),
+        }
+    }
+
+    pub(crate) fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub(crate) fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub(crate) fn greeting(&self) -> &str {
+        &self.greeting
+    }
+
+    pub(crate) fn set_greeting(&mut self, greeting: String) {
+        self.greeting = greeting;
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub(crate) fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    pub(crate) fn get_item(&self, stall_slot: u8) -> Option<&StallItem> {
+        self.items.get(&stall_slot)
+    }
+
+    pub(crate) fn add_item(&mut self, stall_slot: u8, item: StallItem) -> Result<(), StallError> {
+        if self.open {
+            return Err(StallError::StallOpened);
+        }
+
+        if stall_slot >= MAX_STALL_ITEMS
+            || self.items.contains_key(&stall_slot)
+            || self
+                .items
+                .values()
+                .any(|offered| offered.inventory_slot == item.inventory_slot)
+        {
+            return Err(StallError::InvalidItem);
+        }
+
+        self.items.insert(stall_slot, item);
+        Ok(())
+    }
+
+    pub(crate) fn update_item(&mut self, stall_slot: u8, amount: u16, price: u64) -> Result<(), StallError> {
+        if self.open {
+            return Err(StallError::StallOpened);
+        }
+
+        let offered = self.items.get_mut(&stall_slot).ok_or(StallError::InvalidItem)?;
+        if amount == 0 || amount > offered.item.stack_size() {
+            return Err(StallError::InvalidItem);
+        }
+
+        offered.amount = amount;
+        offered.price = price;
+        Ok(())
+    }
+
+    pub(crate) fn remove_item(&mut self, stall_slot: u8) -> Result<StallItem, StallError> {
+        if self.open {
+            return Err(StallError::StallOpened);
+        }
+
+        self.items.remove(&stall_slot).ok_or(StallError::InvalidItem)
+    }
+
+    /// Removes the item after it has been bought, which is possible while the stall is open.
+    pub(crate) fn sell_item(&mut self, stall_slot: u8) -> Option<StallItem> {
+        self.items.remove(&stall_slot)
+    }
+
+    pub(crate) fn visitors(&self) -> &[(Entity, u32)] {
+        &self.visitors
+    }
+
+    pub(crate) fn add_visitor(&mut self, visitor: Entity, unique_id: u32) {
+        if !self.visitors.iter().any(|(entity, _)| *entity == visitor) {
+            self.visitors.push((visitor, unique_id));
+        }
+    }
+
+    pub(crate) fn remove_visitor(&mut self, visitor: Entity) -> Option<u32> {
+        let index = self.visitors.iter().position(|(entity, _)| *entity == visitor)?;
+        Some(self.visitors.remove(index).1)
+    }
+
+    pub(crate) fn item_data(&self) -> Vec<StallItemData> {
+        self.items
+            .iter()
+            .map(|(stall_slot, offered)| StallItemData {
+                stall_slot: *stall_slot,
+                rent_data: RentInfo::Empty,
+                item_id: offered.item.reference.ref_id(),
+                content_data: item_content_data(&offered.item),
+                inventory_slot: offered.inventory_slot,
+                amount: offered.amount,
+                price: offered.price,
+            })
+            .collect()
+    }
+}
+
+/// Marks a player who is browsing the stall of the given owner.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub(crate) struct StallVisitor(pub(crate) Entity);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+
+    fn offer(inventory_slot: u8, amount: u16) -> StallItem {
+        StallItem {
+            inventory_slot,
+            item: item(amount),
+            amount,
+            price: 1000,
+        }
+    }
+
+    #[test]
+    fn test_change_items() {
+        let mut stall = Stall::new("Potions".to_string());
+        assert!(stall.add_item(0, offer(13, 5)).is_ok());
+        assert_eq!(Err(StallError::InvalidItem), stall.add_item(0, offer(14, 5)));
+        assert_eq!(Err(StallError::InvalidItem), stall.add_item(1, offer(13, 5)));
+        assert_eq!(
+            Err(StallError::InvalidItem),
+            stall.add_item(MAX_STALL_ITEMS, offer(14, 5))
+        );
+        assert_eq!(Err(StallError::InvalidItem), stall.update_item(0, 6, 100));
+        assert!(stall.update_item(0, 2, 100).is_ok());
+
+        stall.set_open(true);
+        assert_eq!(Err(StallError::StallOpened), stall.add_item(1, offer(14, 5)));
+        assert!(stall.remove_item(0).is_err());
+        assert_eq!(2, stall.sell_item(0).unwrap().amount);
+        assert!(stall.item_data().is_empty());
+    }
+
+    #[test]
+    fn test_item_availability() {
+        let mut inventory = Inventory::new(45);
+        inventory.set_item(13, item(5));
+        assert!(offer(13, 5).is_available(&inventory));
+        assert!(!offer(13, 6).is_available(&inventory));
+        assert!(!offer(14, 1).is_available(&inventory));
+    }
+
+    #[test]
+    fn test_visitors() {
+        let mut stall = Stall::new("Potions".to_string());
+        stall.add_visitor(Entity::from_raw(1), 0x10);
+        stall.add_visitor(Entity::from_raw(1), 0x10);
+        stall.add_visitor(Entity::from_raw(2), 0x20);
+        assert_eq!(2, stall.visitors().len());
+        assert_eq!(Some(0x10), stall.remove_visitor(Entity::from_raw(1)));
+        assert_eq!(None, stall.remove_visitor(Entity::from_raw(1)));
+    }
+
+    #[test]
+    fn test_text_lengths() {
+        assert!(is_valid_title("Potions"));
+        assert!(is_valid_title(&"ä".repeat(MAX_TITLE_LENGTH)));
+        assert!(!is_valid_title(&"a".repeat(MAX_TITLE_LENGTH + 1)));
+        assert!(is_valid_greeting(""));
+        assert!(!is_valid_greeting(&"a".repeat(MAX_GREETING_LENGTH + 1)));
+    }
+}
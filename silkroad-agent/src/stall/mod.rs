@@ -0,0 +1,16 @@
+mod component;
+mod system;
+
+use crate::stall::system::{close_invalid_stalls, handle_stall_input};
+use bevy::prelude::*;
+pub(crate) use component::*;
+
+pub(crate) struct StallPlugin;
+
+impl Plugin for StallPlugin {
+    fn build(&self, app: &mut App) {
+        // Stalls need to be validated before handling input, such that visitors which have just
+        // entered a stall are already known by the time the stall gets validated.
+        app.add_systems(Update, (close_invalid_stalls, handle_stall_input).chain());
+    }
+}
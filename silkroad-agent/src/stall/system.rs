@@ -0,0 +1,671 @@
+use crate::agent::state::{Dead, Moving};
+use crate::comp::gold::GoldPouch;
+use crate::comp::inventory::{item_content_data, PlayerInventory};
+use crate::comp::net::Client;
+use crate::comp::player::Player;
+use crate::comp::pos::Position;
+use crate::comp::visibility::Visibility;
+use crate::comp::GameEntity;
+use crate::exchange::{persist_exchange, Exchange, ExchangeParticipant};
+use crate::game::logout::Logout;
+use crate::input::PlayerInput;
+use crate::persistence::{PersistenceCollection, PersistenceQueue};
+use crate::stall::component::{
+    is_valid_greeting, is_valid_title, Stall, StallItem, StallVisitor, STALL_DISTANCE_SQUARED,
+};
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use silkroad_game_base::{ChangeTracked, Inventory};
+use silkroad_protocol::inventory::InventoryOperationResult;
+use silkroad_protocol::stall::{
+    StallAction, StallBuyResponse, StallClientProtocol, StallCloseResponse, StallCreateResponse, StallEnterResponse,
+    StallEntityClosed, StallEntityOpened, StallEntityTitle, StallError, StallLeaveResponse, StallResult, StallUpdate,
+    StallUpdateResponse, StallUpdateResult,
+};
+
+type StallPlayerData<'a> = (
+    &'a Client,
+    &'a GameEntity,
+    &'a Player,
+    &'a Position,
+    &'a Visibility,
+    &'a mut PlayerInventory,
+    &'a mut GoldPouch,
+    Option<&'a mut Stall>,
+    Option<&'a StallVisitor>,
+    Has<Exchange>,
+    Has<Moving>,
+    Has<Dead>,
+);
+
+type StallOwnerData<'a> = (
+    Entity,
+    &'a Client,
+    &'a GameEntity,
+    &'a Position,
+    &'a Visibility,
+    &'a mut Stall,
+    Has<Moving>,
+    Has<Logout>,
+    Has<Dead>,
+);
+
+pub(crate) fn handle_stall_input(
+    query: Query<(Entity, &PlayerInput)>,
+    mut players: Query<StallPlayerData>,
+    mut collections: Query<&mut PersistenceCollection<PlayerInventory>>,
+    lookup: Res<EntityLookup>,
+    queue: Res<PersistenceQueue>,
+    mut cmd: Commands,
+) {
+    for (entity, input) in query.iter() {
+        for request in input.stall.iter() {
+            match request {
+                StallClientProtocol::StallCreateRequest(create) => {
+                    create_stall(entity, &create.title, &players, &mut cmd);
+                },
+                StallClientProtocol::StallCloseRequest(_) => {
+                    close_stall(entity, &players, &mut cmd);
+                },
+                StallClientProtocol::StallEnterRequest(enter) => {
+                    enter_stall(entity, enter.owner, &mut players, &lookup, &mut cmd);
+                },
+                StallClientProtocol::StallLeaveRequest(_) => {
+                    leave_stall(entity, &mut players, &mut cmd);
+                },
+                StallClientProtocol::StallBuyRequest(buy) => {
+                    buy_item(entity, buy.stall_slot, &mut players, &mut collections, &queue);
+                },
+                StallClientProtocol::StallUpdateRequest(update) => {
+                    update_stall(entity, &update.update, &mut players);
+                },
+            }
+        }
+    }
+}
+
+fn create_stall(entity: Entity, title: &str, players: &Query<StallPlayerData>, cmd: &mut Commands) {
+    let Ok((client, game_entity, _, _, visibility, _, _, stall, visitor, exchanging, moving, dead)) =
+        players.get(entity)
+    else {
+        return;
+    };
+
+    if stall.is_some() || visitor.is_some() || exchanging || moving || dead {
+        client.send(StallCreateResponse::new(StallResult::error(StallError::Busy)));
+        return;
+    }
+
+    // There is no error for an invalid title, as the client doesn't allow entering one.
+    if !is_valid_title(title) {
+        client.send(StallCreateResponse::new(StallResult::error(StallError::InvalidTarget)));
+        return;
+    }
+
+    cmd.entity(entity).try_insert(Stall::new(title.to_string()));
+    client.send(StallCreateResponse::new(StallResult::Success));
+
+    let opened = StallEntityOpened {
+        unique_id: game_entity.unique_id,
+        title: title.to_string(),
+        decoration: 0,
+    };
+    for reference in visibility.entities_in_radius.iter() {
+        if let Ok((other_client, ..)) = players.get(reference.0) {
+            other_client.send(opened.clone());
+        }
+    }
+}
+
+fn close_stall(entity: Entity, players: &Query<StallPlayerData>, cmd: &mut Commands) {
+    let Ok((client, game_entity, _, _, visibility, _, _, stall, ..)) = players.get(entity) else {
+        return;
+    };
+
+    let Some(stall) = stall else {
+        client.send(StallCloseResponse::new(StallResult::error(StallError::NotInStall)));
+        return;
+    };
+
+    client.send(StallCloseResponse::new(StallResult::Success));
+    remove_stall(
+        entity,
+        game_entity.unique_id,
+        stall,
+        visibility,
+        |other| players.get(other).ok().map(|(client, ..)| client),
+        cmd,
+    );
+}
+
+/// Removes the stall of the given owner, sending out all visitors and removing the stall for
+/// players nearby.
+fn remove_stall<'a>(
+    owner: Entity,
+    unique_id: u32,
+    stall: &Stall,
+    visibility: &Visibility,
+    clients: impl Fn(Entity) -> Option<&'a Client>,
+    cmd: &mut Commands,
+) {
+    for (visitor, _) in stall.visitors() {
+        if let Some(client) = clients(*visitor) {
+            client.send(StallAction::Closed);
+            cmd.entity(*visitor).remove::<StallVisitor>();
+        }
+    }
+
+    let closed = StallEntityClosed { unique_id };
+    for reference in visibility.entities_in_radius.iter() {
+        if let Some(client) = clients(reference.0) {
+            client.send(closed.clone());
+        }
+    }
+
+    cmd.entity(owner).remove::<Stall>();
+}
+
+fn enter_stall(
+    entity: Entity,
+    owner_id: u32,
+    players: &mut Query<StallPlayerData>,
+    lookup: &EntityLookup,
+    cmd: &mut Commands,
+) {
+    let Ok((client, game_entity, player, position, _, _, _, stall, visitor, exchanging, _, dead)) = players.get(entity)
+    else {
+        return;
+    };
+
+    let Some(owner) = lookup.get_entity_for_id(owner_id).filter(|owner| *owner != entity) else {
+        client.send(StallEnterResponse::Failure {
+            code: StallError::InvalidTarget,
+        });
+        return;
+    };
+
+    if stall.is_some() || visitor.is_some() || exchanging || dead {
+        client.send(StallEnterResponse::Failure { code: StallError::Busy });
+        return;
+    }
+
+    let Ok((owner_client, _, _, owner_position, _, _, _, Some(owner_stall), ..)) = players.get(owner) else {
+        client.send(StallEnterResponse::Failure {
+            code: StallError::InvalidTarget,
+        });
+        return;
+    };
+
+    if position.distance_to(owner_position) > STALL_DISTANCE_SQUARED {
+        client.send(StallEnterResponse::Failure {
+            code: StallError::TooFarAway,
+        });
+        return;
+    }
+
+    client.send(StallEnterResponse::Success {
+        owner: owner_id,
+        greeting: owner_stall.greeting().to_string(),
+        open: owner_stall.is_open(),
+        items: owner_stall.item_data(),
+    });
+
+    let entered = StallAction::Entered {
+        unique_id: game_entity.unique_id,
+        name: player.character.name.clone(),
+    };
+    owner_client.send(entered.clone());
+    for (other, _) in owner_stall.visitors() {
+        if let Ok((other_client, ..)) = players.get(*other) {
+            other_client.send(entered.clone());
+        }
+    }
+
+    let unique_id = game_entity.unique_id;
+    if let Ok((.., Some(mut owner_stall), _, _, _, _)) = players.get_mut(owner) {
+        owner_stall.add_visitor(entity, unique_id);
+        cmd.entity(entity).try_insert(StallVisitor(owner));
+    }
+}
+
+fn leave_stall(entity: Entity, players: &mut Query<StallPlayerData>, cmd: &mut Commands) {
+    let Ok((client, game_entity, _, _, _, _, _, _, visitor, ..)) = players.get(entity) else {
+        return;
+    };
+
+    let Some(visitor) = visitor else {
+        client.send(StallLeaveResponse::new(StallResult::error(StallError::NotInStall)));
+        return;
+    };
+
+    client.send(StallLeaveResponse::new(StallResult::Success));
+    cmd.entity(entity).remove::<StallVisitor>();
+
+    let owner = visitor.0;
+    let unique_id = game_entity.unique_id;
+    let recipients = match players.get_mut(owner) {
+        Ok((.., Some(mut stall), _, _, _, _)) => {
+            stall.remove_visitor(entity);
+            member_entities(owner, &stall)
+        },
+        _ => return,
+    };
+
+    notify_members(&recipients, players, StallAction::Left { unique_id });
+}
+
+fn buy_item(
+    entity: Entity,
+    stall_slot: u8,
+    players: &mut Query<StallPlayerData>,
+    collections: &mut Query<&mut PersistenceCollection<PlayerInventory>>,
+    queue: &PersistenceQueue,
+) {
+    let owner = match players.get(entity) {
+        Ok((.., Some(visitor), _, _, _)) => visitor.0,
+        Ok((client, ..)) => {
+            client.send(StallBuyResponse::new(StallResult::error(StallError::NotInStall)));
+            return;
+        },
+        Err(_) => return,
+    };
+
+    let Ok([buyer, seller]) = players.get_many_mut([entity, owner]) else {
+        return;
+    };
+    let (client, _, player, _, _, mut inventory, mut gold, ..) = buyer;
+    let (owner_client, _, owner_player, _, _, mut owner_inventory, mut owner_gold, Some(mut stall), ..) = seller else {
+        client.send(StallBuyResponse::new(StallResult::error(StallError::StallClosed)));
+        return;
+    };
+
+    if !stall.is_open() {
+        client.send(StallBuyResponse::new(StallResult::error(StallError::StallClosed)));
+        return;
+    }
+
+    let Some(offered) = stall.get_item(stall_slot).copied() else {
+        client.send(StallBuyResponse::new(StallResult::error(StallError::InvalidItem)));
+        return;
+    };
+
+    let target_slot = match purchase(
+        &offered,
+        &mut owner_inventory,
+        &mut owner_gold,
+        &mut inventory,
+        &mut gold,
+    ) {
+        Ok(slot) => slot,
+        Err(code) => {
+            client.send(StallBuyResponse::new(StallResult::error(code)));
+            return;
+        },
+    };
+
+    stall.sell_item(stall_slot);
+    client.send(StallBuyResponse::new(StallResult::Success));
+    if let Some(item) = inventory.get_item_at(target_slot) {
+        client.send(InventoryOperationResult::success_gain_item(
+            target_slot,
+            item.reference.ref_id(),
+            item_content_data(item),
+        ));
+    }
+
+    let sold = StallAction::ItemSold {
+        stall_slot,
+        buyer: player.character.name.clone(),
+    };
+    owner_client.send(sold.clone());
+    let recipients: Vec<Entity> = stall
+        .visitors()
+        .iter()
+        .map(|(visitor, _)| *visitor)
+        .filter(|visitor| *visitor != entity)
+        .collect();
+
+    // Like an exchange, both sides of the purchase need to be persisted together, including the
+    // changes that have been collected but not yet persisted.
+    let mut changes = collections
+        .get_mut(entity)
+        .map(|mut collection| collection.take_changes())
+        .unwrap_or_default();
+    changes.append(&mut inventory.changes());
+    let mut owner_changes = collections
+        .get_mut(owner)
+        .map(|mut collection| collection.take_changes())
+        .unwrap_or_default();
+    owner_changes.append(&mut owner_inventory.changes());
+
+    let record = [
+        ExchangeParticipant {
+            character_id: player.character.id,
+            changes,
+            gold: gold.amount(),
+        },
+        ExchangeParticipant {
+            character_id: owner_player.character.id,
+            changes: owner_changes,
+            gold: owner_gold.amount(),
+        },
+    ];
+    persist_exchange(record, queue);
+
+    notify_members(&recipients, players, sold);
+}
+
+/// Moves the offered item from the inventory of the owner into the inventory of the buyer and
+/// the price the other way around. Either all of it happens or, if anything doesn't add up,
+/// nothing changes at all. Returns the slot the item has been placed in.
+fn purchase(
+    offered: &StallItem,
+    owner_inventory: &mut Inventory,
+    owner_gold: &mut GoldPouch,
+    buyer_inventory: &mut Inventory,
+    buyer_gold: &mut GoldPouch,
+) -> Result<u8, StallError> {
+    if !offered.is_available(owner_inventory) {
+        return Err(StallError::ItemNotAvailable);
+    }
+
+    if offered.price > buyer_gold.amount() {
+        return Err(StallError::NotEnoughGold);
+    }
+
+    if buyer_inventory.free_slots() == 0 {
+        return Err(StallError::InventoryFull);
+    }
+
+    let item = owner_inventory
+        .split_item_at(offered.inventory_slot, offered.amount)
+        .map_err(|_| StallError::ItemNotAvailable)?;
+    let slot = buyer_inventory
+        .add_item(item)
+        .expect("Buyer should have space after checking it");
+    buyer_gold.spend(offered.price);
+    owner_gold.gain(offered.price);
+    Ok(slot)
+}
+
+fn update_stall(entity: Entity, update: &StallUpdate, players: &mut Query<StallPlayerData>) {
+    let Ok((client, _, _, _, _, _, _, stall, ..)) = players.get(entity) else {
+        return;
+    };
+
+    if stall.is_none() {
+        client.send(StallUpdateResponse::new(StallUpdateResult::Failure {
+            code: StallError::NotInStall,
+        }));
+        return;
+    }
+
+    let Ok((client, game_entity, _, _, visibility, inventory, _, Some(mut stall), ..)) = players.get_mut(entity) else {
+        return;
+    };
+
+    let result = match update {
+        StallUpdate::AddItem {
+            stall_slot,
+            inventory_slot,
+            amount,
+            price,
+        } => match inventory
+            .get_item_at(*inventory_slot)
+            .filter(|item| !Inventory::is_equipment_slot(*inventory_slot) && *amount <= item.stack_size())
+        {
+            Some(item) if *amount > 0 => stall.add_item(
+                *stall_slot,
+                StallItem {
+                    inventory_slot: *inventory_slot,
+                    item: *item,
+                    amount: *amount,
+                    price: *price,
+                },
+            ),
+            _ => Err(StallError::InvalidItem),
+        },
+        StallUpdate::UpdateItem {
+            stall_slot,
+            amount,
+            price,
+        } => stall.update_item(*stall_slot, *amount, *price),
+        StallUpdate::RemoveItem { stall_slot } => stall.remove_item(*stall_slot).map(|_| ()),
+        StallUpdate::State { open } => {
+            stall.set_open(*open);
+            Ok(())
+        },
+        StallUpdate::Greeting { message } if !is_valid_greeting(message) => Err(StallError::InvalidTarget),
+        StallUpdate::Greeting { message } => {
+            stall.set_greeting(message.clone());
+            Ok(())
+        },
+        StallUpdate::Title { title } if !is_valid_title(title) => Err(StallError::InvalidTarget),
+        StallUpdate::Title { title } => {
+            stall.set_title(title.clone());
+            Ok(())
+        },
+    };
+
+    if let Err(code) = result {
+        client.send(StallUpdateResponse::new(StallUpdateResult::Failure { code }));
+        return;
+    }
+
+    client.send(StallUpdateResponse::new(StallUpdateResult::Success(update.clone())));
+    let unique_id = game_entity.unique_id;
+    let visitors: Vec<Entity> = stall.visitors().iter().map(|(visitor, _)| *visitor).collect();
+    let nearby: Vec<Entity> = visibility
+        .entities_in_radius
+        .iter()
+        .map(|reference| reference.0)
+        .collect();
+    let changed = match update {
+        StallUpdate::Title { .. } => None,
+        StallUpdate::Greeting { message } => Some(StallAction::GreetingUpdated {
+            message: message.clone(),
+        }),
+        _ => Some(StallAction::ItemsUpdated {
+            open: stall.is_open(),
+            items: stall.item_data(),
+        }),
+    };
+
+    if let StallUpdate::Title { title } = update {
+        let changed = StallEntityTitle {
+            unique_id,
+            title: title.clone(),
+        };
+        for other in nearby {
+            if let Ok((other_client, ..)) = players.get(other) {
+                other_client.send(changed.clone());
+            }
+        }
+    }
+
+    if let Some(changed) = changed {
+        notify_members(&visitors, players, changed);
+    }
+}
+
+/// The owner and all visitors of the given stall.
+fn member_entities(owner: Entity, stall: &Stall) -> Vec<Entity> {
+    std::iter::once(owner)
+        .chain(stall.visitors().iter().map(|(visitor, _)| *visitor))
+        .collect()
+}
+
+fn notify_members(members: &[Entity], players: &Query<StallPlayerData>, action: StallAction) {
+    for member in members {
+        if let Ok((client, ..)) = players.get(*member) {
+            client.send(action.clone());
+        }
+    }
+}
+
+/// Closes stalls whose owner started moving, is logging out or died, and sends out visitors which
+/// are no longer able to browse the stall they're in.
+pub(crate) fn close_invalid_stalls(
+    mut owners: Query<StallOwnerData>,
+    visitors: Query<(
+        Entity,
+        &Client,
+        &Position,
+        &StallVisitor,
+        Has<Moving>,
+        Has<Logout>,
+        Has<Dead>,
+    )>,
+    clients: Query<&Client>,
+    mut cmd: Commands,
+) {
+    let mut closing = Vec::new();
+    for (entity, client, game_entity, _, visibility, stall, moving, logout, dead) in owners.iter() {
+        if moving || logout || dead {
+            client.send(StallAction::Closed);
+            remove_stall(
+                entity,
+                game_entity.unique_id,
+                stall,
+                visibility,
+                |other| clients.get(other).ok(),
+                &mut cmd,
+            );
+            closing.push(entity);
+        }
+    }
+
+    let mut leaving = Vec::new();
+    for (entity, client, position, visitor, moving, logout, dead) in visitors.iter() {
+        let owner_position = match owners.get(visitor.0) {
+            Ok(_) if closing.contains(&visitor.0) => continue,
+            Ok((_, _, _, owner_position, ..)) => owner_position,
+            Err(_) => {
+                client.send(StallAction::Closed);
+                cmd.entity(entity).remove::<StallVisitor>();
+                continue;
+            },
+        };
+
+        if moving || logout || dead || position.distance_to(owner_position) > STALL_DISTANCE_SQUARED {
+            client.send(StallAction::Closed);
+            cmd.entity(entity).remove::<StallVisitor>();
+            leaving.push(entity);
+        }
+    }
+
+    for (entity, client, _, _, _, mut stall, ..) in owners.iter_mut() {
+        if closing.contains(&entity) {
+            continue;
+        }
+
+        // Visitors might also have disconnected or ended up in another stall in the meantime.
+        let gone: Vec<Entity> = stall
+            .visitors()
+            .iter()
+            .map(|(visitor, _)| *visitor)
+            .filter(|visitor| {
+                leaving.contains(visitor)
+                    || !visitors
+                        .get(*visitor)
+                        .is_ok_and(|(_, _, _, current, ..)| current.0 == entity)
+            })
+            .collect();
+
+        for visitor in gone {
+            let Some(unique_id) = stall.remove_visitor(visitor) else {
+                continue;
+            };
+            let left = StallAction::Left { unique_id };
+            client.send(left.clone());
+            for (other, _) in stall.visitors() {
+                if let Ok(other_client) = clients.get(*other) {
+                    other_client.send(left.clone());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+
+    fn offer(amount: u16, price: u64) -> StallItem {
+        StallItem {
+            inventory_slot: 13,
+            item: item(5),
+            amount,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_purchase() {
+        let mut owner_inventory = Inventory::new(45);
+        owner_inventory.set_item(13, item(5));
+        let mut owner_gold = GoldPouch::new(0);
+        let mut buyer_inventory = Inventory::new(45);
+        let mut buyer_gold = GoldPouch::new(1000);
+
+        let slot = purchase(
+            &offer(2, 600),
+            &mut owner_inventory,
+            &mut owner_gold,
+            &mut buyer_inventory,
+            &mut buyer_gold,
+        )
+        .unwrap();
+        assert_eq!(2, buyer_inventory.get_item_at(slot).unwrap().stack_size());
+        assert_eq!(3, owner_inventory.get_item_at(13).unwrap().stack_size());
+        assert_eq!(400, buyer_gold.amount());
+        assert_eq!(600, owner_gold.amount());
+
+        assert_eq!(
+            Err(StallError::NotEnoughGold),
+            purchase(
+                &offer(2, 600),
+                &mut owner_inventory,
+                &mut owner_gold,
+                &mut buyer_inventory,
+                &mut buyer_gold,
+            )
+        );
+        assert_eq!(
+            Err(StallError::ItemNotAvailable),
+            purchase(
+                &offer(5, 100),
+                &mut owner_inventory,
+                &mut owner_gold,
+                &mut buyer_inventory,
+                &mut buyer_gold,
+            )
+        );
+        assert_eq!(3, owner_inventory.get_item_at(13).unwrap().stack_size());
+        assert_eq!(400, buyer_gold.amount());
+    }
+
+    #[test]
+    fn test_purchase_inventory_full() {
+        let mut owner_inventory = Inventory::new(45);
+        owner_inventory.set_item(13, item(5));
+        let mut owner_gold = GoldPouch::new(0);
+        let mut buyer_inventory = Inventory::new(14);
+        buyer_inventory.set_item(13, item(1));
+        let mut buyer_gold = GoldPouch::new(1000);
+
+        assert_eq!(
+            Err(StallError::InventoryFull),
+            purchase(
+                &offer(5, 100),
+                &mut owner_inventory,
+                &mut owner_gold,
+                &mut buyer_inventory,
+                &mut buyer_gold,
+            )
+        );
+        assert_eq!(5, owner_inventory.get_item_at(13).unwrap().stack_size());
+        assert_eq!(0, owner_gold.amount());
+    }
+}
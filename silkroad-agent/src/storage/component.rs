@@ -0,0 +1,281 @@
+use crate::comp::inventory::{apply_inventory_change, item_content_data, PlayerInventory};
+use crate::db::character::CharacterItem;
+use crate::persistence::{ApplyToDatabase, PersistenceQueue};
+use axum::async_trait;
+use bevy::prelude::*;
+use silkroad_game_base::{Change, ChangeTracked, Inventory, InventoryChange, MergeResult};
+use silkroad_protocol::inventory::{InventoryItemData, RentInfo};
+use sqlx::{PgConnection, PgPool};
+use std::ops::{Deref, DerefMut};
+use tracing::error;
+
+pub(crate) const STORAGE_SIZE: usize = 150;
+
+/// How close a player needs to be to the storage keeper to use the storage.
+pub(crate) const STORAGE_DISTANCE_SQUARED: f32 = 100.0 * 100.0;
+
+/// The personal storage of a player, which is kept separate from the inventory and only
+/// accessible through a storage keeper.
+#[derive(Component)]
+pub(crate) struct PlayerStorage {
+    storage: Inventory,
+    gold: u64,
+    gold_changed: bool,
+}
+
+impl PlayerStorage {
+    pub(crate) fn new(size: usize, gold: u64) -> Self {
+        PlayerStorage {
+            storage: Inventory::without_equipment(size),
+            gold,
+            gold_changed: false,
+        }
+    }
+
+    pub(crate) fn from_db(items: &[CharacterItem], gold: u64) -> Self {
+        let mut storage = Self::new(STORAGE_SIZE, gold);
+        PlayerInventory::load_db_items(&mut storage.storage, items);
+        storage
+    }
+
+    pub(crate) fn gold(&self) -> u64 {
+        self.gold
+    }
+
+    pub(crate) fn deposit_gold(&mut self, amount: u64) {
+        self.gold = self.gold.saturating_add(amount);
+        self.gold_changed = true;
+    }
+
+    /// Takes the given amount of gold out of the storage. Fails if there's not enough gold stored.
+    pub(crate) fn withdraw_gold(&mut self, amount: u64) -> bool {
+        if amount > self.gold {
+            return false;
+        }
+        self.gold -= amount;
+        self.gold_changed = true;
+        true
+    }
+
+    pub(crate) fn item_data(&self) -> Vec<InventoryItemData> {
+        self.storage
+            .items()
+            .map(|(slot, item)| {
+                InventoryItemData::new(*slot, RentInfo::Empty, item.reference.ref_id(), item_content_data(item))
+            })
+            .collect()
+    }
+}
+
+impl Deref for PlayerStorage {
+    type Target = Inventory;
+
+    fn deref(&self) -> &Self::Target {
+        &self.storage
+    }
+}
+
+impl DerefMut for PlayerStorage {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.storage
+    }
+}
+
+pub(crate) enum StorageChange {
+    Item(InventoryChange),
+    Gold(u64),
+}
+
+impl Change for StorageChange {
+    fn merge(self, other: Self) -> MergeResult<Self> {
+        match (self, other) {
+            (StorageChange::Item(first), StorageChange::Item(second)) => match first.merge(second) {
+                MergeResult::Unchanged(first, second) => {
+                    MergeResult::Unchanged(StorageChange::Item(first), StorageChange::Item(second))
+                },
+                MergeResult::Incompatible(first, second) => {
+                    MergeResult::Incompatible(StorageChange::Item(first), StorageChange::Item(second))
+                },
+                MergeResult::Merged(merged) => MergeResult::Merged(StorageChange::Item(merged)),
+                MergeResult::Cancelled => MergeResult::Cancelled,
+            },
+            // Only the latest amount of gold is relevant.
+            (StorageChange::Gold(_), StorageChange::Gold(amount)) => MergeResult::Merged(StorageChange::Gold(amount)),
+            (first, second) => MergeResult::Unchanged(first, second),
+        }
+    }
+}
+
+impl ChangeTracked for PlayerStorage {
+    type ChangeItem = StorageChange;
+
+    fn changes(&mut self) -> Vec<Self::ChangeItem> {
+        let mut changes: Vec<StorageChange> = self.storage.changes().into_iter().map(StorageChange::Item).collect();
+        if self.gold_changed {
+            self.gold_changed = false;
+            changes.push(StorageChange::Gold(self.gold));
+        }
+        changes
+    }
+}
+
+#[async_trait]
+impl ApplyToDatabase for StorageChange {
+    async fn apply(&self, character_id: u32, pool: &PgPool) -> Result<(), sqlx::Error> {
+        let mut connection = pool.acquire().await?;
+        apply_storage_change(self, character_id, &mut connection).await
+    }
+}
+
+/// All changes caused by moving items or gold between the inventory and the storage of a player,
+/// which need to be persisted together.
+pub(crate) struct StorageTransfer {
+    pub(crate) character_id: u32,
+    pub(crate) inventory_changes: Vec<InventoryChange>,
+    pub(crate) storage_changes: Vec<StorageChange>,
+    pub(crate) gold: u64,
+}
+
+/// Persists the transfer in a single transaction, such that an item or gold is never lost or
+/// duplicated when only one side of the transfer was written.
+pub(crate) fn persist_storage_transfer(transfer: StorageTransfer, queue: &PersistenceQueue) {
+    let character_id = transfer.character_id;
+    queue.spawn(&[character_id], move |pool| async move {
+        if let Err(e) = apply_storage_transfer(&transfer, &pool).await {
+            error!(error = %e, character_id, "Could not persist storage transfer");
+        }
+    });
+}
+
+async fn apply_storage_transfer(transfer: &StorageTransfer, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    for change in transfer.inventory_changes.iter() {
+        apply_inventory_change(change, transfer.character_id, &mut transaction).await?;
+    }
+    for change in transfer.storage_changes.iter() {
+        apply_storage_change(change, transfer.character_id, &mut transaction).await?;
+    }
+    sqlx::query("UPDATE characters SET gold = $1 WHERE id = $2")
+        .bind(transfer.gold as i64)
+        .bind(transfer.character_id as i32)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await
+}
+
+async fn apply_storage_change(
+    change: &StorageChange,
+    character_id: u32,
+    connection: &mut PgConnection,
+) -> Result<(), sqlx::Error> {
+    match change {
+        StorageChange::Item(change) => apply_storage_item_change(change, character_id, connection).await,
+        StorageChange::Gold(amount) => {
+            sqlx::query("UPDATE characters SET storage_gold = $1 WHERE id = $2")
+                .bind(*amount as i64)
+                .bind(character_id as i32)
+                .execute(&mut *connection)
+                .await?;
+            Ok(())
+        },
+    }
+}
+
+async fn apply_storage_item_change(
+    change: &InventoryChange,
+    character_id: u32,
+    connection: &mut PgConnection,
+) -> Result<(), sqlx::Error> {
+    match change {
+        InventoryChange::AddItem { slot, item } => {
+            sqlx::query(
+                "INSERT INTO character_storage_items(character_id, item_obj_id, upgrade_level, slot, variance, amount) VALUES($1, $2, $3, $4, $5, $6) ON CONFLICT(character_id, slot) DO UPDATE SET item_obj_id = EXCLUDED.item_obj_id, upgrade_level = EXCLUDED.upgrade_level, variance = EXCLUDED.variance, amount = EXCLUDED.amount",
+            )
+            .bind(character_id as i32)
+            .bind(item.reference.common.ref_id as i32)
+            .bind(item.type_data.upgrade_level().map(|a| a as i16).unwrap_or(0))
+            .bind(*slot as i16)
+            .bind(item.variance.map(|a| a as i64))
+            .bind(item.type_data.amount() as i16)
+            .execute(&mut *connection)
+            .await?;
+        },
+        InventoryChange::ChangeTypeData { slot, new_item, .. } => {
+            sqlx::query(
+                "UPDATE character_storage_items SET upgrade_level = $1, amount = $2 WHERE character_id = $3 AND slot = $4",
+            )
+            .bind(new_item.upgrade_level().map(|a| a as i16).unwrap_or(0))
+            .bind(new_item.amount() as i16)
+            .bind(character_id as i32)
+            .bind(*slot as i16)
+            .execute(&mut *connection)
+            .await?;
+        },
+        InventoryChange::MoveItem {
+            source_slot,
+            target_slot,
+        } => {
+            sqlx::query("UPDATE character_storage_items SET slot = $1 WHERE character_id = $2 AND slot = $3")
+                .bind(*target_slot as i16)
+                .bind(character_id as i32)
+                .bind(*source_slot as i16)
+                .execute(&mut *connection)
+                .await?;
+        },
+        InventoryChange::RemoveItem { slot } => {
+            sqlx::query("DELETE FROM character_storage_items WHERE character_id = $1 AND slot = $2")
+                .bind(character_id as i32)
+                .bind(*slot as i16)
+                .execute(&mut *connection)
+                .await?;
+        },
+        InventoryChange::Swap {
+            first_slot,
+            second_slot,
+        } => {
+            sqlx::query(
+                "UPDATE character_storage_items SET slot = case slot when $2 then $3 when $3 then $2 end WHERE character_id = $1 AND slot in ($2, $3)",
+            )
+            .bind(character_id as i32)
+            .bind(*first_slot as i16)
+            .bind(*second_slot as i16)
+            .execute(&mut *connection)
+            .await?;
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+
+    #[test]
+    fn test_gold_changes() {
+        let mut storage = PlayerStorage::new(STORAGE_SIZE, 100);
+        assert!(!storage.withdraw_gold(200));
+        assert!(storage.changes().is_empty());
+
+        assert!(storage.withdraw_gold(50));
+        storage.deposit_gold(20);
+        assert_eq!(70, storage.gold());
+        let changes = storage.changes();
+        assert_eq!(1, changes.len());
+        assert!(matches!(changes[0], StorageChange::Gold(70)));
+        assert!(storage.changes().is_empty());
+    }
+
+    #[test]
+    fn test_storage_slots() {
+        let mut storage = PlayerStorage::new(STORAGE_SIZE, 0);
+        assert_eq!(Some(0), storage.add_item(item(5)));
+        assert_eq!(STORAGE_SIZE - 1, storage.free_slots());
+        let changes = storage.changes();
+        assert_eq!(1, changes.len());
+        assert!(matches!(
+            changes[0],
+            StorageChange::Item(InventoryChange::AddItem { slot: 0, .. })
+        ));
+    }
+}
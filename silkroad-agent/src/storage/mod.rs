@@ -0,0 +1,16 @@
+mod component;
+mod system;
+
+use crate::persistence::AppPersistanceExt;
+use crate::storage::system::{handle_storage_input, handle_storage_open};
+use bevy::prelude::*;
+pub(crate) use component::PlayerStorage;
+
+pub(crate) struct StoragePlugin;
+
+impl Plugin for StoragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (handle_storage_open, handle_storage_input))
+            .track_component::<PlayerStorage>();
+    }
+}
@@ -0,0 +1,279 @@
+use crate::comp::gold::GoldPouch;
+use crate::comp::inventory::PlayerInventory;
+use crate::comp::net::Client;
+use crate::comp::npc::{NpcFunction, NpcFunctions, NPC};
+use crate::comp::player::Player;
+use crate::comp::pos::Position;
+use crate::input::PlayerInput;
+use crate::persistence::{PersistenceCollection, PersistenceQueue};
+use crate::storage::component::{persist_storage_transfer, PlayerStorage, StorageTransfer, STORAGE_DISTANCE_SQUARED};
+use crate::world::EntityLookup;
+use bevy::prelude::*;
+use silkroad_game_base::{ChangeTracked, Inventory};
+use silkroad_protocol::inventory::{
+    InventoryOperationError, InventoryOperationRequest, InventoryOperationResponseData, InventoryOperationResult,
+};
+use silkroad_protocol::storage::{StorageGold, StorageItems, StorageItemsEnd, StorageOpenResponse};
+use std::cmp::max;
+
+pub(crate) fn handle_storage_open(
+    query: Query<(&Client, &PlayerInput, &Position, &PlayerStorage)>,
+    npcs: Query<(&NpcFunctions, &Position), With<NPC>>,
+    lookup: Res<EntityLookup>,
+) {
+    for (client, input, position, storage) in query.iter() {
+        let Some(ref request) = input.storage else {
+            continue;
+        };
+
+        if !is_near_storage_keeper(request.npc, position, &npcs, &lookup) {
+            client.send(StorageOpenResponse::Failure(InventoryOperationError::InvalidTarget));
+            continue;
+        }
+
+        client.send(StorageGold { gold: storage.gold() });
+        client.send(StorageItems {
+            capacity: storage.size() as u8,
+            items: storage.item_data(),
+        });
+        client.send(StorageItemsEnd);
+        client.send(StorageOpenResponse::Success);
+    }
+}
+
+pub(crate) fn handle_storage_input(
+    mut query: Query<(
+        &Client,
+        &Player,
+        &PlayerInput,
+        &Position,
+        &mut PlayerInventory,
+        &mut PlayerStorage,
+        &mut GoldPouch,
+        Option<&mut PersistenceCollection<PlayerInventory>>,
+        Option<&mut PersistenceCollection<PlayerStorage>>,
+    )>,
+    npcs: Query<(&NpcFunctions, &Position), With<NPC>>,
+    lookup: Res<EntityLookup>,
+    queue: Res<PersistenceQueue>,
+) {
+    for (
+        client,
+        player,
+        input,
+        position,
+        mut inventory,
+        mut storage,
+        mut gold,
+        inventory_collection,
+        storage_collection,
+    ) in query.iter_mut()
+    {
+        let Some(ref action) = input.inventory else {
+            continue;
+        };
+
+        let npc = match action.data {
+            InventoryOperationRequest::StorageMove { npc, .. }
+            | InventoryOperationRequest::InventoryToStorage { npc, .. }
+            | InventoryOperationRequest::StorageToInventory { npc, .. }
+            | InventoryOperationRequest::StorageDepositGold { npc, .. }
+            | InventoryOperationRequest::StorageWithdrawGold { npc, .. } => npc,
+            _ => continue,
+        };
+
+        if !is_near_storage_keeper(npc, position, &npcs, &lookup) {
+            client.send(InventoryOperationResult::Failure(
+                InventoryOperationError::InvalidTarget,
+            ));
+            continue;
+        }
+
+        let result = match action.data {
+            InventoryOperationRequest::StorageMove {
+                source, target, amount, ..
+            } => move_in_storage(source, target, amount, &mut storage),
+            InventoryOperationRequest::InventoryToStorage { slot, storage_slot, .. } => {
+                store_item(slot, storage_slot, &mut inventory, &mut storage, &mut gold)
+            },
+            InventoryOperationRequest::StorageToInventory { storage_slot, slot, .. } => {
+                retrieve_item(storage_slot, slot, &mut inventory, &mut storage)
+            },
+            InventoryOperationRequest::StorageDepositGold { amount, .. } => {
+                if amount == 0 || amount > gold.amount() {
+                    Err(InventoryOperationError::NotEnoughGold)
+                } else {
+                    gold.spend(amount);
+                    storage.deposit_gold(amount);
+                    Ok(InventoryOperationResponseData::StorageDepositGold { amount })
+                }
+            },
+            InventoryOperationRequest::StorageWithdrawGold { amount, .. } => {
+                if amount == 0 || !storage.withdraw_gold(amount) {
+                    Err(InventoryOperationError::NotEnoughGold)
+                } else {
+                    gold.gain(amount);
+                    Ok(InventoryOperationResponseData::StorageWithdrawGold { amount })
+                }
+            },
+            _ => continue,
+        };
+
+        let data = match result {
+            Ok(data) => data,
+            Err(error) => {
+                client.send(InventoryOperationResult::Failure(error));
+                continue;
+            },
+        };
+        client.send(InventoryOperationResult::Success(data));
+
+        // Changes which have been collected before, but not yet persisted, need to be included as
+        // well, otherwise they would be applied after the transfer and in the wrong order.
+        let mut inventory_changes = inventory_collection
+            .map(|mut collection| collection.take_changes())
+            .unwrap_or_default();
+        inventory_changes.append(&mut inventory.changes());
+        let mut storage_changes = storage_collection
+            .map(|mut collection| collection.take_changes())
+            .unwrap_or_default();
+        storage_changes.append(&mut storage.changes());
+
+        persist_storage_transfer(
+            StorageTransfer {
+                character_id: player.character.id,
+                inventory_changes,
+                storage_changes,
+                gold: gold.amount(),
+            },
+            &queue,
+        );
+    }
+}
+
+/// Checks if the NPC with the given id keeps a storage and if the player is close enough to it.
+fn is_near_storage_keeper(
+    npc: u32,
+    position: &Position,
+    npcs: &Query<(&NpcFunctions, &Position), With<NPC>>,
+    lookup: &EntityLookup,
+) -> bool {
+    let Some((functions, npc_position)) = lookup.get_entity_for_id(npc).and_then(|npc| npcs.get(npc).ok()) else {
+        return false;
+    };
+
+    functions.has(NpcFunction::Storage) && position.distance_to(npc_position) <= STORAGE_DISTANCE_SQUARED
+}
+
+fn move_in_storage(
+    source: u8,
+    target: u8,
+    amount: u16,
+    storage: &mut Inventory,
+) -> Result<InventoryOperationResponseData, InventoryOperationError> {
+    if usize::from(target) >= storage.size() {
+        return Err(InventoryOperationError::InvalidTarget);
+    }
+
+    let amount = storage
+        .move_item(source, target, max(1, amount))
+        .map_err(|_| InventoryOperationError::InvalidTarget)?;
+    Ok(InventoryOperationResponseData::StorageMove { source, target, amount })
+}
+
+fn store_item(
+    slot: u8,
+    storage_slot: u8,
+    inventory: &mut Inventory,
+    storage: &mut Inventory,
+    gold: &mut GoldPouch,
+) -> Result<InventoryOperationResponseData, InventoryOperationError> {
+    if Inventory::is_equipment_slot(slot) {
+        return Err(InventoryOperationError::InvalidTarget);
+    }
+
+    if usize::from(storage_slot) >= storage.size() || storage.get_item_at(storage_slot).is_some() {
+        return Err(InventoryOperationError::InvalidTarget);
+    }
+
+    let item = inventory
+        .get_item_at(slot)
+        .ok_or(InventoryOperationError::InvalidTarget)?;
+    let fee = item.reference.keeping_fee * u64::from(item.stack_size());
+    if fee > gold.amount() {
+        return Err(InventoryOperationError::NotEnoughGold);
+    }
+
+    let item = inventory
+        .take_item_at(slot)
+        .expect("Item should exist after checking it");
+    storage
+        .insert_item_at(storage_slot, item)
+        .expect("Storage slot should be free after checking it");
+    gold.spend(fee);
+
+    Ok(InventoryOperationResponseData::InventoryToStorage { slot, storage_slot })
+}
+
+fn retrieve_item(
+    storage_slot: u8,
+    slot: u8,
+    inventory: &mut Inventory,
+    storage: &mut Inventory,
+) -> Result<InventoryOperationResponseData, InventoryOperationError> {
+    if Inventory::is_equipment_slot(slot) {
+        return Err(InventoryOperationError::InvalidTarget);
+    }
+
+    if usize::from(slot) >= inventory.size() || inventory.get_item_at(slot).is_some() {
+        return Err(InventoryOperationError::InventoryFull);
+    }
+
+    let item = storage
+        .take_item_at(storage_slot)
+        .ok_or(InventoryOperationError::InvalidTarget)?;
+    inventory
+        .insert_item_at(slot, item)
+        .expect("Inventory slot should be free after checking it");
+
+    Ok(InventoryOperationResponseData::StorageToInventory { storage_slot, slot })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::comp::inventory::test_support::consumable as item;
+
+    #[test]
+    fn test_store_and_retrieve() {
+        let mut inventory = Inventory::new(45);
+        let mut storage = Inventory::without_equipment(10);
+        let mut gold = GoldPouch::new(100);
+        inventory.set_item(13, item(5));
+
+        assert!(store_item(13, 10, &mut inventory, &mut storage, &mut gold).is_err());
+        assert!(store_item(13, 2, &mut inventory, &mut storage, &mut gold).is_ok());
+        assert!(inventory.get_item_at(13).is_none());
+        assert_eq!(5, storage.get_item_at(2).unwrap().stack_size());
+        assert_eq!(50, gold.amount());
+
+        inventory.set_item(13, item(1));
+        assert!(retrieve_item(2, 13, &mut inventory, &mut storage).is_err());
+        assert!(retrieve_item(2, 14, &mut inventory, &mut storage).is_ok());
+        assert!(storage.get_item_at(2).is_none());
+    }
+
+    #[test]
+    fn test_store_requires_fee() {
+        let mut inventory = Inventory::new(45);
+        let mut storage = Inventory::without_equipment(10);
+        let mut gold = GoldPouch::new(10);
+        inventory.set_item(13, item(5));
+
+        assert!(matches!(
+            store_item(13, 0, &mut inventory, &mut storage, &mut gold),
+            Err(InventoryOperationError::NotEnoughGold)
+        ));
+        assert!(inventory.get_item_at(13).is_some());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::comp::buff::Buffed;
 use crate::comp::exp::{Experienced, Leveled};
 use crate::comp::mastery::MasteryKnowledge;
 use crate::comp::player::StatPoints;
@@ -5,16 +6,16 @@ use crate::comp::pos::Position;
 use crate::comp::{Health, Mana};
 use crate::sync::reset::AppResetExt;
 use crate::sync::system::{
-    collect_alives, collect_body_states, collect_deaths, collect_gold_changes, collect_mastery_changes,
-    collect_movement_speed_change, collect_movement_update, collect_pickup_animation, collect_stat_changes,
-    synchronize_updates, system_collect_bars_update, system_collect_exp_update, system_collect_level_up,
-    system_collect_sp_update,
+    collect_alives, collect_body_states, collect_buff_changes, collect_deaths, collect_gold_changes,
+    collect_mastery_changes, collect_movement_speed_change, collect_movement_update, collect_pickup_animation,
+    collect_stat_changes, collect_status_effects, synchronize_updates, system_collect_bars_update,
+    system_collect_exp_update, system_collect_level_up, system_collect_sp_update,
 };
 use bevy::prelude::*;
 use derive_more::From;
 pub(crate) use reset::Reset;
 use silkroad_protocol::character::CharacterStatsMessage;
-use silkroad_protocol::combat::ReceiveExperience;
+use silkroad_protocol::combat::{BuffBegin, BuffEnd, ReceiveExperience};
 use silkroad_protocol::movement::{EntityMovementInterrupt, PlayerMovementResponse};
 use silkroad_protocol::skill::LevelUpMasteryResponse;
 use silkroad_protocol::world::{
@@ -31,6 +32,9 @@ pub(crate) struct Update {
     source: Entity,
     change_self: Option<SelfUpdate>,
     change_others: Option<OtherUpdate>,
+    /// Whether the update for others may be dropped if they cannot keep up, because a later
+    /// update will supersede it anyway.
+    droppable: bool,
 }
 
 impl Update {
@@ -39,6 +43,7 @@ impl Update {
             source: entity,
             change_self: Some(update.into()),
             change_others: None,
+            droppable: false,
         }
     }
 
@@ -47,8 +52,16 @@ impl Update {
             source: entity,
             change_self: Some(update.clone().into()),
             change_others: Some(update.into()),
+            droppable: false,
         }
     }
+
+    /// Allows the update to be dropped for other players, like updates of the health bar or of
+    /// the movement, which get replaced by the next update.
+    pub(crate) fn droppable(mut self) -> Self {
+        self.droppable = true;
+        self
+    }
 }
 
 #[derive(From)]
@@ -63,6 +76,8 @@ pub(crate) enum SelfUpdate {
     EntityUpdateState(EntityUpdateState),
     PlayerPickupAnimation(PlayerPickupAnimation),
     LevelUpMasteryResponse(LevelUpMasteryResponse),
+    BuffBegin(BuffBegin),
+    BuffEnd(BuffEnd),
 }
 
 impl AsPacket for SelfUpdate {
@@ -78,6 +93,8 @@ impl AsPacket for SelfUpdate {
             SelfUpdate::EntityUpdateState(p) => p.as_packet(),
             SelfUpdate::PlayerPickupAnimation(p) => p.as_packet(),
             SelfUpdate::LevelUpMasteryResponse(p) => p.as_packet(),
+            SelfUpdate::BuffBegin(p) => p.as_packet(),
+            SelfUpdate::BuffEnd(p) => p.as_packet(),
         }
     }
 }
@@ -90,6 +107,8 @@ pub(crate) enum OtherUpdate {
     PlayerMovementResponse(PlayerMovementResponse),
     EntityUpdateState(EntityUpdateState),
     PlayerPickupAnimation(PlayerPickupAnimation),
+    BuffBegin(BuffBegin),
+    BuffEnd(BuffEnd),
 }
 
 impl AsPacket for OtherUpdate {
@@ -101,6 +120,8 @@ impl AsPacket for OtherUpdate {
             OtherUpdate::PlayerMovementResponse(p) => p.as_packet(),
             OtherUpdate::EntityUpdateState(p) => p.as_packet(),
             OtherUpdate::PlayerPickupAnimation(p) => p.as_packet(),
+            OtherUpdate::BuffBegin(p) => p.as_packet(),
+            OtherUpdate::BuffEnd(p) => p.as_packet(),
         }
     }
 }
@@ -165,6 +186,8 @@ impl Plugin for SynchronizationPlugin {
                     collect_deaths,
                     collect_alives,
                     collect_body_states,
+                    collect_status_effects,
+                    collect_buff_changes,
                     collect_stat_changes,
                     collect_gold_changes,
                     collect_mastery_changes,
@@ -181,6 +204,7 @@ impl Plugin for SynchronizationPlugin {
             .reset::<Experienced>()
             .reset::<StatPoints>()
             .reset::<Leveled>()
-            .reset::<MasteryKnowledge>();
+            .reset::<MasteryKnowledge>()
+            .reset::<Buffed>();
     }
 }
@@ -1,5 +1,8 @@
 use crate::agent::component::MovementState;
-use crate::agent::state::{AgentState, Dead, Idle, MovementTarget, Moving, PickingUp, StateTransitionEvent};
+use crate::agent::state::{
+    AgentState, Dead, Idle, Incapacitated, MovementTarget, Moving, PickingUp, StateTransitionEvent,
+};
+use crate::comp::buff::Buffed;
 use crate::comp::damage::Invincible;
 use crate::comp::exp::{Experienced, Leveled, SP};
 use crate::comp::gold::GoldPouch;
@@ -13,16 +16,16 @@ use crate::event::LoadingFinishedEvent;
 use crate::game::exp::LevelUpEvent;
 use crate::sync::{SynchronizationCollector, Update};
 use bevy::prelude::*;
-use silkroad_game_base::{Heading, LocalPosition, MovementSpeed};
+use silkroad_game_base::{Heading, LocalPosition, MovementSpeed, StatusEffectKind};
 use silkroad_protocol::character::CharacterStatsMessage;
-use silkroad_protocol::combat::ReceiveExperience;
+use silkroad_protocol::combat::{BuffBegin, BuffEnd, ReceiveExperience};
 use silkroad_protocol::movement::{
     EntityMovementInterrupt, MovementDestination, MovementSource, MovementType, PlayerMovementResponse,
 };
 use silkroad_protocol::skill::LevelUpMasteryResponse;
 use silkroad_protocol::world::{
     AliveState, BodyState, CharacterPointsUpdate, EntityBarUpdateSource, EntityBarUpdates, EntityBarsUpdate,
-    EntityUpdateState, LevelUpEffect, PlayerPickupAnimation, UpdatedState,
+    EntityUpdateState, LevelUpEffect, PlayerPickupAnimation, StatusState, UpdatedState,
 };
 use std::ops::Deref;
 use tracing::debug;
@@ -47,7 +50,11 @@ pub(crate) fn synchronize_updates(
                     .map(|reference| others.get(reference.0))
                     .filter_map(|res| res.ok())
                 {
-                    client.send(other_packet.clone());
+                    if update.droppable {
+                        client.send_droppable(other_packet.clone());
+                    } else {
+                        client.send(other_packet.clone());
+                    }
                 }
             }
         }
@@ -70,11 +77,7 @@ pub(crate) fn system_collect_bars_update(
                     },
                 };
 
-                collector.send_update(Update {
-                    source: entity,
-                    change_self: Some(update.clone().into()),
-                    change_others: Some(update.into()),
-                })
+                collector.send_update(Update::update_all(entity, update).droppable())
             },
             (None, None) => continue,
             (change_hp, change_mp) => {
@@ -91,7 +94,7 @@ pub(crate) fn system_collect_bars_update(
                         },
                     };
 
-                    collector.send_update(Update::update_all(entity, update));
+                    collector.send_update(Update::update_all(entity, update).droppable());
                 }
 
                 if let Some(change) = change_mp {
@@ -107,7 +110,7 @@ pub(crate) fn system_collect_bars_update(
                         },
                     };
 
-                    collector.send_update(Update::update_all(entity, update));
+                    collector.send_update(Update::update_all(entity, update).droppable());
                 }
             },
         }
@@ -128,6 +131,7 @@ pub(crate) fn system_collect_sp_update(
             source: entity,
             change_self: Some(CharacterPointsUpdate::sp(sp.current()).into()),
             change_others: None,
+            droppable: false,
         })
     }
 }
@@ -223,7 +227,7 @@ pub(crate) fn collect_movement_starts(
         };
         debug!("Sending movement start. {}", game_entity.unique_id);
         let update = create_movement_packet(game_entity, update);
-        collector.send_update(Update::update_all(entity, update));
+        collector.send_update(Update::update_all(entity, update).droppable());
     }
 }
 
@@ -248,7 +252,7 @@ pub(crate) fn collect_movement_update(
         };
 
         let packet = create_movement_packet(game_entity, update);
-        collector.send_update(Update::update_all(entity, packet));
+        collector.send_update(Update::update_all(entity, packet).droppable());
     }
 }
 
@@ -353,6 +357,7 @@ pub(crate) fn collect_deaths(
             source: entity,
             change_self: Some(update.into()),
             change_others: Some(update.into()),
+            droppable: false,
         });
     }
 }
@@ -371,6 +376,7 @@ pub(crate) fn collect_alives(
             source: event.0,
             change_self: Some(update.into()),
             change_others: Some(update.into()),
+            droppable: false,
         });
     }
 }
@@ -386,6 +392,7 @@ pub(crate) fn collect_body_states(
             source: entity,
             change_self: Some(update.into()),
             change_others: None, // TODO: we may need a way to include other players here
+            droppable: false,
         });
     }
 
@@ -395,10 +402,64 @@ pub(crate) fn collect_body_states(
             source: entity,
             change_self: Some(update.into()),
             change_others: Some(update.into()),
+            droppable: false,
         });
     }
 }
 
+pub(crate) fn collect_status_effects(
+    collector: Res<SynchronizationCollector>,
+    added_query: Query<(Entity, &GameEntity, &Incapacitated), Added<Incapacitated>>,
+    knockback_query: Query<(Entity, &GameEntity, &Position), (Changed<Position>, With<Incapacitated>)>,
+    mut removed: RemovedComponents<Incapacitated>,
+    entity_query: Query<&GameEntity>,
+) {
+    for (entity, game_entity, incapacitated) in added_query.iter() {
+        let status = match incapacitated.parameter.effect.kind {
+            StatusEffectKind::Stun => StatusState::Stunned,
+            StatusEffectKind::Knockdown => StatusState::KnockedDown,
+            StatusEffectKind::Knockback => StatusState::KnockedBack,
+        };
+        let update = EntityUpdateState::status(game_entity.unique_id, status);
+        collector.send_update(Update::update_all(entity, update));
+    }
+
+    for (entity, game_entity, pos) in knockback_query.iter() {
+        if pos.did_move() {
+            let packet = EntityMovementInterrupt {
+                entity_id: game_entity.unique_id,
+                position: pos.as_protocol(),
+            };
+            collector.send_update(Update::update_all(entity, packet));
+        }
+    }
+
+    for entity in removed.read() {
+        // The entity may have been despawned entirely, in which case there's nobody left to tell.
+        let Ok(game_entity) = entity_query.get(entity) else {
+            continue;
+        };
+        let update = EntityUpdateState::status(game_entity.unique_id, StatusState::None);
+        collector.send_update(Update::update_all(entity, update));
+    }
+}
+
+pub(crate) fn collect_buff_changes(
+    collector: Res<SynchronizationCollector>,
+    query: Query<(Entity, &GameEntity, &Buffed), Changed<Buffed>>,
+) {
+    for (entity, game_entity, buffed) in query.iter() {
+        for buff in buffed.ended() {
+            collector.send_update(Update::update_all(entity, BuffEnd::new(buff.token)));
+        }
+
+        for buff in buffed.began() {
+            let update = BuffBegin::new(game_entity.unique_id, buff.skill, buff.token);
+            collector.send_update(Update::update_all(entity, update));
+        }
+    }
+}
+
 pub(crate) fn collect_stat_changes(
     collector: Res<SynchronizationCollector>,
     query: Query<(Entity, &Leveled, &StatPoints), Changed<StatPoints>>,
@@ -425,6 +486,7 @@ pub(crate) fn collect_stat_changes(
                     .into(),
                 ),
                 change_others: None,
+                droppable: false,
             })
         }
 
@@ -433,6 +495,7 @@ pub(crate) fn collect_stat_changes(
                 source: entity,
                 change_self: Some(CharacterPointsUpdate::StatPoints(stats.remaining_points()).into()),
                 change_others: None,
+                droppable: false,
             });
         }
     }
@@ -6,6 +6,7 @@ use silkroad_data::gold::{load_gold_map, GoldMap};
 use silkroad_data::itemdata::{load_item_map, RefItemData};
 use silkroad_data::level::{load_level_map, LevelMap};
 use silkroad_data::masterydata::{load_mastery_map, RefMasteryData};
+use silkroad_data::shop::{load_shop_map, ShopMap};
 use silkroad_data::skilldata::{load_skill_map, RefSkillData};
 use silkroad_data::teleport::{
     load_teleport_buildings, load_teleport_links, load_teleport_map, TeleportBuilding, TeleportLink, TeleportLocation,
@@ -22,6 +23,7 @@ static MASTERIES: OnceCell<DataMap<RefMasteryData>> = OnceCell::new();
 static TELEPORTS: OnceCell<HashMap<u16, TeleportLocation>> = OnceCell::new();
 static TELEPORT_LINKS: OnceCell<Vec<TeleportLink>> = OnceCell::new();
 static TELEPORT_BUILDINGS: OnceCell<DataMap<TeleportBuilding>> = OnceCell::new();
+static SHOPS: OnceCell<ShopMap> = OnceCell::new();
 
 pub struct WorldData;
 
@@ -36,6 +38,7 @@ impl WorldData {
         let teleports = load_teleport_map(media_pk2)?;
         let teleport_links = load_teleport_links(media_pk2)?;
         let teleport_buildings = load_teleport_buildings(media_pk2)?;
+        let shops = load_shop_map(media_pk2)?;
 
         let _ = LEVELS.set(levels);
         let _ = GOLD.set(gold);
@@ -46,6 +49,7 @@ impl WorldData {
         let _ = TELEPORTS.set(teleports);
         let _ = TELEPORT_LINKS.set(teleport_links);
         let _ = TELEPORT_BUILDINGS.set(teleport_buildings);
+        let _ = SHOPS.set(shops);
         Ok(())
     }
 
@@ -72,4 +76,8 @@ impl WorldData {
     pub fn masteries() -> &'static DataMap<RefMasteryData> {
         MASTERIES.get().expect("Masteries should have been set")
     }
+
+    pub fn shops() -> &'static ShopMap {
+        SHOPS.get().expect("Shops should have been set")
+    }
 }
@@ -22,7 +22,7 @@ impl EntityLookup {
         self.id_map.insert(entity_id, entity);
     }
 
-    pub fn get_entity_for_name(&self, name: &String) -> Option<Entity> {
+    pub fn get_entity_for_name(&self, name: &str) -> Option<Entity> {
         self.player_map.get(name).copied()
     }
 
@@ -1,10 +1,12 @@
 use crate::agent::component::{Agent, MovementState};
 use crate::agent::goal::GoalTracker;
 use crate::agent::state::{AgentStateQueue, Dead};
+use crate::comp::buff::Buffed;
 use crate::comp::damage::DamageReceiver;
 use crate::comp::monster::{Monster, MonsterAiBundle, MonsterBundle, RandomStroll, SpawnedBy};
-use crate::comp::npc::NpcBundle;
+use crate::comp::npc::{NpcBundle, NpcFunctions};
 use crate::comp::pos::Position;
+use crate::comp::skill::SkillCooldownTracker;
 use crate::comp::spawner::Spawner;
 use crate::comp::visibility::Visibility;
 use crate::comp::{GameEntity, Health};
@@ -48,6 +50,7 @@ pub(crate) fn spawn_npcs(
                 id_pool.request_id().expect("Should have ID available for NPC"),
                 spawn.npc_id,
                 LocalPosition(spawn.region.into(), Vector3::new(spawn.x, spawn.y, spawn.z)),
+                NpcFunctions::for_npc(character_data),
                 Agent::from_character_data(character_data),
             ));
         } else if matches!(
@@ -213,6 +216,8 @@ fn spawn_monster(
         state_queue: AgentStateQueue::default(),
         movement_state: MovementState::default_monster(),
         damage_receiver: DamageReceiver::default(),
+        buffs: Buffed::default(),
+        cooldowns: SkillCooldownTracker::default(),
     };
 
     let ai_bundle = MonsterAiBundle {
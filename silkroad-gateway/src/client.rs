@@ -7,7 +7,7 @@ use chrono::{TimeZone, Utc};
 use color_eyre::Result;
 use silkroad_gateway_protocol::*;
 use silkroad_rpc::ReserveResponse;
-use skrillax_server::Connection;
+use skrillax_server::{Connection, DisconnectReason};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
@@ -37,6 +37,7 @@ impl Client {
             tokio::select! {
                 res = timeout(Duration::from_secs(10), connection.next_async()) => {
                     let Ok(packet) = res else {
+                        connection.disconnect(DisconnectReason::Timeout);
                         break;
                     };
 
@@ -44,7 +45,7 @@ impl Client {
                         GatewayClientProtocol::KeepAlive(_) => {},
                         GatewayClientProtocol::PatchRequest(patch) => match patcher.get_patch_information(patch.version) {
                             PatchInformation::UpToDate => {
-                                connection.send(PatchResponse::up_to_date())?;
+                                connection.send_async(PatchResponse::up_to_date()).await?;
                             },
                             PatchInformation::RequiresUpdate {
                                 files,
@@ -58,18 +59,18 @@ impl Client {
                                     patch_files: files,
                                     http_server: host,
                                 });
-                                connection.send(response)?;
+                                connection.send_async(response).await?;
                             },
                             PatchInformation::Outdated => {
-                                connection.send(PatchResponse::error(PatchError::InvalidVersion))?;
+                                connection.send_async(PatchResponse::error(PatchError::InvalidVersion)).await?;
                             },
                         },
                         GatewayClientProtocol::IdentityInformation(identity) => {
                             debug!(module = ?identity.module_name, local = identity.locality, "Client application identity");
-                            connection.send(IdentityInformation {
+                            connection.send_async(IdentityInformation {
                                 module_name: "GatewayServer".to_string(),
                                 locality: 0,
-                            })?;
+                            }).await?;
                         },
                         GatewayClientProtocol::GatewayNoticeRequest(_) => {
                             let mut news = news.lock().await;
@@ -82,7 +83,7 @@ impl Client {
                                     published: news.date,
                                 })
                                 .collect();
-                            connection.send(GatewayNoticeResponse::new(news))?;
+                            connection.send_async(GatewayNoticeResponse::new(news)).await?;
                         },
                         GatewayClientProtocol::LoginRequest(login) => {
                             last_credentials = Some(LastCredentials {
@@ -99,13 +100,13 @@ impl Client {
                                     Self::try_reserve_spot(&connection, &agent_servers, id as u32, creds).await?
                                 },
                                 LoginResult::MissingPasscode => {
-                                    connection.send(PasscodeRequiredResponse::passcode_required())?;
+                                    connection.send_async(PasscodeRequiredResponse::passcode_required()).await?;
                                 },
                                 LoginResult::InvalidCredentials => {
-                                    connection.send(LoginResponse::error(SecurityError::InvalidCredentials {
+                                    connection.send_async(LoginResponse::error(SecurityError::InvalidCredentials {
                                         max_attempts: 5,
                                         current_attempts: 1,
-                                    }))?;
+                                    })).await?;
                                 },
                                 LoginResult::Blocked => {
                                     let response = LoginResponse::error(SecurityError::Blocked {
@@ -114,7 +115,7 @@ impl Client {
                                             end: Utc.with_ymd_and_hms(2099, 12, 31, 23, 59, 59).unwrap(),
                                         },
                                     });
-                                    connection.send(response)?;
+                                    connection.send_async(response).await?;
                                 },
                             }
                         },
@@ -127,7 +128,7 @@ impl Client {
                                         Err(_) => {
                                             // Maybe this should return a more fitting response code?
                                             // Or should the client just be ditched?
-                                            connection.send(PasscodeResponse::new(2, 1))?;
+                                            connection.send_async(PasscodeResponse::new(2, 1)).await?;
                                             continue;
                                         },
                                     };
@@ -138,17 +139,17 @@ impl Client {
 
                                 match result {
                                     LoginResult::Success(id) => {
-                                        connection.send(SecurityCodeResponse::success())?;
+                                        connection.send_async(SecurityCodeResponse::success()).await?;
                                         Self::try_reserve_spot(&connection, &agent_servers, id as u32, previous).await?
                                     },
                                     LoginResult::MissingPasscode => {
                                         error!("Player entered passcode but we somehow didn't use it.");
                                     },
                                     LoginResult::InvalidCredentials => {
-                                        connection.send(PasscodeRequiredResponse::passcode_invalid())?;
+                                        connection.send_async(PasscodeRequiredResponse::passcode_invalid()).await?;
                                     },
                                     LoginResult::Blocked => {
-                                        connection.send(PasscodeRequiredResponse::passcode_blocked())?;
+                                        connection.send_async(PasscodeRequiredResponse::passcode_blocked()).await?;
                                     },
                                 }
                             }
@@ -158,11 +159,11 @@ impl Client {
                             let shards = servers.into_iter().map(|server| server.into()).collect();
                             let farms = agent_servers.farms().clone();
 
-                            connection.send(ShardListResponse { farms, shards })?;
+                            connection.send_async(ShardListResponse { farms, shards }).await?;
                         },
                         GatewayClientProtocol::PingServerRequest(_) => {
                             let ping_response = PingServerResponse::new(vec![PingServer::new(1, "localhost".to_string())]);
-                            connection.send(ping_response)?;
+                            connection.send_async(ping_response).await?;
                         },
                     }
                 }
@@ -184,7 +185,9 @@ impl Client {
         let server = match agent_servers.server_details(last_credentials.shard).await {
             Some(addr) => addr,
             None => {
-                connection.send(LoginResponse::error(SecurityError::Inspection))?;
+                connection
+                    .send_async(LoginResponse::error(SecurityError::Inspection))
+                    .await?;
                 return Ok(());
             },
         };
@@ -196,28 +199,46 @@ impl Client {
         match result {
             Err(e) => {
                 debug!(error = %e, "Error when reserving a spot");
-                connection.send(LoginResponse::error(SecurityError::Inspection))?
+                connection
+                    .send_async(LoginResponse::error(SecurityError::Inspection))
+                    .await?
             },
             Ok(result) => match result {
                 ReserveResponse::Success { token, .. } => {
                     let ip = server.ip();
                     let port = server.port();
                     debug!("Got a spot at {ip}:{port}: {token}");
-                    connection.send(LoginResponse {
-                        result: silkroad_gateway_protocol::LoginResult::Success {
-                            session_id: token,
-                            agent_ip: ip.to_string(),
-                            agent_port: port,
-                            unknown: 1,
-                        },
-                    })?
+                    connection
+                        .send_async(LoginResponse {
+                            result: silkroad_gateway_protocol::LoginResult::Success {
+                                session_id: token,
+                                agent_ip: ip.to_string(),
+                                agent_port: port,
+                                unknown: 1,
+                            },
+                        })
+                        .await?
+                },
+                ReserveResponse::NotFound => {
+                    connection
+                        .send_async(LoginResponse::error(SecurityError::Inspection))
+                        .await?
+                },
+                ReserveResponse::Full => {
+                    connection
+                        .send_async(LoginResponse::error(SecurityError::ServerFull))
+                        .await?
+                },
+                ReserveResponse::Duplicate => {
+                    connection
+                        .send_async(LoginResponse::error(SecurityError::AlreadyConnected))
+                        .await?
                 },
-                ReserveResponse::NotFound => connection.send(LoginResponse::error(SecurityError::Inspection))?,
-                ReserveResponse::Full => connection.send(LoginResponse::error(SecurityError::ServerFull))?,
-                ReserveResponse::Duplicate => connection.send(LoginResponse::error(SecurityError::AlreadyConnected))?,
                 ReserveResponse::Error(message) => {
                     debug!("Could not reserve spot: {message}");
-                    connection.send(LoginResponse::error(SecurityError::ServerFull))?
+                    connection
+                        .send_async(LoginResponse::error(SecurityError::ServerFull))
+                        .await?
                 },
             },
         }
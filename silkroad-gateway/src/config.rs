@@ -1,6 +1,7 @@
 use config::{ConfigError, FileFormat};
 use log::LevelFilter;
 use serde::Deserialize;
+use skrillax_server::{ListenAddresses, NetworkConfig};
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{ConnectOptions, PgPool};
 use std::fmt::Debug;
@@ -51,12 +52,13 @@ impl DbOptions {
 #[serde(rename_all = "kebab-case")]
 pub(crate) struct GatewayServerConfig {
     pub(crate) listen_port: Option<u16>,
-    pub(crate) listen_address: Option<String>,
+    pub(crate) listen_address: Option<ListenAddresses>,
     pub(crate) patch: Option<PatchConfig>,
     pub(crate) database: DbOptions,
     pub(crate) news_cache_duration: Option<u64>,
     pub(crate) agent_healthcheck_interval: Option<u64>,
     pub(crate) farms: Option<Vec<String>>,
+    pub(crate) network: Option<NetworkConfig>,
 }
 
 impl GatewayServerConfig {
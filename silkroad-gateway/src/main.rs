@@ -73,17 +73,10 @@ async fn main() -> Result<()> {
         db_pool.clone(),
     );
 
-    let listen_addr = match configuration.listen_address.as_ref() {
-        Some(addr) => {
-            let port = configuration.listen_port.unwrap_or(DEFAULT_LISTEN_PORT);
-            format!("{addr}:{port}")
-                .parse()
-                .expect("Listen address should be a valid ip address and port")
-        },
-        None => SocketAddr::new(
-            Ipv4Addr::new(0, 0, 0, 0).into(),
-            configuration.listen_port.unwrap_or(DEFAULT_LISTEN_PORT),
-        ),
+    let listen_port = configuration.listen_port.unwrap_or(DEFAULT_LISTEN_PORT);
+    let listen_addrs = match configuration.listen_address.as_ref() {
+        Some(addresses) => addresses.socket_addrs(listen_port)?,
+        None => vec![SocketAddr::new(Ipv4Addr::new(0, 0, 0, 0).into(), listen_port)],
     };
 
     let patcher = configuration
@@ -92,9 +85,13 @@ async fn main() -> Result<()> {
         .map(Patcher::new)
         .unwrap_or_else(Patcher::allow_all);
 
+    let network = configuration.network.clone().unwrap_or_default();
+
     let cancellation = CancellationToken::new();
     let server = GatewayServer::new(
-        listen_addr,
+        listen_addrs,
+        network.server_options(),
+        network.traffic_report_interval(),
         cancellation.clone(),
         news,
         patcher,
@@ -3,18 +3,23 @@ use crate::login::LoginProvider;
 use crate::news::NewsCacheAsync;
 use crate::patch::Patcher;
 use crate::AgentServerManager;
-use skrillax_server::Server;
+use skrillax_server::{Server, ServerOptions};
+use std::future::pending;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::{interval_at, Instant, Interval};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
 pub(crate) struct GatewayServer {
     news: Arc<Mutex<NewsCacheAsync>>,
     patcher: Arc<Patcher>,
-    socket: SocketAddr,
+    sockets: Vec<SocketAddr>,
+    options: ServerOptions,
+    traffic_report: Option<Duration>,
     cancellation: CancellationToken,
     login_provider: Arc<LoginProvider>,
     agent_servers: AgentServerManager,
@@ -22,7 +27,9 @@ pub(crate) struct GatewayServer {
 
 impl GatewayServer {
     pub fn new(
-        socket: SocketAddr,
+        sockets: Vec<SocketAddr>,
+        options: ServerOptions,
+        traffic_report: Option<Duration>,
         cancel: CancellationToken,
         news: NewsCacheAsync,
         patcher: Patcher,
@@ -32,7 +39,9 @@ impl GatewayServer {
         GatewayServer {
             news: Arc::new(Mutex::new(news)),
             cancellation: cancel,
-            socket,
+            sockets,
+            options,
+            traffic_report,
             patcher: Arc::new(patcher),
             login_provider: Arc::new(login_provider),
             agent_servers,
@@ -40,12 +49,21 @@ impl GatewayServer {
     }
 
     pub async fn run(self) -> Result<(), io::Error> {
-        let server = Server::new(self.socket)?;
+        let server = Server::new(&self.sockets, self.options.clone())?;
         info!("Server up and accepting clients.");
-        while let Some(connection) = tokio::select! {
-            connected = server.await_client() => Some(connected),
-            _ = self.cancellation.cancelled() => None
-        } {
+        let mut traffic_report = self
+            .traffic_report
+            .map(|period| interval_at(Instant::now() + period, period));
+        loop {
+            let connection = tokio::select! {
+                connected = server.await_client() => connected,
+                _ = next_report(&mut traffic_report) => {
+                    server.report_traffic();
+                    continue;
+                },
+                _ = self.cancellation.cancelled() => break,
+            };
+
             debug!(
                 id = connection.id(),
                 socket = ?connection.remote_address(),
@@ -68,3 +86,12 @@ impl GatewayServer {
         Ok(())
     }
 }
+
+async fn next_report(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        },
+        None => pending().await,
+    }
+}